@@ -0,0 +1,10 @@
+-- {{{ aliases
+CREATE TABLE IF NOT EXISTS aliases (
+    id INTEGER NOT NULL PRIMARY KEY,
+    chart_id INTEGER NOT NULL,
+    alias TEXT NOT NULL,
+
+    FOREIGN KEY (chart_id) REFERENCES charts(id),
+    UNIQUE(chart_id, alias)
+);
+-- }}}
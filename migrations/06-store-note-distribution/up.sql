@@ -0,0 +1,5 @@
+-- Only far notes used to be stored, with the rest of the distribution
+-- getting reconstructed from the score. When OCR reads every count
+-- consistently, we now keep the pure and lost counts around as well.
+ALTER TABLE plays ADD COLUMN pure_notes INTEGER;
+ALTER TABLE plays ADD COLUMN lost_notes INTEGER;
@@ -0,0 +1,3 @@
+-- Songs with non-english names (e.g. japanese titles) can now store their
+-- original title alongside the english one, so both can be searched for.
+ALTER TABLE songs ADD COLUMN og_title TEXT;
@@ -0,0 +1,6 @@
+-- {{{ guild configs
+CREATE TABLE IF NOT EXISTS guild_configs (
+    guild_id TEXT NOT NULL PRIMARY KEY,
+    prefix TEXT NOT NULL
+);
+-- }}}
@@ -0,0 +1,4 @@
+-- How the run ended (track lost, normal clear, full recall or pure memory),
+-- as read off the banner on the score screen. Older plays, song select
+-- screenshots and layouts without a measured banner leave this empty.
+ALTER TABLE plays ADD COLUMN clear_kind TEXT;
@@ -0,0 +1,14 @@
+-- {{{ b30 exclusions
+-- Charts a user does not want to count towards their b30 (e.g. charts that
+-- got removed from the game, or fluke scores). Plays on these charts are
+-- kept around, they just get skipped when computing best plays.
+CREATE TABLE IF NOT EXISTS b30_exclusions (
+    id INTEGER NOT NULL PRIMARY KEY,
+    user_id INTEGER NOT NULL,
+    chart_id INTEGER NOT NULL,
+
+    FOREIGN KEY (user_id) REFERENCES users(id),
+    FOREIGN KEY (chart_id) REFERENCES charts(id),
+    UNIQUE(user_id, chart_id)
+);
+-- }}}
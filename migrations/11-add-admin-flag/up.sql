@@ -0,0 +1,4 @@
+-- Admins can run maintenance commands and write to other users' data. Unlike
+-- pookie status (which can be granted automatically), this flag is only ever
+-- set by hand.
+ALTER TABLE users ADD COLUMN is_admin BOOL NOT NULL DEFAULT 0;
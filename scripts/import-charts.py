@@ -16,6 +16,7 @@ def import_charts_from_csv():
     chart_count = 0
     song_count = 0
     shorthand_count = 0
+    og_title_count = 0
 
     with open(config_dir + "/charts.csv", mode="r") as file:
         for i, row in enumerate(csv.reader(file)):
@@ -111,10 +112,29 @@ def import_charts_from_csv():
                 ],
             )
 
+    # Songs with non-english names are listed under their romanized title
+    # in charts.csv, so their original titles are kept in a separate file.
+    with open(config_dir + "/og_titles.csv", mode="r") as file:
+        for i, row in enumerate(csv.reader(file)):
+            if i == 0 or len(row) == 0:
+                continue
+
+            og_title_count += 1
+            [name, artist, og_title] = map(lambda v: v.strip(), row)
+            conn.execute(
+                f"""
+                    UPDATE songs
+                    SET og_title=?
+                    WHERE title=?
+                    {"" if artist=="" else "AND artist=?"}
+                """,
+                [og_title, name, *([] if artist == "" else [artist])],
+            )
+
     conn.commit()
 
     print(
-        f"Imported {chart_count} charts, {song_count} songs, and {shorthand_count} shorthands"
+        f"Imported {chart_count} charts, {song_count} songs, {shorthand_count} shorthands, and {og_title_count} original titles"
     )
 
 
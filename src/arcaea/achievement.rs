@@ -7,7 +7,7 @@ use crate::context::{ErrorKind, TagError, TaggedError, UserContext};
 use crate::user::User;
 
 use super::chart::{Difficulty, Level};
-use super::play::get_best_plays;
+use super::play::{get_best_plays, PlayCollection};
 use super::score::{Grade, ScoringSystem};
 // }}}
 
@@ -32,6 +32,22 @@ pub enum Goal {
 	MultiDifficultyPM(Difficulty),
 }
 
+/// The levels [Goal::GradeEntireLevel] achievements exist for, alongside the
+/// minimum number of charts one must own of each of them. Both the EX and
+/// EX+ towers share these.
+pub const GRADE_LEVEL_MINIMUMS: [(Level, usize); 10] = [
+	(Level::Seven, 5),
+	(Level::SevenP, 5),
+	(Level::Eight, 10),
+	(Level::EightP, 5),
+	(Level::Nine, 20),
+	(Level::NineP, 15),
+	(Level::Ten, 15),
+	(Level::TenP, 10),
+	(Level::Eleven, 5),
+	(Level::Twelve, 1),
+];
+
 impl Goal {
 	// {{{ Texture names
 	#[inline]
@@ -159,33 +175,14 @@ impl GoalStats {
 			.map_err(|_| anyhow!("No ptt history data found").tag(ErrorKind::User))?;
 		// }}}
 		// {{{ Peak PM relay
-		let peak_pm_relay = {
-			let mut pm_checklist = [false; Level::LEVELS.len()];
-			for (play, _, chart) in &plays {
-				if play.score(scoring_system).is_pm() {
-					pm_checklist[chart.level.to_index()] = true;
-				}
-			}
-
-			pm_checklist
-				.into_iter()
-				.enumerate()
-				.find(|(_, has_pm)| !*has_pm)
-				.map_or(Some(Level::Twelve), |(i, _)| {
-					Level::LEVELS.get(i.checked_sub(1)?).copied()
-				})
-		};
-		// }}}
-		// {{{ Per level lowest grades
-		let mut per_level_lowest_grades = [(Grade::EXP, 0); Level::LEVELS.len()];
-		for (play, _, chart) in plays {
-			let element = &mut per_level_lowest_grades[chart.level.to_index()];
-			*element = (
-				element.0.min(play.score(scoring_system).grade()),
-				element.1 + 1,
-			);
-		}
+		let peak_pm_relay = compute_peak_pm_relay(
+			plays
+				.iter()
+				.filter(|(play, _, _)| play.score(scoring_system).is_pm())
+				.map(|(_, _, chart)| chart.level),
+		);
 		// }}}
+		let per_level_lowest_grades = compute_per_level_lowest_grades(&plays, scoring_system);
 
 		Ok(GoalStats {
 			pm_count,
@@ -198,6 +195,48 @@ impl GoalStats {
 		})
 	}
 }
+
+/// Computes the lowest grade the user has on each level, alongside the
+/// number of charts of said level they own (i.e. have played). Levels
+/// without any plays are reported as `(Grade::EXP, 0)`.
+pub fn compute_per_level_lowest_grades(
+	plays: &PlayCollection,
+	scoring_system: ScoringSystem,
+) -> [(Grade, usize); Level::LEVELS.len()] {
+	let mut per_level_lowest_grades = [(Grade::EXP, 0); Level::LEVELS.len()];
+	for (play, _, chart) in plays {
+		let element = &mut per_level_lowest_grades[chart.level.to_index()];
+		*element = (
+			element.0.min(play.score(scoring_system).grade()),
+			element.1 + 1,
+		);
+	}
+
+	per_level_lowest_grades
+}
+
+/// Computes the highest level `L` such that the user has PMed a chart of
+/// every level from One up to `L`, given the levels of all PMed charts.
+///
+/// [Level::Unknown] is explicitly skipped, as it sits at the very start of
+/// [Level::LEVELS] despite not being part of the relay. Counting it would
+/// make every relay stop before it even began, unless the user happened to
+/// PM a chart whose level we do not know.
+pub fn compute_peak_pm_relay(pm_levels: impl IntoIterator<Item = Level>) -> Option<Level> {
+	let mut pm_checklist = [false; Level::LEVELS.len()];
+	for level in pm_levels {
+		pm_checklist[level.to_index()] = true;
+	}
+
+	let relay_levels = &Level::LEVELS[Level::One.to_index()..];
+	match relay_levels
+		.iter()
+		.position(|level| !pm_checklist[level.to_index()])
+	{
+		None => Some(Level::Twelve),
+		Some(i) => relay_levels.get(i.checked_sub(1)?).copied(),
+	}
+}
 // }}}
 // {{{ Achievement
 #[derive(Debug, Clone)]
@@ -331,31 +370,17 @@ impl Default for AchievementTowers {
 		]);
 		// }}}
 		// {{{ EX(+) level tower
-		let ex_level_tower = AchievementTower::new(vec![
-			Achievement::new(GradeEntireLevel(EX, Seven, 5)),
-			Achievement::new(GradeEntireLevel(EX, SevenP, 5)),
-			Achievement::new(GradeEntireLevel(EX, Eight, 10)),
-			Achievement::new(GradeEntireLevel(EX, EightP, 5)),
-			Achievement::new(GradeEntireLevel(EX, Nine, 20)),
-			Achievement::new(GradeEntireLevel(EX, NineP, 15)),
-			Achievement::new(GradeEntireLevel(EX, Ten, 15)),
-			Achievement::new(GradeEntireLevel(EX, TenP, 10)),
-			Achievement::new(GradeEntireLevel(EX, Eleven, 5)),
-			Achievement::new(GradeEntireLevel(EX, Twelve, 1)),
-		]);
+		let grade_level_tower = |grade| {
+			AchievementTower::new(
+				GRADE_LEVEL_MINIMUMS
+					.iter()
+					.map(|&(level, min)| Achievement::new(GradeEntireLevel(grade, level, min)))
+					.collect(),
+			)
+		};
 
-		let exp_level_tower = AchievementTower::new(vec![
-			Achievement::new(GradeEntireLevel(EXP, Seven, 5)),
-			Achievement::new(GradeEntireLevel(EXP, SevenP, 5)),
-			Achievement::new(GradeEntireLevel(EXP, Eight, 10)),
-			Achievement::new(GradeEntireLevel(EXP, EightP, 5)),
-			Achievement::new(GradeEntireLevel(EXP, Nine, 20)),
-			Achievement::new(GradeEntireLevel(EXP, NineP, 15)),
-			Achievement::new(GradeEntireLevel(EXP, Ten, 15)),
-			Achievement::new(GradeEntireLevel(EXP, TenP, 10)),
-			Achievement::new(GradeEntireLevel(EXP, Eleven, 5)),
-			Achievement::new(GradeEntireLevel(EXP, Twelve, 1)),
-		]);
+		let ex_level_tower = grade_level_tower(EX);
+		let exp_level_tower = grade_level_tower(EXP);
 		// }}}
 		// {{{ Submit plays
 		let submit_plays_tower = AchievementTower::new(vec![
@@ -397,3 +422,23 @@ impl Default for AchievementTowers {
 	// }}}
 }
 // }}}
+// {{{ Tests
+#[cfg(test)]
+mod achievement_tests {
+	use super::*;
+	use Level::*;
+
+	#[test]
+	fn pm_relay_ignores_unknown_levels() {
+		assert_eq!(compute_peak_pm_relay([]), None);
+		assert_eq!(compute_peak_pm_relay([Unknown]), None);
+		assert_eq!(compute_peak_pm_relay([Unknown, Two, Three]), None);
+		assert_eq!(compute_peak_pm_relay([Unknown, One, Two, Four]), Some(Two));
+		assert_eq!(compute_peak_pm_relay([One, Two, Four]), Some(Two));
+		assert_eq!(
+			compute_peak_pm_relay(Level::LEVELS[1..].iter().copied()),
+			Some(Twelve)
+		);
+	}
+}
+// }}}
@@ -1,4 +1,8 @@
 // {{{ Imports
+use std::collections::HashMap;
+use std::str::FromStr;
+use std::sync::atomic::{AtomicU32, Ordering};
+use std::sync::{Arc, RwLock};
 use std::{fmt::Display, num::NonZeroU16};
 
 use anyhow::anyhow;
@@ -58,6 +62,25 @@ impl Display for Difficulty {
 	}
 }
 
+impl FromStr for Difficulty {
+	type Err = Error;
+
+	/// Accepts both shorthands (`FTR`) and full names (`FUTURE`), ignoring
+	/// case and surrounding whitespace.
+	fn from_str(s: &str) -> Result<Self, Self::Err> {
+		let trimmed = s.trim();
+		for (i, difficulty) in Self::DIFFICULTIES.iter().enumerate() {
+			if trimmed.eq_ignore_ascii_case(Self::DIFFICULTY_SHORTHANDS[i])
+				|| trimmed.eq_ignore_ascii_case(Self::DIFFICULTY_STRINGS[i])
+			{
+				return Ok(*difficulty);
+			}
+		}
+
+		Err(anyhow!("Cannot convert {} to difficulty", s))
+	}
+}
+
 pub const DIFFICULTY_MENU_PIXEL_COLORS: [Color; Difficulty::DIFFICULTIES.len()] = [
 	Color::from_rgb_int(0xAAE5F7),
 	Color::from_rgb_int(0xBFDD85),
@@ -117,6 +140,15 @@ impl Level {
 	pub fn to_index(self) -> usize {
 		self as usize
 	}
+
+	/// Iterates over every level between the given bounds (both inclusive),
+	/// in increasing order. [Self::Unknown] is never yielded, and the range
+	/// is empty when `from > to`.
+	pub fn range(from: Self, to: Self) -> impl Iterator<Item = Self> {
+		let start = from.to_index().max(Self::One.to_index());
+		let end = to.to_index() + 1;
+		Self::LEVELS[start..end.max(start)].iter().copied()
+	}
 }
 
 impl Display for Level {
@@ -125,6 +157,23 @@ impl Display for Level {
 	}
 }
 
+impl FromStr for Level {
+	type Err = Error;
+
+	/// Ignores surrounding whitespace, as well as any whitespace between the
+	/// number and the `+` suffix (so `9 +` parses as `9+`).
+	fn from_str(s: &str) -> Result<Self, Self::Err> {
+		let normalized: String = s.chars().filter(|c| !c.is_whitespace()).collect();
+		for (i, level) in Self::LEVEL_STRINGS.iter().enumerate() {
+			if normalized == *level {
+				return Ok(Self::LEVELS[i]);
+			}
+		}
+
+		Err(anyhow!("Cannot convert {} to level", s))
+	}
+}
+
 impl FromSql for Level {
 	fn column_result(value: ValueRef<'_>) -> FromSqlResult<Self> {
 		let str: String = rusqlite::types::FromSql::column_result(value)?;
@@ -142,7 +191,7 @@ impl FromSql for Level {
 }
 // }}}
 // {{{ Side
-#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, poise::ChoiceParameter)]
 pub enum Side {
 	Light,
 	Conflict,
@@ -182,6 +231,11 @@ pub struct Song {
 	pub title: String,
 	pub lowercase_title: String,
 
+	/// The original (usually japanese) title of the song, when it differs
+	/// from the english one.
+	pub og_title: Option<String>,
+	pub lowercase_og_title: Option<String>,
+
 	#[allow(dead_code)]
 	pub artist: String,
 
@@ -215,6 +269,49 @@ pub struct Jacket {
 	pub bitmap: &'static ImageBuffer<Rgb<u8>, Vec<u8>>,
 }
 
+/// Chart constants can be edited at runtime (see `chart set-constant`), hence
+/// the atomic. The value is stored in fixed point (i.e. multiplied by 100).
+#[derive(Debug, Default, Serialize, Deserialize)]
+#[serde(from = "u32", into = "u32")]
+pub struct ChartConstant(AtomicU32);
+
+impl ChartConstant {
+	#[inline]
+	pub fn new(value: u32) -> Self {
+		Self(AtomicU32::new(value))
+	}
+
+	#[inline]
+	pub fn get(&self) -> u32 {
+		self.0.load(Ordering::Relaxed)
+	}
+
+	#[inline]
+	pub fn set(&self, value: u32) {
+		self.0.store(value, Ordering::Relaxed)
+	}
+}
+
+/// Clones get detached from the original, which keeps copies of the song
+/// cache (like the ones used in tests) from affecting each other.
+impl Clone for ChartConstant {
+	fn clone(&self) -> Self {
+		Self::new(self.get())
+	}
+}
+
+impl From<u32> for ChartConstant {
+	fn from(value: u32) -> Self {
+		Self::new(value)
+	}
+}
+
+impl From<ChartConstant> for u32 {
+	fn from(value: ChartConstant) -> Self {
+		value.get()
+	}
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Chart {
 	pub id: u32,
@@ -226,7 +323,7 @@ pub struct Chart {
 	pub level: Level,
 
 	pub note_count: u32,
-	pub chart_constant: u32,
+	pub chart_constant: ChartConstant,
 
 	#[serde(skip)]
 	pub cached_jacket: Option<Jacket>,
@@ -262,10 +359,16 @@ impl CachedSong {
 }
 // }}}
 // {{{ Song cache
+/// Maps chart ids to user-defined (lowercase) nicknames.
+pub type ChartAliases = HashMap<u32, Vec<String>>;
+
 #[derive(Debug, Clone, Default)]
 pub struct SongCache {
 	pub songs: Vec<Option<CachedSong>>,
 	pub charts: Vec<Option<Chart>>,
+
+	/// Aliases can be edited at runtime, hence the lock.
+	pub aliases: Arc<RwLock<ChartAliases>>,
 }
 
 impl SongCache {
@@ -365,6 +468,10 @@ impl SongCache {
 				id: row.get("id")?,
 				lowercase_title: row.get::<_, String>("title")?.to_lowercase(),
 				title: row.get("title")?,
+				lowercase_og_title: row
+					.get::<_, Option<String>>("og_title")?
+					.map(|t| t.to_lowercase()),
+				og_title: row.get("og_title")?,
 				artist: row.get("artist")?,
 				pack: row.get("pack")?,
 				bpm: row.get("bpm")?,
@@ -392,7 +499,7 @@ impl SongCache {
 				shorthand: row.get("shorthand")?,
 				difficulty: row.get("difficulty")?,
 				level: row.get("level")?,
-				chart_constant: row.get("chart_constant")?,
+				chart_constant: ChartConstant::new(row.get("chart_constant")?),
 				note_count: row.get("note_count")?,
 				note_design: row.get("note_design")?,
 				cached_jacket: None,
@@ -421,9 +528,125 @@ impl SongCache {
 			// }}}
 		}
 		// }}}
+		// {{{ Aliases
+		let mut query = conn.prepare_cached("SELECT chart_id, alias FROM aliases")?;
+		let aliases = query.query_map((), |row| {
+			Ok((
+				row.get::<_, u32>("chart_id")?,
+				row.get::<_, String>("alias")?,
+			))
+		})?;
+
+		{
+			let mut cached_aliases = result.aliases.write().unwrap();
+			for alias in aliases {
+				let (chart_id, alias) = alias?;
+				cached_aliases.entry(chart_id).or_default().push(alias);
+			}
+		}
+		// }}}
 
 		Ok(result)
 	}
 	// }}}
+	// {{{ Reload chart constants
+	/// Re-reads every chart constant from the database, returning the number
+	/// of charts whose constant changed. Useful after editing the `charts`
+	/// table by hand (or running the import script) while the bot is live.
+	pub fn reload_chart_constants(&self, conn: &DbConnection) -> Result<usize, Error> {
+		let conn = conn.get()?;
+		let mut query = conn.prepare_cached("SELECT id, chart_constant FROM charts")?;
+		let constants = query.query_map((), |row| {
+			Ok((
+				row.get::<_, u32>("id")?,
+				row.get::<_, u32>("chart_constant")?,
+			))
+		})?;
+
+		let mut changed = 0;
+		for constant in constants {
+			let (chart_id, constant) = constant?;
+			let (_, chart) = self.lookup_chart(chart_id)?;
+
+			if chart.chart_constant.get() != constant {
+				chart.chart_constant.set(constant);
+				changed += 1;
+			}
+		}
+
+		Ok(changed)
+	}
+	// }}}
+}
+// }}}
+// {{{ Tests
+#[cfg(test)]
+mod chart_tests {
+	use super::*;
+
+	#[test]
+	fn difficulty_parsing_is_lenient() -> Result<(), Error> {
+		assert_eq!(Difficulty::from_str("FTR")?, Difficulty::FTR);
+		assert_eq!(Difficulty::from_str("ftr")?, Difficulty::FTR);
+		assert_eq!(Difficulty::from_str("past")?, Difficulty::PST);
+		assert_eq!(Difficulty::from_str("Future")?, Difficulty::FTR);
+		assert_eq!(Difficulty::from_str(" BEYOND ")?, Difficulty::BYD);
+		assert!(Difficulty::from_str("FTRR").is_err());
+		assert!(Difficulty::from_str("").is_err());
+		Ok(())
+	}
+
+	#[test]
+	fn level_ranges() {
+		let range = |from, to| Level::range(from, to).collect::<Vec<_>>();
+
+		assert_eq!(range(Level::Nine, Level::Nine), [Level::Nine]);
+		assert_eq!(range(Level::NineP, Level::NineP), [Level::NineP]);
+		assert_eq!(
+			range(Level::Seven, Level::Eight),
+			[Level::Seven, Level::SevenP, Level::Eight]
+		);
+		assert_eq!(
+			range(Level::SevenP, Level::NineP),
+			[
+				Level::SevenP,
+				Level::Eight,
+				Level::EightP,
+				Level::Nine,
+				Level::NineP
+			]
+		);
+		assert_eq!(
+			range(Level::TenP, Level::Twelve),
+			[Level::TenP, Level::Eleven, Level::Twelve]
+		);
+		assert_eq!(range(Level::Unknown, Level::Two), [Level::One, Level::Two]);
+		assert!(range(Level::Unknown, Level::Unknown).is_empty());
+		assert!(range(Level::Ten, Level::Nine).is_empty());
+		assert_eq!(range(Level::One, Level::Twelve), Level::LEVELS[1..]);
+	}
+
+	#[test]
+	fn level_parsing_is_lenient() -> Result<(), Error> {
+		assert_eq!(Level::from_str("7+")?, Level::SevenP);
+		assert_eq!(Level::from_str(" 10 + ")?, Level::TenP);
+		assert_eq!(Level::from_str("12")?, Level::Twelve);
+		assert!(Level::from_str("13").is_err());
+		assert!(Level::from_str("9++").is_err());
+		Ok(())
+	}
+
+	#[test]
+	fn display_round_trips() -> Result<(), Error> {
+		for difficulty in Difficulty::DIFFICULTIES {
+			assert_eq!(Difficulty::from_str(&difficulty.to_string())?, difficulty);
+		}
+
+		for level in Level::LEVELS {
+			assert_eq!(Level::from_str(&level.to_string())?, level);
+		}
+
+		Ok(())
+	}
 }
 // }}}
@@ -1,7 +1,7 @@
 // {{{ Imports
 use std::fs;
 
-use anyhow::Context;
+use anyhow::{anyhow, bail, Context};
 use faer::{Mat, MatRef};
 use image::{GenericImageView, Pixel};
 use num::{Integer, ToPrimitive};
@@ -10,14 +10,38 @@ use serde::{Deserialize, Serialize};
 use crate::arcaea::chart::{Difficulty, Jacket, SongCache};
 use crate::assets::get_asset_dir;
 use crate::context::Error;
+use crate::metrics;
 // }}}
 
 /// How many sub-segments to split each side into
 pub const SPLIT_FACTOR: u32 = 8;
 pub const IMAGE_VEC_DIM: usize = (SPLIT_FACTOR * SPLIT_FACTOR * 3) as usize;
+
+/// The side length of the prepared jacket bitmaps (`{difficulty}_{size}.jpg`),
+/// which get used both for recognition and for rendering.
+///
+/// Changing this requires re-running `prepare-jackets`, as the stored
+/// recognition matrix remembers the size it was built for (see
+/// [JacketCache::bitmap_size]).
 pub const BITMAP_IMAGE_SIZE: u32 = 174;
 pub const JACKET_RECOGNITITION_DIMENSIONS: usize = 20;
 
+/// The version of the on-disk [JacketCache] layout. Must be bumped whenever
+/// the layout or the way images get encoded (see [image_to_vec]) changes, as
+/// older recognition matrices cannot be used anymore after such changes.
+pub const JACKET_CACHE_VERSION: u32 = 1;
+/// Matches whose (projected) distance exceeds this fraction of the rejection
+/// threshold are double-checked in the full, unprojected image space.
+pub const JACKET_UNCERTAINTY_RATIO: f32 = 0.75;
+
+/// The rejection threshold used when it cannot be computed from the jackets
+/// themselves (i.e. when every jacket belongs to the same song).
+pub const DEFAULT_JACKET_REJECTION_THRESHOLD: f32 = (IMAGE_VEC_DIM * 3) as f32;
+
+/// Which percentile of the distances between each jacket and the closest
+/// jacket of a different song to use as the rejection threshold.
+pub const JACKET_REJECTION_PERCENTILE: f32 = 0.5;
+
 // {{{ (Image => vector) encoding
 #[allow(clippy::identity_op)]
 pub fn image_to_vec(image: &impl GenericImageView) -> MVec<f32> {
@@ -71,6 +95,13 @@ pub type MVec<T> = Mat<T>;
 /// - an projection matrix for dimensionality reduction
 #[derive(Clone, Serialize, Deserialize)]
 pub struct JacketCache {
+	/// The [JACKET_CACHE_VERSION] this cache was generated with. Kept as the
+	/// first field, such that it can be read without decoding everything else.
+	pub version: u32,
+
+	/// The [BITMAP_IMAGE_SIZE] this cache was generated with.
+	pub bitmap_size: u32,
+
 	/// A matrix with each column corresponding to the result of passing a jacket
 	/// through [[image_to_vec]], and then projecting it through `transform_matrix`
 	pub jacket_matrix: Mat<f32>,
@@ -80,8 +111,113 @@ pub struct JacketCache {
 
 	/// A projection matrix for dimensionality reduction.
 	pub transform_matrix: Mat<f32>,
+
+	/// Matches further away than this (in the projected space) get rejected.
+	/// See [compute_rejection_threshold].
+	pub rejection_threshold: f32,
+
+	/// Unprojected vectors for every known jacket, used to double-check
+	/// uncertain matches. These are not saved to disk, and are instead
+	/// computed at startup (see [JacketCache::index_raw_vectors]).
+	#[serde(skip)]
+	pub raw_vectors: Vec<(u32, MVec<f32>)>,
+}
+
+/// The fields every version of [JacketCache] starts with. Postcard is not
+/// self-describing, so we have to check these before decoding the rest.
+#[derive(Deserialize)]
+struct JacketCacheHeader {
+	version: u32,
+	bitmap_size: u32,
 }
 
+/// Computes a rejection threshold adapted to the (projected) jacket matrix,
+/// by looking at how far each jacket is from the closest jacket belonging to
+/// a different song, and taking the [JACKET_REJECTION_PERCENTILE] percentile
+/// of said distances.
+pub fn compute_rejection_threshold(jacket_matrix: MatRef<f32>, jacket_ids: &[u32]) -> f32 {
+	let mut distances: Vec<f32> = (0..jacket_matrix.ncols())
+		.filter_map(|i| {
+			(0..jacket_matrix.ncols())
+				.filter(|j| jacket_ids[*j] != jacket_ids[i])
+				.map(|j| {
+					(jacket_matrix.subcols(i, 1) - jacket_matrix.subcols(j, 1))
+						.squared_norm_l2()
+						.sqrt()
+				})
+				.min_by(|d1, d2| d1.partial_cmp(d2).expect("NaN distance encountered"))
+		})
+		.collect();
+
+	if distances.is_empty() {
+		return DEFAULT_JACKET_REJECTION_THRESHOLD;
+	}
+
+	distances.sort_by(|d1, d2| d1.partial_cmp(d2).expect("NaN distance encountered"));
+	let index = (distances.len() - 1) as f32 * JACKET_REJECTION_PERCENTILE;
+	distances[index.round() as usize]
+}
+
+// {{{ Jacket files
+/// The different kinds of raw jacket files found inside a song directory,
+/// ordered from most to least preferred.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum JacketFile {
+	/// A jacket specific to a single difficulty (`0.jpg` to `4.jpg`).
+	Difficulty(Difficulty),
+	/// The default jacket of the song (`base.jpg`).
+	Base,
+	/// Alternative default jackets (`base_night.jpg`, `base_ja.jpg`).
+	Alternate,
+}
+
+impl JacketFile {
+	/// Parses the name of a raw jacket file (without the extension).
+	pub fn from_file_stem(name: &str) -> Option<Self> {
+		match name {
+			"0" => Some(Self::Difficulty(Difficulty::PST)),
+			"1" => Some(Self::Difficulty(Difficulty::PRS)),
+			"2" => Some(Self::Difficulty(Difficulty::FTR)),
+			"3" => Some(Self::Difficulty(Difficulty::BYD)),
+			"4" => Some(Self::Difficulty(Difficulty::ETR)),
+			"base" => Some(Self::Base),
+			"base_night" | "base_ja" => Some(Self::Alternate),
+			_ => None,
+		}
+	}
+
+	/// The difficulty this jacket is specific to, or [None] for default jackets.
+	#[inline]
+	pub fn difficulty(self) -> Option<Difficulty> {
+		match self {
+			Self::Difficulty(difficulty) => Some(difficulty),
+			_ => None,
+		}
+	}
+}
+
+/// Decides which of the raw jacket files of a song get used. The fallback
+/// order is as follows:
+/// 1. charts with a difficulty-specific jacket always use it
+/// 2. every other chart uses `base`
+/// 3. `base_night`/`base_ja` are only used when no `base` jacket exists,
+///    in which case the first one (in the order given) wins.
+///
+/// This way, every chart ends up with a jacket as long as the song has at
+/// least one default jacket of any kind.
+pub fn select_jacket_files<T>(mut files: Vec<(JacketFile, T)>) -> Vec<(JacketFile, T)> {
+	// The sort is stable, so ties keep the order they were given in
+	files.sort_by_key(|(kind, _)| *kind);
+
+	let mut has_default = false;
+	files.retain(|(kind, _)| match kind {
+		JacketFile::Difficulty(_) => true,
+		_ => !std::mem::replace(&mut has_default, true),
+	});
+
+	files
+}
+// }}}
 // {{{ Read jackets
 pub fn read_jackets(song_cache: &mut SongCache) -> Result<(), Error> {
 	let suffix = format!("_{BITMAP_IMAGE_SIZE}.jpg");
@@ -120,26 +256,41 @@ pub fn read_jackets(song_cache: &mut SongCache) -> Result<(), Error> {
 				.with_context(|| "Could not load jacket image from prepared bytes")?;
 			let bitmap: &'static _ = Box::leak(Box::new(image.into_rgb8()));
 
-			if let Some(difficulty) = difficulty {
-				let chart = song_cache
-					.lookup_by_difficulty_mut(song_id, difficulty)
-					.unwrap();
-				chart.jacket_source = Some(difficulty);
-				chart.cached_jacket = Some(Jacket {
+			assign_jacket(
+				song_cache,
+				song_id,
+				difficulty,
+				Jacket {
 					raw: contents,
 					bitmap,
-				});
-			} else {
-				for (_, chart_id) in song_cache.lookup_song(song_id)?.charts() {
-					let chart = song_cache.lookup_chart_mut(chart_id)?;
-					if chart.jacket_source.is_none() {
-						chart.cached_jacket = Some(Jacket {
-							raw: contents,
-							bitmap,
-						});
-						chart.jacket_source = None;
-					}
-				}
+				},
+			)?;
+		}
+	}
+
+	Ok(())
+}
+
+/// Attaches a jacket to the charts it belongs to. Jackets without a
+/// difficulty are used for every chart that has no jacket of its own.
+///
+/// See [select_jacket_files] for how the default jacket gets chosen.
+pub fn assign_jacket(
+	song_cache: &mut SongCache,
+	song_id: u32,
+	difficulty: Option<Difficulty>,
+	jacket: Jacket,
+) -> Result<(), Error> {
+	if let Some(difficulty) = difficulty {
+		let chart = song_cache.lookup_by_difficulty_mut(song_id, difficulty)?;
+		chart.jacket_source = Some(difficulty);
+		chart.cached_jacket = Some(jacket);
+	} else {
+		for (_, chart_id) in song_cache.lookup_song(song_id)?.charts() {
+			let chart = song_cache.lookup_chart_mut(chart_id)?;
+			if chart.jacket_source.is_none() {
+				chart.cached_jacket = Some(jacket);
+				chart.jacket_source = None;
 			}
 		}
 	}
@@ -154,8 +305,33 @@ impl JacketCache {
 		let bytes = fs::read(get_asset_dir().join("songs/recognition_matrix"))
 			.with_context(|| "Could not read jacket recognition matrix")?;
 
-		let result = postcard::from_bytes(&bytes)?;
-		// .with_context(|| "Could not decode jacket recognition matrix")?;
+		Self::decode(&bytes)
+	}
+
+	/// Decodes a recognition matrix, refusing matrices generated by an
+	/// incompatible version of the bot, or for a different bitmap size.
+	pub fn decode(bytes: &[u8]) -> Result<Self, Error> {
+		// Matrices generated before the version field existed start with the
+		// dimensions of the jacket matrix instead, which never match.
+		let header = postcard::take_from_bytes::<JacketCacheHeader>(bytes)
+			.ok()
+			.map(|(header, _)| header)
+			.filter(|header| header.version == JACKET_CACHE_VERSION)
+			.ok_or_else(|| {
+				anyhow!(
+					"The jacket recognition matrix is outdated (expected version {JACKET_CACHE_VERSION}). Run `shimmeringmoon-cli prepare-jackets` to regenerate it"
+				)
+			})?;
+
+		if header.bitmap_size != BITMAP_IMAGE_SIZE {
+			bail!(
+				"The jacket recognition matrix was generated for {}px jackets, but {BITMAP_IMAGE_SIZE}px jackets are in use. Run `shimmeringmoon-cli prepare-jackets` to regenerate it",
+				header.bitmap_size
+			);
+		}
+
+		let result = postcard::from_bytes(bytes)
+			.with_context(|| "Could not decode jacket recognition matrix")?;
 
 		Ok(result)
 	}
@@ -182,4 +358,206 @@ impl JacketCache {
 			.map(|(i, d)| (d.sqrt(), *i))
 	}
 	// }}}
+	// {{{ Unprojected recognition
+	/// Computes the unprojected vectors of every jacket present in the song
+	/// cache. Must be called after [read_jackets].
+	pub fn index_raw_vectors(&mut self, song_cache: &SongCache) {
+		self.raw_vectors.clear();
+		for chart in song_cache.charts() {
+			if let Some(jacket) = chart.cached_jacket {
+				self.raw_vectors
+					.push((chart.song_id, image_to_vec(jacket.bitmap)));
+			}
+		}
+	}
+
+	/// Similar to [Self::recognise], except the nearest neighbour is searched
+	/// for in the full image space.
+	///
+	/// We only have about a thousand jackets, so a linear scan is fast enough.
+	#[inline]
+	pub fn recognise_unprojected(&self, image: &impl GenericImageView) -> Option<(f32, u32)> {
+		let vec = image_to_vec(image);
+		self.raw_vectors
+			.iter()
+			.map(|(id, v)| (id, (v.as_ref() - &vec).squared_norm_l2()))
+			.min_by(|(_, d1), (_, d2)| d1.partial_cmp(d2).expect("NaN distance encountered"))
+			.map(|(i, d)| (d.sqrt(), *i))
+	}
+
+	/// Checks whether the unprojected nearest neighbour agrees with the given
+	/// (projected) match. How often the two methods disagree gets recorded
+	/// (see [metrics::record_jacket_double_check]).
+	pub fn double_check(&self, image: &impl GenericImageView, song_id: u32) -> bool {
+		let agrees = self
+			.recognise_unprojected(image)
+			.map_or(false, |(_, raw_song_id)| raw_song_id == song_id);
+
+		metrics::record_jacket_double_check(agrees);
+
+		agrees
+	}
+	// }}}
 }
+// {{{ Tests
+#[cfg(test)]
+mod jacket_tests {
+	use image::RgbImage;
+
+	use crate::context::testing::get_mock_context;
+	use crate::recognition::fuzzy_song_name::guess_song_and_chart;
+
+	use super::*;
+
+	fn dummy_jacket() -> Jacket {
+		Jacket {
+			raw: &[],
+			bitmap: Box::leak(Box::new(RgbImage::new(1, 1))),
+		}
+	}
+
+	#[test]
+	fn base_takes_precedence_over_alternates() {
+		let files = vec![
+			(JacketFile::Alternate, "base_ja"),
+			(JacketFile::Difficulty(Difficulty::BYD), "3"),
+			(JacketFile::Base, "base"),
+			(JacketFile::Alternate, "base_night"),
+		];
+
+		let selected: Vec<_> = select_jacket_files(files)
+			.into_iter()
+			.map(|(_, name)| name)
+			.collect();
+		assert_eq!(selected, ["3", "base"]);
+	}
+
+	#[test]
+	fn rejection_threshold_ignores_jackets_of_the_same_song() {
+		let positions = [0.0, 1.0, 10.0, 14.0];
+		let matrix = Mat::from_fn(1, positions.len(), |_, j| positions[j]);
+
+		// The first two jackets belong to the same song, so their distance
+		// to each other must not be taken into account.
+		let threshold = compute_rejection_threshold(matrix.as_ref(), &[1, 1, 2, 3]);
+		assert_eq!(threshold, 9.0);
+
+		assert_eq!(
+			compute_rejection_threshold(matrix.as_ref(), &[1, 1, 1, 1]),
+			DEFAULT_JACKET_REJECTION_THRESHOLD
+		);
+	}
+
+	#[tokio::test]
+	async fn recognition_self_test() -> Result<(), Error> {
+		let (ctx, _guard) = get_mock_context().await?;
+		let song_cache = &ctx.data.song_cache;
+		let jacket_cache = &ctx.data.jacket_cache;
+		assert_eq!(jacket_cache.bitmap_size, BITMAP_IMAGE_SIZE);
+
+		for chart in song_cache.charts() {
+			let Some(jacket) = chart.cached_jacket else {
+				continue;
+			};
+
+			assert_eq!(
+				jacket.bitmap.dimensions(),
+				(BITMAP_IMAGE_SIZE, BITMAP_IMAGE_SIZE)
+			);
+
+			let song = &song_cache.lookup_song(chart.song_id)?.song;
+			let recognised = jacket_cache.recognise(jacket.bitmap).map(|(_, id)| id);
+			assert_eq!(
+				recognised,
+				Some(chart.song_id),
+				"Could not recognise jacket for {song} [{}]",
+				chart.difficulty
+			);
+		}
+
+		Ok(())
+	}
+
+	#[test]
+	fn double_check_overrides_wrong_projected_matches() {
+		let black = RgbImage::new(8, 8);
+		let gray = RgbImage::from_pixel(8, 8, image::Rgb([200, 200, 200]));
+		let query = RgbImage::from_pixel(8, 8, image::Rgb([190, 190, 190]));
+
+		// The projection throws everything away, which makes the projected
+		// match depend solely on the (made up) projected jacket positions
+		let mut jacket_matrix = Mat::zeros(1, 2);
+		jacket_matrix[(0, 0)] = 3.0;
+		jacket_matrix[(0, 1)] = 5.0;
+
+		let cache = JacketCache {
+			version: JACKET_CACHE_VERSION,
+			bitmap_size: BITMAP_IMAGE_SIZE,
+			jacket_matrix,
+			jacket_ids: vec![1, 2],
+			transform_matrix: Mat::zeros(1, IMAGE_VEC_DIM),
+			rejection_threshold: 3.5,
+			raw_vectors: vec![(1, image_to_vec(&black)), (2, image_to_vec(&gray))],
+		};
+
+		// The projected match is wrong, but uncertain enough to get checked...
+		let (distance, song_id) = cache.recognise(&query).unwrap();
+		assert_eq!(song_id, 1);
+		assert!(distance > cache.rejection_threshold * JACKET_UNCERTAINTY_RATIO);
+		assert!(distance <= cache.rejection_threshold);
+
+		// ...at which point the unprojected nearest neighbour overrules it
+		assert_eq!(cache.recognise_unprojected(&query).unwrap().1, 2);
+		assert!(!cache.double_check(&query, song_id));
+		assert!(cache.double_check(&query, 2));
+	}
+
+	#[test]
+	fn outdated_caches_get_rejected() {
+		let encode = |version: u32, bitmap_size: u32| {
+			let cache = JacketCache {
+				version,
+				bitmap_size,
+				jacket_matrix: Mat::zeros(1, 1),
+				jacket_ids: vec![1],
+				transform_matrix: Mat::zeros(1, 1),
+				rejection_threshold: DEFAULT_JACKET_REJECTION_THRESHOLD,
+				raw_vectors: Vec::new(),
+			};
+
+			postcard::to_allocvec(&cache).unwrap()
+		};
+
+		let decoded = JacketCache::decode(&encode(JACKET_CACHE_VERSION, BITMAP_IMAGE_SIZE));
+		assert_eq!(decoded.unwrap().jacket_ids, [1]);
+
+		assert!(JacketCache::decode(&encode(JACKET_CACHE_VERSION + 1, BITMAP_IMAGE_SIZE)).is_err());
+		assert!(JacketCache::decode(&encode(JACKET_CACHE_VERSION, BITMAP_IMAGE_SIZE + 1)).is_err());
+	}
+
+	#[tokio::test]
+	async fn alternate_only_jacket_covers_every_chart() -> Result<(), Error> {
+		let (mut ctx, _guard) = get_mock_context().await?;
+		let song_id = guess_song_and_chart(&ctx.data, "Pentiment")?.0.id;
+
+		let song_cache = &mut ctx.data.song_cache;
+		for chart in song_cache.charts_mut().filter(|c| c.song_id == song_id) {
+			chart.cached_jacket = None;
+			chart.jacket_source = None;
+		}
+
+		let files = vec![(JacketFile::from_file_stem("base_night").unwrap(), ())];
+		for (kind, _) in select_jacket_files(files) {
+			assign_jacket(song_cache, song_id, kind.difficulty(), dummy_jacket())?;
+		}
+
+		let cached_song = song_cache.lookup_song(song_id)?;
+		for (_, chart_id) in cached_song.charts() {
+			let (_, chart) = song_cache.lookup_chart(chart_id)?;
+			assert!(chart.cached_jacket.is_some());
+		}
+
+		Ok(())
+	}
+}
+// }}}
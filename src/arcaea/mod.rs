@@ -4,3 +4,4 @@ pub mod jacket;
 pub mod play;
 pub mod rating;
 pub mod score;
+pub mod scoring;
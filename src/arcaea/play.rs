@@ -1,6 +1,9 @@
 // {{{ Imports
 use std::array;
+use std::collections::HashMap;
+use std::fmt::Display;
 use std::num::NonZeroU64;
+use std::sync::{Arc, RwLock};
 
 use anyhow::anyhow;
 use anyhow::Context;
@@ -11,21 +14,87 @@ use num::CheckedDiv;
 use num::Rational32;
 use num::Zero;
 use poise::serenity_prelude::{CreateAttachment, CreateEmbed, CreateEmbedAuthor, Timestamp};
-use rusqlite::Row;
+use r2d2::PooledConnection;
+use r2d2_sqlite::SqliteConnectionManager;
+use rusqlite::types::{FromSql, FromSqlError, FromSqlResult, ValueRef};
+use rusqlite::{Connection, Row};
 use serde::Deserialize;
 use serde::Serialize;
 
-use crate::arcaea::chart::{Chart, Song};
+use crate::arcaea::chart::{Chart, Side, Song};
 use crate::context::ErrorKind;
 use crate::context::TagError;
 use crate::context::TaggedError;
 use crate::context::{Error, UserContext};
 use crate::user::User;
 
-use super::rating::{rating_as_fixed, rating_as_float};
+use super::rating::rating_as_fixed;
 use super::score::{Score, ScoringSystem};
 // }}}
 
+// {{{ Clear kind
+/// How a run ended, as shown by the banner on the score screen.
+///
+/// The order matches the first few values of the `clear_type` field used by
+/// Arcaea private servers.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+pub enum ClearKind {
+	TrackLost,
+	NormalClear,
+	FullRecall,
+	PureMemory,
+}
+
+impl ClearKind {
+	pub const CLEAR_KINDS: [Self; 4] = [
+		Self::TrackLost,
+		Self::NormalClear,
+		Self::FullRecall,
+		Self::PureMemory,
+	];
+
+	/// The text shown on the score screen banner.
+	pub const CLEAR_KIND_BANNERS: [&'static str; 4] =
+		["TRACK LOST", "TRACK COMPLETE", "FULL RECALL", "PURE MEMORY"];
+
+	pub const CLEAR_KIND_DB_STRINGS: [&'static str; 4] =
+		["track_lost", "normal_clear", "full_recall", "pure_memory"];
+
+	#[inline]
+	pub fn to_index(self) -> usize {
+		self as usize
+	}
+}
+
+impl Display for ClearKind {
+	fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+		let name = match self {
+			Self::TrackLost => "Track Lost",
+			Self::NormalClear => "Track Complete",
+			Self::FullRecall => "Full Recall",
+			Self::PureMemory => "Pure Memory",
+		};
+
+		write!(f, "{name}")
+	}
+}
+
+impl FromSql for ClearKind {
+	fn column_result(value: ValueRef<'_>) -> FromSqlResult<Self> {
+		let str: String = FromSql::column_result(value)?;
+
+		for (i, s) in Self::CLEAR_KIND_DB_STRINGS.iter().enumerate() {
+			if str == **s {
+				return Ok(Self::CLEAR_KINDS[i]);
+			}
+		}
+
+		FromSqlResult::Err(FromSqlError::Other(
+			format!("Cannot convert {} to clear kind", str).into(),
+		))
+	}
+}
+// }}}
 // {{{ Create play
 #[derive(Debug, Clone)]
 pub struct CreatePlay {
@@ -35,6 +104,9 @@ pub struct CreatePlay {
 	score: Score,
 	max_recall: Option<u32>,
 	far_notes: Option<u32>,
+	pure_notes: Option<u32>,
+	lost_notes: Option<u32>,
+	clear_kind: Option<ClearKind>,
 }
 
 impl CreatePlay {
@@ -45,6 +117,9 @@ impl CreatePlay {
 			score,
 			max_recall: None,
 			far_notes: None,
+			pure_notes: None,
+			lost_notes: None,
+			clear_kind: None,
 		}
 	}
 
@@ -60,26 +135,57 @@ impl CreatePlay {
 		self
 	}
 
+	/// Stores the full (pure, far, lost) distribution read off a screenshot.
+	#[inline]
+	pub fn with_distribution(mut self, distribution: Option<(u32, u32, u32)>) -> Self {
+		if let Some((pures, fars, losts)) = distribution {
+			self.pure_notes = Some(pures);
+			self.far_notes = Some(fars);
+			self.lost_notes = Some(losts);
+		}
+
+		self
+	}
+
 	#[inline]
 	pub fn with_max_recall(mut self, max_recall: Option<u32>) -> Self {
 		self.max_recall = max_recall;
 		self
 	}
 
+	#[inline]
+	pub fn with_clear_kind(mut self, clear_kind: Option<ClearKind>) -> Self {
+		self.clear_kind = clear_kind;
+		self
+	}
+
+	#[inline]
+	pub fn score(&self) -> Score {
+		self.score
+	}
+
+	#[inline]
+	pub fn far_notes(&self) -> Option<u32> {
+		self.far_notes
+	}
+
 	// {{{ Save
+	/// Saves the play alongside its scores. Everything happens inside a single
+	/// transaction, so a play can never end up without its scores.
 	pub fn save(self, ctx: &UserContext, user: &User, chart: &Chart) -> Result<Play, TaggedError> {
-		let conn = ctx.db.get()?;
+		let mut conn = ctx.db.get()?;
+		let tx = conn.transaction()?;
 		let attachment_id = self.discord_attachment_id.map(|i| i.get() as i64);
 
 		// {{{ Save current data to play
-		let (id, created_at) = conn
+		let (id, created_at) = tx
 			.prepare_cached(
 				"
         INSERT INTO plays(
             user_id,chart_id,discord_attachment_id,
-            max_recall,far_notes
+            max_recall,far_notes,pure_notes,lost_notes,clear_kind
         )
-        VALUES(?,?,?,?,?)
+        VALUES(?,?,?,?,?,?,?,?)
         RETURNING id, created_at
       ",
 			)?
@@ -90,6 +196,10 @@ impl CreatePlay {
 					attachment_id,
 					self.max_recall,
 					self.far_notes,
+					self.pure_notes,
+					self.lost_notes,
+					self.clear_kind
+						.map(|kind| ClearKind::CLEAR_KIND_DB_STRINGS[kind.to_index()]),
 				),
 				|row| {
 					Ok((
@@ -110,15 +220,9 @@ impl CreatePlay {
 
 		for system in ScoringSystem::SCORING_SYSTEMS {
 			let i = system.to_index();
-			let creation_ptt = try_compute_ptt(ctx, user.id, system, None)?;
+			let creation_ptt = try_compute_ptt_in(&tx, ctx, user.id, system, None)?;
 
-			conn.prepare_cached(
-				"
-          INSERT INTO scores(play_id, score, creation_ptt, scoring_system)
-          VALUES (?,?,?,?)
-        ",
-			)?
-			.execute((
+			tx.prepare_cached(UPSERT_SCORE_QUERY)?.execute((
 				id,
 				scores.0[i].0,
 				creation_ptt,
@@ -127,6 +231,7 @@ impl CreatePlay {
 		}
 
 		// }}}
+		tx.commit()?;
 
 		Ok(Play {
 			id,
@@ -136,12 +241,71 @@ impl CreatePlay {
 			user_id: user.id,
 			max_recall: self.max_recall,
 			far_notes: self.far_notes,
+			pure_notes: self.pure_notes,
+			lost_notes: self.lost_notes,
+			clear_kind: self.clear_kind,
 		})
 	}
 	// }}}
+	// {{{ Update
+	/// Overwrites the details of an existing play, keeping its id, owner,
+	/// attachment and creation date. Only the standard score gets updated, so
+	/// callers must run [generate_missing_scores] afterwards, which also
+	/// recomputes the creation ptt of every later play.
+	pub fn update(self, ctx: &UserContext, play_id: u32, chart: &Chart) -> Result<(), TaggedError> {
+		let mut conn = ctx.db.get()?;
+		let tx = conn.transaction()?;
+
+		tx.prepare_cached(
+			"
+        UPDATE plays
+        SET chart_id=?, max_recall=?, far_notes=?, pure_notes=?, lost_notes=?, clear_kind=?
+        WHERE id=?
+      ",
+		)?
+		.execute((
+			chart.id,
+			self.max_recall,
+			self.far_notes,
+			self.pure_notes,
+			self.lost_notes,
+			self.clear_kind
+				.map(|kind| ClearKind::CLEAR_KIND_DB_STRINGS[kind.to_index()]),
+			play_id,
+		))
+		.with_context(|| format!("Could not update play {play_id} with {self:?}"))?;
+
+		tx.prepare_cached(
+			"
+        UPDATE scores
+        SET score=?
+        WHERE play_id=?
+        AND scoring_system='standard'
+      ",
+		)?
+		.execute((self.score.0, play_id))?;
+
+		tx.commit()?;
+
+		Ok(())
+	}
+	// }}}
 }
 // }}}
 // {{{ Score data
+/// Inserts a score, overwriting the existing one (if any) for the same play
+/// and scoring system. Expects the play id, the score, the creation ptt, and
+/// the scoring system (in this order).
+pub const UPSERT_SCORE_QUERY: &str = "
+  INSERT INTO scores(play_id, score, creation_ptt, scoring_system)
+  VALUES ($1, $2, $3, $4)
+  ON CONFLICT(play_id, scoring_system)
+    DO UPDATE SET
+      score=$2, creation_ptt=$3
+    WHERE play_id = $1
+    AND scoring_system = $4
+";
+
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
 pub struct ScoreCollection([Score; ScoringSystem::SCORING_SYSTEMS.len()]);
 
@@ -157,7 +321,6 @@ impl ScoreCollection {
 #[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
 pub struct Play {
 	pub id: u32,
-	#[allow(unused)]
 	pub chart_id: u32,
 	pub user_id: u32,
 	pub created_at: chrono::NaiveDateTime,
@@ -165,6 +328,9 @@ pub struct Play {
 	// Score details
 	pub max_recall: Option<u32>,
 	pub far_notes: Option<u32>,
+	pub pure_notes: Option<u32>,
+	pub lost_notes: Option<u32>,
+	pub clear_kind: Option<ClearKind>,
 	pub scores: ScoreCollection,
 }
 
@@ -190,6 +356,9 @@ impl Play {
 			user_id: row.get("user_id")?,
 			max_recall: row.get("max_recall")?,
 			far_notes: row.get("far_notes")?,
+			pure_notes: row.get("pure_notes")?,
+			lost_notes: row.get("lost_notes")?,
+			clear_kind: row.get("clear_kind")?,
 			scores: ScoreCollection::from_standard_score(Score(row.get("score")?), chart),
 			created_at: default_while_testing(row.get("created_at")?),
 		})
@@ -205,16 +374,18 @@ impl Play {
 	pub fn play_rating(&self, system: ScoringSystem, chart_constant: u32) -> Rational32 {
 		self.score(system).play_rating(chart_constant)
 	}
-
-	#[inline]
-	pub fn play_rating_f32(&self, system: ScoringSystem, chart_constant: u32) -> f32 {
-		rating_as_float(self.score(system).play_rating(chart_constant))
-	}
 	// }}}
 	// {{{ Play => distribution
 	pub fn distribution(&self, note_count: u32) -> Option<(u32, u32, u32, u32)> {
 		if let Some(fars) = self.far_notes {
 			let (_, shinies, units) = self.score(ScoringSystem::Standard).analyse(note_count);
+
+			// Prefer the counts read off the screenshot when we have them
+			if let (Some(pures), Some(lost)) = (self.pure_notes, self.lost_notes) {
+				let non_max_pures = pures.checked_sub(shinies)?;
+				return Some((shinies, non_max_pures, fars, lost));
+			}
+
 			let (pures, rem) = units.checked_sub(fars)?.div_rem_euclid(&2);
 			if rem == 1 {
 				println!("The impossible happened: got an invalid amount of far notes!");
@@ -229,51 +400,115 @@ impl Play {
 		}
 	}
 	// }}}
-	// {{{ Play => status
+	// {{{ Play => shinies
+	/// Shows the amount of shinies (max pures) read off the standard score.
+	/// When the distribution is known, the total amount of pures is included
+	/// as well.
+	fn display_shinies(&self, note_count: u32) -> String {
+		let shinies = self.score(ScoringSystem::Standard).shinies(note_count);
+		match self.distribution(note_count) {
+			Some((_, non_max_pures, _, _)) => {
+				format!("{shinies} of {} pures", shinies + non_max_pures)
+			}
+			None => format!("{shinies}"),
+		}
+	}
+	// }}}
+	// {{{ Play => clear kind
+	/// How many (non-shiny) pures a pure memory is short of a max pure memory,
+	/// or [None] if the score is not a (valid) pure memory.
 	#[inline]
-	pub fn status(&self, scoring_system: ScoringSystem, chart: &Chart) -> Option<String> {
+	fn non_max_pures(&self, scoring_system: ScoringSystem, chart: &Chart) -> Option<u32> {
 		let score = self.score(scoring_system).0;
-		if score >= 10_000_000 {
-			if score > chart.note_count + 10_000_000 {
-				return None;
-			}
+		if score < 10_000_000 {
+			return None;
+		}
 
-			let non_max_pures = (chart.note_count + 10_000_000).checked_sub(score)?;
-			if non_max_pures == 0 {
-				Some("MPM".to_string())
-			} else {
-				Some(format!("PM (-{})", non_max_pures))
-			}
-		} else if let Some(distribution) = self.distribution(chart.note_count) {
-			// if no lost notes...
-			if distribution.3 == 0 {
-				Some(format!("FR (-{}/-{})", distribution.1, distribution.2))
+		(chart.note_count + 10_000_000).checked_sub(score)
+	}
+
+	/// Classifies the play using the stored score and distribution.
+	///
+	/// Track losts cannot be told apart from clears this way, so they are
+	/// only ever returned when the banner on the screenshot said so. Said
+	/// banner is also used as a fallback when no distribution is known.
+	pub fn derived_clear_kind(
+		&self,
+		scoring_system: ScoringSystem,
+		chart: &Chart,
+	) -> Option<ClearKind> {
+		if self.clear_kind == Some(ClearKind::TrackLost) {
+			Some(ClearKind::TrackLost)
+		} else if self.score(scoring_system).0 >= 10_000_000 {
+			self.non_max_pures(scoring_system, chart)
+				.map(|_| ClearKind::PureMemory)
+		} else if let Some((_, _, _, lost)) = self.distribution(chart.note_count) {
+			if lost == 0 {
+				Some(ClearKind::FullRecall)
 			} else {
-				Some(format!(
-					"C (-{}/-{}/-{})",
-					distribution.1, distribution.2, distribution.3
-				))
+				Some(ClearKind::NormalClear)
 			}
 		} else {
-			None
+			self.clear_kind
+				.filter(|kind| matches!(kind, ClearKind::FullRecall | ClearKind::NormalClear))
 		}
 	}
+	// }}}
+	// {{{ Play => status
+	#[inline]
+	pub fn status(&self, scoring_system: ScoringSystem, chart: &Chart) -> Option<String> {
+		let distribution = self.distribution(chart.note_count);
+		let status = match self.derived_clear_kind(scoring_system, chart)? {
+			ClearKind::PureMemory => {
+				let non_max_pures = self.non_max_pures(scoring_system, chart)?;
+				if non_max_pures == 0 {
+					"MPM".to_string()
+				} else {
+					format!("PM (-{})", non_max_pures)
+				}
+			}
+			ClearKind::FullRecall => match distribution {
+				Some((_, non_max_pures, fars, _)) => format!("FR (-{non_max_pures}/-{fars})"),
+				None => "FR".to_string(),
+			},
+			ClearKind::NormalClear | ClearKind::TrackLost => {
+				let prefix = if self.clear_kind == Some(ClearKind::TrackLost) {
+					"TL"
+				} else {
+					"C"
+				};
+
+				match distribution {
+					Some((_, non_max_pures, fars, lost)) => {
+						format!("{prefix} (-{non_max_pures}/-{fars}/-{lost})")
+					}
+					None => prefix.to_string(),
+				}
+			}
+		};
+
+		Some(status)
+	}
 
 	#[inline]
 	pub fn short_status(&self, scoring_system: ScoringSystem, chart: &Chart) -> Option<char> {
-		let score = self.score(scoring_system).0;
-		if score >= 10_000_000 {
-			let non_max_pures = (chart.note_count + 10_000_000).checked_sub(score)?;
-			if non_max_pures == 0 {
-				Some('M')
-			} else {
-				Some('P')
+		let status = match self.derived_clear_kind(scoring_system, chart) {
+			Some(ClearKind::TrackLost) => 'L',
+			Some(ClearKind::PureMemory) => {
+				if self.non_max_pures(scoring_system, chart)? == 0 {
+					'M'
+				} else {
+					'P'
+				}
 			}
-		} else if let Some((_, _, _, 0)) = self.distribution(chart.note_count) {
-			Some('F')
-		} else {
-			Some('C')
-		}
+			Some(ClearKind::FullRecall) => 'F',
+			Some(ClearKind::NormalClear) => 'C',
+			// Impossibly high scores have no status
+			None if self.score(scoring_system).0 >= 10_000_000 => return None,
+			None => 'C',
+		};
+
+		Some(status)
 	}
 	// }}}
 	// {{{ Play to embed
@@ -297,7 +532,7 @@ impl Play {
 				"
           SELECT 
             p.id, p.chart_id, p.user_id, p.created_at,
-            p.max_recall, p.far_notes, s.score
+            p.max_recall, p.far_notes, p.pure_notes, p.lost_notes, p.clear_kind, s.score
           FROM plays p
           JOIN scores s ON s.play_id = p.id
           WHERE s.scoring_system='standard'
@@ -349,6 +584,12 @@ impl Play {
 				format!("{}", self.score(ScoringSystem::Standard).grade()),
 				true,
 			)
+			.field(
+				"Accuracy",
+				self.score(ScoringSystem::Standard)
+					.display_percentage(chart.note_count),
+				true,
+			)
 			.field(
 				"ξ-Score",
 				self.score(ScoringSystem::EX)
@@ -374,6 +615,12 @@ impl Play {
 					.unwrap_or("-".to_string()),
 				true,
 			)
+			.field(
+				"Clear",
+				self.derived_clear_kind(ScoringSystem::Standard, chart)
+					.map_or("-".to_string(), |kind| kind.to_string()),
+				true,
+			)
 			.field(
 				"Max recall",
 				if let Some(max_recall) = self.max_recall {
@@ -383,6 +630,7 @@ impl Play {
 				},
 				true,
 			)
+			.field("Shinies", self.display_shinies(chart.note_count), true)
 			.field("ID", format!("{}", self.id), true);
 
 		if icon_attachement.is_some() {
@@ -407,9 +655,155 @@ impl Play {
 	// }}}
 }
 // }}}
+// {{{ Best plays cache
+/// Cheap summary of a user's plays, which changes whenever a play gets
+/// created or deleted.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct BestPlaysKey {
+	play_count: u32,
+	max_play_id: Option<u32>,
+}
+
+impl BestPlaysKey {
+	fn query(conn: &Connection, user_id: u32) -> Result<Self, Error> {
+		let key = conn
+			.prepare_cached("SELECT COUNT(*), MAX(id) FROM plays WHERE user_id=?")?
+			.query_row([user_id], |row| {
+				Ok(Self {
+					play_count: row.get(0)?,
+					max_play_id: row.get(1)?,
+				})
+			})?;
+
+		Ok(key)
+	}
+}
+
+/// Remembers the full (unfiltered, best-first) ranking of every user's best
+/// plays, such that commands like `stats b30` don't have to re-query and
+/// re-sort every play on each call. Rankings with and without the user's b30
+/// exclusions are cached separately.
+///
+/// Entries are validated against a [BestPlaysKey] before being used. Chart
+/// constant changes, score recomputations and b30 exclusions affect the
+/// ranking without touching the plays table, so they must call
+/// [BestPlaysCache::clear] explicitly. So must deletions, as a play saved
+/// after deleting the latest one can reuse its id, leaving the key as is.
+#[derive(Debug, Clone, Default)]
+pub struct BestPlaysCache {
+	entries: Arc<RwLock<HashMap<(u32, usize, bool), (BestPlaysKey, Vec<Play>)>>>,
+}
+
+impl BestPlaysCache {
+	fn get(
+		&self,
+		user_id: u32,
+		scoring_system: ScoringSystem,
+		skip_b30_exclusions: bool,
+		key: BestPlaysKey,
+	) -> Option<Vec<Play>> {
+		let entries = self.entries.read().unwrap();
+		let (cached_key, plays) =
+			entries.get(&(user_id, scoring_system.to_index(), skip_b30_exclusions))?;
+		(*cached_key == key).then(|| plays.clone())
+	}
+
+	fn insert(
+		&self,
+		user_id: u32,
+		scoring_system: ScoringSystem,
+		skip_b30_exclusions: bool,
+		key: BestPlaysKey,
+		plays: Vec<Play>,
+	) {
+		self.entries.write().unwrap().insert(
+			(user_id, scoring_system.to_index(), skip_b30_exclusions),
+			(key, plays),
+		);
+	}
+
+	/// Forgets every cached ranking.
+	pub fn clear(&self) {
+		self.entries.write().unwrap().clear();
+	}
+}
+// }}}
 // {{{ General functions
 pub type PlayCollection<'a> = Vec<(Play, &'a Song, &'a Chart)>;
 
+/// The order in which [get_best_plays_in] ranks the best play on each chart.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum PlayOrder {
+	/// Highest rated plays first.
+	#[default]
+	Best,
+	/// Lowest rated plays first.
+	Worst,
+}
+
+/// Restricts which songs are taken into account when looking up best plays.
+#[derive(Debug, Clone, Default)]
+pub struct PlayFilter {
+	/// Only keep songs from this pack (case insensitive).
+	pub pack: Option<String>,
+	/// Only keep songs from this side.
+	pub side: Option<Side>,
+	/// Only keep charts the user has cleared (i.e. has at least one play on
+	/// which did not end in a track lost).
+	pub cleared_only: bool,
+	/// Skip charts the user has excluded from their b30 (see
+	/// [count_b30_exclusions]). Only b30 and ptt computations should set
+	/// this, as exclusions do not affect the rest of the play history.
+	pub skip_b30_exclusions: bool,
+}
+
+impl PlayFilter {
+	/// The filter used for b30 and ptt computations.
+	#[inline]
+	pub fn b30() -> Self {
+		Self {
+			skip_b30_exclusions: true,
+			..Default::default()
+		}
+	}
+
+	/// Whether every song is taken into account. Skipping b30 exclusions
+	/// does not count as a restriction here.
+	#[inline]
+	pub fn is_empty(&self) -> bool {
+		self.pack.is_none() && self.side.is_none() && !self.cleared_only
+	}
+
+	#[inline]
+	pub fn matches(&self, song: &Song) -> bool {
+		let pack_matches = self.pack.as_ref().map_or(true, |pack| {
+			song.pack
+				.as_ref()
+				.is_some_and(|song_pack| song_pack.eq_ignore_ascii_case(pack))
+		});
+
+		let side_matches = self
+			.side
+			.map_or(true, |side| side.to_index() == song.side.to_index());
+
+		pack_matches && side_matches
+	}
+
+	/// Describes the filter in a human readable manner, e.g. `pack: Arcaea, side: light`.
+	pub fn describe(&self) -> String {
+		let mut parts = Vec::new();
+		if let Some(pack) = &self.pack {
+			parts.push(format!("pack: {pack}"));
+		}
+
+		if let Some(side) = self.side {
+			parts.push(format!("side: {}", Side::SIDE_STRINGS[side.to_index()]));
+		}
+
+		parts.join(", ")
+	}
+}
+
 pub fn get_best_plays(
 	ctx: &UserContext,
 	user_id: u32,
@@ -419,13 +813,135 @@ pub fn get_best_plays(
 	before: Option<NaiveDateTime>,
 ) -> Result<PlayCollection<'_>, TaggedError> {
 	let conn = ctx.db.get()?;
+	get_best_plays_in(
+		&conn,
+		ctx,
+		user_id,
+		scoring_system,
+		min_amount,
+		max_amount,
+		before,
+		&PlayFilter::default(),
+		PlayOrder::Best,
+	)
+}
+
+/// Similar to [get_best_plays], except charts the user has excluded from their
+/// b30 are skipped. Use this for anything computing ptt.
+pub fn get_b30_plays(
+	ctx: &UserContext,
+	user_id: u32,
+	scoring_system: ScoringSystem,
+	min_amount: usize,
+	max_amount: usize,
+	before: Option<NaiveDateTime>,
+) -> Result<PlayCollection<'_>, TaggedError> {
+	let conn = ctx.db.get()?;
+	get_best_plays_in(
+		&conn,
+		ctx,
+		user_id,
+		scoring_system,
+		min_amount,
+		max_amount,
+		before,
+		&PlayFilter::b30(),
+		PlayOrder::Best,
+	)
+}
+
+/// Similar to [get_best_plays], except the queries run on the given
+/// connection (useful for seeing uncommitted changes inside a transaction),
+/// only songs matching the given filter are taken into account, and the
+/// (per-chart best) plays can be ranked from worst to best instead.
+///
+/// Unfiltered best-first rankings of the present are cached (see
+/// [BestPlaysCache]), unless a transaction is in progress.
+#[allow(clippy::too_many_arguments)]
+pub fn get_best_plays_in<'a>(
+	conn: &Connection,
+	ctx: &'a UserContext,
+	user_id: u32,
+	scoring_system: ScoringSystem,
+	min_amount: usize,
+	max_amount: usize,
+	before: Option<NaiveDateTime>,
+	filter: &PlayFilter,
+	order: PlayOrder,
+) -> Result<PlayCollection<'a>, TaggedError> {
+	// {{{ Cache lookup
+	let cache_key = if before.is_none()
+		&& filter.is_empty()
+		&& order == PlayOrder::Best
+		&& conn.is_autocommit()
+	{
+		Some(BestPlaysKey::query(conn, user_id)?)
+	} else {
+		None
+	};
+
+	let cached = cache_key.and_then(|key| {
+		ctx.best_plays_cache
+			.get(user_id, scoring_system, filter.skip_b30_exclusions, key)
+	});
+	// }}}
+
+	let mut plays = if let Some(cached) = cached {
+		cached
+			.into_iter()
+			.map(|play| {
+				let (song, chart) = ctx.song_cache.lookup_chart(play.chart_id)?;
+				Ok((play, song, chart))
+			})
+			.collect::<Result<Vec<_>, Error>>()?
+	} else {
+		let plays = fetch_best_plays(conn, ctx, user_id, scoring_system, before, filter, order)?;
+
+		if let Some(key) = cache_key {
+			let to_cache = plays.iter().map(|(play, _, _)| play.clone()).collect();
+			ctx.best_plays_cache.insert(
+				user_id,
+				scoring_system,
+				filter.skip_b30_exclusions,
+				key,
+				to_cache,
+			);
+		}
+
+		plays
+	};
+
+	if plays.len() < min_amount {
+		return Err(anyhow!(
+			"Not enough plays found ({} out of a minimum of {min_amount})",
+			plays.len()
+		)
+		.tag(crate::context::ErrorKind::User));
+	}
+
+	plays.truncate(max_amount);
+
+	Ok(plays)
+}
+
+/// Fetches the best play on every chart matching the given filter, ranked in
+/// the given order.
+fn fetch_best_plays<'a>(
+	conn: &Connection,
+	ctx: &'a UserContext,
+	user_id: u32,
+	scoring_system: ScoringSystem,
+	before: Option<NaiveDateTime>,
+	filter: &PlayFilter,
+	order: PlayOrder,
+) -> Result<PlayCollection<'a>, TaggedError> {
 	// {{{ DB data fetching
 	let mut plays = conn
 		.prepare_cached(
 			"
         SELECT 
           p.id, p.chart_id, p.user_id, p.created_at,
-          p.max_recall, p.far_notes, s.score,
+          p.max_recall, p.far_notes, p.pure_notes, p.lost_notes, p.clear_kind, s.score,
           MAX(cs.score) as _cscore 
           -- ^ This is only here to make sqlite pick the correct row for the bare columns
         FROM plays p
@@ -435,6 +951,15 @@ pub fn get_best_plays(
         AND cs.scoring_system=?
         AND p.user_id=?
         AND p.created_at<=?
+        AND (NOT ? OR p.chart_id NOT IN (
+          SELECT chart_id FROM b30_exclusions WHERE user_id=p.user_id
+        ))
+        AND (? OR EXISTS (
+          SELECT 1 FROM plays c
+          WHERE c.user_id=p.user_id
+          AND c.chart_id=p.chart_id
+          AND (c.clear_kind IS NULL OR c.clear_kind!=?)
+        ))
         GROUP BY p.chart_id
       ",
 		)?
@@ -443,6 +968,9 @@ pub fn get_best_plays(
 				ScoringSystem::SCORING_SYSTEM_DB_STRINGS[scoring_system.to_index()],
 				user_id,
 				before.unwrap_or_else(|| Utc::now().naive_utc()),
+				filter.skip_b30_exclusions,
+				!filter.cleared_only,
+				ClearKind::CLEAR_KIND_DB_STRINGS[ClearKind::TrackLost.to_index()],
 			),
 			|row| {
 				let (song, chart) = ctx.song_cache.lookup_chart(row.get("chart_id")?)?;
@@ -453,25 +981,35 @@ pub fn get_best_plays(
 		.collect::<Result<Vec<_>, Error>>()?;
 	// }}}
 
-	if plays.len() < min_amount {
-		return Err(anyhow!(
-			"Not enough plays found ({} out of a minimum of {min_amount})",
-			plays.len()
-		)
-		.tag(crate::context::ErrorKind::User));
-	}
+	plays.retain(|(_, song, _)| filter.matches(song));
 
 	// {{{ B30 computation
-	plays.sort_by_key(|(play, _, chart)| -play.play_rating(scoring_system, chart.chart_constant));
-	plays.truncate(max_amount);
+	plays.sort_by_key(|(play, _, chart)| {
+		let rating = play.play_rating(scoring_system, chart.chart_constant.get());
+		match order {
+			PlayOrder::Best => -rating,
+			PlayOrder::Worst => rating,
+		}
+	});
 	// }}}
 
 	Ok(plays)
 }
 
+/// Counts how many charts the given user has excluded from their b30. Plays
+/// on excluded charts still show up in the play history, but are skipped by
+/// [get_b30_plays] and the ptt computations built on top of it.
+pub fn count_b30_exclusions(conn: &Connection, user_id: u32) -> Result<usize, Error> {
+	let count = conn
+		.prepare_cached("SELECT COUNT(*) FROM b30_exclusions WHERE user_id=?")?
+		.query_row([user_id], |row| row.get(0))?;
+
+	Ok(count)
+}
+
 /// Compute the current ptt of a given user.
 ///
-/// This is similar to directly calling [get_best_plays] and then passing the
+/// This is similar to directly calling [get_b30_plays] and then passing the
 /// result into [compute_b30_ptt], except any user errors (i.e.: not enough
 /// plays available) get turned into [None] values.
 pub fn try_compute_ptt(
@@ -480,7 +1018,29 @@ pub fn try_compute_ptt(
 	system: ScoringSystem,
 	before: Option<NaiveDateTime>,
 ) -> Result<Option<i32>, Error> {
-	match get_best_plays(ctx, user_id, system, 30, 30, before) {
+	let conn = ctx.db.get()?;
+	try_compute_ptt_in(&conn, ctx, user_id, system, before)
+}
+
+/// Similar to [try_compute_ptt], except the queries run on the given connection.
+pub fn try_compute_ptt_in(
+	conn: &Connection,
+	ctx: &UserContext,
+	user_id: u32,
+	system: ScoringSystem,
+	before: Option<NaiveDateTime>,
+) -> Result<Option<i32>, Error> {
+	match get_best_plays_in(
+		conn,
+		ctx,
+		user_id,
+		system,
+		30,
+		30,
+		before,
+		&PlayFilter::b30(),
+		PlayOrder::Best,
+	) {
 		Err(err) => match err.kind {
 			ErrorKind::User => Ok(None),
 			ErrorKind::Internal => Err(err.error),
@@ -493,66 +1053,153 @@ pub fn try_compute_ptt(
 pub fn compute_b30_ptt(scoring_system: ScoringSystem, plays: &PlayCollection<'_>) -> Rational32 {
 	plays
 		.iter()
-		.map(|(play, _, chart)| play.play_rating(scoring_system, chart.chart_constant))
+		.map(|(play, _, chart)| play.play_rating(scoring_system, chart.chart_constant.get()))
 		.sum::<Rational32>()
 		.checked_div(&Rational32::from_integer(plays.len() as i32))
 		.unwrap_or(Rational32::zero())
 }
 // }}}
 // {{{ Maintenance functions
-pub async fn generate_missing_scores(ctx: &UserContext) -> Result<(), Error> {
-	let conn = ctx.db.get()?;
-	let mut query = conn.prepare_cached(
-		"
-      SELECT 
-        p.id, p.chart_id, p.user_id, p.created_at,
-        p.max_recall, p.far_notes, s.score
-      FROM plays p
-      JOIN scores s ON s.play_id = p.id
-      WHERE s.scoring_system='standard'
-      ORDER BY p.created_at ASC
-    ",
-	)?;
-
-	let plays = query.query_and_then((), |row| -> Result<_, Error> {
-		let (_, chart) = ctx.song_cache.lookup_chart(row.get("chart_id")?)?;
-		let play = Play::from_sql(chart, row)?;
-		Ok(play)
-	})?;
-
-	let mut i = 0;
-
-	for play in plays {
-		let play = play?;
-		for system in ScoringSystem::SCORING_SYSTEMS {
-			let i = system.to_index();
-			let creation_ptt = try_compute_ptt(ctx, play.user_id, system, Some(play.created_at))?;
-
-			let raw_score = play.scores.0[i].0;
+/// An in-progress recomputation of the scores (and their creation ptt) of
+/// every play. When a user id is given, only the plays of said user are
+/// touched. The plays are processed in batches (see [Self::step]), such that
+/// callers can report on the progress in between.
+///
+/// Everything happens inside a single transaction, which only gets committed
+/// by [Self::commit]. Dropping the recomputation before that (e.g. because a
+/// batch failed midway) leaves the database untouched.
+pub struct ScoreRecomputation {
+	// The transaction is managed by hand (instead of through
+	// [rusqlite::Transaction]), as the latter cannot be held across awaits.
+	conn: PooledConnection<SqliteConnectionManager>,
+	plays: Vec<Play>,
+	processed: usize,
+	committed: bool,
+}
 
-			conn.prepare_cached(
+impl ScoreRecomputation {
+	pub fn begin(ctx: &UserContext, user_id: Option<u32>) -> Result<Self, Error> {
+		let conn = ctx.db.get()?;
+		let plays = conn
+			.prepare_cached(
 				"
-	          INSERT INTO scores(play_id, score, creation_ptt, scoring_system)
-	          VALUES ($1, $2, $3, $4)
-            ON CONFLICT(play_id, scoring_system)
-              DO UPDATE SET
-                score=$2, creation_ptt=$3
-              WHERE play_id = $1
-              AND scoring_system = $4
-	      ",
+          SELECT 
+            p.id, p.chart_id, p.user_id, p.created_at,
+            p.max_recall, p.far_notes, p.pure_notes, p.lost_notes, p.clear_kind, s.score
+          FROM plays p
+          JOIN scores s ON s.play_id = p.id
+          WHERE s.scoring_system='standard'
+          AND ($1 IS NULL OR p.user_id = $1)
+          ORDER BY p.created_at ASC
+        ",
 			)?
-			.execute((
-				play.id,
-				raw_score,
-				creation_ptt,
-				ScoringSystem::SCORING_SYSTEM_DB_STRINGS[i],
-			))?;
+			.query_and_then([user_id], |row| -> Result<_, Error> {
+				let (_, chart) = ctx.song_cache.lookup_chart(row.get("chart_id")?)?;
+				let play = Play::from_sql(chart, row)?;
+				Ok(play)
+			})?
+			.collect::<Result<Vec<_>, Error>>()?;
+
+		conn.execute_batch("BEGIN")?;
+
+		Ok(Self {
+			conn,
+			plays,
+			processed: 0,
+			committed: false,
+		})
+	}
+
+	/// The total number of plays to process.
+	#[inline]
+	pub fn len(&self) -> usize {
+		self.plays.len()
+	}
+
+	#[inline]
+	pub fn is_empty(&self) -> bool {
+		self.plays.is_empty()
+	}
+
+	#[inline]
+	pub fn is_done(&self) -> bool {
+		self.processed == self.plays.len()
+	}
+
+	/// Processes (at most) the next `count` plays, returning how many plays
+	/// have been processed so far.
+	pub fn step(&mut self, ctx: &UserContext, count: usize) -> Result<usize, Error> {
+		let end = self.plays.len().min(self.processed.saturating_add(count));
+		let mut insert = self.conn.prepare_cached(UPSERT_SCORE_QUERY)?;
+
+		for play in &self.plays[self.processed..end] {
+			for system in ScoringSystem::SCORING_SYSTEMS {
+				let system_index = system.to_index();
+
+				// The ptt must be computed inside the transaction, as it depends
+				// on the (freshly updated) scores of earlier plays.
+				let creation_ptt = try_compute_ptt_in(
+					&self.conn,
+					ctx,
+					play.user_id,
+					system,
+					Some(play.created_at),
+				)?;
+
+				insert.execute((
+					play.id,
+					play.scores.0[system_index].0,
+					creation_ptt,
+					ScoringSystem::SCORING_SYSTEM_DB_STRINGS[system_index],
+				))?;
+			}
 		}
 
-		i += 1;
-		println!("Processed {i} plays");
+		self.processed = end;
+		Ok(end)
 	}
-	Ok(())
+
+	/// Commits the recomputed scores, returning the number of plays processed.
+	pub fn commit(mut self, ctx: &UserContext) -> Result<usize, Error> {
+		if !self.is_done() {
+			return Err(anyhow!(
+				"Cannot commit a score recomputation after {}/{} plays",
+				self.processed,
+				self.plays.len()
+			));
+		}
+
+		self.conn.execute_batch("COMMIT")?;
+		self.committed = true;
+
+		// Recomputed scores can change which play is the best on a chart
+		ctx.best_plays_cache.clear();
+
+		Ok(self.plays.len())
+	}
+}
+
+impl Drop for ScoreRecomputation {
+	fn drop(&mut self) {
+		// The connection goes back into the pool, so it must not be left
+		// inside an open transaction.
+		if !self.committed {
+			if let Err(err) = self.conn.execute_batch("ROLLBACK") {
+				println!("Could not roll back score recomputation: {err}");
+			}
+		}
+	}
+}
+
+/// Recomputes the scores (and their creation ptt) of every play, all at once.
+/// See [ScoreRecomputation] for more details.
+pub async fn generate_missing_scores(
+	ctx: &UserContext,
+	user_id: Option<u32>,
+) -> Result<usize, Error> {
+	let mut recomputation = ScoreRecomputation::begin(ctx, user_id)?;
+	recomputation.step(ctx, usize::MAX)?;
+	recomputation.commit(ctx)
 }
 // }}}
 // {{{ Play + chart + song triplet
@@ -563,3 +1210,378 @@ pub struct PlayWithDetails {
 	pub chart: Chart,
 }
 // }}}
+// {{{ Tests
+#[cfg(test)]
+mod play_filter_tests {
+	use super::*;
+
+	fn song(pack: Option<&str>, side: Side) -> Song {
+		Song {
+			id: 0,
+			title: "Test".to_owned(),
+			lowercase_title: "test".to_owned(),
+			og_title: None,
+			lowercase_og_title: None,
+			artist: "Test".to_owned(),
+			bpm: "200".to_owned(),
+			pack: pack.map(|p| p.to_owned()),
+			side,
+		}
+	}
+
+	#[test]
+	fn empty_filter_matches_everything() {
+		let filter = PlayFilter::default();
+		assert!(filter.is_empty());
+		assert!(filter.matches(&song(None, Side::Light)));
+		assert!(filter.matches(&song(Some("Arcaea"), Side::Conflict)));
+	}
+
+	#[test]
+	fn pack_and_side() {
+		let filter = PlayFilter {
+			pack: Some("arcaea".to_owned()),
+			side: Some(Side::Conflict),
+			..Default::default()
+		};
+
+		assert!(filter.matches(&song(Some("Arcaea"), Side::Conflict)));
+		assert!(!filter.matches(&song(Some("Arcaea"), Side::Light)));
+		assert!(!filter.matches(&song(Some("Eternal Core"), Side::Conflict)));
+		assert!(!filter.matches(&song(None, Side::Conflict)));
+		assert_eq!(filter.describe(), "pack: arcaea, side: conflict");
+	}
+}
+
+#[cfg(test)]
+mod save_tests {
+	use crate::context::testing::get_mock_context;
+	use crate::recognition::fuzzy_song_name::guess_song_and_chart;
+
+	use super::*;
+
+	fn score_rows(ctx: &UserContext, play_id: u32) -> Result<Vec<(String, u32)>, Error> {
+		let rows = ctx
+			.db
+			.get()?
+			.prepare(
+				"
+          SELECT scoring_system, score FROM scores
+          WHERE play_id=?
+          ORDER BY scoring_system
+        ",
+			)?
+			.query_map([play_id], |row| Ok((row.get(0)?, row.get(1)?)))?
+			.collect::<Result<Vec<_>, _>>()?;
+
+		Ok(rows)
+	}
+
+	#[tokio::test]
+	async fn saving_twice_is_consistent() -> Result<(), Error> {
+		let (ctx, _guard) = get_mock_context().await?;
+		let user = User::create_from_context(&ctx).map_err(|e| e.error)?;
+		let (_, chart) = guess_song_and_chart(&ctx.data, "Fracture Ray [FTR]")?;
+
+		let create = CreatePlay::new(Score(9_800_000)).with_fars(Some(40));
+		let first = create
+			.clone()
+			.save(&ctx.data, &user, chart)
+			.map_err(|e| e.error)?;
+		let second = create.save(&ctx.data, &user, chart).map_err(|e| e.error)?;
+
+		assert_ne!(first.id, second.id);
+		assert_eq!(first.scores, second.scores);
+
+		let rows = score_rows(&ctx.data, first.id)?;
+		assert_eq!(rows.len(), ScoringSystem::SCORING_SYSTEMS.len());
+		assert_eq!(rows, score_rows(&ctx.data, second.id)?);
+
+		// Racing inserts of the same scores must overwrite instead of failing
+		let conn = ctx.data.db.get()?;
+		for (system, score) in &rows {
+			conn.execute(UPSERT_SCORE_QUERY, (first.id, score, None::<i32>, system))?;
+		}
+
+		assert_eq!(score_rows(&ctx.data, first.id)?, rows);
+
+		Ok(())
+	}
+
+	#[tokio::test]
+	async fn cleared_only_skips_lost_charts() -> Result<(), Error> {
+		let (ctx, _guard) = get_mock_context().await?;
+		let user = User::create_from_context(&ctx).map_err(|e| e.error)?;
+		let (_, lost) = guess_song_and_chart(&ctx.data, "Fracture Ray [FTR]")?;
+		let (_, cleared) = guess_song_and_chart(&ctx.data, "Pentiment [BYD]")?;
+
+		CreatePlay::new(Score(9_000_000))
+			.with_clear_kind(Some(ClearKind::TrackLost))
+			.save(&ctx.data, &user, lost)
+			.map_err(|e| e.error)?;
+		CreatePlay::new(Score(9_500_000))
+			.save(&ctx.data, &user, cleared)
+			.map_err(|e| e.error)?;
+
+		let filter = PlayFilter {
+			cleared_only: true,
+			..Default::default()
+		};
+		let plays = get_best_plays_in(
+			&ctx.data.db.get()?,
+			&ctx.data,
+			user.id,
+			ScoringSystem::Standard,
+			0,
+			30,
+			None,
+			&filter,
+			PlayOrder::Worst,
+		)
+		.map_err(|e| e.error)?;
+
+		assert_eq!(plays.len(), 1);
+		assert_eq!(plays[0].2.id, cleared.id);
+
+		Ok(())
+	}
+}
+
+#[cfg(test)]
+mod recomputation_tests {
+	use crate::context::testing::get_mock_context;
+	use crate::recognition::fuzzy_song_name::guess_song_and_chart;
+
+	use super::*;
+
+	fn sdf_score(ctx: &UserContext, play_id: u32) -> Result<u32, Error> {
+		let score = ctx.db.get()?.query_row(
+			"SELECT score FROM scores WHERE play_id=? AND scoring_system='sdf'",
+			[play_id],
+			|row| row.get(0),
+		)?;
+
+		Ok(score)
+	}
+
+	#[tokio::test]
+	async fn only_committed_recomputations_stick() -> Result<(), Error> {
+		let (ctx, _guard) = get_mock_context().await?;
+		let user = User::create_from_context(&ctx).map_err(|e| e.error)?;
+		let (_, fracture) = guess_song_and_chart(&ctx.data, "Fracture Ray [FTR]")?;
+		let (_, pentiment) = guess_song_and_chart(&ctx.data, "Pentiment [BYD]")?;
+
+		let play = CreatePlay::new(Score(9_500_000))
+			.save(&ctx.data, &user, fracture)
+			.map_err(|e| e.error)?;
+		CreatePlay::new(Score(9_900_000))
+			.save(&ctx.data, &user, pentiment)
+			.map_err(|e| e.error)?;
+
+		let expected = sdf_score(&ctx.data, play.id)?;
+		assert_ne!(expected, 0);
+		ctx.data
+			.db
+			.get()?
+			.execute("UPDATE scores SET score=0 WHERE scoring_system='sdf'", ())?;
+
+		// Dropping a recomputation midway rolls everything back
+		let mut recomputation = ScoreRecomputation::begin(&ctx.data, Some(user.id))?;
+		assert_eq!(recomputation.len(), 2);
+		assert_eq!(recomputation.step(&ctx.data, 1)?, 1);
+		assert!(!recomputation.is_done());
+		drop(recomputation);
+		assert_eq!(sdf_score(&ctx.data, play.id)?, 0);
+
+		let mut recomputation = ScoreRecomputation::begin(&ctx.data, Some(user.id))?;
+		assert_eq!(recomputation.step(&ctx.data, 1)?, 1);
+		assert_eq!(recomputation.step(&ctx.data, 5)?, 2);
+		assert_eq!(recomputation.commit(&ctx.data)?, 2);
+		assert_eq!(sdf_score(&ctx.data, play.id)?, expected);
+
+		Ok(())
+	}
+}
+
+#[cfg(test)]
+mod best_plays_cache_tests {
+	use crate::context::testing::get_mock_context;
+	use crate::recognition::fuzzy_song_name::guess_song_and_chart;
+
+	use super::*;
+
+	fn best_play_ids(ctx: &UserContext, user: &User) -> Result<Vec<u32>, Error> {
+		let plays = get_best_plays(ctx, user.id, ScoringSystem::Standard, 0, 30, None)
+			.map_err(|e| e.error)?;
+		Ok(plays.iter().map(|(play, _, _)| play.id).collect())
+	}
+
+	#[tokio::test]
+	async fn invalidated_by_play_changes() -> Result<(), Error> {
+		let (ctx, _guard) = get_mock_context().await?;
+		let user = User::create_from_context(&ctx).map_err(|e| e.error)?;
+		let (_, fracture) = guess_song_and_chart(&ctx.data, "Fracture Ray [FTR]")?;
+		let (_, pentiment) = guess_song_and_chart(&ctx.data, "Pentiment [BYD]")?;
+
+		let first = CreatePlay::new(Score(9_500_000))
+			.save(&ctx.data, &user, fracture)
+			.map_err(|e| e.error)?;
+		assert_eq!(best_play_ids(&ctx.data, &user)?, [first.id]);
+		// Served from the cache the second time around
+		assert_eq!(best_play_ids(&ctx.data, &user)?, [first.id]);
+
+		let second = CreatePlay::new(Score(9_900_000))
+			.save(&ctx.data, &user, pentiment)
+			.map_err(|e| e.error)?;
+		assert_eq!(best_play_ids(&ctx.data, &user)?, [second.id, first.id]);
+
+		ctx.data
+			.db
+			.get()?
+			.execute("DELETE FROM plays WHERE id=?", [second.id])?;
+		assert_eq!(best_play_ids(&ctx.data, &user)?, [first.id]);
+
+		Ok(())
+	}
+}
+
+#[cfg(test)]
+mod clear_kind_tests {
+	use crate::context::testing::get_mock_context;
+	use crate::recognition::fuzzy_song_name::guess_song_and_chart;
+
+	use super::*;
+
+	#[tokio::test]
+	async fn track_lost_is_stored_and_shown() -> Result<(), Error> {
+		let (ctx, _guard) = get_mock_context().await?;
+		let user = User::create_from_context(&ctx).map_err(|e| e.error)?;
+		let (_, chart) = guess_song_and_chart(&ctx.data, "Fracture Ray [FTR]")?;
+
+		CreatePlay::new(Score(9_500_000))
+			.with_fars(Some(40))
+			.with_clear_kind(Some(ClearKind::TrackLost))
+			.save(&ctx.data, &user, chart)
+			.map_err(|e| e.error)?;
+
+		let plays = get_best_plays(&ctx.data, user.id, ScoringSystem::Standard, 1, 1, None)
+			.map_err(|e| e.error)?;
+		let (play, _, _) = &plays[0];
+
+		assert_eq!(play.clear_kind, Some(ClearKind::TrackLost));
+		assert!(play
+			.status(ScoringSystem::Standard, chart)
+			.is_some_and(|status| status.starts_with("TL")));
+		assert_eq!(play.short_status(ScoringSystem::Standard, chart), Some('L'));
+
+		Ok(())
+	}
+
+	/// Builds an unsaved play with the given score and (optional) distribution.
+	fn play(
+		chart: &Chart,
+		score: Score,
+		distribution: Option<(u32, u32, u32)>,
+		clear_kind: Option<ClearKind>,
+	) -> Play {
+		Play {
+			id: 0,
+			chart_id: chart.id,
+			user_id: 0,
+			created_at: Default::default(),
+			max_recall: None,
+			far_notes: distribution.map(|d| d.1),
+			pure_notes: distribution.map(|d| d.0),
+			lost_notes: distribution.map(|d| d.2),
+			clear_kind,
+			scores: ScoreCollection::from_standard_score(score, chart),
+		}
+	}
+
+	#[tokio::test]
+	async fn derived_boundaries() -> Result<(), Error> {
+		let (ctx, _guard) = get_mock_context().await?;
+		let (_, chart) = guess_song_and_chart(&ctx.data, "Fracture Ray [FTR]")?;
+		let notes = chart.note_count;
+		let system = ScoringSystem::Standard;
+
+		// {{{ Pure memories
+		let max = play(chart, Score(10_000_000 + notes), None, None);
+		assert_eq!(
+			max.derived_clear_kind(system, chart),
+			Some(ClearKind::PureMemory)
+		);
+		assert_eq!(max.status(system, chart).as_deref(), Some("MPM"));
+		assert_eq!(max.short_status(system, chart), Some('M'));
+
+		let no_shinies = play(chart, Score(10_000_000), None, None);
+		assert_eq!(
+			no_shinies.derived_clear_kind(system, chart),
+			Some(ClearKind::PureMemory)
+		);
+		assert_eq!(
+			no_shinies.status(system, chart),
+			Some(format!("PM (-{notes})"))
+		);
+		assert_eq!(no_shinies.short_status(system, chart), Some('P'));
+
+		let impossible = play(chart, Score(10_000_001 + notes), None, None);
+		assert_eq!(impossible.derived_clear_kind(system, chart), None);
+		assert_eq!(impossible.status(system, chart), None);
+		assert_eq!(impossible.short_status(system, chart), None);
+		// }}}
+		// {{{ Full recalls & clears
+		let one_far = play(
+			chart,
+			Score::compute_naive(notes, notes - 1, 1),
+			Some((notes - 1, 1, 0)),
+			None,
+		);
+		assert_eq!(
+			one_far.derived_clear_kind(system, chart),
+			Some(ClearKind::FullRecall)
+		);
+		assert_eq!(one_far.short_status(system, chart), Some('F'));
+
+		let one_lost = play(
+			chart,
+			Score::compute_naive(notes, notes - 1, 0),
+			Some((notes - 1, 0, 1)),
+			None,
+		);
+		assert_eq!(
+			one_lost.derived_clear_kind(system, chart),
+			Some(ClearKind::NormalClear)
+		);
+		assert_eq!(one_lost.short_status(system, chart), Some('C'));
+
+		// The distribution takes precedence over the banner
+		let misread = play(
+			chart,
+			Score::compute_naive(notes, notes - 1, 0),
+			Some((notes - 1, 0, 1)),
+			Some(ClearKind::FullRecall),
+		);
+		assert_eq!(
+			misread.derived_clear_kind(system, chart),
+			Some(ClearKind::NormalClear)
+		);
+		// }}}
+		// {{{ Unknown distributions
+		let unknown = play(chart, Score(9_900_000), None, None);
+		assert_eq!(unknown.derived_clear_kind(system, chart), None);
+		assert_eq!(unknown.status(system, chart), None);
+		assert_eq!(unknown.short_status(system, chart), Some('C'));
+
+		let banner = play(chart, Score(9_900_000), None, Some(ClearKind::FullRecall));
+		assert_eq!(
+			banner.derived_clear_kind(system, chart),
+			Some(ClearKind::FullRecall)
+		);
+		assert_eq!(banner.status(system, chart).as_deref(), Some("FR"));
+		// }}}
+
+		Ok(())
+	}
+}
+// }}}
@@ -1,7 +1,14 @@
+use std::fmt::Display;
+
+use anyhow::{anyhow, Error};
 use num::{Rational32, ToPrimitive};
 
 pub type Rating = Rational32;
 
+/// The highest rating we consider plausible. Chart constants are capped at 20
+/// (see `chart set-constant`), and no score is worth more than 2 on top.
+pub const MAX_RATING: i32 = 22;
+
 /// Saves a rating rational as an integer where it's multiplied by 100.
 #[inline]
 pub fn rating_as_fixed(rating: Rating) -> i32 {
@@ -24,3 +31,65 @@ pub fn rating_as_float(rating: Rating) -> f32 {
 pub fn rating_from_fixed(fixed: i32) -> Rating {
 	Rating::new(fixed, 100)
 }
+
+/// Makes sure a rating (usually provided by a user) is non-negative, and no
+/// higher than [MAX_RATING].
+pub fn checked_rating(rating: Rating) -> Result<Rating, Error> {
+	if rating < Rating::from_integer(0) || rating > Rating::from_integer(MAX_RATING) {
+		return Err(anyhow!(
+			"{} is not a valid rating. Ratings must be between 0 and {MAX_RATING}",
+			RatingDisplay(rating)
+		));
+	}
+
+	Ok(rating)
+}
+
+// {{{ Display
+/// Displays a rating with two decimals. The rounding happens on the
+/// underlying rational (see [rating_as_fixed]), so half-way values like
+/// `12.345` consistently get rounded away from zero.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RatingDisplay(pub Rating);
+
+impl Display for RatingDisplay {
+	fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+		let fixed = rating_as_fixed(self.0);
+		let sign = if fixed < 0 { "-" } else { "" };
+		let fixed = fixed.unsigned_abs();
+		write!(f, "{sign}{}.{:0>2}", fixed / 100, fixed % 100)
+	}
+}
+// }}}
+// {{{ Tests
+#[cfg(test)]
+mod rating_tests {
+	use super::*;
+
+	fn display(numer: i32, denom: i32) -> String {
+		RatingDisplay(Rating::new(numer, denom)).to_string()
+	}
+
+	#[test]
+	fn display_rounding() {
+		assert_eq!(display(12, 1), "12.00");
+		assert_eq!(display(12344, 1000), "12.34");
+		assert_eq!(display(12345, 1000), "12.35");
+		assert_eq!(display(12355, 1000), "12.36");
+		assert_eq!(display(4, 1000), "0.00");
+		assert_eq!(display(5, 1000), "0.01");
+		assert_eq!(display(-5, 1000), "-0.01");
+		assert_eq!(display(-4, 1000), "0.00");
+		assert_eq!(display(-12345, 1000), "-12.35");
+	}
+
+	#[test]
+	fn checked_ratings() {
+		assert!(checked_rating(Rating::from_integer(0)).is_ok());
+		assert!(checked_rating(rating_from_fixed(1250)).is_ok());
+		assert!(checked_rating(Rating::from_integer(MAX_RATING)).is_ok());
+		assert!(checked_rating(rating_from_fixed(-1)).is_err());
+		assert!(checked_rating(rating_from_fixed(MAX_RATING * 100 + 1)).is_err());
+	}
+}
+// }}}
@@ -1,13 +1,16 @@
 // {{{ Imports
 use std::fmt::{Display, Write};
+use std::str::FromStr;
 
-use num::{Rational32, Rational64};
+use anyhow::anyhow;
+use num::Rational64;
 use serde::{Deserialize, Serialize};
 
 use crate::context::Error;
 
 use super::chart::Chart;
-use super::rating::{rating_as_float, rating_from_fixed, Rating};
+use super::rating::{rating_as_fixed, rating_from_fixed, Rating, RatingDisplay};
+use super::scoring;
 // }}}
 
 // {{{ Scoring system
@@ -31,6 +34,10 @@ impl ScoringSystem {
 	pub const SCORING_SYSTEM_DB_STRINGS: [&'static str; Self::SCORING_SYSTEMS.len()] =
 		["standard", "sdf", "ex"];
 
+	/// Human readable names
+	pub const SCORING_SYSTEM_STRINGS: [&'static str; Self::SCORING_SYSTEMS.len()] =
+		["Standard", "SDF", "EX"];
+
 	#[inline]
 	pub fn to_index(self) -> usize {
 		self as usize
@@ -96,7 +103,7 @@ impl Score {
 
 	#[inline]
 	pub fn increment(note_count: u32) -> Rational64 {
-		Rational64::new_raw(5_000_000, note_count as i64).reduced()
+		scoring::increment(note_count)
 	}
 
 	/// Remove the contribution made by shinies to a score.
@@ -111,36 +118,17 @@ impl Score {
 	/// This is the simplest way to compute a score, and is useful for error analysis.
 	#[inline]
 	pub fn compute_naive(note_count: u32, pures: u32, fars: u32) -> Self {
-		Self(
-			(Self::increment(note_count) * Rational64::from_integer((2 * pures + fars) as i64))
-				.floor()
-				.to_integer() as u32,
-		)
+		Self(scoring::compute_naive(note_count, pures, fars))
 	}
 
 	/// Returns the zeta score, the number of shinies, and the number of score units.
 	///
 	/// Pure (and higher) notes reward two score units, far notes reward one, and lost notes reward
 	/// none.
+	#[inline]
 	pub fn analyse(self, note_count: u32) -> (Score, u32, u32) {
-		// Smallest possible difference between (zeta-)scores
-		let increment = Self::increment(note_count);
-		let zeta_increment = Rational64::new_raw(2_000_000, note_count as i64).reduced();
-
-		let score = Rational64::from_integer(self.0 as i64);
-		let score_units = (score / increment).floor();
-
-		let non_shiny_score = (score_units * increment).floor();
-		let shinies = score - non_shiny_score;
-
-		let zeta_score_units = Rational64::from_integer(2) * score_units + shinies;
-		let zeta_score = Score((zeta_increment * zeta_score_units).floor().to_integer() as u32);
-
-		(
-			zeta_score,
-			shinies.to_integer() as u32,
-			score_units.to_integer() as u32,
-		)
+		let (zeta_score, shinies, score_units) = scoring::analyse(self.0, note_count);
+		(Score(zeta_score), shinies, score_units)
 	}
 	// }}}
 	// {{{ Scoring system conversion
@@ -150,11 +138,8 @@ impl Score {
 	pub fn convert_to(self, scoring_system: ScoringSystem, chart: &Chart) -> Self {
 		match scoring_system {
 			ScoringSystem::Standard => self,
-			ScoringSystem::SDF => {
-				let shinies = self.shinies(chart.note_count);
-				Self(self.0 + 9.min(chart.note_count - shinies)).to_zeta(chart.note_count)
-			}
-			ScoringSystem::EX => self.to_zeta(chart.note_count),
+			ScoringSystem::SDF => Self(scoring::to_sdf(self.0, chart.note_count)),
+			ScoringSystem::EX => Self(scoring::to_ex(self.0, chart.note_count)),
 		}
 	}
 
@@ -162,30 +147,31 @@ impl Score {
 	// {{{ Score => Play rating
 	#[inline]
 	pub fn play_rating(self, chart_constant: u32) -> Rating {
-		rating_from_fixed(chart_constant as i32)
-			+ if self.0 >= 10_000_000 {
-				Rational32::from_integer(2)
-			} else if self.0 >= 9_800_000 {
-				Rational32::from_integer(1)
-					+ Rational32::new(self.0 as i32 - 9_800_000, 200_000).reduced()
-			} else {
-				Rational32::new(self.0 as i32 - 9_500_000, 300_000).reduced()
-			}
+		scoring::play_rating(self.0, chart_constant)
+	}
+
+	/// The inverse of [Self::play_rating]. Computes the smallest score whose
+	/// play rating is at least the given one, returning [None] if the rating
+	/// is out of reach on the given chart constant.
+	#[inline]
+	pub fn from_play_rating(rating: Rating, chart_constant: u32) -> Option<Self> {
+		scoring::from_play_rating(rating, chart_constant).map(Self)
 	}
 
 	pub fn display_play_rating(self, prev: Option<Self>, chart: &Chart) -> Result<String, Error> {
 		let mut buffer = String::with_capacity(14);
 
-		let play_rating = rating_as_float(self.play_rating(chart.chart_constant));
-		write!(buffer, "{:.2}", play_rating)?;
+		let play_rating = rating_as_fixed(self.play_rating(chart.chart_constant.get()));
+		write!(buffer, "{}", RatingDisplay(rating_from_fixed(play_rating)))?;
 
 		if let Some(prev) = prev {
-			let prev_play_rating = rating_as_float(prev.play_rating(chart.chart_constant));
+			let prev_play_rating = rating_as_fixed(prev.play_rating(chart.chart_constant.get()));
+			let diff = RatingDisplay(rating_from_fixed(play_rating - prev_play_rating));
 
 			if play_rating >= prev_play_rating {
-				write!(buffer, " (+{:.2})", play_rating - prev_play_rating)?;
+				write!(buffer, " (+{diff})")?;
 			} else {
-				write!(buffer, " ({:.2})", play_rating - prev_play_rating)?;
+				write!(buffer, " ({diff})")?;
 			}
 		}
 
@@ -215,46 +201,64 @@ impl Score {
 	}
 	// }}}
 	// {{{ Scores & Distribution => score
+	#[inline]
 	pub fn resolve_distibution_ambiguities(
 		score: Score,
 		read_distribution: Option<(u32, u32, u32)>,
 		note_count: u32,
 	) -> Option<u32> {
-		let read_distribution = read_distribution?;
-		let pures = read_distribution.0;
-		let fars = read_distribution.1;
-		let losts = read_distribution.2;
+		Self::analyse_distribution(score, read_distribution?, note_count).fars
+	}
 
+	/// Does the work behind [Self::resolve_distibution_ambiguities], keeping
+	/// the intermediate values around such that the decision can be explained.
+	pub fn analyse_distribution(
+		score: Score,
+		(pures, fars, losts): (u32, u32, u32),
+		note_count: u32,
+	) -> DistributionAnalysis {
 		// {{{ Compute score from note breakdown subpairs
 		let pf_score = Score::compute_naive(note_count, pures, fars);
 		let fl_score =
 			Score::compute_naive(note_count, note_count.saturating_sub(losts + fars), fars);
 		let lp_score =
 			Score::compute_naive(note_count, pures, note_count.saturating_sub(losts + pures));
+		let no_shiny_score = score.forget_shinies(note_count);
 		// }}}
 		// {{{ Look for consensus among recomputed scores
 		// Lemma: if two computed scores agree, then so will the third
-		if pf_score == fl_score {
-			Some(fars)
+		let (consensus, fars) = if pf_score == fl_score {
+			(DistributionConsensus::Subpairs, Some(fars))
 		} else {
 			// Due to the above lemma, we know all three scores must be distinct by
 			// this point.
 			//
 			// Our strategy is to check which of the three scores agrees with the real
 			// score, and to then trust the `far` value that contributed to that pair.
-			let no_shiny_score = score.forget_shinies(note_count);
 			let pf_appears = no_shiny_score == pf_score;
 			let fl_appears = no_shiny_score == fl_score;
 			let lp_appears = no_shiny_score == lp_score;
 
 			match (pf_appears, fl_appears, lp_appears) {
-				(true, false, false) => Some(fars),
-				(false, true, false) => Some(fars),
-				(false, false, true) => Some(note_count - pures - losts),
-				_ => None,
+				(true, false, false) => (DistributionConsensus::PureFar, Some(fars)),
+				(false, true, false) => (DistributionConsensus::FarLost, Some(fars)),
+				(false, false, true) => (
+					DistributionConsensus::LostPure,
+					Some(note_count - pures - losts),
+				),
+				_ => (DistributionConsensus::None, None),
 			}
-		}
+		};
 		// }}}
+
+		DistributionAnalysis {
+			pf_score,
+			fl_score,
+			lp_score,
+			no_shiny_score,
+			consensus,
+			fars,
+		}
 	}
 	// }}}
 	// {{{ Display self with diff
@@ -291,6 +295,15 @@ impl Score {
 
 		Ok(buffer)
 	}
+
+	/// Displays how close this score is to the theoretical max (a PM where
+	/// every note is shiny) as a percentage. The result is truncated (not
+	/// rounded), such that only the theoretical max shows up as `100.00%`.
+	pub fn display_percentage(self, note_count: u32) -> String {
+		let max = 10_000_000 + note_count as u64;
+		let hundredths = (self.0 as u64).min(max) * 10_000 / max;
+		format!("{}.{:0>2}%", hundredths / 100, hundredths % 100)
+	}
 	// }}}
 	// {{{ PM detection
 	#[inline]
@@ -298,8 +311,64 @@ impl Score {
 		self.0 >= 10_000_000
 	}
 	// }}}
+	// {{{ Validity checks
+	/// Checks whether this (standard) score can actually be obtained on a chart
+	/// with the given amount of notes.
+	#[inline]
+	pub fn is_achievable(self, note_count: u32) -> bool {
+		let (zeta, shinies, score_units) = self.analyse(note_count);
+		self.0 <= 10_000_000 + note_count
+			&& zeta.0 <= 10_000_000
+			&& shinies <= note_count
+			&& score_units <= 2 * note_count
+	}
+	// }}}
+}
+
+// {{{ Distribution analysis
+/// Describes which piece of evidence the far count has been inferred from.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DistributionConsensus {
+	/// The pure+far and far+lost subpairs agree with each other (and hence
+	/// with the lost+pure subpair as well).
+	Subpairs,
+
+	/// Only the pure+far subpair agrees with the actual score.
+	PureFar,
+
+	/// Only the far+lost subpair agrees with the actual score.
+	FarLost,
+
+	/// Only the lost+pure subpair agrees with the actual score.
+	LostPure,
+
+	/// No conclusion could be drawn.
+	None,
 }
 
+/// The intermediate values [Score::resolve_distibution_ambiguities] bases
+/// its decision on.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct DistributionAnalysis {
+	/// Score recomputed from the pure and far counts.
+	pub pf_score: Score,
+
+	/// Score recomputed from the far and lost counts.
+	pub fl_score: Score,
+
+	/// Score recomputed from the lost and pure counts.
+	pub lp_score: Score,
+
+	/// The actual score, without the contribution made by shinies.
+	pub no_shiny_score: Score,
+
+	pub consensus: DistributionConsensus,
+
+	/// The resulting far count, if any.
+	pub fars: Option<u32>,
+}
+// }}}
+
 impl Display for Score {
 	fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
 		let score = self.0;
@@ -312,10 +381,39 @@ impl Display for Score {
 		)
 	}
 }
+
+impl FromStr for Score {
+	type Err = Error;
+
+	/// Parses scores written either as raw digits (`9926250`), or using the
+	/// apostrophe-separated format produced by [Display] (`9'926'250`).
+	fn from_str(s: &str) -> Result<Self, Self::Err> {
+		let digits = s
+			.chars()
+			.filter(|c| *c != '\'' && !c.is_whitespace())
+			.collect::<String>();
+
+		if digits.is_empty() || !digits.chars().all(|c| c.is_ascii_digit()) {
+			return Err(anyhow!("'{}' is not a valid score", s));
+		}
+
+		let score: u32 = digits
+			.parse()
+			.map_err(|_| anyhow!("'{}' is not a valid score", s))?;
+
+		if score > 10_010_000 {
+			return Err(anyhow!("Score {} is too large", s));
+		}
+
+		Ok(Self(score))
+	}
+}
 // }}}
 // {{{ Tests
 #[cfg(test)]
 mod score_tests {
+	use crate::arcaea::rating::rating_from_fixed;
+
 	use super::*;
 
 	#[test]
@@ -334,5 +432,75 @@ mod score_tests {
 			}
 		}
 	}
+
+	#[test]
+	fn from_play_rating_inverts_play_rating() {
+		for chart_constant in [100, 790, 1000, 1140, 1230] {
+			for fixed_diff in -400..=200 {
+				let rating = rating_from_fixed(chart_constant as i32 + fixed_diff);
+				let score = Score::from_play_rating(rating, chart_constant).unwrap();
+
+				assert!(
+					score.play_rating(chart_constant) >= rating,
+					"{score} does not reach {rating} on constant {chart_constant}"
+				);
+
+				if score.0 > 0 {
+					assert!(
+						Score(score.0 - 1).play_rating(chart_constant) < rating,
+						"{score} is not the smallest score reaching {rating} on constant {chart_constant}"
+					);
+				}
+			}
+
+			let unreachable = rating_from_fixed(chart_constant as i32 + 201);
+			assert_eq!(Score::from_play_rating(unreachable, chart_constant), None);
+		}
+	}
+
+	#[test]
+	fn achievable_scores_at_pm_boundaries() {
+		for note_count in [200, 1000, 2000] {
+			assert!(Score(10_000_000).is_achievable(note_count));
+			assert!(Score(10_000_000 + note_count).is_achievable(note_count));
+			assert!(!Score(10_000_000 + note_count + 1).is_achievable(note_count));
+		}
+	}
+
+	#[test]
+	fn percentage_at_grade_boundaries() {
+		// D/C boundary
+		assert_eq!(Score(8_599_999).display_percentage(1000), "85.99%");
+		// AA/EX boundary
+		assert_eq!(Score(9_500_000).display_percentage(1000), "94.99%");
+		// PM boundary
+		assert_eq!(Score(9_999_999).display_percentage(1000), "99.98%");
+		assert_eq!(Score(10_000_000).display_percentage(1000), "99.99%");
+		assert_eq!(Score(10_000_999).display_percentage(1000), "99.99%");
+		assert_eq!(Score(10_001_000).display_percentage(1000), "100.00%");
+
+		// Impossible scores get clamped
+		assert_eq!(Score(10_002_000).display_percentage(1000), "100.00%");
+		assert_eq!(Score(0).display_percentage(1000), "0.00%");
+	}
+
+	#[test]
+	fn parse_consistent_with_display() {
+		for raw in [0, 7, 1_234, 999_999, 9_926_250, 10_000_000, 10_001_234] {
+			let score = Score(raw);
+			assert_eq!(Score::from_str(&format!("{score}")).unwrap(), score);
+			assert_eq!(Score::from_str(&format!("{raw}")).unwrap(), score);
+		}
+	}
+
+	#[test]
+	fn parse_rejects_invalid_scores() {
+		assert_eq!(Score::from_str(" 9'926 250 ").unwrap(), Score(9_926_250));
+		assert!(Score::from_str("").is_err());
+		assert!(Score::from_str("'''").is_err());
+		assert!(Score::from_str("9'9x6'250").is_err());
+		assert!(Score::from_str("-9'926'250").is_err());
+		assert!(Score::from_str("10'010'001").is_err());
+	}
 }
 // }}}
@@ -0,0 +1,161 @@
+//! Pure scoring math, operating on plain integers.
+//!
+//! Nothing in here knows about charts, plays or the bot itself — scores are
+//! raw standard scores, note counts are plain integers, and chart constants
+//! are stored in fixed point (multiplied by 100). The only dependencies are
+//! [num] and the [super::rating] helpers, which keeps the module easy to
+//! extract for reuse by other tools (like a web calculator). Please keep it
+//! that way; convenience wrappers belong in [super::score].
+
+// {{{ Imports
+use num::{Rational32, Rational64};
+
+use super::rating::{rating_from_fixed, Rating};
+// }}}
+
+// {{{ Score analysis
+/// The amount of score a single far note is worth.
+#[inline]
+pub fn increment(note_count: u32) -> Rational64 {
+	Rational64::new_raw(5_000_000, note_count as i64).reduced()
+}
+
+/// Returns the zeta score, the number of shinies, and the number of score units.
+///
+/// Pure (and higher) notes reward two score units, far notes reward one, and lost notes reward
+/// none.
+pub fn analyse(score: u32, note_count: u32) -> (u32, u32, u32) {
+	// Smallest possible difference between (zeta-)scores
+	let increment = increment(note_count);
+	let zeta_increment = Rational64::new_raw(2_000_000, note_count as i64).reduced();
+
+	let score = Rational64::from_integer(score as i64);
+	let score_units = (score / increment).floor();
+
+	let non_shiny_score = (score_units * increment).floor();
+	let shinies = score - non_shiny_score;
+
+	let zeta_score_units = Rational64::from_integer(2) * score_units + shinies;
+	let zeta_score = (zeta_increment * zeta_score_units).floor().to_integer() as u32;
+
+	(
+		zeta_score,
+		shinies.to_integer() as u32,
+		score_units.to_integer() as u32,
+	)
+}
+
+/// Compute a score without making a distinction between shinies and pures. That is, the given
+/// value for `pures` must refer to the sum of `pure` and `shiny` notes.
+///
+/// This is the simplest way to compute a score, and is useful for error analysis.
+#[inline]
+pub fn compute_naive(note_count: u32, pures: u32, fars: u32) -> u32 {
+	(increment(note_count) * Rational64::from_integer((2 * pures + fars) as i64))
+		.floor()
+		.to_integer() as u32
+}
+// }}}
+// {{{ Scoring system conversion
+/// Converts a standard score to the EX scoring system (i.e. the zeta score).
+#[inline]
+pub fn to_ex(score: u32, note_count: u32) -> u32 {
+	analyse(score, note_count).0
+}
+
+/// Converts a standard score to the SDF scoring system, which forgives up to
+/// 9 missed shinies before falling back to EX scoring.
+#[inline]
+pub fn to_sdf(score: u32, note_count: u32) -> u32 {
+	let shinies = analyse(score, note_count).1;
+	to_ex(score + 9.min(note_count - shinies), note_count)
+}
+// }}}
+// {{{ Play rating
+/// Computes the play rating of a standard score on a chart with the given
+/// (fixed point) constant.
+#[inline]
+pub fn play_rating(score: u32, chart_constant: u32) -> Rating {
+	rating_from_fixed(chart_constant as i32)
+		+ if score >= 10_000_000 {
+			Rational32::from_integer(2)
+		} else if score >= 9_800_000 {
+			Rational32::from_integer(1)
+				+ Rational32::new(score as i32 - 9_800_000, 200_000).reduced()
+		} else {
+			Rational32::new(score as i32 - 9_500_000, 300_000).reduced()
+		}
+}
+
+/// The inverse of [play_rating]. Computes the smallest score whose play
+/// rating is at least the given one, returning [None] if the rating is out of
+/// reach on the given chart constant.
+pub fn from_play_rating(rating: Rating, chart_constant: u32) -> Option<u32> {
+	let rating = Rational64::new(*rating.numer() as i64, *rating.denom() as i64);
+	let diff = rating - Rational64::new(chart_constant as i64, 100);
+
+	let score = if diff > Rational64::from_integer(2) {
+		return None;
+	} else if diff > Rational64::from_integer(1) {
+		Rational64::from_integer(9_800_000)
+			+ (diff - Rational64::from_integer(1)) * Rational64::from_integer(200_000)
+	} else {
+		Rational64::from_integer(9_500_000) + diff * Rational64::from_integer(300_000)
+	};
+
+	Some(score.ceil().to_integer().max(0) as u32)
+}
+// }}}
+// {{{ Tests
+#[cfg(test)]
+mod scoring_tests {
+	use super::*;
+
+	#[test]
+	fn analyse_splits_shinies_from_units() {
+		// 1000 notes: a far is worth 5000, a pure 10000 (+1 if shiny)
+		assert_eq!(analyse(10_000_000, 1000), (8_000_000, 0, 2000));
+		assert_eq!(analyse(10_001_000, 1000), (10_000_000, 1000, 2000));
+		assert_eq!(analyse(9_995_000, 1000), (7_996_000, 0, 1999));
+		assert_eq!(analyse(0, 1000), (0, 0, 0));
+	}
+
+	#[test]
+	fn naive_scores() {
+		assert_eq!(compute_naive(1000, 1000, 0), 10_000_000);
+		assert_eq!(compute_naive(1000, 999, 1), 9_995_000);
+		assert_eq!(compute_naive(1000, 0, 0), 0);
+		// Non-integral increments get floored
+		assert_eq!(compute_naive(3, 1, 0), 3_333_333);
+	}
+
+	#[test]
+	fn conversions() {
+		assert_eq!(to_ex(10_001_000, 1000), 10_000_000);
+		assert_eq!(to_ex(10_000_000, 1000), 8_000_000);
+
+		// Up to 9 missed shinies get forgiven
+		assert_eq!(to_sdf(10_000_991, 1000), 10_000_000);
+		assert_eq!(to_sdf(10_000_990, 1000), to_ex(10_000_999, 1000));
+		assert_eq!(to_sdf(10_001_000, 1000), 10_000_000);
+	}
+
+	#[test]
+	fn play_ratings() {
+		assert_eq!(play_rating(10_000_000, 1000), rating_from_fixed(1200));
+		assert_eq!(play_rating(9_800_000, 1000), rating_from_fixed(1100));
+		assert_eq!(play_rating(9_500_000, 1000), rating_from_fixed(1000));
+		assert_eq!(play_rating(9_200_000, 1000), rating_from_fixed(900));
+
+		assert_eq!(
+			from_play_rating(rating_from_fixed(1100), 1000),
+			Some(9_800_000)
+		);
+		assert_eq!(
+			from_play_rating(rating_from_fixed(1000), 1000),
+			Some(9_500_000)
+		);
+		assert_eq!(from_play_rating(rating_from_fixed(1201), 1000), None);
+	}
+}
+// }}}
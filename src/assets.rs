@@ -6,10 +6,12 @@ use std::str::FromStr;
 use std::sync::{LazyLock, OnceLock};
 use std::thread::LocalKey;
 
+use anyhow::anyhow;
 use freetype::{Face, Library};
 use image::{DynamicImage, RgbaImage};
 
 use crate::arcaea::chart::Difficulty;
+use crate::context::Error;
 use crate::timed;
 // }}}
 
@@ -41,33 +43,59 @@ pub fn get_asset_dir() -> PathBuf {
 }
 // }}}
 // {{{ Font helpers
+/// A lazily loaded font face. Loading errors are kept around (as messages,
+/// since faces are stored in thread locals) instead of panicking, such that
+/// only the features depending on the font in question become unavailable.
+pub type FontCell = RefCell<Result<Face, String>>;
+
 #[inline]
-fn get_font(name: &str) -> RefCell<Face> {
+fn get_font(key: &str, name: &str) -> FontCell {
 	let fonts_dir = get_path("SHIMMERING_FONTS_DIR");
+	let path = fonts_dir.join(name);
 	let face = FREETYPE_LIB.with(|lib| {
-		lib.new_face(fonts_dir.join(name), 0)
-			.unwrap_or_else(|_| panic!("Could not load {} font", name))
+		lib.new_face(&path, 0).map_err(|e| {
+			format!(
+				"Could not load font {key} from {}: {e}",
+				path.to_string_lossy()
+			)
+		})
 	});
+
 	RefCell::new(face)
 }
 
+/// Runs a computation on a single font face, erroring out if the font
+/// could not be loaded.
+#[inline]
+pub fn with_face<T>(
+	font: &'static LocalKey<FontCell>,
+	f: impl FnOnce(&mut Face) -> Result<T, Error>,
+) -> Result<T, Error> {
+	font.with_borrow_mut(|face| match face {
+		Ok(face) => f(face),
+		Err(message) => Err(anyhow!("{message}")),
+	})
+}
+
 #[inline]
 pub fn with_font<T>(
-	primary: &'static LocalKey<RefCell<Face>>,
-	f: impl FnOnce(&mut [&mut Face]) -> T,
-) -> T {
-	UNI_FONT.with_borrow_mut(|uni| primary.with_borrow_mut(|primary| f(&mut [primary, uni])))
+	primary: &'static LocalKey<FontCell>,
+	f: impl FnOnce(&mut [&mut Face]) -> Result<T, Error>,
+) -> Result<T, Error> {
+	with_face(&UNI_FONT, |uni| {
+		with_face(primary, |primary| f(&mut [primary, uni]))
+	})
 }
 // }}}
 // {{{ Font loading
 // TODO: I might want to embed those into the binary 🤔
 thread_local! {
 pub static FREETYPE_LIB: Library = Library::init().unwrap();
-pub static EXO_FONT: RefCell<Face> = get_font("Exo[wght].ttf");
-pub static GEOSANS_FONT: RefCell<Face> = get_font("GeosansLight.ttf");
-pub static KAZESAWA_FONT: RefCell<Face> = get_font("Kazesawa-Regular.ttf");
-pub static KAZESAWA_BOLD_FONT: RefCell<Face> = get_font("Kazesawa-Bold.ttf");
-pub static UNI_FONT: RefCell<Face> = get_font("unifont.otf");
+pub static EXO_FONT: FontCell = get_font("EXO_FONT", "Exo[wght].ttf");
+pub static GEOSANS_FONT: FontCell = get_font("GEOSANS_FONT", "GeosansLight.ttf");
+pub static KAZESAWA_FONT: FontCell = get_font("KAZESAWA_FONT", "Kazesawa-Regular.ttf");
+pub static KAZESAWA_BOLD_FONT: FontCell = get_font("KAZESAWA_BOLD_FONT", "Kazesawa-Bold.ttf");
+pub static UNI_FONT: FontCell = get_font("UNI_FONT", "unifont.otf");
 }
 // }}}
 // {{{ Asset art helpers
@@ -123,3 +151,56 @@ pub fn get_difficulty_background(difficulty: Difficulty) -> &'static RgbaImage {
 	})[difficulty.to_index()]
 }
 // }}}
+// {{{ Verification
+/// Every file loaded by the `get_asset!` calls above. Must be kept in sync!
+const ASSET_FILES: [&str; 8] = [
+	"count_background.png",
+	"score_background.png",
+	"status_background.png",
+	"grade_background.png",
+	"top_background.png",
+	"name_background.png",
+	"ptt_emblem.png",
+	"b30_background.jpg",
+];
+
+/// Attempts to load every known asset image and font, such that missing or
+/// corrupt files can be reported upfront (instead of making commands panic
+/// the first time they use them). Returns a description of every problem
+/// found.
+pub fn verify_assets() -> Vec<String> {
+	let mut problems = Vec::new();
+	let assets_dir = get_asset_dir();
+
+	let difficulty_files = Difficulty::DIFFICULTY_SHORTHANDS
+		.map(|shorthand| format!("diff_{}.png", shorthand.to_lowercase()));
+
+	for name in ASSET_FILES
+		.iter()
+		.copied()
+		.chain(difficulty_files.iter().map(String::as_str))
+	{
+		let path = assets_dir.join(name);
+		if let Err(err) = image::open(&path) {
+			problems.push(format!(
+				"Could not read asset {}: {err}",
+				path.to_string_lossy()
+			));
+		}
+	}
+
+	for font in [
+		&EXO_FONT,
+		&GEOSANS_FONT,
+		&KAZESAWA_FONT,
+		&KAZESAWA_BOLD_FONT,
+		&UNI_FONT,
+	] {
+		if let Err(err) = with_face(font, |_| Ok(())) {
+			problems.push(format!("{err}"));
+		}
+	}
+
+	problems
+}
+// }}}
@@ -7,6 +7,10 @@ pub struct Cli {
 
 #[derive(clap::Subcommand)]
 pub enum Command {
-	PrepareJackets {},
+	PrepareJackets(crate::commands::prepare_jackets::Args),
 	Analyse(crate::commands::analyse::Args),
+	/// Runs recognition on every screenshot in a directory, writing the results to a CSV.
+	Batch(crate::commands::batch::Args),
+	/// Utilities for checking on the asset directory.
+	Assets(crate::commands::assets::Args),
 }
@@ -1,20 +1,114 @@
 // {{{ Imports
 use std::path::PathBuf;
 
+use serde::Serialize;
+
+use crate::commands::batch::recognise;
 use crate::context::CliContext;
+use shimmeringmoon::arcaea::chart::Difficulty;
 use shimmeringmoon::commands::discord::MessageContext;
 use shimmeringmoon::commands::score::magic_impl;
 use shimmeringmoon::context::{Error, UserContext};
+use shimmeringmoon::recognition::recognize::ImageAnalyzer;
+use shimmeringmoon::user::User;
 // }}}
 
+/// How the results get printed. Either way, recognised plays get saved
+/// exactly like `score magic` would.
+#[derive(Debug, Clone, Copy, Default, clap::ValueEnum)]
+pub enum Format {
+	/// Human readable output.
+	#[default]
+	Text,
+	/// A JSON array with one entry per file.
+	Json,
+}
+
 #[derive(clap::Args)]
 pub struct Args {
 	files: Vec<PathBuf>,
+
+	#[arg(long, value_enum, default_value_t)]
+	format: Format,
+}
+
+/// The JSON representation of a single analysed screenshot. Files which could
+/// not be recognised only have their `error` field set.
+#[derive(Debug, Default, Serialize)]
+struct Analysis {
+	file: PathBuf,
+	#[serde(skip_serializing_if = "Option::is_none")]
+	play_id: Option<u32>,
+	#[serde(skip_serializing_if = "Option::is_none")]
+	song_id: Option<u32>,
+	#[serde(skip_serializing_if = "Option::is_none")]
+	title: Option<String>,
+	#[serde(skip_serializing_if = "Option::is_none")]
+	difficulty: Option<Difficulty>,
+	#[serde(skip_serializing_if = "Option::is_none")]
+	score: Option<u32>,
+	#[serde(skip_serializing_if = "Option::is_none")]
+	grade: Option<String>,
+	#[serde(skip_serializing_if = "Option::is_none")]
+	far_notes: Option<u32>,
+	#[serde(skip_serializing_if = "Option::is_none")]
+	jacket_distance: Option<f32>,
+	#[serde(skip_serializing_if = "Vec::is_empty")]
+	warnings: Vec<String>,
+	#[serde(skip_serializing_if = "Option::is_none")]
+	error: Option<String>,
 }
 
 pub async fn run(args: Args) -> Result<(), Error> {
-	let mut ctx = CliContext::new(UserContext::new().await?);
-	let res = magic_impl(&mut ctx, &args.files).await;
-	ctx.handle_error(res).await?;
+	match args.format {
+		Format::Text => {
+			let mut ctx = CliContext::new(UserContext::new().await?);
+			let res = magic_impl(&mut ctx, &args.files).await;
+			ctx.handle_error(res).await?;
+		}
+		Format::Json => {
+			let ctx = CliContext::new(UserContext::new().await?);
+			let user = User::from_context(&ctx).map_err(|err| err.error)?;
+			let mut analyzer = ImageAnalyzer::default();
+			let mut results = Vec::with_capacity(args.files.len());
+
+			for file in args.files {
+				analyzer.clear();
+				let saved = recognise(&ctx, &mut analyzer, &file).and_then(|result| {
+					let score = result.play.score();
+					let far_notes = result.play.far_notes();
+					let play = result
+						.play
+						.save(ctx.data(), &user, result.chart)
+						.map_err(|err| err.error)?;
+
+					Ok(Analysis {
+						play_id: Some(play.id),
+						song_id: Some(result.song.id),
+						title: Some(result.song.title.clone()),
+						difficulty: Some(result.chart.difficulty),
+						score: Some(score.0),
+						grade: Some(score.grade().to_string()),
+						far_notes,
+						jacket_distance: result.jacket_distance,
+						warnings: result.warnings,
+						file: file.clone(),
+						..Default::default()
+					})
+				});
+
+				let analysis = saved.unwrap_or_else(|err| Analysis {
+					error: Some(err.to_string()),
+					file,
+					..Default::default()
+				});
+
+				results.push(analysis);
+			}
+
+			println!("{}", serde_json::to_string_pretty(&results)?);
+		}
+	}
+
 	Ok(())
 }
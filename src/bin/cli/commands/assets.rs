@@ -0,0 +1,37 @@
+// {{{ Imports
+use anyhow::bail;
+
+use shimmeringmoon::assets::verify_assets;
+use shimmeringmoon::context::Error;
+// }}}
+
+#[derive(clap::Args)]
+pub struct Args {
+	#[command(subcommand)]
+	command: Command,
+}
+
+#[derive(clap::Subcommand)]
+enum Command {
+	/// Makes sure every asset image and font can be loaded.
+	Verify,
+}
+
+pub fn run(args: Args) -> Result<(), Error> {
+	match args.command {
+		Command::Verify => {
+			let problems = verify_assets();
+			for problem in &problems {
+				println!("{problem}");
+			}
+
+			if !problems.is_empty() {
+				bail!("Found {} problem(s) with the assets", problems.len());
+			}
+
+			println!("All assets loaded successfully");
+		}
+	}
+
+	Ok(())
+}
@@ -0,0 +1,152 @@
+// {{{ Imports
+use std::fmt::Write as _;
+use std::fs;
+use std::io::{stdout, Write};
+use std::path::PathBuf;
+
+use anyhow::{anyhow, Context};
+use image::DynamicImage;
+
+use crate::context::CliContext;
+use shimmeringmoon::arcaea::chart::{Chart, Song};
+use shimmeringmoon::arcaea::play::CreatePlay;
+use shimmeringmoon::commands::score::{decode_screenshot, magic_detect_one};
+use shimmeringmoon::context::{Error, UserContext};
+use shimmeringmoon::recognition::recognize::ImageAnalyzer;
+// }}}
+
+#[derive(clap::Args)]
+pub struct Args {
+	/// Directory containing the screenshots to analyse.
+	dir: PathBuf,
+
+	/// Where to write the CSV containing the recognised scores.
+	#[arg(long, default_value = "results.csv")]
+	output: PathBuf,
+
+	/// Where to write the list of screenshots which could not be recognised.
+	#[arg(long, default_value = "failures.txt")]
+	failures: PathBuf,
+}
+
+/// Everything we can read off a single screenshot.
+pub struct Recognised<'a> {
+	pub song: &'a Song,
+	pub chart: &'a Chart,
+	pub play: CreatePlay,
+	pub warnings: Vec<String>,
+	pub jacket_distance: Option<f32>,
+}
+
+// {{{ Recognition
+/// Runs the same recognition steps as `score magic` (see [magic_detect_one]),
+/// without saving anything.
+pub fn recognise<'a>(
+	ctx: &'a CliContext,
+	analyzer: &mut ImageAnalyzer,
+	path: &PathBuf,
+) -> Result<Recognised<'a>, Error> {
+	let bytes = fs::read(path).with_context(|| format!("Could not read {path:?}"))?;
+	let mut image = decode_screenshot::<CliContext>(path, &bytes).map_err(|err| err.error)?;
+	let mut grayscale_image = DynamicImage::ImageLuma8(image.to_luma8());
+
+	let mut warnings = Vec::new();
+	let (song, chart, play) = magic_detect_one(
+		ctx,
+		analyzer,
+		&mut image,
+		&mut grayscale_image,
+		path,
+		&mut warnings,
+	)
+	.map_err(|err| err.error)?;
+
+	Ok(Recognised {
+		song,
+		chart,
+		play,
+		warnings,
+		jacket_distance: analyzer.last_jacket_distance(),
+	})
+}
+// }}}
+// {{{ CSV helpers
+/// Quotes a CSV field, escaping any quotes inside it.
+#[inline]
+fn csv_field(field: &str) -> String {
+	format!("\"{}\"", field.replace('"', "\"\""))
+}
+// }}}
+
+pub async fn run(args: Args) -> Result<(), Error> {
+	// Nothing gets saved, so there's no need to know who the user is
+	let ctx = CliContext {
+		user_id: 0,
+		data: UserContext::new().await?,
+	};
+	let mut analyzer = ImageAnalyzer::default();
+
+	let mut entries = fs::read_dir(&args.dir)
+		.with_context(|| format!("Could not read directory {:?}", args.dir))?
+		.map(|entry| entry.map(|e| e.path()))
+		.collect::<Result<Vec<_>, _>>()?;
+	entries.retain(|path| path.is_file());
+	entries.sort();
+
+	let mut csv = String::from("filename,song,difficulty,score,far_notes\n");
+	let mut failures = String::new();
+	let mut failure_count = 0;
+
+	for (i, path) in entries.iter().enumerate() {
+		let filename = path
+			.file_name()
+			.and_then(|f| f.to_str())
+			.ok_or_else(|| anyhow!("Invalid filename {path:?}"))?;
+
+		print!("\r{}/{}: {filename}", i + 1, entries.len());
+		stdout().flush()?;
+
+		analyzer.clear();
+		match recognise(&ctx, &mut analyzer, path) {
+			Ok(result) => {
+				writeln!(
+					csv,
+					"{},{},{},{},{}",
+					csv_field(filename),
+					csv_field(&result.song.title),
+					result.chart.difficulty,
+					result.play.score().0,
+					result
+						.play
+						.far_notes()
+						.map(|f| f.to_string())
+						.unwrap_or_default()
+				)?;
+
+				for warning in result.warnings {
+					println!("\n{warning}");
+				}
+			}
+			Err(err) => {
+				failure_count += 1;
+				writeln!(failures, "{filename}: {err}")?;
+			}
+		}
+	}
+
+	println!();
+
+	fs::write(&args.output, csv).with_context(|| format!("Could not write {:?}", args.output))?;
+	fs::write(&args.failures, failures)
+		.with_context(|| format!("Could not write {:?}", args.failures))?;
+
+	println!(
+		"Recognised {}/{} screenshots. Results written to {:?}, failures to {:?}",
+		entries.len() - failure_count,
+		entries.len(),
+		args.output,
+		args.failures
+	);
+
+	Ok(())
+}
@@ -1,2 +1,4 @@
 pub mod analyse;
+pub mod assets;
+pub mod batch;
 pub mod prepare_jackets;
@@ -1,19 +1,24 @@
 // {{{ Imports
 use std::fs;
 use std::io::{stdout, Write};
+use std::path::{Path, PathBuf};
 
 use anyhow::{anyhow, bail, Context};
 use faer::Mat;
 use image::imageops::FilterType;
+use image::RgbImage;
 
+use shimmeringmoon::arcaea::chart::Jacket;
 use shimmeringmoon::arcaea::chart::{Difficulty, SongCache};
 use shimmeringmoon::arcaea::jacket::{
-	image_to_vec, read_jackets, JacketCache, BITMAP_IMAGE_SIZE, IMAGE_VEC_DIM,
+	assign_jacket, compute_rejection_threshold, image_to_vec, read_jackets, select_jacket_files,
+	JacketCache, JacketFile, BITMAP_IMAGE_SIZE, IMAGE_VEC_DIM, JACKET_CACHE_VERSION,
 	JACKET_RECOGNITITION_DIMENSIONS,
 };
 use shimmeringmoon::assets::{get_asset_dir, get_data_dir};
+use shimmeringmoon::bitmap::{BitmapCanvas, Color};
 use shimmeringmoon::context::{connect_db, Error};
-use shimmeringmoon::recognition::fuzzy_song_name::guess_chart_name;
+use shimmeringmoon::recognition::fuzzy_song_name::{guess_chart_name, MatchTolerance};
 // }}}
 
 /// Hacky function which clears the current line of the standard output.
@@ -22,7 +27,88 @@ fn clear_line() {
 	print!("\r                                                                        \r");
 }
 
-pub fn run() -> Result<(), Error> {
+#[derive(clap::Args)]
+pub struct Args {
+	/// Run every check without touching the disk, printing what would have
+	/// been written instead.
+	#[arg(long)]
+	dry_run: bool,
+
+	/// When a jacket gets recognised as the wrong song, write an image
+	/// comparing the two jackets to this directory.
+	#[arg(long)]
+	mismatch_dir: Option<PathBuf>,
+}
+
+// {{{ Mismatch visualisation
+/// Draws the given jacket next to the jacket it has been mistaken for, with
+/// a heat strip underneath showing how far apart each component of their
+/// projected vectors is (brighter means further apart).
+fn visualise_mismatch(
+	jacket_cache: &JacketCache,
+	query: &RgbImage,
+	found: &RgbImage,
+	path: &Path,
+) -> Result<(), Error> {
+	let margin = 10;
+	let strip_height = 24;
+	let width = 2 * BITMAP_IMAGE_SIZE + 3 * margin;
+	let height = BITMAP_IMAGE_SIZE + strip_height + 3 * margin;
+
+	let mut canvas = BitmapCanvas::new(width, height);
+	canvas.fill((0, 0), (width, height), Color::BLACK);
+	canvas.blit_rbg(
+		(margin as i32, margin as i32),
+		query.dimensions(),
+		query.as_raw(),
+	);
+	canvas.blit_rbg(
+		((2 * margin + BITMAP_IMAGE_SIZE) as i32, margin as i32),
+		found.dimensions(),
+		found.as_raw(),
+	);
+
+	// {{{ Draw heat strip
+	let query_vec = jacket_cache.transform_vec(image_to_vec(query).as_ref());
+	let found_vec = jacket_cache.transform_vec(image_to_vec(found).as_ref());
+	let differences = (0..JACKET_RECOGNITITION_DIMENSIONS)
+		.map(|i| (query_vec[(i, 0)] - found_vec[(i, 0)]).abs())
+		.collect::<Vec<_>>();
+	let max_difference = differences.iter().copied().fold(f32::EPSILON, f32::max);
+
+	let cell_width = (width - 2 * margin) / JACKET_RECOGNITITION_DIMENSIONS as u32;
+	for (i, difference) in differences.into_iter().enumerate() {
+		// Goes from black, through red, to yellow
+		let t = difference / max_difference;
+		let color = Color(
+			(255.0 * (2.0 * t).min(1.0)) as u8,
+			(255.0 * (2.0 * t - 1.0).max(0.0)) as u8,
+			0,
+			0xff,
+		);
+
+		canvas.fill(
+			(
+				(margin + i as u32 * cell_width) as i32,
+				(2 * margin + BITMAP_IMAGE_SIZE) as i32,
+			),
+			(cell_width, strip_height),
+			color,
+		);
+	}
+	// }}}
+
+	let image = RgbImage::from_raw(width, height, canvas.buffer.into_vec())
+		.ok_or_else(|| anyhow!("Could not convert canvas to image"))?;
+	image
+		.save(path)
+		.with_context(|| format!("Could not save mismatch visualisation to {path:?}"))?;
+
+	Ok(())
+}
+// }}}
+
+pub fn run(args: Args) -> Result<(), Error> {
 	let db = connect_db(&get_data_dir());
 	let mut song_cache = SongCache::new(&db)?;
 
@@ -34,10 +120,14 @@ pub fn run() -> Result<(), Error> {
 	let raw_songs_dir = songs_dir.join("raw");
 
 	let by_id_dir = songs_dir.join("by_id");
-	if by_id_dir.exists() {
-		fs::remove_dir_all(&by_id_dir).with_context(|| "Could not remove `by_id` dir")?;
+	if args.dry_run {
+		println!("Would recreate {by_id_dir:?}");
+	} else {
+		if by_id_dir.exists() {
+			fs::remove_dir_all(&by_id_dir).with_context(|| "Could not remove `by_id` dir")?;
+		}
+		fs::create_dir_all(&by_id_dir).with_context(|| "Could not create `by_id` dir")?;
 	}
-	fs::create_dir_all(&by_id_dir).with_context(|| "Could not create `by_id` dir")?;
 	// }}}
 	// {{{ Traverse raw songs directory
 	let entries = fs::read_dir(&raw_songs_dir)
@@ -58,31 +148,34 @@ pub fn run() -> Result<(), Error> {
 		stdout().flush()?;
 		// }}}
 
-		let entries = fs::read_dir(dir.path())
+		let mut entries = fs::read_dir(dir.path())
 			.with_context(|| "Couldn't read song directory")?
 			.map(|f| f.unwrap())
 			.filter(|f| !f.file_name().to_str().unwrap().ends_with("_256.jpg"))
 			.collect::<Vec<_>>();
+		entries.sort_by_key(|f| f.file_name());
+
+		let files = entries
+			.into_iter()
+			.map(|file| {
+				let raw_name = file.file_name();
+				let name = raw_name
+					.to_str()
+					.unwrap()
+					.strip_suffix(".jpg")
+					.ok_or_else(|| {
+						anyhow!("No '.jpg' suffix to remove from filename {raw_name:?}")
+					})?;
 
-		for file in &entries {
-			let raw_name = file.file_name();
-			let name = raw_name
-				.to_str()
-				.unwrap()
-				.strip_suffix(".jpg")
-				.ok_or_else(|| anyhow!("No '.jpg' suffix to remove from filename {raw_name:?}"))?;
-
-			let difficulty = match name {
-				"0" => Some(Difficulty::PST),
-				"1" => Some(Difficulty::PRS),
-				"2" => Some(Difficulty::FTR),
-				"3" => Some(Difficulty::BYD),
-				"4" => Some(Difficulty::ETR),
-				"base" => None,
-				"base_night" => None,
-				"base_ja" => None,
-				_ => bail!("Unknown jacket suffix {}", name),
-			};
+				let kind = JacketFile::from_file_stem(name)
+					.ok_or_else(|| anyhow!("Unknown jacket suffix {}", name))?;
+
+				Ok((kind, file))
+			})
+			.collect::<Result<Vec<_>, Error>>()?;
+
+		for (kind, file) in select_jacket_files(files) {
+			let difficulty = kind.difficulty();
 
 			// Sometimes it's useful to distinguish between separate (but related)
 			// charts like "Vicious Heroism" and "Vicious [ANTi] Heroism" being in
@@ -91,13 +184,19 @@ pub fn run() -> Result<(), Error> {
 			// (or unless we are parsing the tutorial)
 			let search_difficulty = difficulty;
 
-			let (song, _) = guess_chart_name(dir_name, &song_cache, search_difficulty, true)
-				.with_context(|| format!("Could not recognise chart name from '{dir_name}'"))?;
+			// Directory names are written by hand, so leniency is fine here
+			let (song, _) = guess_chart_name(
+				dir_name,
+				&song_cache,
+				search_difficulty,
+				MatchTolerance::LENIENT,
+			)
+			.with_context(|| format!("Could not recognise chart name from '{dir_name}'"))?;
 
 			// {{{ Set up `out_dir` paths
 			let out_dir = {
 				let out = by_id_dir.join(song.id.to_string());
-				if !out.exists() {
+				if !out.exists() && !args.dry_run {
 					fs::create_dir_all(&out).with_context(|| {
 						format!(
 							"Could not create parent dir for song '{}' inside `by_id`",
@@ -126,6 +225,38 @@ pub fn run() -> Result<(), Error> {
 			jacket_vector_ids.push(song.id);
 			jacket_vectors.push(image_to_vec(&image));
 
+			// {{{ Dry run
+			// Nothing gets written to disk, so the jackets must be handed to
+			// the song cache directly for the recognition test to work.
+			if args.dry_run {
+				let song_id = song.id;
+				let bitmap: &'static _ = Box::leak(Box::new(small_image.into_rgb8()));
+				assign_jacket(
+					&mut song_cache,
+					song_id,
+					difficulty,
+					Jacket {
+						raw: contents,
+						bitmap,
+					},
+				)?;
+
+				clear_line();
+				for kind in [
+					format!("{BITMAP_IMAGE_SIZE}"),
+					"full".to_string(),
+					"blurred".to_string(),
+				] {
+					println!(
+						"Would write {:?}",
+						out_dir.join(format!("{difficulty_string}_{kind}.jpg"))
+					);
+				}
+
+				continue;
+			}
+			// }}}
+
 			{
 				let image_small_path =
 					out_dir.join(format!("{difficulty_string}_{BITMAP_IMAGE_SIZE}.jpg"));
@@ -155,8 +286,10 @@ pub fn run() -> Result<(), Error> {
 	clear_line();
 	println!("Successfully processed jackets");
 
-	read_jackets(&mut song_cache)?;
-	println!("Successfully read jackets");
+	if !args.dry_run {
+		read_jackets(&mut song_cache)?;
+		println!("Successfully read jackets");
+	}
 
 	// {{{ Warn on missing jackets
 	for chart in song_cache.charts() {
@@ -189,10 +322,19 @@ pub fn run() -> Result<(), Error> {
 	};
 	// }}}
 	// {{{ Build jacket cache
+	let projected_matrix = &transform_matrix * &jacket_matrix;
+	let rejection_threshold =
+		compute_rejection_threshold(projected_matrix.as_ref(), &jacket_vector_ids);
+	println!("Using a jacket rejection threshold of {rejection_threshold:.2}");
+
 	let jacket_cache = JacketCache {
+		version: JACKET_CACHE_VERSION,
+		bitmap_size: BITMAP_IMAGE_SIZE,
 		jacket_ids: jacket_vector_ids,
-		jacket_matrix: &transform_matrix * &jacket_matrix,
+		jacket_matrix: projected_matrix,
 		transform_matrix,
+		rejection_threshold,
+		raw_vectors: Vec::new(),
 	};
 	// }}}
 
@@ -217,6 +359,27 @@ pub fn run() -> Result<(), Error> {
 			if let Some((_, song_id)) = jacket_cache.recognise(jacket.bitmap) {
 				if song_id != song.id {
 					let mistake = &song_cache.lookup_song(song_id)?.song;
+
+					// {{{ Visualise mismatch
+					if let Some(mismatch_dir) = &args.mismatch_dir {
+						let found = song_cache
+							.lookup_song(song_id)?
+							.charts()
+							.filter_map(|(_, chart_id)| song_cache.lookup_chart(chart_id).ok())
+							.find_map(|(_, chart)| chart.cached_jacket);
+
+						if let Some(found) = found {
+							fs::create_dir_all(mismatch_dir)
+								.with_context(|| "Could not create mismatch dir")?;
+							let path = mismatch_dir.join(format!("{}_vs_{}.png", song.id, song_id));
+							visualise_mismatch(&jacket_cache, jacket.bitmap, found.bitmap, &path)?;
+
+							clear_line();
+							println!("Wrote mismatch visualisation to {path:?}");
+						}
+					}
+					// }}}
+
 					bail!(
 						"Could not recognise jacket for {song} [{}]. Found song {mistake} instead.",
 						chart.difficulty
@@ -240,8 +403,13 @@ pub fn run() -> Result<(), Error> {
 		println!("Encoded {} images", jacket_vectors.len());
 		let bytes = postcard::to_allocvec(&jacket_cache)
 			.with_context(|| "Coult not encode jacket matrix")?;
-		fs::write(songs_dir.join("recognition_matrix"), bytes)
-			.with_context(|| "Could not write jacket matrix")?;
+		let matrix_path = songs_dir.join("recognition_matrix");
+
+		if args.dry_run {
+			println!("Would write {} bytes to {matrix_path:?}", bytes.len());
+		} else {
+			fs::write(matrix_path, bytes).with_context(|| "Could not write jacket matrix")?;
+		}
 	}
 	// }}}
 
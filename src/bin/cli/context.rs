@@ -60,6 +60,20 @@ impl MessageContext for CliContext {
 		Ok(())
 	}
 
+	// {{{ Editable replies
+	type ReplyHandle = ();
+
+	async fn reply_editable(&mut self, text: &str) -> Result<Self::ReplyHandle, Error> {
+		self.reply(text).await
+	}
+
+	/// Printed output cannot be edited, so edits are printed as new lines.
+	async fn edit_reply(&mut self, _handle: &Self::ReplyHandle, text: &str) -> Result<(), Error> {
+		println!("[Edit] {text}");
+		Ok(())
+	}
+	// }}}
+
 	// {{{ Input attachments
 	type Attachment = PathBuf;
 
@@ -10,12 +10,18 @@ mod context;
 async fn main() -> Result<(), Error> {
 	let cli = Cli::parse();
 	match cli.command {
-		Command::PrepareJackets {} => {
-			commands::prepare_jackets::run()?;
+		Command::PrepareJackets(args) => {
+			commands::prepare_jackets::run(args)?;
 		}
 		Command::Analyse(args) => {
 			commands::analyse::run(args).await?;
 		}
+		Command::Batch(args) => {
+			commands::batch::run(args).await?;
+		}
+		Command::Assets(args) => {
+			commands::assets::run(args)?;
+		}
 	}
 
 	Ok(())
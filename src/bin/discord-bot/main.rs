@@ -1,9 +1,35 @@
 use poise::serenity_prelude::{self as serenity};
 use shimmeringmoon::arcaea::play::generate_missing_scores;
 use shimmeringmoon::context::{Error, UserContext};
+use shimmeringmoon::guild::DEFAULT_PREFIX;
 use shimmeringmoon::{commands, timed};
 use std::{env::var, sync::Arc, time::Duration};
 
+// {{{ Metrics
+#[cfg(feature = "metrics")]
+async fn serve_metrics() {
+	let app = axum::Router::new().route(
+		"/metrics",
+		axum::routing::get(|| async {
+			shimmeringmoon::metrics::render()
+				.map_err(|e| (axum::http::StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))
+		}),
+	);
+
+	let port: u32 = shimmeringmoon::assets::get_var("SHIMMERING_METRICS_PORT")
+		.parse()
+		.expect("`SHIMMERING_METRICS_PORT` is not a valid port");
+	let listener = tokio::net::TcpListener::bind(format!("127.0.0.1:{}", port))
+		.await
+		.unwrap();
+
+	println!("Serving metrics on {}", listener.local_addr().unwrap());
+
+	if let Err(e) = axum::serve(listener, app).await {
+		println!("Metrics server crashed: {}", e)
+	}
+}
+// }}}
 // {{{ Error handler
 async fn on_error(error: poise::FrameworkError<'_, UserContext, Error>) {
 	if let Err(e) = poise::builtins::on_error(error).await {
@@ -14,22 +40,43 @@ async fn on_error(error: poise::FrameworkError<'_, UserContext, Error>) {
 
 #[tokio::main]
 async fn main() {
+	// {{{ Asset verification
+	if var("SHIMMERING_VERIFY_ASSETS").unwrap_or_default() == "1" {
+		let problems = shimmeringmoon::assets::verify_assets();
+		if !problems.is_empty() {
+			for problem in &problems {
+				println!("{problem}");
+			}
+
+			panic!("Found {} problem(s) with the assets", problems.len());
+		}
+	}
+	// }}}
 	// {{{ Poise options
 	let options = poise::FrameworkOptions {
 		commands: vec![
 			commands::help(),
+			commands::commands(),
 			commands::score::score(),
 			commands::stats::stats(),
 			commands::chart::chart(),
 			commands::calc::calc(),
+			commands::admin::admin(),
+			commands::user::user(),
+			commands::config::config(),
 		],
 		prefix_options: poise::PrefixFrameworkOptions {
-			stripped_dynamic_prefix: Some(|_ctx, message, _user_ctx| {
-				Box::pin(async {
+			stripped_dynamic_prefix: Some(|_ctx, message, user_ctx| {
+				Box::pin(async move {
+					let prefix = match message.guild_id {
+						Some(guild_id) => user_ctx.guild_configs.prefix(guild_id.get()),
+						None => DEFAULT_PREFIX.to_string(),
+					};
+
 					if message.author.bot || Into::<u64>::into(message.author.id) == 1 {
 						Ok(None)
-					} else if message.content.starts_with("!") {
-						Ok(Some(message.content.split_at(1)))
+					} else if message.content.starts_with(&prefix) {
+						Ok(Some(message.content.split_at(prefix.len())))
 					} else if message.guild_id.is_none() {
 						if message.content.trim().is_empty() {
 							Ok(Some(("", "score magic")))
@@ -58,9 +105,12 @@ async fn main() {
 				poise::builtins::register_globally(ctx, &framework.options().commands).await?;
 				let ctx = UserContext::new().await?;
 
+				#[cfg(feature = "metrics")]
+				tokio::spawn(serve_metrics());
+
 				if var("SHIMMERING_REGEN_SCORES").unwrap_or_default() == "1" {
 					timed!("generate_missing_scores", {
-						generate_missing_scores(&ctx).await?;
+						generate_missing_scores(&ctx, None).await?;
 					});
 				}
 
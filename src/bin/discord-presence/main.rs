@@ -11,10 +11,39 @@ use shimmeringmoon::assets::get_var;
 use shimmeringmoon::context::Error;
 // }}}
 
+// {{{ Reconnection
+/// Upper bound for the delay between two reconnection attempts.
+const MAX_RECONNECT_DELAY: Duration = Duration::from_secs(5 * 60);
+
+/// Keeps trying to reconnect to discord (e.g. after the client got
+/// restarted), doubling the delay between attempts each time.
+async fn reconnect(ipc: &mut DiscordIpcClient) {
+	let mut delay = Duration::from_secs(1);
+	let mut attempt = 1;
+
+	loop {
+		println!("Reconnecting to discord (attempt {attempt})...");
+		match ipc.reconnect().map_err(|e| e.to_string()) {
+			Ok(()) => {
+				println!("Reconnected to discord");
+				return;
+			}
+			Err(e) => println!("Could not reconnect: {e}. Retrying in {delay:?}"),
+		}
+
+		tokio::time::sleep(delay).await;
+		delay = (delay * 2).min(MAX_RECONNECT_DELAY);
+		attempt += 1;
+	}
+}
+// }}}
+
 #[tokio::main]
 async fn main() -> Result<(), Error> {
 	let server_url = get_var("SHIMMERING_SERVER_URL");
 	let client_id = get_var("SHIMMERING_DISCORD_ID");
+	let server_token = std::env::var("SHIMMERING_SERVER_TOKEN").ok();
+	let client = reqwest::Client::new();
 
 	println!("Connecting to discord...");
 	let mut ipc = DiscordIpcClient::new(&client_id).map_err(|e| anyhow!("{}", e))?;
@@ -23,14 +52,23 @@ async fn main() -> Result<(), Error> {
 	println!("Starting presence loop...");
 	loop {
 		println!("Getting most recent score...");
-		let res = reqwest::get(format!("{}/plays/latest", server_url)).await;
+		let mut req = client.get(format!("{}/plays/latest", server_url));
+		if let Some(token) = &server_token {
+			req = req.bearer_auth(token);
+		}
+
+		let res = req.send().await;
 
 		let res = match res.and_then(|r| r.error_for_status()) {
 			Ok(v) => v,
 			Err(e) => {
-				ipc.clear_activity().map_err(|e| anyhow!("{}", e))?;
 				println!("{e}");
 
+				if let Err(e) = ipc.clear_activity().map_err(|e| e.to_string()) {
+					println!("Could not clear activity: {e}");
+					reconnect(&mut ipc).await;
+				}
+
 				tokio::time::sleep(Duration::from_secs(10)).await;
 				continue;
 			}
@@ -64,7 +102,12 @@ async fn main() -> Result<(), Error> {
 			.state(&state);
 
 		println!("Sending activity");
-		ipc.set_activity(activity).map_err(|e| anyhow!("{}", e))?;
+		if let Err(e) = ipc.set_activity(activity).map_err(|e| e.to_string()) {
+			println!("Could not set activity: {e}");
+			reconnect(&mut ipc).await;
+			continue;
+		}
+
 		tokio::time::sleep(Duration::from_secs(30)).await;
 	}
 }
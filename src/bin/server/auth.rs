@@ -0,0 +1,57 @@
+// {{{ Imports
+use anyhow::anyhow;
+use axum::extract::{Query, Request, State};
+use axum::http::{header, StatusCode};
+use axum::middleware::Next;
+use axum::response::Response;
+use serde::Deserialize;
+
+use crate::context::AppContext;
+use crate::error::AppError;
+// }}}
+
+/// Compares two byte strings in a manner which does not leak the position
+/// of the first mismatch through timing.
+fn constant_time_eq(a: &[u8], b: &[u8]) -> bool {
+	a.len() == b.len() && a.iter().zip(b).fold(0, |acc, (x, y)| acc | (x ^ y)) == 0
+}
+
+/// Query parameters which can carry the token (see [require_token]).
+#[derive(Deserialize)]
+struct TokenQuery {
+	token: String,
+}
+
+/// Rejects requests which do not carry the configured bearer token. When no
+/// token has been configured, every request is let through.
+///
+/// Browsers cannot attach headers to websocket connections, so the token can
+/// also be provided through a (percent-encoded) `token` query parameter.
+pub async fn require_token(
+	State(state): State<AppContext>,
+	request: Request,
+	next: Next,
+) -> Result<Response, AppError> {
+	if let Some(expected) = state.token {
+		let provided = request
+			.headers()
+			.get(header::AUTHORIZATION)
+			.and_then(|value| value.to_str().ok())
+			.and_then(|value| value.strip_prefix("Bearer "))
+			.map(str::to_owned)
+			.or_else(|| {
+				Query::<TokenQuery>::try_from_uri(request.uri())
+					.ok()
+					.map(|Query(query)| query.token)
+			});
+
+		if !provided.is_some_and(|token| constant_time_eq(token.as_bytes(), expected.as_bytes())) {
+			return Err(AppError::new(
+				anyhow!("Missing or invalid authorization token"),
+				StatusCode::UNAUTHORIZED,
+			));
+		}
+	}
+
+	Ok(next.run(request).await)
+}
@@ -1,12 +1,25 @@
+use shimmeringmoon::arcaea::play::PlayWithDetails;
 use shimmeringmoon::context::UserContext;
+use tokio::sync::broadcast;
 
 #[derive(Clone, Copy)]
 pub struct AppContext {
 	pub ctx: &'static UserContext,
+
+	/// Token requests to protected routes must carry. Routes are left open
+	/// when this is not set.
+	pub token: Option<&'static str>,
+
+	/// Every newly saved play gets published here (see [crate::live]).
+	pub plays: &'static broadcast::Sender<PlayWithDetails>,
 }
 
 impl AppContext {
-	pub fn new(ctx: &'static UserContext) -> Self {
-		Self { ctx }
+	pub fn new(
+		ctx: &'static UserContext,
+		token: Option<&'static str>,
+		plays: &'static broadcast::Sender<PlayWithDetails>,
+	) -> Self {
+		Self { ctx, token, plays }
 	}
 }
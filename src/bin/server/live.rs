@@ -0,0 +1,89 @@
+//! Plays are saved by the discord bot, which runs in a separate process from
+//! the server. Instead of having the bot notify the server (which would
+//! require the two to know about each other), the server polls the database
+//! they share for plays it has not seen yet, and publishes them to a
+//! broadcast channel every websocket client is subscribed to.
+
+// {{{ Imports
+use std::time::Duration;
+
+use shimmeringmoon::arcaea::play::{Play, PlayWithDetails};
+use shimmeringmoon::context::{Error, UserContext};
+use tokio::sync::broadcast;
+// }}}
+
+/// How often the database gets checked for new plays.
+const POLL_INTERVAL: Duration = Duration::from_secs(5);
+
+/// How many plays a slow client can fall behind by before missing any.
+pub const CHANNEL_CAPACITY: usize = 64;
+
+// {{{ Query plays
+/// The id of the most recently saved play, or `0` if there are none.
+pub fn latest_play_id(ctx: &UserContext) -> Result<u32, Error> {
+	let id = ctx
+		.db
+		.get()?
+		.prepare_cached("SELECT COALESCE(MAX(id), 0) FROM plays")?
+		.query_row((), |row| row.get(0))?;
+
+	Ok(id)
+}
+
+/// Returns every play with an id greater than the given one, oldest first.
+fn plays_after(ctx: &UserContext, after_id: u32) -> Result<Vec<PlayWithDetails>, Error> {
+	let conn = ctx.db.get()?;
+	let mut query = conn.prepare_cached(
+		"
+        SELECT
+        p.id, p.chart_id, p.user_id, p.created_at,
+        p.max_recall, p.far_notes, p.pure_notes, p.lost_notes, p.clear_kind, s.score
+        FROM plays p
+        JOIN scores s ON s.play_id = p.id
+        WHERE s.scoring_system='standard'
+        AND p.id>?
+        ORDER BY p.id
+    ",
+	)?;
+
+	let plays = query
+		.query_and_then([after_id], |row| -> Result<_, Error> {
+			let (song, chart) = ctx.song_cache.lookup_chart(row.get("chart_id")?)?;
+			let play = Play::from_sql(chart, row)?;
+			Ok(PlayWithDetails {
+				play,
+				song: song.clone(),
+				chart: chart.clone(),
+			})
+		})?
+		.collect::<Result<Vec<_>, _>>()?;
+
+	Ok(plays)
+}
+// }}}
+// {{{ Watch loop
+/// Publishes every play with an id greater than the given one (see
+/// [latest_play_id]). Never returns.
+pub async fn watch_plays(
+	ctx: &'static UserContext,
+	sender: &'static broadcast::Sender<PlayWithDetails>,
+	mut last_id: u32,
+) {
+	let mut interval = tokio::time::interval(POLL_INTERVAL);
+	loop {
+		interval.tick().await;
+
+		match plays_after(ctx, last_id) {
+			Ok(plays) => {
+				for play in plays {
+					last_id = last_id.max(play.play.id);
+
+					// Errors only mean nobody is currently listening
+					let _ = sender.send(play);
+				}
+			}
+			Err(e) => println!("Could not poll for new plays: {e}"),
+		}
+	}
+}
+// }}}
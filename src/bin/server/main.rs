@@ -1,30 +1,59 @@
+use auth::require_token;
 use context::AppContext;
 use routes::jacket::get_jacket_image;
+use routes::live_plays::subscribe_to_plays;
 use routes::recent_plays::get_recent_play;
 use shimmeringmoon::assets::get_var;
 use shimmeringmoon::context::{Error, UserContext};
 
+mod auth;
 mod context;
 mod error;
+mod live;
 mod routes;
 
 #[tokio::main]
 async fn main() -> Result<(), Error> {
 	let ctx = Box::leak(Box::new(UserContext::new().await?));
+	let token = std::env::var("SHIMMERING_SERVER_TOKEN")
+		.ok()
+		.filter(|token| !token.is_empty())
+		.map(|token| &*token.leak());
 
-	let app = axum::Router::new()
+	let (plays, _) = tokio::sync::broadcast::channel(live::CHANNEL_CAPACITY);
+	let plays = Box::leak(Box::new(plays));
+
+	// Starting from zero would replay every play ever saved to the first
+	// clients, so we'd rather not start at all.
+	let last_id = live::latest_play_id(ctx)?;
+	tokio::spawn(live::watch_plays(ctx, plays, last_id));
+
+	let state = AppContext::new(ctx, token, plays);
+
+	// NOTE: jackets are fetched by discord's media proxy, which has no way of
+	// sending the token along, so they are left public.
+	let protected = axum::Router::new()
 		.route("/plays/latest", axum::routing::get(get_recent_play))
+		.route("/ws", axum::routing::get(subscribe_to_plays))
+		.route_layer(axum::middleware::from_fn_with_state(state, require_token));
+
+	let app = axum::Router::new()
+		.merge(protected)
 		.route(
 			"/jackets/by_chart_id/:chart_id",
 			axum::routing::get(get_jacket_image),
 		)
-		.with_state(AppContext::new(ctx));
+		.with_state(state);
 
 	let port: u32 = get_var("SHIMMERING_SERVER_PORT").parse()?;
 	let listener = tokio::net::TcpListener::bind(format!("127.0.0.1:{}", port))
 		.await
 		.unwrap();
 
+	if token.is_none() {
+		println!("SHIMMERING_SERVER_TOKEN is not set, requests will not be authenticated");
+	}
+
 	println!("listening on {}", listener.local_addr().unwrap());
 
 	axum::serve(listener, app).await?;
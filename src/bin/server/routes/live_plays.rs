@@ -0,0 +1,42 @@
+// {{{ Imports
+use axum::extract::ws::{Message, WebSocket, WebSocketUpgrade};
+use axum::extract::State;
+use axum::response::Response;
+use shimmeringmoon::arcaea::play::PlayWithDetails;
+use tokio::sync::broadcast::{error::RecvError, Receiver};
+
+use crate::context::AppContext;
+// }}}
+
+/// Upgrades the connection to a websocket, which then receives a JSON-encoded
+/// [PlayWithDetails] message for every newly saved play.
+pub async fn subscribe_to_plays(State(state): State<AppContext>, ws: WebSocketUpgrade) -> Response {
+	let receiver = state.plays.subscribe();
+	ws.on_upgrade(move |socket| forward_plays(socket, receiver))
+}
+
+async fn forward_plays(mut socket: WebSocket, mut receiver: Receiver<PlayWithDetails>) {
+	loop {
+		let play = match receiver.recv().await {
+			Ok(play) => play,
+			Err(RecvError::Lagged(skipped)) => {
+				println!("Websocket client fell behind, skipping {skipped} plays");
+				continue;
+			}
+			Err(RecvError::Closed) => return,
+		};
+
+		let message = match serde_json::to_string(&play) {
+			Ok(message) => message,
+			Err(e) => {
+				println!("Could not encode play: {e}");
+				continue;
+			}
+		};
+
+		// The client has disconnected
+		if socket.send(Message::Text(message)).await.is_err() {
+			return;
+		}
+	}
+}
@@ -1,2 +1,3 @@
 pub mod jacket;
+pub mod live_plays;
 pub mod recent_plays;
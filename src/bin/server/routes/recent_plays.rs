@@ -23,7 +23,7 @@ pub async fn get_recent_play(
 			"
         SELECT 
         p.id, p.chart_id, p.user_id, p.created_at,
-        p.max_recall, p.far_notes, s.score
+        p.max_recall, p.far_notes, p.pure_notes, p.lost_notes, p.clear_kind, s.score
         FROM plays p
         JOIN scores s ON s.play_id = p.id
         WHERE s.scoring_system='standard'
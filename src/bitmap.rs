@@ -52,6 +52,47 @@ impl Color {
 		self
 	}
 
+	/// Composites this color over another (opaque) one, according to the
+	/// alpha channel of the former.
+	#[inline]
+	pub fn blend(self, background: Self) -> Self {
+		let alpha = self.3 as u32;
+		let mix = |fg: u8, bg: u8| ((alpha * fg as u32 + (255 - alpha) * bg as u32) / 255) as u8;
+
+		Self(
+			mix(self.0, background.0),
+			mix(self.1, background.1),
+			mix(self.2, background.2),
+			background.3,
+		)
+	}
+
+	/// Similar to [Self::blend], except the result gets rounded to the nearest
+	/// value instead of being truncated. Truncation always errs towards zero,
+	/// so compositing many translucent layers using [Self::blend] slowly
+	/// darkens the result, which shows up as banding on smooth gradients.
+	#[inline]
+	pub fn blend_rounded(self, background: Self) -> Self {
+		let alpha = self.3 as u32;
+		let mix =
+			|fg: u8, bg: u8| ((fg as u32 * alpha + bg as u32 * (255 - alpha) + 127) / 255) as u8;
+
+		Self(
+			mix(self.0, background.0),
+			mix(self.1, background.1),
+			mix(self.2, background.2),
+			background.3,
+		)
+	}
+
+	/// Scales the alpha channel by the given amount (between 0 and 1).
+	/// Useful for anti-aliasing, where partially covered pixels should only
+	/// be partially painted.
+	#[inline]
+	pub fn with_coverage(self, coverage: f32) -> Self {
+		self.alpha((self.3 as f32 * coverage.clamp(0.0, 1.0)).round() as u8)
+	}
+
 	#[inline]
 	pub fn distance(self, other: Self) -> f32 {
 		let dr = self.0 as f32 - other.0 as f32;
@@ -59,10 +100,27 @@ impl Color {
 		let db = self.2 as f32 - other.2 as f32;
 		(dr * dr + dg * dg + db * db).sqrt()
 	}
+
+	/// Approximates how different two colors look to a human, using the
+	/// "redmean" weighting of the RGB channels. This is much cheaper than
+	/// going through Lab, yet holds up a lot better than [Self::distance]
+	/// on dimmed or tinted screenshots.
+	#[inline]
+	pub fn perceptual_distance(self, other: Self) -> f32 {
+		let mean_r = (self.0 as f32 + other.0 as f32) / 2.0;
+		let dr = self.0 as f32 - other.0 as f32;
+		let dg = self.1 as f32 - other.1 as f32;
+		let db = self.2 as f32 - other.2 as f32;
+
+		let wr = 2.0 + mean_r / 256.0;
+		let wg = 4.0;
+		let wb = 2.0 + (255.0 - mean_r) / 256.0;
+		(wr * dr * dr + wg * dg * dg + wb * db * db).sqrt()
+	}
 }
 // }}}
 // {{{ Rect
-#[derive(Debug, Clone, Copy)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub struct Rect {
 	pub x: i32,
 	pub y: i32,
@@ -122,6 +180,21 @@ impl Rect {
 	pub fn top_left(&self) -> Position {
 		(self.x, self.y)
 	}
+
+	/// Computes the region covered by both rectangles, if any.
+	#[inline]
+	pub fn intersection(&self, other: &Self) -> Option<Self> {
+		let x_min = self.x.max(other.x);
+		let y_min = self.y.max(other.y);
+		let x_max = (self.x + self.width as i32).min(other.x + other.width as i32);
+		let y_max = (self.y + self.height as i32).min(other.y + other.height as i32);
+
+		if x_min >= x_max || y_min >= y_max {
+			return None;
+		}
+
+		Some(Self::from_extremes(x_min, y_min, x_max, y_max))
+	}
 }
 // }}}
 // {{{ Align
@@ -144,6 +217,36 @@ impl Align {
 	}
 }
 // }}}
+// {{{ Gamma correction
+/// When enabled, glyph coverage gets blended in linear space rather than
+/// directly in sRGB space. Blending in sRGB space makes text edges look too
+/// light (especially against dark backgrounds).
+///
+/// Toggling this changes the output of every text rendering operation, so
+/// golden images must be regenerated deliberately afterwards.
+pub const GAMMA_CORRECT_GLYPHS: bool = true;
+
+#[inline]
+fn srgb_to_linear(c: u8) -> f32 {
+	let c = c as f32 / 255.0;
+	if c <= 0.04045 {
+		c / 12.92
+	} else {
+		((c + 0.055) / 1.055).powf(2.4)
+	}
+}
+
+#[inline]
+fn linear_to_srgb(c: f32) -> u8 {
+	let c = if c <= 0.0031308 {
+		c * 12.92
+	} else {
+		1.055 * c.powf(1.0 / 2.4) - 0.055
+	};
+
+	(c.clamp(0.0, 1.0) * 255.0).round() as u8
+}
+// }}}
 // {{{ Other types
 pub type Position = (i32, i32);
 
@@ -165,6 +268,10 @@ pub struct TextStyle {
 pub struct BitmapCanvas {
 	pub buffer: Box<[u8]>,
 	pub width: u32,
+
+	/// Whether to blend using [Color::blend_rounded] instead of
+	/// [Color::blend]. Worth enabling for renders with many layers.
+	pub rounded_blending: bool,
 }
 
 impl BitmapCanvas {
@@ -173,37 +280,60 @@ impl BitmapCanvas {
 		self.buffer.len() as u32 / 3 / self.width
 	}
 
+	/// Clips the given rectangle to the bounds of the canvas. Drawing routines
+	/// use this to avoid looping over off-canvas pixels.
+	#[inline]
+	fn visible_part(&self, rect: Rect) -> Option<Rect> {
+		rect.intersection(&Rect::new(0, 0, self.width, self.height()))
+	}
+
 	// {{{ Draw pixel
 	#[allow(clippy::identity_op)]
 	pub fn set_pixel(&mut self, pos: (u32, u32), color: Color) {
 		let index = 3 * (pos.1 * self.width + pos.0) as usize;
-		let alpha = color.3 as u32;
+		let background = Color(
+			self.buffer[index + 0],
+			self.buffer[index + 1],
+			self.buffer[index + 2],
+			0xff,
+		);
+
+		let result = if self.rounded_blending {
+			color.blend_rounded(background)
+		} else {
+			color.blend(background)
+		};
 
-		self.buffer[index + 0] =
-			((alpha * color.0 as u32 + (255 - alpha) * self.buffer[index + 0] as u32) / 255) as u8;
-		self.buffer[index + 1] =
-			((alpha * color.1 as u32 + (255 - alpha) * self.buffer[index + 1] as u32) / 255) as u8;
-		self.buffer[index + 2] =
-			((alpha * color.2 as u32 + (255 - alpha) * self.buffer[index + 2] as u32) / 255) as u8;
+		self.buffer[index + 0] = result.0;
+		self.buffer[index + 1] = result.1;
+		self.buffer[index + 2] = result.2;
+	}
+
+	/// Similar to [Self::set_pixel], except the blending happens in linear
+	/// space (i.e. both colors get converted out of sRGB and back).
+	pub fn set_pixel_linear(&mut self, pos: (u32, u32), color: Color) {
+		let index = 3 * (pos.1 * self.width + pos.0) as usize;
+		let alpha = color.3 as f32 / 255.0;
+
+		for (i, channel) in [color.0, color.1, color.2].into_iter().enumerate() {
+			let src = srgb_to_linear(channel);
+			let dest = srgb_to_linear(self.buffer[index + i]);
+			self.buffer[index + i] = linear_to_srgb(alpha * src + (1.0 - alpha) * dest);
+		}
 	}
 	// }}}
 	// {{{ Draw RGB image
 	/// Draws a bitmap image with no alpha channel.
 	pub fn blit_rbg(&mut self, pos: Position, (iw, ih): (u32, u32), src: &[u8]) {
-		let iw = iw as i32;
-		let ih = ih as i32;
-		let width = self.width as i32;
-		let height = self.height() as i32;
-
-		let x_start = 0.max(-pos.0);
-		let y_start = 0.max(-pos.1);
-		let x_end = iw.min(width - pos.0);
-		let y_end = ih.min(height - pos.1);
+		let Some(visible) = self.visible_part(Rect::new(pos.0, pos.1, iw, ih)) else {
+			return;
+		};
 
-		for dx in x_start..x_end {
-			for dy in y_start..y_end {
-				let x = pos.0 + dx;
-				let y = pos.1 + dy;
+		let iw = iw as i32;
+		for y in visible.y..visible.y + visible.height as i32 {
+			for x in visible.x..visible.x + visible.width as i32 {
+				let dx = x - pos.0;
+				let dy = y - pos.1;
 
 				let r = src[(dx + dy * iw) as usize * 3];
 				let g = src[(dx + dy * iw) as usize * 3 + 1];
@@ -219,20 +349,15 @@ impl BitmapCanvas {
 	// {{{ Draw RGBA image
 	/// Draws a bitmap image taking care of the alpha channel.
 	pub fn blit_rbga(&mut self, pos: Position, (iw, ih): (u32, u32), src: &[u8]) {
-		let iw = iw as i32;
-		let ih = ih as i32;
-		let width = self.width as i32;
-		let height = self.height() as i32;
-
-		let x_start = 0.max(-pos.0);
-		let y_start = 0.max(-pos.1);
-		let x_end = iw.min(width - pos.0);
-		let y_end = ih.min(height - pos.1);
+		let Some(visible) = self.visible_part(Rect::new(pos.0, pos.1, iw, ih)) else {
+			return;
+		};
 
-		for dx in x_start..x_end {
-			for dy in y_start..y_end {
-				let x = pos.0 + dx;
-				let y = pos.1 + dy;
+		let iw = iw as i32;
+		for y in visible.y..visible.y + visible.height as i32 {
+			for x in visible.x..visible.x + visible.width as i32 {
+				let dx = x - pos.0;
+				let dy = y - pos.1;
 
 				let r = src[(dx + dy * iw) as usize * 4];
 				let g = src[(dx + dy * iw) as usize * 4 + 1];
@@ -254,20 +379,15 @@ impl BitmapCanvas {
 		src: &[u8],
 		scale: u32,
 	) {
-		let scale = scale as i32;
+		let Some(visible) = self.visible_part(Rect::new(pos.0, pos.1, iw * scale, ih * scale))
+		else {
+			return;
+		};
 
+		let scale = scale as i32;
 		let iw = iw as i32;
-		let ih = ih as i32;
-		let width = self.width as i32;
-		let height = self.height() as i32;
-
-		let x_start = pos.0.max(0);
-		let y_start = pos.1.max(0);
-		let x_end = (pos.0 + iw * scale).min(width);
-		let y_end = (pos.1 + ih * scale).min(height);
-
-		for x in x_start..x_end {
-			for y in y_start..y_end {
+		for y in visible.y..visible.y + visible.height as i32 {
+			for x in visible.x..visible.x + visible.width as i32 {
 				// NOTE: I could instead keep separate counters.
 				// It would introduce an additional if statement,
 				// but would not perform division.
@@ -288,14 +408,50 @@ impl BitmapCanvas {
 	// {{{ Fill
 	/// Fill with solid color
 	pub fn fill(&mut self, pos: Position, (iw, ih): (u32, u32), color: Color) {
-		let height = self.height();
-		for dx in 0..iw {
-			for dy in 0..ih {
-				let x = pos.0 + dx as i32;
-				let y = pos.1 + dy as i32;
-				if x >= 0 && (x as u32) < self.width && y >= 0 && (y as u32) < height {
-					self.set_pixel((x as u32, y as u32), color);
+		let Some(visible) = self.visible_part(Rect::new(pos.0, pos.1, iw, ih)) else {
+			return;
+		};
+
+		for y in visible.y..visible.y + visible.height as i32 {
+			for x in visible.x..visible.x + visible.width as i32 {
+				self.set_pixel((x as u32, y as u32), color);
+			}
+		}
+	}
+
+	/// Fill a rectangle with rounded corners. The edges of the corners are
+	/// anti-aliased by painting each pixel proportionally to how much of it
+	/// lies inside the shape.
+	pub fn fill_rounded(&mut self, rect: Rect, corner_radius: f32, color: Color) {
+		let Some(visible) = self.visible_part(rect) else {
+			return;
+		};
+
+		let radius = corner_radius
+			.min(rect.width as f32 / 2.0)
+			.min(rect.height as f32 / 2.0)
+			.max(0.0);
+
+		for y in visible.y..visible.y + visible.height as i32 {
+			for x in visible.x..visible.x + visible.width as i32 {
+				let dx = x - rect.x;
+				let dy = y - rect.y;
+
+				// Distance from the center of the pixel to the nearest corner
+				// circle center (only meaningful within the corner squares).
+				let px = dx as f32 + 0.5;
+				let py = dy as f32 + 0.5;
+				let cx = px.clamp(radius, rect.width as f32 - radius);
+				let cy = py.clamp(radius, rect.height as f32 - radius);
+				let dist = ((px - cx).powi(2) + (py - cy).powi(2)).sqrt();
+
+				// Approximates the fraction of the pixel covered by the circle
+				let coverage = radius - dist + 0.5;
+				if coverage <= 0.0 {
+					continue;
 				}
+
+				self.set_pixel((x as u32, y as u32), color.with_coverage(coverage));
 			}
 		}
 	}
@@ -490,25 +646,30 @@ impl BitmapCanvas {
 	) {
 		let iw = bitmap.width();
 		let ih = bitmap.rows();
-		let height = self.buffer.len() as u32 / 3 / self.width;
 		let src = bitmap.buffer();
 
-		for dx in 0..iw {
-			for dy in 0..ih {
-				let x = pos.0 + dx + b_glyph.left();
-				let y = pos.1 + dy - b_glyph.top();
+		let origin = (pos.0 + b_glyph.left(), pos.1 - b_glyph.top());
+		let Some(visible) = self.visible_part(Rect::new(origin.0, origin.1, iw as u32, ih as u32))
+		else {
+			return;
+		};
 
-				// TODO: gamma correction
-				if x >= 0 && (x as u32) < self.width && y >= 0 && (y as u32) < height {
-					let gray = src[(dx + dy * iw) as usize];
+		for y in visible.y..visible.y + visible.height as i32 {
+			for x in visible.x..visible.x + visible.width as i32 {
+				let dx = x - origin.0;
+				let dy = y - origin.1;
+				let gray = src[(dx + dy * iw) as usize];
 
-					let r = color.0;
-					let g = color.1;
-					let b = color.2;
-					let a = ((color.3 as u32 * gray as u32) / 0xff) as u8;
+				let r = color.0;
+				let g = color.1;
+				let b = color.2;
+				let a = ((color.3 as u32 * gray as u32) / 0xff) as u8;
 
-					let color = Color(r, g, b, a);
+				let color = Color(r, g, b, a);
 
+				if GAMMA_CORRECT_GLYPHS {
+					self.set_pixel_linear((x as u32, y as u32), color);
+				} else {
 					self.set_pixel((x as u32, y as u32), color);
 				}
 			}
@@ -519,7 +680,17 @@ impl BitmapCanvas {
 	#[inline]
 	pub fn new(width: u32, height: u32) -> Self {
 		let buffer = vec![u8::MAX; 3 * (width * height) as usize].into_boxed_slice();
-		Self { buffer, width }
+		Self {
+			buffer,
+			width,
+			rounded_blending: false,
+		}
+	}
+
+	#[inline]
+	pub fn with_rounded_blending(mut self) -> Self {
+		self.rounded_blending = true;
+		self
 	}
 }
 // }}}
@@ -762,6 +933,13 @@ impl LayoutDrawer {
 			color,
 		);
 	}
+
+	/// Fills with solid color, rounding off the corners
+	#[inline]
+	pub fn fill_rounded(&mut self, id: LayoutBoxId, corner_radius: f32, color: Color) {
+		let current = self.layout.lookup(id);
+		self.canvas.fill_rounded(current, corner_radius, color);
+	}
 	// }}}
 	// {{{ Draw text
 	/// Render text
@@ -781,3 +959,130 @@ impl LayoutDrawer {
 	// }}}
 }
 // }}}
+// {{{ Tests
+#[cfg(test)]
+mod bitmap_tests {
+	use std::fs;
+	use std::path::PathBuf;
+
+	use image::RgbImage;
+
+	use super::*;
+
+	#[test]
+	fn rect_intersection() {
+		let a = Rect::new(0, 0, 10, 10);
+		assert_eq!(
+			a.intersection(&Rect::new(5, -5, 10, 10)),
+			Some(Rect::new(5, 0, 5, 5))
+		);
+		assert_eq!(
+			a.intersection(&Rect::new(2, 3, 4, 5)),
+			Some(Rect::new(2, 3, 4, 5))
+		);
+		assert_eq!(a.intersection(&Rect::new(10, 0, 5, 5)), None);
+		assert_eq!(a.intersection(&Rect::new(-20, -20, 5, 5)), None);
+	}
+
+	#[test]
+	fn rounded_fill_golden() -> Result<(), Error> {
+		let (width, height) = (64, 48);
+		let mut canvas = BitmapCanvas::new(width, height);
+		canvas.fill_rounded(
+			Rect::new(4, 4, width - 8, height - 8),
+			12.5,
+			Color::from_rgb_int(0x271E35),
+		);
+
+		let image = RgbImage::from_raw(width, height, canvas.buffer.into_vec())
+			.ok_or_else(|| anyhow!("Canvas buffer has the wrong size"))?;
+
+		let path = PathBuf::from("test/bitmap/rounded_fill.png");
+		if path.exists() {
+			let expected = image::open(&path)?.into_rgb8();
+			assert!(expected == image, "Rounded fill does not match {path:?}");
+		} else {
+			fs::create_dir_all(path.parent().unwrap())?;
+			image.save(&path)?;
+		}
+
+		Ok(())
+	}
+
+	/// Composites a few translucent white layers over a grayscale gradient,
+	/// returning the canvas alongside the exact (floating point) result for
+	/// every column.
+	fn layered_gradient(rounded_blending: bool) -> (BitmapCanvas, Vec<f32>) {
+		const LAYERS: usize = 8;
+		const ALPHA: u8 = 64;
+
+		let (width, height) = (256, 16);
+		let mut canvas = BitmapCanvas::new(width, height);
+		canvas.rounded_blending = rounded_blending;
+
+		let mut expected = Vec::with_capacity(width as usize);
+		for x in 0..width {
+			let gray = x as u8;
+			canvas.fill((x as i32, 0), (1, height), Color(gray, gray, gray, 0xff));
+
+			let mut exact = gray as f32;
+			for _ in 0..LAYERS {
+				exact += (255.0 - exact) * ALPHA as f32 / 255.0;
+			}
+			expected.push(exact);
+		}
+
+		for _ in 0..LAYERS {
+			canvas.fill((0, 0), (width, height), Color::WHITE.alpha(ALPHA));
+		}
+
+		(canvas, expected)
+	}
+
+	/// Returns the mean and maximum (absolute) error of the first row.
+	fn gradient_error(canvas: &BitmapCanvas, expected: &[f32]) -> (f32, f32) {
+		let errors: Vec<f32> = expected
+			.iter()
+			.enumerate()
+			.map(|(x, exact)| canvas.buffer[3 * x] as f32 - exact)
+			.collect();
+
+		let mean = errors.iter().sum::<f32>() / errors.len() as f32;
+		let max = errors.iter().fold(0.0f32, |max, e| max.max(e.abs()));
+		(mean, max)
+	}
+
+	#[test]
+	fn rounded_blending_golden() -> Result<(), Error> {
+		let (truncated, expected) = layered_gradient(false);
+		let (truncated_mean, _) = gradient_error(&truncated, &expected);
+		assert!(
+			truncated_mean < -1.0,
+			"Truncated blending was expected to be biased, got a mean error of {truncated_mean}"
+		);
+
+		let (canvas, expected) = layered_gradient(true);
+		let (mean, max) = gradient_error(&canvas, &expected);
+		assert!(mean.abs() < 0.5, "Rounded blending is biased ({mean})");
+		assert!(max < 1.5, "Rounded blending is off by up to {max}");
+
+		let (width, height) = (canvas.width, canvas.height());
+		let image = RgbImage::from_raw(width, height, canvas.buffer.into_vec())
+			.ok_or_else(|| anyhow!("Canvas buffer has the wrong size"))?;
+
+		let path = PathBuf::from("test/bitmap/rounded_blending.png");
+		if path.exists() {
+			let expected = image::open(&path)?.into_rgb8();
+			assert!(
+				expected == image,
+				"Rounded blending does not match {path:?}"
+			);
+		} else {
+			fs::create_dir_all(path.parent().unwrap())?;
+			image.save(&path)?;
+		}
+
+		Ok(())
+	}
+}
+// }}}
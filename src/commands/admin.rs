@@ -0,0 +1,170 @@
+// {{{ Imports
+use std::time::Instant;
+
+use poise::serenity_prelude as serenity;
+
+use crate::arcaea::play::ScoreRecomputation;
+use crate::context::{Context, Error, TaggedError};
+use crate::user::User;
+
+use super::discord::MessageContext;
+// }}}
+
+// {{{ Top command
+/// Maintenance utilities. Only available to admins.
+#[poise::command(
+	prefix_command,
+	slash_command,
+	subcommands("recompute", "reload_constants"),
+	subcommand_required,
+	hide_in_help
+)]
+pub async fn admin(_ctx: Context<'_>) -> Result<(), Error> {
+	Ok(())
+}
+// }}}
+// {{{ Recompute scores
+// {{{ Implementation
+/// How many plays get recomputed between progress updates.
+const RECOMPUTE_PROGRESS_INTERVAL: usize = 250;
+
+/// Regenerates the scores (and creation ptt) of either every play, or only
+/// the plays of the given user. Useful after chart constants get rebalanced.
+/// The reply gets edited to show the progress as the plays get processed.
+async fn recompute_impl<C: MessageContext>(
+	ctx: &mut C,
+	discord_id: Option<&str>,
+) -> Result<usize, TaggedError> {
+	let user = User::from_context(ctx)?;
+	user.assert_is_admin()?;
+
+	let target = match discord_id {
+		Some(discord_id) => Some(User::by_discord_id(ctx.data(), discord_id)?),
+		None => None,
+	};
+
+	let scope = match &target {
+		Some(target) => format!("the plays of <@{}>", target.discord_id),
+		None => "every play".to_string(),
+	};
+
+	let start = Instant::now();
+	let mut recomputation = ScoreRecomputation::begin(ctx.data(), target.map(|t| t.id))?;
+	let total = recomputation.len();
+	let handle = ctx
+		.reply_editable(&format!("Recomputing scores for {scope} (0/{total})..."))
+		.await?;
+
+	while !recomputation.is_done() {
+		let processed = recomputation.step(ctx.data(), RECOMPUTE_PROGRESS_INTERVAL)?;
+		ctx.edit_reply(
+			&handle,
+			&format!("Recomputing scores for {scope} ({processed}/{total})..."),
+		)
+		.await?;
+	}
+
+	let count = recomputation.commit(ctx.data())?;
+	ctx.edit_reply(
+		&handle,
+		&format!(
+			"Recomputed the scores of {count} plays in {:.1}s",
+			start.elapsed().as_secs_f32()
+		),
+	)
+	.await?;
+
+	Ok(count)
+}
+// }}}
+// {{{ Tests
+#[cfg(test)]
+mod recompute_tests {
+	use crate::{commands::discord::mock::MockContext, golden_test};
+
+	use super::*;
+
+	golden_test!(not_an_admin, "commands/admin/recompute/not_an_admin");
+	async fn not_an_admin(ctx: &mut MockContext) -> Result<(), TaggedError> {
+		recompute_impl(ctx, None).await?;
+		Ok(())
+	}
+}
+// }}}
+// {{{ Discord wrapper
+/// Recompute stored scores after a chart constant update
+#[poise::command(prefix_command, slash_command)]
+async fn recompute(
+	mut ctx: Context<'_>,
+	#[description = "Only recompute the scores of this user"] user: Option<serenity::User>,
+) -> Result<(), Error> {
+	let discord_id = user.map(|u| u.id.to_string());
+	let res = recompute_impl(&mut ctx, discord_id.as_deref()).await;
+	ctx.handle_error(res).await?;
+
+	Ok(())
+}
+// }}}
+// }}}
+// {{{ Reload chart constants
+// {{{ Implementation
+/// Re-reads every chart constant from the database into the in-memory song
+/// cache, returning how many of them changed.
+async fn reload_constants_impl<C: MessageContext>(ctx: &mut C) -> Result<usize, TaggedError> {
+	let user = User::from_context(ctx)?;
+	user.assert_is_admin()?;
+
+	let changed = ctx
+		.data()
+		.song_cache
+		.reload_chart_constants(&ctx.data().db)?;
+	ctx.data().best_plays_cache.clear();
+
+	ctx.reply(&format!("Reloaded chart constants ({changed} changed)"))
+		.await?;
+
+	Ok(changed)
+}
+// }}}
+// {{{ Tests
+#[cfg(test)]
+mod reload_constants_tests {
+	use crate::{commands::discord::mock::MockContext, golden_test};
+
+	use super::*;
+
+	golden_test!(not_an_admin, "commands/admin/reload_constants/not_an_admin");
+	async fn not_an_admin(ctx: &mut MockContext) -> Result<(), TaggedError> {
+		reload_constants_impl(ctx).await?;
+		Ok(())
+	}
+
+	golden_test!(
+		picks_up_db_changes,
+		"commands/admin/reload_constants/picks_up_db_changes"
+	);
+	async fn picks_up_db_changes(ctx: &mut MockContext) -> Result<(), TaggedError> {
+		let conn = ctx.data.db.get()?;
+		conn.execute("UPDATE users SET is_admin=1", ())?;
+		conn.execute("UPDATE charts SET chart_constant=1234 WHERE id=1", ())?;
+		drop(conn);
+
+		assert_eq!(reload_constants_impl(ctx).await?, 1);
+		let (_, chart) = ctx.data.song_cache.lookup_chart(1)?;
+		assert_eq!(chart.chart_constant.get(), 1234);
+
+		Ok(())
+	}
+}
+// }}}
+// {{{ Discord wrapper
+/// Reload chart constants after editing the database by hand
+#[poise::command(prefix_command, slash_command, rename = "reload-constants")]
+async fn reload_constants(mut ctx: Context<'_>) -> Result<(), Error> {
+	let res = reload_constants_impl(&mut ctx).await;
+	ctx.handle_error(res).await?;
+
+	Ok(())
+}
+// }}}
+// }}}
@@ -1,9 +1,18 @@
 // {{{ Imports
+use std::fmt::Write;
+use std::str::FromStr;
+
+use anyhow::anyhow;
 use num::{FromPrimitive, Rational32};
+use poise::serenity_prelude::CreateEmbed;
+use poise::CreateReply;
 
-use crate::arcaea::play::{compute_b30_ptt, get_best_plays};
-use crate::arcaea::rating::{rating_as_float, rating_from_fixed, Rating};
-use crate::context::{Context, Error, TaggedError};
+use crate::arcaea::chart::Chart;
+use crate::arcaea::play::{compute_b30_ptt, get_b30_plays, PlayCollection};
+use crate::arcaea::rating::{
+	checked_rating, rating_as_float, rating_from_fixed, Rating, RatingDisplay,
+};
+use crate::context::{Context, Error, ErrorKind, TagError, TaggedError};
 use crate::recognition::fuzzy_song_name::guess_song_and_chart;
 use crate::user::User;
 
@@ -17,7 +26,7 @@ use super::discord::MessageContext;
 #[poise::command(
 	prefix_command,
 	slash_command,
-	subcommands("expected", "rating"),
+	subcommands("expected", "rating", "target", "ptt", "pushrate", "roadmap"),
 	subcommand_required
 )]
 pub async fn calc(_ctx: Context<'_>) -> Result<(), Error> {
@@ -39,11 +48,11 @@ async fn expected_impl(
 		let user = User::from_context(ctx)?;
 		compute_b30_ptt(
 			ScoringSystem::Standard,
-			&get_best_plays(ctx.data(), user.id, ScoringSystem::Standard, 30, 30, None)?,
+			&get_b30_plays(ctx.data(), user.id, ScoringSystem::Standard, 30, 30, None)?,
 		)
 	};
 
-	let cc = rating_from_fixed(chart.chart_constant as i32);
+	let cc = rating_from_fixed(chart.chart_constant.get() as i32);
 
 	let score = if ptt >= cc + 2 {
 		Rational32::from_integer(chart.note_count as i32 + 10_000_000)
@@ -57,8 +66,8 @@ async fn expected_impl(
 	let score = Score(score.to_integer().max(0) as u32);
 
 	ctx.reply(&format!(
-		"The expected score for a player of potential {:.2} on {} [{}] is {}",
-		rating_as_float(ptt),
+		"The expected score for a player of potential {} on {} [{}] is {}",
+		RatingDisplay(ptt),
 		song,
 		chart.difficulty,
 		score
@@ -120,7 +129,16 @@ async fn expected(
 	#[description = "Name of chart (difficulty at the end)"]
 	name: String,
 ) -> Result<(), Error> {
-	let res = expected_impl(&mut ctx, ptt.and_then(Rational32::from_f32), &name).await;
+	let res = async {
+		let ptt = ptt
+			.and_then(Rational32::from_f32)
+			.map(checked_rating)
+			.transpose()
+			.map_err(|e| e.tag(ErrorKind::User))?;
+
+		expected_impl(&mut ctx, ptt, &name).await
+	}
+	.await;
 	ctx.handle_error(res).await?;
 
 	Ok(())
@@ -136,14 +154,25 @@ async fn rating_impl(
 ) -> Result<Rating, TaggedError> {
 	let (song, chart) = guess_song_and_chart(ctx.data(), name)?;
 
-	let rating = score.play_rating(chart.chart_constant);
+	if !score.is_achievable(chart.note_count) {
+		return Err(anyhow!(
+			"The score {} is impossible on {} [{}], which has {} notes",
+			score,
+			song,
+			chart.difficulty,
+			chart.note_count
+		)
+		.tag(ErrorKind::User));
+	}
+
+	let rating = score.play_rating(chart.chart_constant.get());
 
 	ctx.reply(&format!(
-		"The score {} on {} [{}] yields a rating of {:.2}",
+		"The score {} on {} [{}] yields a rating of {}",
 		score,
 		song,
 		chart.difficulty,
-		rating_as_float(rating),
+		RatingDisplay(rating),
 	))
 	.await?;
 
@@ -163,6 +192,13 @@ mod rating_tests {
 
 		Ok(())
 	}
+
+	golden_test!(impossible_score, "commands/calc/rating/impossible_score");
+	async fn impossible_score(ctx: &mut MockContext) -> Result<(), TaggedError> {
+		rating_impl(ctx, Score(10_009_999), "Arcana Eden [PRS]").await?;
+
+		Ok(())
+	}
 }
 // }}}
 // {{{ Discord wrapper
@@ -170,12 +206,675 @@ mod rating_tests {
 #[poise::command(prefix_command, slash_command, user_cooldown = 1)]
 async fn rating(
 	mut ctx: Context<'_>,
-	score: u32,
+	#[description = "The score to compute the rating of (e.g. 9'926'250)"] score: String,
+	#[rest]
+	#[description = "Name of chart (difficulty at the end)"]
+	name: String,
+) -> Result<(), Error> {
+	let res = async {
+		let score = Score::from_str(&score).map_err(|e| e.tag(ErrorKind::User))?;
+		rating_impl(&mut ctx, score, &name).await
+	}
+	.await;
+	ctx.handle_error(res).await?;
+
+	Ok(())
+}
+// }}}
+// }}}
+// {{{ Target
+// {{{ Implementation
+async fn target_impl(
+	ctx: &mut impl MessageContext,
+	rating: Rating,
+	name: &str,
+) -> Result<Score, TaggedError> {
+	let (song, chart) = guess_song_and_chart(ctx.data(), name)?;
+
+	let score = Score::from_play_rating(rating, chart.chart_constant.get()).ok_or_else(|| {
+		anyhow!(
+			"A rating of {} is out of reach on {} [{}], which caps out at {}",
+			RatingDisplay(rating),
+			song,
+			chart.difficulty,
+			RatingDisplay(Score(10_000_000).play_rating(chart.chart_constant.get()))
+		)
+		.tag(ErrorKind::User)
+	})?;
+
+	ctx.reply(&format!(
+		"A rating of {} on {} [{}] requires a score of at least {} ({})",
+		RatingDisplay(rating),
+		song,
+		chart.difficulty,
+		score,
+		score.grade()
+	))
+	.await?;
+
+	Ok(score)
+}
+// }}}
+// {{{ Tests
+#[cfg(test)]
+mod target_tests {
+	use crate::{commands::discord::mock::MockContext, golden_test};
+
+	use super::*;
+
+	golden_test!(basic_usage, "commands/calc/target/basic_usage");
+	async fn basic_usage(ctx: &mut MockContext) -> Result<(), TaggedError> {
+		let score = target_impl(ctx, rating_from_fixed(1250), "Pentiment [BYD]").await?;
+		let (_, chart) = guess_song_and_chart(ctx.data(), "Pentiment [BYD]")?;
+		assert!(score.play_rating(chart.chart_constant.get()) >= rating_from_fixed(1250));
+
+		Ok(())
+	}
+
+	golden_test!(out_of_reach, "commands/calc/target/out_of_reach");
+	async fn out_of_reach(ctx: &mut MockContext) -> Result<(), TaggedError> {
+		target_impl(ctx, rating_from_fixed(1500), "Arcana Eden [PRS]").await?;
+
+		Ok(())
+	}
+}
+// }}}
+// {{{ Discord wrapper
+/// Computes the score needed to reach some play rating on a given chart.
+#[poise::command(prefix_command, slash_command, user_cooldown = 1)]
+async fn target(
+	mut ctx: Context<'_>,
+	#[description = "The play rating to aim for (e.g. 12.5)"] rating: f32,
 	#[rest]
 	#[description = "Name of chart (difficulty at the end)"]
 	name: String,
 ) -> Result<(), Error> {
-	let res = rating_impl(&mut ctx, Score(score), &name).await;
+	let res = async {
+		let rating = checked_rating(rating_from_fixed((rating * 100.0).round() as i32))
+			.map_err(|e| e.tag(ErrorKind::User))?;
+
+		target_impl(&mut ctx, rating, &name).await
+	}
+	.await;
+	ctx.handle_error(res).await?;
+
+	Ok(())
+}
+// }}}
+// }}}
+// {{{ Potential breakdown
+// {{{ Implementation
+/// Lists the plays making up the b30 of the current user, alongside their
+/// ratings and the running average, in order to explain how the final
+/// potential comes to be. Returns the final b30 average.
+async fn ptt_impl<C: MessageContext>(
+	ctx: &mut C,
+	scoring_system: Option<ScoringSystem>,
+) -> Result<Rational32, TaggedError> {
+	let user = User::from_context(ctx)?;
+	let scoring_system = scoring_system.unwrap_or_default();
+	let plays = get_b30_plays(ctx.data(), user.id, scoring_system, 1, 30, None)?;
+
+	let mut description = String::new();
+	let mut sum = Rational32::from_integer(0);
+	for (i, (play, song, chart)) in plays.iter().enumerate() {
+		let rating = play.play_rating(scoring_system, chart.chart_constant.get());
+		sum += rating;
+
+		write!(
+			description,
+			"`{:>2}` **{}** (avg {}) {} [{}] — {}",
+			i + 1,
+			RatingDisplay(rating),
+			RatingDisplay(sum / Rational32::from_integer(i as i32 + 1)),
+			song,
+			chart.difficulty,
+			play.score(scoring_system)
+		)?;
+
+		if i == 29 {
+			write!(description, " ← cutoff")?;
+		}
+
+		writeln!(description)?;
+	}
+
+	let ptt = compute_b30_ptt(scoring_system, &plays);
+	writeln!(
+		description,
+		"\nYour b30 is the average of the ratings above, i.e. **{}**.",
+		RatingDisplay(ptt)
+	)?;
+
+	if plays.len() == 30 {
+		writeln!(
+			description,
+			"A new play only changes your b30 if its rating beats the cutoff play."
+		)?;
+	} else {
+		writeln!(
+			description,
+			"You have fewer than 30 scores, so any new chart you play counts towards your b30."
+		)?;
+	}
+
+	let embed = CreateEmbed::default()
+		.title("Potential breakdown")
+		.description(description);
+
+	ctx.send(CreateReply::default().reply(true).embed(embed))
+		.await?;
+
+	Ok(ptt)
+}
+// }}}
+// {{{ Tests
+#[cfg(test)]
+mod ptt_tests {
+	use std::{path::PathBuf, str::FromStr};
+
+	use crate::{
+		commands::{discord::mock::MockContext, score::magic_impl},
+		golden_test, with_test_ctx,
+	};
+
+	use super::*;
+
+	#[tokio::test]
+	async fn no_plays() -> Result<(), Error> {
+		with_test_ctx!("commands/calc/ptt/no_plays", |ctx| async move {
+			ptt_impl(ctx, None).await?;
+			Ok(())
+		})
+	}
+
+	golden_test!(few_plays, "commands/calc/ptt/few_plays");
+	async fn few_plays(ctx: &mut MockContext) -> Result<(), TaggedError> {
+		let plays = magic_impl(
+			ctx,
+			&[
+				PathBuf::from_str("test/screenshots/fracture_ray_ex.jpg")?,
+				PathBuf::from_str("test/screenshots/antithese_74_kerning.jpg")?,
+			],
+		)
+		.await?;
+
+		let ptt = ptt_impl(ctx, None).await?;
+		let expected = compute_b30_ptt(
+			ScoringSystem::Standard,
+			&get_b30_plays(
+				ctx.data(),
+				plays[0].user_id,
+				ScoringSystem::Standard,
+				1,
+				30,
+				None,
+			)?,
+		);
+		assert_eq!(ptt, expected);
+
+		Ok(())
+	}
+}
+// }}}
+// {{{ Discord wrapper
+/// Explains how your potential is computed from your best 30 plays.
+#[poise::command(prefix_command, slash_command, user_cooldown = 1)]
+async fn ptt(mut ctx: Context<'_>, scoring_system: Option<ScoringSystem>) -> Result<(), Error> {
+	let res = ptt_impl(&mut ctx, scoring_system).await;
+	ctx.handle_error(res).await?;
+
+	Ok(())
+}
+// }}}
+// }}}
+// {{{ Push rate
+// {{{ Implementation
+/// The smallest score on the given chart whose play rating is strictly
+/// higher than the given one.
+fn min_score_beating(rating: Rating, chart: &Chart) -> Option<Score> {
+	let constant = chart.chart_constant.get();
+	let score = Score::from_play_rating(rating, constant)?;
+	let score = if score.play_rating(constant) > rating {
+		score
+	} else {
+		Score(score.0 + 1)
+	};
+
+	(score.play_rating(constant) > rating).then_some(score)
+}
+
+/// Computes the b30 average after playing the given chart with the given
+/// score, given every best play (and not just the best 30).
+fn ptt_after(plays: &PlayCollection<'_>, chart: &Chart, score: Score) -> Rating {
+	let mut ratings: Vec<Rating> = plays
+		.iter()
+		.filter(|(_, _, other)| other.id != chart.id)
+		.map(|(play, _, chart)| {
+			play.play_rating(ScoringSystem::Standard, chart.chart_constant.get())
+		})
+		.collect();
+	ratings.push(score.play_rating(chart.chart_constant.get()));
+	ratings.sort_by(|a, b| b.cmp(a));
+	ratings.truncate(30);
+
+	ratings.iter().sum::<Rating>() / Rational32::from_integer(ratings.len() as i32)
+}
+
+/// Computes the minimum scores required on a chart in order to enter the b30
+/// of the current user, and in order to beat their current play on it
+/// (in this order). Charts already in the b30 have nothing to enter.
+async fn pushrate_impl(
+	ctx: &mut impl MessageContext,
+	name: &str,
+) -> Result<(Option<Score>, Option<Score>), TaggedError> {
+	let user = User::from_context(ctx)?;
+	let (song, chart) = guess_song_and_chart(ctx.data(), name)?;
+	let system = ScoringSystem::Standard;
+
+	let plays = get_b30_plays(ctx.data(), user.id, system, 0, usize::MAX, None)?;
+	let b30 = get_b30_plays(ctx.data(), user.id, system, 0, 30, None)?;
+	let ptt = compute_b30_ptt(system, &b30);
+
+	let mut description = format!("Your potential is currently **{}**.\n", RatingDisplay(ptt));
+	let gain = |score: Score| rating_as_float(ptt_after(&plays, chart, score) - ptt);
+
+	// {{{ Entering the b30
+	let in_b30 = b30.iter().any(|(_, _, other)| other.id == chart.id);
+	let to_enter = if in_b30 {
+		writeln!(description, "- This chart is already part of your b30.")?;
+		None
+	} else {
+		// With fewer than 30 plays, a new play only helps if it beats the average
+		let (threshold, label) = match b30.get(29) {
+			Some((play, _, chart)) => (
+				play.play_rating(system, chart.chart_constant.get()),
+				"the b30 cutoff",
+			),
+			None => (ptt, "your b30 average"),
+		};
+
+		let score = min_score_beating(threshold, chart);
+		match score {
+			Some(score) => writeln!(
+				description,
+				"- Beating {label} ({}) requires at least **{score}**, which would raise your potential by {:.4}.",
+				RatingDisplay(threshold),
+				gain(score),
+			)?,
+			None => writeln!(
+				description,
+				"- Beating {label} ({}) is out of reach on this chart.",
+				RatingDisplay(threshold),
+			)?,
+		}
+
+		score
+	};
+	// }}}
+	// {{{ Beating the current play
+	let current = plays
+		.iter()
+		.find(|(_, _, other)| other.id == chart.id)
+		.map(|(play, _, _)| play.score(system));
+
+	let to_improve = match current {
+		Some(current) => {
+			let score = min_score_beating(current.play_rating(chart.chart_constant.get()), chart);
+			match score {
+				Some(score) => writeln!(
+					description,
+					"- Beating your current {current} requires at least **{score}**, which would raise your potential by {:.4}.",
+					gain(score),
+				)?,
+				None => writeln!(
+					description,
+					"- Your current {current} already has the highest possible rating.",
+				)?,
+			}
+
+			score
+		}
+		None => {
+			writeln!(description, "- You have not played this chart yet.")?;
+			None
+		}
+	};
+	// }}}
+
+	let embed = CreateEmbed::default()
+		.title(format!("Pushing {} [{}]", song, chart.difficulty))
+		.description(description);
+
+	ctx.send(CreateReply::default().reply(true).embed(embed))
+		.await?;
+
+	Ok((to_enter, to_improve))
+}
+// }}}
+// {{{ Tests
+#[cfg(test)]
+mod pushrate_tests {
+	use std::{path::PathBuf, str::FromStr};
+
+	use crate::{
+		commands::{discord::mock::MockContext, score::magic_impl},
+		golden_test,
+	};
+
+	use super::*;
+
+	golden_test!(no_plays, "commands/calc/pushrate/no_plays");
+	async fn no_plays(ctx: &mut MockContext) -> Result<(), TaggedError> {
+		let (to_enter, to_improve) = pushrate_impl(ctx, "Pentiment [BYD]").await?;
+		assert!(to_enter.is_some());
+		assert_eq!(to_improve, None);
+
+		Ok(())
+	}
+
+	golden_test!(already_in_b30, "commands/calc/pushrate/already_in_b30");
+	async fn already_in_b30(ctx: &mut MockContext) -> Result<(), TaggedError> {
+		let plays = magic_impl(
+			ctx,
+			&[PathBuf::from_str("test/screenshots/fracture_ray_ex.jpg")?],
+		)
+		.await?;
+
+		let (song, chart) = ctx.data().song_cache.lookup_chart(plays[0].chart_id)?;
+		let name = format!("{} [{}]", song.title, chart.difficulty);
+
+		let (to_enter, to_improve) = pushrate_impl(ctx, &name).await?;
+		assert_eq!(to_enter, None);
+		assert_eq!(
+			to_improve,
+			Some(Score(plays[0].score(ScoringSystem::Standard).0 + 1))
+		);
+
+		Ok(())
+	}
+}
+// }}}
+// {{{ Discord wrapper
+/// Computes the scores needed on a chart to raise your potential.
+#[poise::command(prefix_command, slash_command, user_cooldown = 1)]
+async fn pushrate(
+	mut ctx: Context<'_>,
+	#[rest]
+	#[description = "Name of chart (difficulty at the end)"]
+	name: String,
+) -> Result<(), Error> {
+	let res = pushrate_impl(&mut ctx, &name).await;
+	ctx.handle_error(res).await?;
+
+	Ok(())
+}
+// }}}
+// }}}
+// {{{ Roadmap
+// {{{ Implementation
+/// The roadmap gives up after suggesting this many pushes.
+const ROADMAP_MAX_STEPS: usize = 15;
+
+/// Computes the b30 average of the given (per-chart, sorted from best to
+/// worst) ratings, after replacing the rating of the given chart.
+fn b30_with(ratings: &[(u32, Rating)], chart_id: u32, rating: Rating) -> Rating {
+	let mut inserted = false;
+	let mut sum = Rational32::from_integer(0);
+	let mut count = 0;
+
+	for (other_id, other) in ratings {
+		if count == 30 {
+			break;
+		}
+
+		if !inserted && rating >= *other {
+			inserted = true;
+			sum += rating;
+			count += 1;
+
+			if count == 30 {
+				break;
+			}
+		}
+
+		if *other_id != chart_id {
+			sum += *other;
+			count += 1;
+		}
+	}
+
+	if !inserted && count < 30 {
+		sum += rating;
+		count += 1;
+	}
+
+	sum / Rational32::from_integer(count)
+}
+
+/// A single step of a roadmap: pushing a chart to a given score, raising the
+/// b30 average to the given value.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct RoadmapStep {
+	chart_id: u32,
+	score: Score,
+	ptt: Rating,
+}
+
+/// Greedily picks the pushes raising the b30 of the current user the most,
+/// until it reaches the given target. We assume every chart can be pushed up
+/// to the rating of the user's current best play (capped at a PM), as that
+/// is a level they have already shown they can play at.
+async fn roadmap_impl(
+	ctx: &mut impl MessageContext,
+	target: Rating,
+) -> Result<Vec<RoadmapStep>, TaggedError> {
+	let user = User::from_context(ctx)?;
+	let system = ScoringSystem::Standard;
+	let plays = get_b30_plays(ctx.data(), user.id, system, 0, usize::MAX, None)?;
+	if plays.is_empty() {
+		return Err(anyhow!("You have no plays yet").tag(ErrorKind::User));
+	}
+
+	let mut ratings: Vec<(u32, Rating)> = plays
+		.iter()
+		.map(|(play, _, chart)| {
+			(
+				chart.id,
+				play.play_rating(system, chart.chart_constant.get()),
+			)
+		})
+		.collect();
+	ratings.sort_by(|a, b| b.1.cmp(&a.1));
+
+	let ceiling = ratings[0].1;
+	let start = b30_with(&ratings, ratings[0].0, ceiling);
+	let mut ptt = start;
+	let mut steps = Vec::new();
+
+	// {{{ Greedy search
+	while ptt < target && steps.len() < ROADMAP_MAX_STEPS {
+		let best = ctx
+			.data()
+			.song_cache
+			.charts()
+			.filter(|chart| chart.chart_constant.get() > 0)
+			.filter_map(|chart| {
+				let constant = chart.chart_constant.get();
+				let rating = ceiling.min(Score(10_000_000).play_rating(constant));
+				let current = ratings.iter().find(|(id, _)| *id == chart.id);
+				if current.is_some_and(|(_, current)| *current >= rating) {
+					return None;
+				}
+
+				let score = Score::from_play_rating(rating, constant)?;
+				let after = b30_with(&ratings, chart.id, rating);
+				(after > ptt).then_some((chart, rating, score, after))
+			})
+			// Prefer charts with lower constants on ties, as those should be easier
+			.max_by(|a, b| {
+				a.3.cmp(&b.3)
+					.then(b.0.chart_constant.get().cmp(&a.0.chart_constant.get()))
+					.then(b.0.id.cmp(&a.0.id))
+			});
+
+		let Some((chart, rating, score, after)) = best else {
+			break;
+		};
+
+		ratings.retain(|(id, _)| *id != chart.id);
+		let index = ratings.partition_point(|(_, other)| *other >= rating);
+		ratings.insert(index, (chart.id, rating));
+
+		ptt = after;
+		steps.push(RoadmapStep {
+			chart_id: chart.id,
+			score,
+			ptt,
+		});
+	}
+	// }}}
+	// {{{ Describe the steps
+	let mut description = format!(
+		"Your potential is currently **{}**. Assuming you can play any chart at the level of your best play ({}):\n",
+		RatingDisplay(start),
+		RatingDisplay(ceiling)
+	);
+
+	for (i, step) in steps.iter().enumerate() {
+		let (song, chart) = ctx.data().song_cache.lookup_chart(step.chart_id)?;
+		let current = plays
+			.iter()
+			.find(|(_, _, other)| other.id == chart.id)
+			.map_or("unplayed".to_string(), |(play, _, _)| {
+				play.score(system).to_string()
+			});
+
+		writeln!(
+			description,
+			"{}. {} [{}]: {current} → **{}**, for a potential of {}",
+			i + 1,
+			song,
+			chart.difficulty,
+			step.score,
+			RatingDisplay(step.ptt)
+		)?;
+	}
+
+	if steps.is_empty() && ptt >= target {
+		writeln!(description, "You have already reached this potential!")?;
+	} else if ptt < target {
+		writeln!(
+			description,
+			"This is as far as I can get you ({}), so reaching {} requires playing above your current level.",
+			RatingDisplay(ptt),
+			RatingDisplay(target)
+		)?;
+	}
+	// }}}
+
+	let embed = CreateEmbed::default()
+		.title(format!("Roadmap to {}", RatingDisplay(target)))
+		.description(description);
+
+	ctx.send(CreateReply::default().reply(true).embed(embed))
+		.await?;
+
+	Ok(steps)
+}
+// }}}
+// {{{ Tests
+#[cfg(test)]
+mod roadmap_tests {
+	use std::{path::PathBuf, str::FromStr};
+
+	use crate::{
+		commands::{discord::mock::MockContext, score::magic_impl},
+		golden_test, with_test_ctx,
+	};
+
+	use super::*;
+
+	#[test]
+	fn b30_replacement() {
+		let ratings: Vec<(u32, Rating)> = (0..40)
+			.map(|i| (i, rating_from_fixed(1200 - i as i32 * 10)))
+			.collect();
+		let b30 = |ratings: &[(u32, Rating)]| {
+			ratings.iter().take(30).map(|(_, r)| r).sum::<Rating>() / Rational32::from_integer(30)
+		};
+
+		// Replacing a chart with its own rating changes nothing
+		assert_eq!(b30_with(&ratings, 5, ratings[5].1), b30(&ratings));
+
+		// Plays below the cutoff do not matter
+		assert_eq!(b30_with(&ratings, 100, rating_from_fixed(0)), b30(&ratings));
+
+		// Better plays push the cutoff play out
+		let mut expected = ratings.clone();
+		expected.insert(0, (100, rating_from_fixed(1300)));
+		assert_eq!(
+			b30_with(&ratings, 100, rating_from_fixed(1300)),
+			b30(&expected)
+		);
+
+		// With fewer than 30 plays, every play counts
+		let few = &ratings[..2];
+		assert_eq!(
+			b30_with(few, 100, rating_from_fixed(1000)),
+			(few[0].1 + few[1].1 + rating_from_fixed(1000)) / Rational32::from_integer(3)
+		);
+	}
+
+	#[tokio::test]
+	async fn no_plays() -> Result<(), Error> {
+		with_test_ctx!("commands/calc/roadmap/no_plays", |ctx| async move {
+			roadmap_impl(ctx, rating_from_fixed(1200)).await?;
+			Ok(())
+		})
+	}
+
+	golden_test!(few_plays, "commands/calc/roadmap/few_plays");
+	async fn few_plays(ctx: &mut MockContext) -> Result<(), TaggedError> {
+		magic_impl(
+			ctx,
+			&[
+				PathBuf::from_str("test/screenshots/fracture_ray_ex.jpg")?,
+				PathBuf::from_str("test/screenshots/alter_ego.jpg")?,
+			],
+		)
+		.await?;
+
+		let user = User::from_context(ctx)?;
+		let plays = get_b30_plays(ctx.data(), user.id, ScoringSystem::Standard, 1, 30, None)?;
+		let ptt = compute_b30_ptt(ScoringSystem::Standard, &plays);
+
+		let steps = roadmap_impl(ctx, ptt + rating_from_fixed(1)).await?;
+		assert!(!steps.is_empty());
+		assert!(steps[0].ptt > ptt);
+		assert!(steps.windows(2).all(|w| w[0].ptt < w[1].ptt));
+
+		// Targets we already reached need no steps
+		assert!(roadmap_impl(ctx, ptt).await?.is_empty());
+
+		Ok(())
+	}
+}
+// }}}
+// {{{ Discord wrapper
+/// Suggests which charts to push (and to what score) to reach a potential.
+#[poise::command(prefix_command, slash_command, user_cooldown = 5)]
+async fn roadmap(
+	mut ctx: Context<'_>,
+	#[description = "The potential to aim for (e.g. 12.5)"] target: f32,
+) -> Result<(), Error> {
+	let res = async {
+		let target = checked_rating(rating_from_fixed((target * 100.0).round() as i32))
+			.map_err(|e| e.tag(ErrorKind::User))?;
+
+		roadmap_impl(&mut ctx, target).await
+	}
+	.await;
 	ctx.handle_error(res).await?;
 
 	Ok(())
@@ -1,26 +1,36 @@
 // {{{ Imports
 use anyhow::anyhow;
-use poise::serenity_prelude::{CreateAttachment, CreateEmbed};
+use poise::serenity_prelude::{
+	ComponentInteractionCollector, CreateActionRow, CreateAttachment, CreateButton, CreateEmbed,
+	CreateInteractionResponse,
+};
 
-use crate::arcaea::{chart::Side, play::Play};
-use crate::context::{Context, Error, ErrorKind, TagError, TaggedError};
-use crate::recognition::fuzzy_song_name::guess_song_and_chart;
+use crate::arcaea::achievement::compute_peak_pm_relay;
+use crate::arcaea::chart::{Chart, Difficulty, Level, Side, Song, DIFFICULTY_MENU_PIXEL_COLORS};
+use crate::arcaea::play::{get_best_plays, ClearKind, Play};
+use crate::bitmap::Color;
+use crate::context::{Context, Error, ErrorKind, TagError, TaggedError, UserContext};
+use crate::recognition::fuzzy_song_name::{
+	guess_song_and_chart, guess_song_and_chart_with, rank_chart_names, strip_difficulty_suffix,
+	MatchTolerance,
+};
 use crate::user::User;
 use std::io::Cursor;
+use std::time::Duration;
 
-use chrono::DateTime;
+use chrono::{DateTime, NaiveDate, NaiveDateTime};
 use image::{ImageBuffer, Rgb};
 use plotters::backend::{BitMapBackend, PixelFormat, RGBPixel};
 use plotters::chart::{ChartBuilder, LabelAreaPosition};
 use plotters::drawing::IntoDrawingArea;
-use plotters::element::Circle;
+use plotters::element::{Circle, PathElement};
 use plotters::series::LineSeries;
-use plotters::style::{IntoFont, TextStyle, BLUE, WHITE};
+use plotters::style::{IntoFont, RGBColor, TextStyle, BLACK, WHITE};
 use poise::CreateReply;
 
 use crate::arcaea::score::{Score, ScoringSystem};
 
-use super::discord::{CreateReplyExtra, MessageContext};
+use super::discord::{CreateReplyExtra, MessageContext, MAX_EMBEDS_PER_MESSAGE};
 // }}}
 
 // {{{ Top command
@@ -28,25 +38,176 @@ use super::discord::{CreateReplyExtra, MessageContext};
 #[poise::command(
 	prefix_command,
 	slash_command,
-	subcommands("info", "best", "plot"),
+	subcommands(
+		"info",
+		"by_id",
+		"search",
+		"best",
+		"recent",
+		"best_all",
+		"history",
+		"leaderboard",
+		"plot",
+		"alias",
+		"set_constant",
+		"unplayed_pm_relay"
+	),
 	subcommand_required
 )]
 pub async fn chart(_ctx: Context<'_>) -> Result<(), Error> {
 	Ok(())
 }
 // }}}
+// {{{ Difficulty picker
+/// How long to wait for the user to pick a difficulty before giving up.
+const DIFFICULTY_PICKER_TIMEOUT: Duration = Duration::from_secs(60);
+
+/// When no difficulty is specified and the song has more than one chart,
+/// asks the user which one they meant using buttons. Returns the name with
+/// the chosen difficulty appended.
+///
+/// Only the discord wrappers go through this, so the implementations (and
+/// therefore the tests) keep defaulting to FTR.
+async fn pick_difficulty(
+	ctx: Context<'_>,
+	name: &str,
+	tolerance: MatchTolerance,
+) -> Result<String, Error> {
+	if strip_difficulty_suffix(name).1.is_some() {
+		return Ok(name.to_string());
+	}
+
+	// Errors get reported by the implementation, which will run into them again
+	let Ok((song, _)) = guess_song_and_chart_with(ctx.data(), name, tolerance) else {
+		return Ok(name.to_string());
+	};
+
+	let difficulties: Vec<Difficulty> = ctx
+		.data()
+		.song_cache
+		.lookup_song(song.id)?
+		.charts()
+		.map(|(difficulty, _)| difficulty)
+		.collect();
+
+	if difficulties.len() <= 1 {
+		return Ok(name.to_string());
+	}
+
+	let id_prefix = format!("{}-difficulty-", ctx.id());
+	let buttons = difficulties
+		.iter()
+		.map(|difficulty| {
+			CreateButton::new(format!("{id_prefix}{}", difficulty.to_index()))
+				.label(Difficulty::DIFFICULTY_SHORTHANDS[difficulty.to_index()])
+		})
+		.collect();
+
+	let handle = poise::send_reply(
+		ctx,
+		CreateReply::default()
+			.reply(true)
+			.content(format!("Which difficulty of {song} did you mean?"))
+			.components(vec![CreateActionRow::Buttons(buttons)]),
+	)
+	.await?;
+
+	let interaction = ComponentInteractionCollector::new(ctx.serenity_context())
+		.author_id(ctx.author().id)
+		.channel_id(ctx.channel_id())
+		.timeout(DIFFICULTY_PICKER_TIMEOUT)
+		.filter({
+			let id_prefix = id_prefix.clone();
+			move |interaction| interaction.data.custom_id.starts_with(&id_prefix)
+		})
+		.await;
+
+	handle.delete(ctx).await?;
+
+	let Some(interaction) = interaction else {
+		return Ok(name.to_string());
+	};
+
+	interaction
+		.create_response(
+			ctx.serenity_context(),
+			CreateInteractionResponse::Acknowledge,
+		)
+		.await?;
+
+	let chosen = interaction
+		.data
+		.custom_id
+		.strip_prefix(&id_prefix)
+		.and_then(|index| index.parse::<usize>().ok())
+		.and_then(|index| Difficulty::DIFFICULTIES.get(index));
+
+	Ok(match chosen {
+		Some(difficulty) => format!(
+			"{name} {}",
+			Difficulty::DIFFICULTY_SHORTHANDS_IN_BRACKETS[difficulty.to_index()]
+		),
+		None => name.to_string(),
+	})
+}
+// }}}
 // {{{ Info
 // {{{ Implementation
-async fn info_impl(ctx: &mut impl MessageContext, name: &str) -> Result<(), TaggedError> {
-	let (song, chart) = guess_song_and_chart(ctx.data(), name)?;
+/// Counts how many of the charts in the given pack the user has cleared (i.e.
+/// played at least once without a track lost), returning the count alongside
+/// the total number of charts.
+fn pack_progress(ctx: &UserContext, user: &User, pack: &str) -> Result<(usize, usize), Error> {
+	let progress = ctx
+		.db
+		.get()?
+		.prepare_cached(
+			"
+        SELECT
+          (
+            SELECT COUNT(DISTINCT p.chart_id)
+            FROM plays p
+            JOIN charts c ON c.id = p.chart_id
+            JOIN songs s ON s.id = c.song_id
+            WHERE p.user_id = ?1 AND s.pack = ?2
+            AND (p.clear_kind IS NULL OR p.clear_kind != ?3)
+          ),
+          (
+            SELECT COUNT(*)
+            FROM charts c
+            JOIN songs s ON s.id = c.song_id
+            WHERE s.pack = ?2
+          )
+      ",
+		)?
+		.query_row(
+			(
+				user.id,
+				pack,
+				ClearKind::CLEAR_KIND_DB_STRINGS[ClearKind::TrackLost.to_index()],
+			),
+			|row| Ok((row.get(0)?, row.get(1)?)),
+		)?;
+
+	Ok(progress)
+}
 
-	let attachement_name = "chart.png";
+/// Builds the embed describing a chart. The jacket (if any) is returned as an
+/// attachment with the given name.
+///
+/// When a user is provided, their progress through the chart's pack is
+/// included as well.
+fn chart_info_embed(
+	ctx: &UserContext,
+	user: Option<&User>,
+	song: &Song,
+	chart: &Chart,
+	attachement_name: &str,
+) -> Result<(CreateEmbed, Option<CreateAttachment>), Error> {
 	let icon_attachement = chart
 		.cached_jacket
 		.map(|jacket| CreateAttachment::bytes(jacket.raw, attachement_name));
 
 	let play_count: usize = ctx
-		.data()
 		.db
 		.get()?
 		.prepare_cached(
@@ -67,7 +228,7 @@ async fn info_impl(ctx: &mut impl MessageContext, name: &str) -> Result<(), Tagg
 		.field("Note count", format!("{}", chart.note_count), true)
 		.field(
 			"Chart constant",
-			format!("{:.1}", chart.chart_constant as f32 / 100.0),
+			format!("{:.1}", chart.chart_constant.get() as f32 / 100.0),
 			true,
 		)
 		.field("Total plays", format!("{play_count}"), true)
@@ -81,17 +242,41 @@ async fn info_impl(ctx: &mut impl MessageContext, name: &str) -> Result<(), Tagg
 
 	if let Some(pack) = &song.pack {
 		embed = embed.field("Pack", pack, true);
+
+		if let Some(user) = user {
+			let (cleared, total) = pack_progress(ctx, user, pack)?;
+			embed = embed.field(
+				"Pack progress",
+				format!("{cleared}/{total} charts in pack cleared"),
+				true,
+			);
+		}
 	}
 
 	if icon_attachement.is_some() {
 		embed = embed.thumbnail(format!("attachment://{}", &attachement_name));
 	}
 
+	Ok((embed, icon_attachement))
+}
+
+async fn info_impl(
+	ctx: &mut impl MessageContext,
+	name: &str,
+	tolerance: MatchTolerance,
+) -> Result<(), TaggedError> {
+	let (song, chart) = guess_song_and_chart_with(ctx.data(), name, tolerance)?;
+
+	// Unregistered users can still look charts up, they just won't see their progress
+	let user = User::from_context(ctx).ok();
+	let (embed, attachment) =
+		chart_info_embed(ctx.data(), user.as_ref(), song, chart, "chart.png")?;
+
 	ctx.send(
 		CreateReply::default()
 			.reply(true)
 			.embed(embed)
-			.attachments(icon_attachement),
+			.attachments(attachment),
 	)
 	.await?;
 
@@ -108,7 +293,7 @@ mod info_tests {
 	#[tokio::test]
 	async fn no_suffix() -> Result<(), Error> {
 		with_test_ctx!("commands/commands/chart/info/no_suffix", |ctx| async move {
-			info_impl(ctx, "Pentiment").await?;
+			info_impl(ctx, "Pentiment", MatchTolerance::LENIENT).await?;
 			Ok(())
 		})
 	}
@@ -118,7 +303,7 @@ mod info_tests {
 		with_test_ctx!(
 			"commands/commands/chart/info/specify_difficulty",
 			|ctx| async move {
-				info_impl(ctx, "Hellohell [ETR]").await?;
+				info_impl(ctx, "Hellohell [ETR]", MatchTolerance::LENIENT).await?;
 				Ok(())
 			}
 		)
@@ -126,8 +311,8 @@ mod info_tests {
 
 	golden_test!(last_byd, "commands/chart/info/last_byd");
 	async fn last_byd(ctx: &mut MockContext) -> Result<(), TaggedError> {
-		info_impl(ctx, "Last | Moment [BYD]").await?;
-		info_impl(ctx, "Last | Eternity [BYD]").await?;
+		info_impl(ctx, "Last | Moment [BYD]", MatchTolerance::LENIENT).await?;
+		info_impl(ctx, "Last | Eternity [BYD]", MatchTolerance::LENIENT).await?;
 		Ok(())
 	}
 }
@@ -137,32 +322,229 @@ mod info_tests {
 #[poise::command(prefix_command, slash_command, user_cooldown = 1)]
 async fn info(
 	mut ctx: Context<'_>,
+	#[description = "Only accept close matches (off by default)"] strict: Option<bool>,
 	#[rest]
 	#[description = "Name of chart (difficulty at the end)"]
 	name: String,
 ) -> Result<(), Error> {
-	let res = info_impl(&mut ctx, &name).await;
+	let tolerance = MatchTolerance::from_strict(strict.unwrap_or(false));
+	let name = pick_difficulty(ctx, &name, tolerance).await?;
+	let res = info_impl(&mut ctx, &name, tolerance).await;
 	ctx.handle_error(res).await?;
 
 	Ok(())
 }
 // }}}
 // }}}
-// {{{ Best score
+// {{{ By id
 // {{{ Implementation
-async fn best_impl<C: MessageContext>(ctx: &mut C, name: &str) -> Result<Play, TaggedError> {
-	let user = User::from_context(ctx)?;
+/// Shows the info embed of a chart given its internal id, or of every chart
+/// of a song given the song's id. Exactly one of the two must be provided.
+async fn by_id_impl(
+	ctx: &mut impl MessageContext,
+	chart_id: Option<u32>,
+	song_id: Option<u32>,
+) -> Result<(), TaggedError> {
+	let mut embeds = Vec::new();
+	let mut attachments = Vec::new();
 
-	let (song, chart) = guess_song_and_chart(ctx.data(), name)?;
+	match (chart_id, song_id) {
+		(Some(chart_id), None) => {
+			let (song, chart) = ctx
+				.data()
+				.song_cache
+				.lookup_chart(chart_id)
+				.map_err(|e| e.tag(ErrorKind::User))?;
+
+			let (embed, attachment) = chart_info_embed(ctx.data(), None, song, chart, "chart.png")?;
+			embeds.push(embed);
+			attachments.extend(attachment);
+		}
+		(None, Some(song_id)) => {
+			let cached_song = ctx
+				.data()
+				.song_cache
+				.lookup_song(song_id)
+				.map_err(|e| e.tag(ErrorKind::User))?;
+
+			for (_, chart_id) in cached_song.charts() {
+				let (song, chart) = ctx.data().song_cache.lookup_chart(chart_id)?;
+				let (embed, attachment) = chart_info_embed(
+					ctx.data(),
+					None,
+					song,
+					chart,
+					&format!("chart_{chart_id}.png"),
+				)?;
+
+				embeds.push(embed);
+				attachments.extend(attachment);
+			}
+		}
+		_ => {
+			return Err(
+				anyhow!("Please provide exactly one of a chart id or a song id")
+					.tag(ErrorKind::User),
+			);
+		}
+	}
+
+	ctx.send(
+		CreateReply::default()
+			.reply(true)
+			.embeds(embeds)
+			.attachments(attachments),
+	)
+	.await?;
+
+	Ok(())
+}
+// }}}
+// {{{ Tests
+#[cfg(test)]
+mod by_id_tests {
+	use crate::{
+		commands::discord::mock::MockContext, golden_test,
+		recognition::fuzzy_song_name::guess_song_and_chart,
+	};
+
+	use super::*;
+
+	golden_test!(chart_id, "commands/chart/by_id/chart_id");
+	async fn chart_id(ctx: &mut MockContext) -> Result<(), TaggedError> {
+		let (_, chart) = guess_song_and_chart(ctx.data(), "Pentiment [BYD]")?;
+		let chart_id = chart.id;
+		by_id_impl(ctx, Some(chart_id), None).await?;
+		Ok(())
+	}
+
+	golden_test!(song_id, "commands/chart/by_id/song_id");
+	async fn song_id(ctx: &mut MockContext) -> Result<(), TaggedError> {
+		let (song, _) = guess_song_and_chart(ctx.data(), "Pentiment")?;
+		let song_id = song.id;
+		by_id_impl(ctx, None, Some(song_id)).await?;
+		Ok(())
+	}
+
+	golden_test!(out_of_range, "commands/chart/by_id/out_of_range");
+	async fn out_of_range(ctx: &mut MockContext) -> Result<(), TaggedError> {
+		by_id_impl(ctx, Some(u32::MAX), None).await?;
+		Ok(())
+	}
+
+	golden_test!(no_ids, "commands/chart/by_id/no_ids");
+	async fn no_ids(ctx: &mut MockContext) -> Result<(), TaggedError> {
+		by_id_impl(ctx, None, None).await?;
+		Ok(())
+	}
+}
+// }}}
+// {{{ Discord wrapper
+/// Show a chart (or every chart of a song) given its internal id
+#[poise::command(prefix_command, slash_command, user_cooldown = 1, rename = "by-id")]
+async fn by_id(
+	mut ctx: Context<'_>,
+	#[description = "Internal id of the chart"] chart_id: Option<u32>,
+	#[description = "Show every chart of the song with this id instead"] song: Option<u32>,
+) -> Result<(), Error> {
+	let res = by_id_impl(&mut ctx, chart_id, song).await;
+	ctx.handle_error(res).await?;
+
+	Ok(())
+}
+// }}}
+// }}}
+// {{{ Search
+/// How many matches the search command shows.
+const SEARCH_RESULT_COUNT: usize = 5;
+
+// {{{ Implementation
+/// Lists the charts closest to the given name, returning their ids.
+async fn search_impl(ctx: &mut impl MessageContext, name: &str) -> Result<Vec<u32>, TaggedError> {
+	let (name, difficulty) = strip_difficulty_suffix(name);
+	let matches = rank_chart_names(
+		name,
+		&ctx.data().song_cache,
+		difficulty,
+		MatchTolerance::LENIENT,
+	);
+
+	if matches.is_empty() {
+		return Err(anyhow!("No charts match '{}'", name).tag(ErrorKind::User));
+	}
+
+	let mut chart_ids = Vec::with_capacity(SEARCH_RESULT_COUNT);
+	let mut description = String::new();
+	for (i, (song, chart, distance)) in matches.into_iter().take(SEARCH_RESULT_COUNT).enumerate() {
+		chart_ids.push(chart.id);
+		description.push_str(&format!(
+			"{}. {} [{:?} {}] (id {}, distance {})\n",
+			i + 1,
+			song.title,
+			chart.difficulty,
+			chart.level,
+			chart.id,
+			distance
+		));
+	}
+
+	let embed = CreateEmbed::default()
+		.title(format!("Charts matching '{}'", name))
+		.description(description);
+	ctx.send(CreateReply::default().reply(true).embed(embed))
+		.await?;
+
+	Ok(chart_ids)
+}
+// }}}
+// {{{ Tests
+#[cfg(test)]
+mod search_tests {
+	use crate::{commands::discord::mock::MockContext, golden_test};
+
+	use super::*;
+
+	golden_test!(closest_first, "commands/chart/search/closest_first");
+	async fn closest_first(ctx: &mut MockContext) -> Result<(), TaggedError> {
+		let (_, chart) = guess_song_and_chart(ctx.data(), "Pentiment [BYD]")?;
+		let chart_id = chart.id;
+
+		let chart_ids = search_impl(ctx, "Pentiment [BYD]").await?;
+		assert_eq!(chart_ids.first(), Some(&chart_id));
+		assert!(chart_ids.len() <= SEARCH_RESULT_COUNT);
+
+		Ok(())
+	}
+}
+// }}}
+// {{{ Discord wrapper
+/// Show the charts closest to a given name
+#[poise::command(prefix_command, slash_command, user_cooldown = 1)]
+async fn search(
+	mut ctx: Context<'_>,
+	#[rest]
+	#[description = "Name of chart to search for (difficulty at the end)"]
+	name: String,
+) -> Result<(), Error> {
+	let res = search_impl(&mut ctx, &name).await;
+	ctx.handle_error(res).await?;
+
+	Ok(())
+}
+// }}}
+// }}}
+// {{{ Best score
+// {{{ Implementation
+/// Looks up the best standard-scored play a user has on a given chart.
+fn best_play(ctx: &UserContext, user: &User, chart: &Chart) -> Result<Option<Play>, TaggedError> {
 	let play = ctx
-		.data()
 		.db
 		.get()?
 		.prepare_cached(
 			"
         SELECT 
         p.id, p.chart_id, p.user_id, p.created_at,
-        p.max_recall, p.far_notes, s.score
+        p.max_recall, p.far_notes, p.pure_notes, p.lost_notes, p.clear_kind, s.score
         FROM plays p
         JOIN scores s ON s.play_id = p.id
         WHERE s.scoring_system='standard'
@@ -172,15 +554,29 @@ async fn best_impl<C: MessageContext>(ctx: &mut C, name: &str) -> Result<Play, T
         LIMIT 1
       ",
 		)?
-		.query_row((user.id, chart.id), |row| Play::from_sql(chart, row))
-		.map_err(|_| {
-			anyhow!(
-				"Could not find any scores for {} [{:?}]",
-				song.title,
-				chart.difficulty
-			)
-			.tag(ErrorKind::User)
-		})?;
+		.query_map((user.id, chart.id), |row| Play::from_sql(chart, row))?
+		.next()
+		.transpose()?;
+
+	Ok(play)
+}
+
+async fn best_impl<C: MessageContext>(
+	ctx: &mut C,
+	name: &str,
+	tolerance: MatchTolerance,
+) -> Result<Play, TaggedError> {
+	let user = User::from_context(ctx)?;
+
+	let (song, chart) = guess_song_and_chart_with(ctx.data(), name, tolerance)?;
+	let play = best_play(ctx.data(), &user, chart)?.ok_or_else(|| {
+		anyhow!(
+			"Could not find any scores for {} [{:?}]",
+			song.title,
+			chart.difficulty
+		)
+		.tag(ErrorKind::User)
+	})?;
 
 	let (embed, attachment) = play.to_embed(
 		ctx.data(),
@@ -218,7 +614,7 @@ mod best_tests {
 	#[tokio::test]
 	async fn no_scores() -> Result<(), Error> {
 		with_test_ctx!("commands/chart/best/no_scores", |ctx| async move {
-			best_impl(ctx, "Pentiment").await?;
+			best_impl(ctx, "Pentiment", MatchTolerance::LENIENT).await?;
 			Ok(())
 		})
 	}
@@ -236,7 +632,7 @@ mod best_tests {
 		)
 		.await?;
 
-		let play = best_impl(ctx, "Fracture ray").await?;
+		let play = best_impl(ctx, "Fracture ray", MatchTolerance::LENIENT).await?;
 		assert_eq!(play.score(ScoringSystem::Standard).0, 9_805_651);
 		assert_eq!(plays[0], play);
 
@@ -250,135 +646,834 @@ mod best_tests {
 #[poise::command(prefix_command, slash_command, user_cooldown = 1)]
 async fn best(
 	mut ctx: Context<'_>,
+	#[description = "Only accept close matches (off by default)"] strict: Option<bool>,
 	#[rest]
 	#[description = "Name of chart (difficulty at the end)"]
 	name: String,
 ) -> Result<(), Error> {
-	let res = best_impl(&mut ctx, &name).await;
+	let tolerance = MatchTolerance::from_strict(strict.unwrap_or(false));
+	let name = pick_difficulty(ctx, &name, tolerance).await?;
+	let res = best_impl(&mut ctx, &name, tolerance).await;
 	ctx.handle_error(res).await?;
 
 	Ok(())
 }
 // }}}
 // }}}
-// {{{ Score plot
+// {{{ Most recent score
 // {{{ Implementation
-async fn plot_impl<C: MessageContext>(
-	ctx: &mut C,
-	scoring_system: Option<ScoringSystem>,
-	name: String,
-) -> Result<(), TaggedError> {
-	let user = User::from_context(ctx)?;
-	let scoring_system = scoring_system.unwrap_or_default();
-
-	let (song, chart) = guess_song_and_chart(ctx.data(), &name)?;
-
-	// SAFETY: we limit the amount of plotted plays to 1000.
-	let plays = ctx
-		.data()
+/// Looks up the most recent play a user has on a given chart.
+fn recent_play(ctx: &UserContext, user: &User, chart: &Chart) -> Result<Option<Play>, TaggedError> {
+	let play = ctx
 		.db
 		.get()?
 		.prepare_cached(
 			"
-      SELECT 
+        SELECT 
         p.id, p.chart_id, p.user_id, p.created_at,
-        p.max_recall, p.far_notes, s.score
-      FROM plays p
-      JOIN scores s ON s.play_id = p.id
-      WHERE s.scoring_system='standard'
-      AND p.user_id=?
-      AND p.chart_id=?
-      ORDER BY s.score DESC
-      LIMIT 1000
-    ",
+        p.max_recall, p.far_notes, p.pure_notes, p.lost_notes, p.clear_kind, s.score
+        FROM plays p
+        JOIN scores s ON s.play_id = p.id
+        WHERE s.scoring_system='standard'
+        AND p.user_id=?
+        AND p.chart_id=?
+        ORDER BY p.created_at DESC, p.id DESC
+        LIMIT 1
+      ",
 		)?
 		.query_map((user.id, chart.id), |row| Play::from_sql(chart, row))?
-		.collect::<Result<Vec<_>, _>>()?;
-
-	if plays.is_empty() {
-		return Err(
-			anyhow!("No plays found on {} [{:?}]", song.title, chart.difficulty)
-				.tag(ErrorKind::User),
-		);
-	}
+		.next()
+		.transpose()?;
 
-	let min_time = plays.iter().map(|p| p.created_at).min().unwrap();
-	let max_time = plays.iter().map(|p| p.created_at).max().unwrap();
-	let mut min_score = plays
-		.iter()
-		.map(|p| p.score(scoring_system))
-		.min()
-		.unwrap()
-		.0 as i64;
+	Ok(play)
+}
 
-	if min_score > 9_900_000 {
-		min_score = 9_900_000;
-	} else if min_score > 9_800_000 {
-		min_score = 9_800_000;
-	} else if min_score > 9_500_000 {
-		min_score = 9_500_000;
-	} else {
-		min_score = 9_000_000
-	};
+async fn recent_impl<C: MessageContext>(
+	ctx: &mut C,
+	name: &str,
+	tolerance: MatchTolerance,
+) -> Result<Play, TaggedError> {
+	let user = User::from_context(ctx)?;
 
-	let max_score = 10_010_000;
-	let width = 1024;
-	let height = 768;
+	let (song, chart) = guess_song_and_chart_with(ctx.data(), name, tolerance)?;
+	let play = recent_play(ctx.data(), &user, chart)?.ok_or_else(|| {
+		anyhow!(
+			"You have not played {} [{:?}] yet",
+			song.title,
+			chart.difficulty
+		)
+		.tag(ErrorKind::User)
+	})?;
 
-	let mut buffer = vec![u8::MAX; RGBPixel::PIXEL_SIZE * (width * height) as usize];
+	let (embed, attachment) = play.to_embed(
+		ctx.data(),
+		&user,
+		song,
+		chart,
+		0,
+		Some(&ctx.fetch_user(&user.discord_id).await?),
+	)?;
 
-	{
-		let root = BitMapBackend::with_buffer(&mut buffer, (width, height)).into_drawing_area();
+	ctx.send(
+		CreateReply::default()
+			.reply(true)
+			.embed(embed)
+			.attachments(attachment),
+	)
+	.await?;
 
-		let mut chart_buider = ChartBuilder::on(&root)
-			.margin(25)
-			.caption(
-				format!("{} [{:?}]", song.title, chart.difficulty),
-				("sans-serif", 40),
-			)
-			.set_label_area_size(LabelAreaPosition::Left, 100)
-			.set_label_area_size(LabelAreaPosition::Bottom, 40)
-			.build_cartesian_2d(
-				min_time.and_utc().timestamp_millis()..max_time.and_utc().timestamp_millis(),
-				min_score..max_score,
-			)?;
+	Ok(play)
+}
+// }}}
+// {{{ Tests
+#[cfg(test)]
+mod recent_tests {
+	use std::{path::PathBuf, str::FromStr};
 
-		chart_buider
-			.configure_mesh()
-			.light_line_style(WHITE)
-			.y_label_formatter(&|s| format!("{}", Score(*s as u32)))
-			.y_desc("Score")
-			.x_label_formatter(&|d| {
-				format!(
-					"{}",
-					DateTime::from_timestamp_millis(*d).unwrap().date_naive()
-				)
-			})
-			.y_label_style(TextStyle::from(("sans-serif", 20).into_font()))
-			.x_label_style(TextStyle::from(("sans-serif", 20).into_font()))
-			.draw()?;
+	use crate::{
+		commands::{discord::mock::MockContext, score::magic_impl},
+		golden_test, with_test_ctx,
+	};
 
-		let mut points: Vec<_> = plays
-			.into_iter()
-			.map(|play| {
-				(
-					play.created_at.and_utc().timestamp_millis(),
-					play.score(scoring_system),
-				)
-			})
-			.collect();
+	use super::*;
 
-		points.sort();
+	#[tokio::test]
+	async fn no_scores() -> Result<(), Error> {
+		with_test_ctx!("commands/chart/recent/no_scores", |ctx| async move {
+			recent_impl(ctx, "Pentiment", MatchTolerance::LENIENT).await?;
+			Ok(())
+		})
+	}
+
+	golden_test!(pick_latest_score, "commands/chart/recent/pick_latest_score");
+	async fn pick_latest_score(ctx: &mut MockContext) -> Result<(), TaggedError> {
+		let plays = magic_impl(
+			ctx,
+			&[
+				PathBuf::from_str("test/screenshots/fracture_ray_ex.jpg")?,
+				PathBuf::from_str("test/screenshots/antithese_74_kerning.jpg")?,
+				PathBuf::from_str("test/screenshots/fracture_ray_missed_ex.jpg")?,
+			],
+		)
+		.await?;
+
+		// Unlike `chart best`, the later (and worse) score gets picked
+		let play = recent_impl(ctx, "Fracture ray", MatchTolerance::LENIENT).await?;
+		assert_eq!(plays[2], play);
+		assert_ne!(
+			best_impl(ctx, "Fracture ray", MatchTolerance::LENIENT).await?,
+			play
+		);
+
+		Ok(())
+	}
+}
+// }}}
+// {{{ Discord wrapper
+/// Show your most recent score on a given chart
+#[poise::command(prefix_command, slash_command, user_cooldown = 1)]
+async fn recent(
+	mut ctx: Context<'_>,
+	#[description = "Only accept close matches (off by default)"] strict: Option<bool>,
+	#[rest]
+	#[description = "Name of chart (difficulty at the end)"]
+	name: String,
+) -> Result<(), Error> {
+	let tolerance = MatchTolerance::from_strict(strict.unwrap_or(false));
+	let name = pick_difficulty(ctx, &name, tolerance).await?;
+	let res = recent_impl(&mut ctx, &name, tolerance).await;
+	ctx.handle_error(res).await?;
+
+	Ok(())
+}
+// }}}
+// }}}
+// {{{ Best score on every difficulty
+// {{{ Implementation
+async fn best_all_impl<C: MessageContext>(
+	ctx: &mut C,
+	name: &str,
+) -> Result<Vec<Play>, TaggedError> {
+	let user = User::from_context(ctx)?;
+
+	let (song, _) = guess_song_and_chart(ctx.data(), name)?;
+	let cached_song = ctx.data().song_cache.lookup_song(song.id)?;
+	let author = ctx.fetch_user(&user.discord_id).await?;
+
+	let mut plays = Vec::new();
+	let mut embeds = Vec::new();
+	let mut attachments = Vec::new();
+	for (difficulty, _) in cached_song.charts() {
+		let (song, chart) = ctx
+			.data()
+			.song_cache
+			.lookup_by_difficulty(song.id, difficulty)?;
+
+		let Some(play) = best_play(ctx.data(), &user, chart)? else {
+			continue;
+		};
+
+		let (embed, attachment) =
+			play.to_embed(ctx.data(), &user, song, chart, plays.len(), Some(&author))?;
+
+		embeds.push(embed);
+		attachments.extend(attachment);
+		plays.push(play);
+	}
+
+	if plays.is_empty() {
+		return Err(anyhow!("No plays found on any chart of {}", song.title).tag(ErrorKind::User));
+	}
+
+	ctx.send(
+		CreateReply::default()
+			.reply(true)
+			.embeds(embeds)
+			.attachments(attachments),
+	)
+	.await?;
+
+	Ok(plays)
+}
+// }}}
+// {{{ Tests
+#[cfg(test)]
+mod best_all_tests {
+	use std::{path::PathBuf, str::FromStr};
+
+	use crate::{
+		commands::{discord::mock::MockContext, score::magic_impl},
+		golden_test, with_test_ctx,
+	};
+
+	use super::*;
+
+	#[tokio::test]
+	async fn no_scores() -> Result<(), Error> {
+		with_test_ctx!("commands/chart/best_all/no_scores", |ctx| async move {
+			best_all_impl(ctx, "Pentiment").await?;
+			Ok(())
+		})
+	}
+
+	golden_test!(
+		ignores_difficulty,
+		"commands/chart/best_all/ignores_difficulty"
+	);
+	async fn ignores_difficulty(ctx: &mut MockContext) -> Result<(), TaggedError> {
+		magic_impl(
+			ctx,
+			&[
+				PathBuf::from_str("test/screenshots/fracture_ray_ex.jpg")?,
+				PathBuf::from_str("test/screenshots/fracture_ray_missed_ex.jpg")?,
+			],
+		)
+		.await?;
+
+		let plays = best_all_impl(ctx, "Fracture ray [PST]").await?;
+		assert_eq!(plays.len(), 1);
+		assert_eq!(plays[0].score(ScoringSystem::Standard).0, 9_805_651);
+
+		Ok(())
+	}
+}
+// }}}
+// {{{ Discord wrapper
+/// Show your best score on every difficulty of a song
+#[poise::command(prefix_command, slash_command, user_cooldown = 1, rename = "best-all")]
+async fn best_all(
+	mut ctx: Context<'_>,
+	#[rest]
+	#[description = "Name of song (difficulty is ignored)"]
+	name: String,
+) -> Result<(), Error> {
+	let res = best_all_impl(&mut ctx, &name).await;
+	ctx.handle_error(res).await?;
+
+	Ok(())
+}
+// }}}
+// }}}
+// {{{ Score history
+/// How many entries the history command shows at most.
+const HISTORY_MAX_ROWS: usize = 20;
+
+// {{{ Implementation
+async fn history_impl<C: MessageContext>(
+	ctx: &mut C,
+	name: &str,
+) -> Result<Vec<Play>, TaggedError> {
+	let user = User::from_context(ctx)?;
+
+	let (song, chart) = guess_song_and_chart(ctx.data(), name)?;
+
+	// We fetch one extra play, such that the oldest row we show still has
+	// something to diff against, and so we know whether we truncated.
+	let mut plays = ctx
+		.data()
+		.db
+		.get()?
+		.prepare_cached(
+			"
+      SELECT 
+        p.id, p.chart_id, p.user_id, p.created_at,
+        p.max_recall, p.far_notes, p.pure_notes, p.lost_notes, p.clear_kind, s.score
+      FROM plays p
+      JOIN scores s ON s.play_id = p.id
+      WHERE s.scoring_system='standard'
+      AND p.user_id=?
+      AND p.chart_id=?
+      ORDER BY p.created_at DESC, p.id DESC
+      LIMIT ?
+    ",
+		)?
+		.query_map((user.id, chart.id, HISTORY_MAX_ROWS + 1), |row| {
+			Play::from_sql(chart, row)
+		})?
+		.collect::<Result<Vec<_>, _>>()?;
+
+	if plays.is_empty() {
+		return Err(
+			anyhow!("No plays found on {} [{:?}]", song.title, chart.difficulty)
+				.tag(ErrorKind::User),
+		);
+	}
+
+	plays.reverse();
+	let truncated = plays.len() > HISTORY_MAX_ROWS;
+	let skip = if truncated { 1 } else { 0 };
+
+	let mut description = String::new();
+	for (i, play) in plays.iter().enumerate().skip(skip) {
+		let prev_score = i
+			.checked_sub(1)
+			.map(|j| plays[j].score(ScoringSystem::Standard));
+
+		description += &format!(
+			"`{}` {}\n",
+			play.created_at.format("%Y-%m-%d %H:%M"),
+			play.score(ScoringSystem::Standard)
+				.display_with_diff(prev_score)?
+		);
+	}
+
+	if truncated {
+		description += &format!("*Only showing the latest {HISTORY_MAX_ROWS} plays*");
+	}
+
+	let embed = CreateEmbed::default()
+		.title(format!(
+			"{} [{:?} {}] history",
+			song.title, chart.difficulty, chart.level
+		))
+		.description(description);
+
+	ctx.send(CreateReply::default().reply(true).embed(embed))
+		.await?;
+
+	Ok(plays.split_off(skip))
+}
+// }}}
+// {{{ Tests
+#[cfg(test)]
+mod history_tests {
+	use std::{path::PathBuf, str::FromStr};
+
+	use crate::{
+		commands::{discord::mock::MockContext, score::magic_impl},
+		golden_test, with_test_ctx,
+	};
+
+	use super::*;
+
+	#[tokio::test]
+	async fn no_scores() -> Result<(), Error> {
+		with_test_ctx!("commands/chart/history/no_scores", |ctx| async move {
+			history_impl(ctx, "Pentiment").await?;
+			Ok(())
+		})
+	}
+
+	golden_test!(chronological, "commands/chart/history/chronological");
+	async fn chronological(ctx: &mut MockContext) -> Result<(), TaggedError> {
+		let plays = magic_impl(
+			ctx,
+			&[
+				PathBuf::from_str("test/screenshots/fracture_ray_ex.jpg")?,
+				PathBuf::from_str("test/screenshots/fracture_ray_missed_ex.jpg")?,
+			],
+		)
+		.await?;
+
+		let history = history_impl(ctx, "Fracture ray").await?;
+		assert_eq!(history, plays);
+
+		Ok(())
+	}
+}
+// }}}
+// {{{ Discord wrapper
+/// Show how your score on a chart evolved over time
+#[poise::command(prefix_command, slash_command, user_cooldown = 1)]
+async fn history(
+	mut ctx: Context<'_>,
+	#[rest]
+	#[description = "Name of chart (difficulty at the end)"]
+	name: String,
+) -> Result<(), Error> {
+	let res = history_impl(&mut ctx, &name).await;
+	ctx.handle_error(res).await?;
+
+	Ok(())
+}
+// }}}
+// }}}
+// {{{ Leaderboard
+/// How many users to list in a single embed.
+const LEADERBOARD_PAGE_SIZE: usize = 20;
+
+// {{{ Implementation
+async fn leaderboard_impl<C: MessageContext>(
+	ctx: &mut C,
+	scoring_system: Option<ScoringSystem>,
+	name: &str,
+) -> Result<Vec<(u32, Score)>, TaggedError> {
+	let scoring_system = scoring_system.unwrap_or_default();
+	let (song, chart) = guess_song_and_chart(ctx.data(), name)?;
+
+	let entries: Vec<(u32, String, Score)> = ctx
+		.data()
+		.db
+		.get()?
+		.prepare_cached(
+			"
+      SELECT u.id, u.discord_id, MAX(s.score) as score
+      FROM plays p
+      JOIN scores s ON s.play_id = p.id
+      JOIN users u ON p.user_id = u.id
+      WHERE s.scoring_system=?
+      AND p.chart_id=?
+      GROUP BY u.id
+      ORDER BY score DESC, u.id ASC
+    ",
+		)?
+		.query_map(
+			(
+				ScoringSystem::SCORING_SYSTEM_DB_STRINGS[scoring_system.to_index()],
+				chart.id,
+			),
+			|row| Ok((row.get(0)?, row.get(1)?, Score(row.get(2)?))),
+		)?
+		.collect::<Result<_, _>>()?;
+
+	if entries.is_empty() {
+		return Err(
+			anyhow!("No plays found on {} [{:?}]", song.title, chart.difficulty)
+				.tag(ErrorKind::User),
+		);
+	}
+
+	let mut lines = Vec::with_capacity(entries.len());
+	for (i, (id, discord_id, score)) in entries.iter().enumerate() {
+		let name = match ctx.fetch_user(discord_id).await {
+			Ok(user) => user.name,
+			Err(_) => format!("User #{id}"),
+		};
+
+		lines.push(format!("{}. {name} — {score}", i + 1));
+	}
+
+	let page_count = lines.len().div_ceil(LEADERBOARD_PAGE_SIZE);
+	let embeds = lines
+		.chunks(LEADERBOARD_PAGE_SIZE)
+		.enumerate()
+		.map(|(i, page)| {
+			CreateEmbed::default()
+				.title(format!(
+					"{} [{:?} {}] leaderboard ({}/{})",
+					song.title,
+					chart.difficulty,
+					chart.level,
+					i + 1,
+					page_count
+				))
+				.description(page.join("\n"))
+		})
+		.collect::<Vec<_>>();
+
+	for embeds in embeds.chunks(MAX_EMBEDS_PER_MESSAGE) {
+		ctx.send(
+			CreateReply::default()
+				.reply(true)
+				.embeds(embeds.iter().cloned()),
+		)
+		.await?;
+	}
+
+	Ok(entries
+		.into_iter()
+		.map(|(id, _, score)| (id, score))
+		.collect())
+}
+// }}}
+// {{{ Tests
+#[cfg(test)]
+mod leaderboard_tests {
+	use std::{path::PathBuf, str::FromStr};
+
+	use crate::{
+		commands::{discord::mock::MockContext, score::magic_impl},
+		golden_test, with_test_ctx,
+	};
+
+	use super::*;
+
+	#[tokio::test]
+	async fn no_scores() -> Result<(), Error> {
+		with_test_ctx!("commands/chart/leaderboard/no_scores", |ctx| async move {
+			leaderboard_impl(ctx, None, "Pentiment").await?;
+			Ok(())
+		})
+	}
+
+	golden_test!(best_per_user, "commands/chart/leaderboard/best_per_user");
+	async fn best_per_user(ctx: &mut MockContext) -> Result<(), TaggedError> {
+		magic_impl(
+			ctx,
+			&[
+				PathBuf::from_str("test/screenshots/fracture_ray_ex.jpg")?,
+				PathBuf::from_str("test/screenshots/fracture_ray_missed_ex.jpg")?,
+			],
+		)
+		.await?;
+
+		let entries = leaderboard_impl(ctx, None, "Fracture ray").await?;
+		assert_eq!(entries.len(), 1);
+		assert_eq!(entries[0].1, Score(9_805_651));
+
+		Ok(())
+	}
+}
+// }}}
+// {{{ Discord wrapper
+/// Show the best score of every user on a given chart
+#[poise::command(prefix_command, slash_command, user_cooldown = 5)]
+async fn leaderboard(
+	mut ctx: Context<'_>,
+	scoring_system: Option<ScoringSystem>,
+	#[rest]
+	#[description = "Name of chart (difficulty at the end)"]
+	name: String,
+) -> Result<(), Error> {
+	let res = leaderboard_impl(&mut ctx, scoring_system, &name).await;
+	ctx.handle_error(res).await?;
+
+	Ok(())
+}
+// }}}
+// }}}
+// {{{ Score plot
+// {{{ Implementation
+/// A single line on a score plot.
+struct PlotSeries<'a> {
+	song: &'a Song,
+	chart: &'a Chart,
+	scoring_system: ScoringSystem,
+	plays: Vec<Play>,
+}
+
+/// The (inclusive) range of dates a score plot covers.
+#[derive(Debug, Clone, Copy)]
+struct PlotRange {
+	since: Option<NaiveDate>,
+	until: Option<NaiveDate>,
+}
+
+impl PlotRange {
+	fn new(since: Option<NaiveDate>, until: Option<NaiveDate>) -> Result<Self, TaggedError> {
+		if let (Some(since), Some(until)) = (since, until) {
+			if since > until {
+				return Err(anyhow!(
+					"The start date ({since}) must not come after the end date ({until})"
+				)
+				.tag(ErrorKind::User));
+			}
+		}
+
+		Ok(Self { since, until })
+	}
+
+	// NOTE: timestamps are stored as text, so the bounds must stay within
+	// four digit years for the comparisons to make sense.
+	#[inline]
+	fn start(&self) -> NaiveDateTime {
+		self.since
+			.unwrap_or(NaiveDate::from_ymd_opt(1, 1, 1).unwrap())
+			.and_hms_opt(0, 0, 0)
+			.unwrap()
+	}
+
+	#[inline]
+	fn end(&self) -> NaiveDateTime {
+		self.until
+			.unwrap_or(NaiveDate::from_ymd_opt(9999, 12, 31).unwrap())
+			.and_hms_milli_opt(23, 59, 59, 999)
+			.unwrap()
+	}
+
+	fn describe(&self) -> String {
+		match (self.since, self.until) {
+			(None, None) => String::new(),
+			(Some(since), None) => format!(" since {since}"),
+			(None, Some(until)) => format!(" until {until}"),
+			(Some(since), Some(until)) => format!(" between {since} and {until}"),
+		}
+	}
+}
+
+impl<'a> PlotSeries<'a> {
+	fn new<C: MessageContext>(
+		ctx: &'a C,
+		user: &User,
+		song: &'a Song,
+		chart: &'a Chart,
+		scoring_system: ScoringSystem,
+		range: PlotRange,
+	) -> Result<Self, TaggedError> {
+		// SAFETY: we limit the amount of plotted plays to 1000.
+		let plays = ctx
+			.data()
+			.db
+			.get()?
+			.prepare_cached(
+				"
+        SELECT 
+          p.id, p.chart_id, p.user_id, p.created_at,
+          p.max_recall, p.far_notes, p.pure_notes, p.lost_notes, p.clear_kind, s.score
+        FROM plays p
+        JOIN scores s ON s.play_id = p.id
+        WHERE s.scoring_system='standard'
+        AND p.user_id=?
+        AND p.chart_id=?
+        AND p.created_at BETWEEN ? AND ?
+        ORDER BY s.score DESC
+        LIMIT 1000
+      ",
+			)?
+			.query_map((user.id, chart.id, range.start(), range.end()), |row| {
+				Play::from_sql(chart, row)
+			})?
+			.collect::<Result<Vec<_>, _>>()?;
+
+		if plays.is_empty() {
+			return Err(anyhow!(
+				"No plays found on {} [{:?}]{}",
+				song.title,
+				chart.difficulty,
+				range.describe()
+			)
+			.tag(ErrorKind::User));
+		}
+
+		Ok(Self {
+			song,
+			chart,
+			scoring_system,
+			plays,
+		})
+	}
+
+	#[inline]
+	fn label(&self) -> String {
+		format!(
+			"{} [{:?}] ({})",
+			self.song.title,
+			self.chart.difficulty,
+			ScoringSystem::SCORING_SYSTEM_DB_STRINGS[self.scoring_system.to_index()]
+		)
+	}
+
+	fn points(&self) -> Vec<(i64, i64)> {
+		let mut points: Vec<_> = self
+			.plays
+			.iter()
+			.map(|play| {
+				(
+					play.created_at.and_utc().timestamp_millis(),
+					play.score(self.scoring_system).0 as i64,
+				)
+			})
+			.collect();
+
+		points.sort();
 		points.dedup();
+		points
+	}
+}
+
+/// Converts one of our colors to one plotters understands, discarding the
+/// alpha channel.
+#[inline]
+pub fn to_plotters_color(color: Color) -> RGBColor {
+	RGBColor(color.0, color.1, color.2)
+}
+
+/// The color used to plot a chart of the given difficulty. The menu colors
+/// are a bit too light to stand out on a white background, so they get
+/// darkened. Higher `shade` values result in darker variations of the same
+/// color, which is useful for telling apart charts of the same difficulty.
+pub fn difficulty_plot_color(difficulty: Difficulty, shade: usize) -> RGBColor {
+	let alpha = (0x50 + 0x30 * shade).min(0xc0) as u8;
+	let color = Color::BLACK
+		.alpha(alpha)
+		.blend(DIFFICULTY_MENU_PIXEL_COLORS[difficulty.to_index()]);
+
+	to_plotters_color(color)
+}
+
+async fn plot_impl<C: MessageContext>(
+	ctx: &mut C,
+	scoring_system: Option<ScoringSystem>,
+	compare_system: Option<ScoringSystem>,
+	since: Option<NaiveDate>,
+	until: Option<NaiveDate>,
+	name: String,
+) -> Result<(), TaggedError> {
+	let user = User::from_context(ctx)?;
+	let scoring_system = scoring_system.unwrap_or_default();
+	let range = PlotRange::new(since, until)?;
+
+	// Another chart can be overlaid by writing `<chart> vs <other chart>`
+	let (name, compare_with) = match name.split_once(" vs ") {
+		Some((name, other)) => (name, Some(other)),
+		None => (name.as_str(), None),
+	};
+
+	let (song, chart) = guess_song_and_chart(ctx.data(), name)?;
+	let mut series = vec![PlotSeries::new(
+		ctx,
+		&user,
+		song,
+		chart,
+		scoring_system,
+		range,
+	)?];
+
+	// {{{ Optional second series
+	if compare_system.is_some() || compare_with.is_some() {
+		let (song, chart) = match compare_with {
+			Some(name) => guess_song_and_chart(ctx.data(), name)?,
+			None => (song, chart),
+		};
+
+		let scoring_system = compare_system.unwrap_or(scoring_system);
+		series.push(PlotSeries::new(
+			ctx,
+			&user,
+			song,
+			chart,
+			scoring_system,
+			range,
+		)?);
+	}
+	// }}}
+
+	let all_plays = || {
+		series
+			.iter()
+			.flat_map(|s| s.plays.iter().map(move |p| (s, p)))
+	};
+	let min_time = all_plays().map(|(_, p)| p.created_at).min().unwrap();
+	let max_time = all_plays().map(|(_, p)| p.created_at).max().unwrap();
+	let mut min_score = all_plays()
+		.map(|(s, p)| p.score(s.scoring_system))
+		.min()
+		.unwrap()
+		.0 as i64;
+
+	if min_score > 9_900_000 {
+		min_score = 9_900_000;
+	} else if min_score > 9_800_000 {
+		min_score = 9_800_000;
+	} else if min_score > 9_500_000 {
+		min_score = 9_500_000;
+	} else {
+		min_score = 9_000_000
+	};
+
+	let max_score = 10_010_000;
+	let width = 1024;
+	let height = 768;
+
+	let caption = if series.iter().all(|s| s.chart.id == chart.id) {
+		format!("{} [{:?}]", song.title, chart.difficulty)
+	} else {
+		series
+			.iter()
+			.map(|s| format!("{} [{:?}]", s.song.title, s.chart.difficulty))
+			.collect::<Vec<_>>()
+			.join(" vs ")
+	};
+
+	let mut buffer = vec![u8::MAX; RGBPixel::PIXEL_SIZE * (width * height) as usize];
+
+	{
+		let root = BitMapBackend::with_buffer(&mut buffer, (width, height)).into_drawing_area();
+
+		let mut chart_buider = ChartBuilder::on(&root)
+			.margin(25)
+			.caption(caption, ("sans-serif", 40))
+			.set_label_area_size(LabelAreaPosition::Left, 100)
+			.set_label_area_size(LabelAreaPosition::Bottom, 40)
+			.build_cartesian_2d(
+				min_time.and_utc().timestamp_millis()..max_time.and_utc().timestamp_millis(),
+				min_score..max_score,
+			)?;
+
+		chart_buider
+			.configure_mesh()
+			.light_line_style(WHITE)
+			.y_label_formatter(&|s| format!("{}", Score(*s as u32)))
+			.y_desc("Score")
+			.x_label_formatter(&|d| {
+				format!(
+					"{}",
+					DateTime::from_timestamp_millis(*d).unwrap().date_naive()
+				)
+			})
+			.y_label_style(TextStyle::from(("sans-serif", 20).into_font()))
+			.x_label_style(TextStyle::from(("sans-serif", 20).into_font()))
+			.draw()?;
+
+		for (i, current) in series.iter().enumerate() {
+			let points = current.points();
+			let shade = series[..i]
+				.iter()
+				.filter(|s| s.chart.difficulty == current.chart.difficulty)
+				.count();
+			let color = difficulty_plot_color(current.chart.difficulty, shade);
 
-		chart_buider.draw_series(LineSeries::new(
-			points.iter().map(|(t, s)| (*t, s.0 as i64)),
-			&BLUE,
-		))?;
+			chart_buider
+				.draw_series(LineSeries::new(points.iter().copied(), &color))?
+				.label(current.label())
+				.legend(move |(x, y)| PathElement::new(vec![(x, y), (x + 20, y)], color));
+
+			chart_buider.draw_series(
+				points
+					.iter()
+					.map(|(t, s)| Circle::new((*t, *s), 3, plotters::style::Color::filled(&color))),
+			)?;
+		}
+
+		if series.len() > 1 {
+			chart_buider
+				.configure_series_labels()
+				.label_font(("sans-serif", 20))
+				.background_style(WHITE)
+				.border_style(BLACK)
+				.draw()?;
+		}
 
-		chart_buider.draw_series(points.iter().map(|(t, s)| {
-			Circle::new((*t, s.0 as i64), 3, plotters::style::Color::filled(&BLUE))
-		}))?;
 		root.present()?;
 	}
 
@@ -396,17 +1491,540 @@ async fn plot_impl<C: MessageContext>(
 	Ok(())
 }
 // }}}
+// {{{ Tests
+#[cfg(test)]
+mod plot_tests {
+	use std::{path::PathBuf, str::FromStr};
+
+	use crate::{
+		commands::{discord::mock::MockContext, score::magic_impl},
+		golden_test,
+	};
+
+	use super::*;
+
+	golden_test!(since_after_until, "commands/chart/plot/since_after_until");
+	async fn since_after_until(ctx: &mut MockContext) -> Result<(), TaggedError> {
+		plot_impl(
+			ctx,
+			None,
+			None,
+			NaiveDate::from_ymd_opt(2024, 6, 1),
+			NaiveDate::from_ymd_opt(2024, 1, 1),
+			"Fracture ray [FTR]".to_string(),
+		)
+		.await
+	}
+
+	golden_test!(empty_range, "commands/chart/plot/empty_range");
+	async fn empty_range(ctx: &mut MockContext) -> Result<(), TaggedError> {
+		magic_impl(
+			ctx,
+			&[PathBuf::from_str("test/screenshots/fracture_ray_ex.jpg")?],
+		)
+		.await?;
+
+		plot_impl(
+			ctx,
+			None,
+			None,
+			NaiveDate::from_ymd_opt(2000, 1, 1),
+			NaiveDate::from_ymd_opt(2000, 12, 31),
+			"Fracture ray [FTR]".to_string(),
+		)
+		.await
+	}
+
+	#[test]
+	fn difficulty_colors_are_distinct() {
+		let colors = Difficulty::DIFFICULTIES.map(|d| difficulty_plot_color(d, 0));
+		for (i, a) in colors.iter().enumerate() {
+			for b in &colors[i + 1..] {
+				assert_ne!(a, b);
+			}
+		}
+
+		// Further shades get darker
+		let brightness = |c: RGBColor| c.0 as u32 + c.1 as u32 + c.2 as u32;
+		for difficulty in Difficulty::DIFFICULTIES {
+			assert!(
+				brightness(difficulty_plot_color(difficulty, 1))
+					< brightness(difficulty_plot_color(difficulty, 0))
+			);
+		}
+	}
+}
+// }}}
 // {{{ Discord wrapper
 /// Show the best score on a given chart
 #[poise::command(prefix_command, slash_command, user_cooldown = 10)]
 async fn plot(
 	mut ctx: Context<'_>,
 	scoring_system: Option<ScoringSystem>,
+	#[description = "Overlay another scoring system"] compare_system: Option<ScoringSystem>,
+	#[description = "Only plot plays from this date onwards (YYYY-MM-DD)"] since: Option<NaiveDate>,
+	#[description = "Only plot plays up to this date (YYYY-MM-DD)"] until: Option<NaiveDate>,
+	#[rest]
+	#[description = "Name of chart (difficulty at the end). Use `<chart> vs <chart>` to compare"]
+	name: String,
+) -> Result<(), Error> {
+	let res = plot_impl(&mut ctx, scoring_system, compare_system, since, until, name).await;
+	ctx.handle_error(res).await?;
+
+	Ok(())
+}
+// }}}
+// }}}
+// {{{ Aliases
+/// Manage the nicknames charts can be looked up by.
+#[poise::command(
+	prefix_command,
+	slash_command,
+	subcommands("alias_add", "alias_remove", "alias_list"),
+	subcommand_required
+)]
+async fn alias(_ctx: Context<'_>) -> Result<(), Error> {
+	Ok(())
+}
+
+// {{{ Add
+// {{{ Implementation
+async fn alias_add_impl<C: MessageContext>(
+	ctx: &mut C,
+	alias: &str,
+	name: &str,
+) -> Result<(), TaggedError> {
+	let user = User::from_context(ctx)?;
+	user.assert_is_admin()?;
+
+	let alias = alias.trim().to_lowercase();
+	if alias.is_empty() {
+		return Err(anyhow!("Aliases cannot be empty").tag(ErrorKind::User));
+	}
+
+	let (song, chart) = guess_song_and_chart(ctx.data(), name)?;
+	let inserted = ctx
+		.data()
+		.db
+		.get()?
+		.prepare_cached("INSERT OR IGNORE INTO aliases(chart_id, alias) VALUES (?,?)")?
+		.execute((chart.id, &alias))?;
+
+	if inserted == 0 {
+		return Err(anyhow!(
+			"`{alias}` is already an alias of {} [{}]",
+			song,
+			chart.difficulty
+		)
+		.tag(ErrorKind::User));
+	}
+
+	ctx.data()
+		.song_cache
+		.aliases
+		.write()
+		.unwrap()
+		.entry(chart.id)
+		.or_default()
+		.push(alias.clone());
+
+	ctx.reply(&format!(
+		"Added alias `{alias}` for {} [{}]",
+		song, chart.difficulty
+	))
+	.await?;
+
+	Ok(())
+}
+// }}}
+// {{{ Discord wrapper
+/// Add a nickname for a chart.
+#[poise::command(prefix_command, slash_command, rename = "add")]
+async fn alias_add(
+	mut ctx: Context<'_>,
+	#[description = "The nickname to add"] alias: String,
+	#[rest]
+	#[description = "Name of chart (difficulty at the end)"]
+	name: String,
+) -> Result<(), Error> {
+	let res = alias_add_impl(&mut ctx, &alias, &name).await;
+	ctx.handle_error(res).await?;
+
+	Ok(())
+}
+// }}}
+// }}}
+// {{{ Remove
+// {{{ Implementation
+async fn alias_remove_impl<C: MessageContext>(ctx: &mut C, alias: &str) -> Result<(), TaggedError> {
+	let user = User::from_context(ctx)?;
+	user.assert_is_admin()?;
+
+	let alias = alias.trim().to_lowercase();
+	let removed = ctx
+		.data()
+		.db
+		.get()?
+		.prepare_cached("DELETE FROM aliases WHERE alias=?")?
+		.execute([&alias])?;
+
+	if removed == 0 {
+		return Err(anyhow!("No chart has the alias `{alias}`").tag(ErrorKind::User));
+	}
+
+	for aliases in ctx.data().song_cache.aliases.write().unwrap().values_mut() {
+		aliases.retain(|a| *a != alias);
+	}
+
+	ctx.reply(&format!("Removed alias `{alias}` from {removed} chart(s)"))
+		.await?;
+
+	Ok(())
+}
+// }}}
+// {{{ Discord wrapper
+/// Remove a chart nickname.
+#[poise::command(prefix_command, slash_command, rename = "remove")]
+async fn alias_remove(
+	mut ctx: Context<'_>,
+	#[description = "The nickname to remove"] alias: String,
+) -> Result<(), Error> {
+	let res = alias_remove_impl(&mut ctx, &alias).await;
+	ctx.handle_error(res).await?;
+
+	Ok(())
+}
+// }}}
+// }}}
+// {{{ List
+// {{{ Implementation
+async fn alias_list_impl<C: MessageContext>(
+	ctx: &mut C,
+	name: &str,
+) -> Result<Vec<String>, TaggedError> {
+	let (song, chart) = guess_song_and_chart(ctx.data(), name)?;
+	let aliases = ctx
+		.data()
+		.song_cache
+		.aliases
+		.read()
+		.unwrap()
+		.get(&chart.id)
+		.cloned()
+		.unwrap_or_default();
+
+	if aliases.is_empty() {
+		ctx.reply(&format!("{} [{}] has no aliases", song, chart.difficulty))
+			.await?;
+	} else {
+		let list = aliases
+			.iter()
+			.map(|alias| format!("`{alias}`"))
+			.collect::<Vec<_>>()
+			.join(", ");
+
+		ctx.reply(&format!(
+			"Aliases for {} [{}]: {list}",
+			song, chart.difficulty
+		))
+		.await?;
+	}
+
+	Ok(aliases)
+}
+// }}}
+// {{{ Tests
+#[cfg(test)]
+mod alias_tests {
+	use crate::{commands::discord::mock::MockContext, golden_test};
+
+	use super::*;
+
+	golden_test!(not_an_admin, "commands/chart/alias/not_an_admin");
+	async fn not_an_admin(ctx: &mut MockContext) -> Result<(), TaggedError> {
+		alias_add_impl(ctx, "pentiment", "Pentiment").await?;
+		Ok(())
+	}
+
+	golden_test!(add_then_remove, "commands/chart/alias/add_then_remove");
+	async fn add_then_remove(ctx: &mut MockContext) -> Result<(), TaggedError> {
+		ctx.data
+			.db
+			.get()?
+			.execute("UPDATE users SET is_admin=1", ())?;
+
+		alias_add_impl(ctx, "fray", "Fracture Ray [FTR]").await?;
+		assert_eq!(alias_list_impl(ctx, "Fracture Ray [FTR]").await?, ["fray"]);
+		assert_eq!(
+			guess_song_and_chart(&ctx.data, "fray")?.0.title,
+			"Fracture Ray"
+		);
+
+		alias_remove_impl(ctx, "fray").await?;
+		assert!(alias_list_impl(ctx, "Fracture Ray [FTR]").await?.is_empty());
+		Ok(())
+	}
+}
+// }}}
+// {{{ Discord wrapper
+/// List the nicknames of a chart.
+#[poise::command(prefix_command, slash_command, rename = "list")]
+async fn alias_list(
+	mut ctx: Context<'_>,
+	#[rest]
+	#[description = "Name of chart (difficulty at the end)"]
+	name: String,
+) -> Result<(), Error> {
+	let res = alias_list_impl(&mut ctx, &name).await;
+	ctx.handle_error(res).await?;
+
+	Ok(())
+}
+// }}}
+// }}}
+// }}}
+// {{{ Set constant
+// {{{ Implementation
+/// Overrides the constant of a chart, both in the database and in memory.
+/// Returns the new constant (in fixed point).
+async fn set_constant_impl<C: MessageContext>(
+	ctx: &mut C,
+	value: f32,
+	name: &str,
+) -> Result<u32, TaggedError> {
+	let user = User::from_context(ctx)?;
+	user.assert_is_admin()?;
+
+	if !(0.0..=20.0).contains(&value) {
+		return Err(anyhow!("Chart constants must be between 0 and 20").tag(ErrorKind::User));
+	}
+
+	let constant = (value * 100.0).round() as u32;
+	let (song, chart) = guess_song_and_chart(ctx.data(), name)?;
+	let previous = chart.chart_constant.get();
+
+	ctx.data()
+		.db
+		.get()?
+		.prepare_cached("UPDATE charts SET chart_constant=? WHERE id=?")?
+		.execute((constant, chart.id))?;
+
+	chart.chart_constant.set(constant);
+	ctx.data().best_plays_cache.clear();
+
+	ctx.reply(&format!(
+		"Changed the constant of {} [{}] from {:.1} to {:.1}. Stored scores are left untouched until the next `admin recompute`.",
+		song,
+		chart.difficulty,
+		previous as f32 / 100.0,
+		constant as f32 / 100.0
+	))
+	.await?;
+
+	Ok(constant)
+}
+// }}}
+// {{{ Tests
+#[cfg(test)]
+mod set_constant_tests {
+	use crate::{commands::discord::mock::MockContext, golden_test};
+
+	use super::*;
+
+	golden_test!(not_an_admin, "commands/chart/set_constant/not_an_admin");
+	async fn not_an_admin(ctx: &mut MockContext) -> Result<(), TaggedError> {
+		set_constant_impl(ctx, 10.5, "Pentiment").await?;
+		Ok(())
+	}
+
+	golden_test!(out_of_range, "commands/chart/set_constant/out_of_range");
+	async fn out_of_range(ctx: &mut MockContext) -> Result<(), TaggedError> {
+		ctx.data
+			.db
+			.get()?
+			.execute("UPDATE users SET is_admin=1", ())?;
+
+		set_constant_impl(ctx, -1.0, "Pentiment").await?;
+		Ok(())
+	}
+
+	golden_test!(set_constant, "commands/chart/set_constant/set_constant");
+	async fn set_constant(ctx: &mut MockContext) -> Result<(), TaggedError> {
+		ctx.data
+			.db
+			.get()?
+			.execute("UPDATE users SET is_admin=1", ())?;
+
+		let constant = set_constant_impl(ctx, 11.4, "Fracture Ray [FTR]").await?;
+		assert_eq!(constant, 1140);
+
+		let (_, chart) = guess_song_and_chart(&ctx.data, "Fracture Ray [FTR]")?;
+		assert_eq!(chart.chart_constant.get(), 1140);
+
+		let stored: u32 = ctx.data.db.get()?.query_row(
+			"SELECT chart_constant FROM charts WHERE id=?",
+			[chart.id],
+			|row| row.get(0),
+		)?;
+		assert_eq!(stored, 1140);
+
+		Ok(())
+	}
+}
+// }}}
+// {{{ Discord wrapper
+/// Override the constant of a chart.
+#[poise::command(prefix_command, slash_command, rename = "set-constant")]
+async fn set_constant(
+	mut ctx: Context<'_>,
+	#[description = "The new chart constant (e.g. 10.8)"] value: f32,
 	#[rest]
 	#[description = "Name of chart (difficulty at the end)"]
 	name: String,
 ) -> Result<(), Error> {
-	let res = plot_impl(&mut ctx, scoring_system, name).await;
+	let res = set_constant_impl(&mut ctx, value, &name).await;
+	ctx.handle_error(res).await?;
+
+	Ok(())
+}
+// }}}
+// }}}
+// {{{ PM relay
+// {{{ Implementation
+/// Returns the level following the peak of a PM relay (see
+/// [compute_peak_pm_relay]), or [None] once the relay is complete.
+fn next_relay_level(peak: Option<Level>) -> Option<Level> {
+	match peak {
+		None => Some(Level::One),
+		Some(level) => Level::LEVELS.get(level.to_index() + 1).copied(),
+	}
+}
+
+/// Finds the lowest level the user has not PM'd a chart of yet (making it the
+/// next step of their PM relay), and suggests the chart with the lowest
+/// constant of said level they already have a score on. Returns the level and
+/// the id of the suggested chart, if any.
+async fn unplayed_pm_relay_impl<C: MessageContext>(
+	ctx: &mut C,
+	scoring_system: Option<ScoringSystem>,
+) -> Result<(Option<Level>, Option<u32>), TaggedError> {
+	let user = User::from_context(ctx)?;
+	let scoring_system = scoring_system.unwrap_or_default();
+
+	let plays = get_best_plays(ctx.data(), user.id, scoring_system, 0, usize::MAX, None)?;
+	let peak = compute_peak_pm_relay(
+		plays
+			.iter()
+			.filter(|(play, _, _)| play.score(scoring_system).is_pm())
+			.map(|(_, _, chart)| chart.level),
+	);
+
+	let Some(level) = next_relay_level(peak) else {
+		ctx.reply("You have PM'd a chart of every level. The relay is complete!")
+			.await?;
+		return Ok((None, None));
+	};
+
+	let easiest = ctx
+		.data()
+		.db
+		.get()?
+		.prepare_cached(
+			"
+        SELECT p.chart_id, MAX(s.score) as score
+        FROM plays p
+        JOIN scores s ON s.play_id = p.id
+        JOIN charts c ON c.id = p.chart_id
+        WHERE s.scoring_system=?
+        AND p.user_id=?
+        AND c.level=?
+        GROUP BY p.chart_id
+        ORDER BY c.chart_constant ASC, score DESC
+        LIMIT 1
+      ",
+		)?
+		.query_map(
+			(
+				ScoringSystem::SCORING_SYSTEM_DB_STRINGS[scoring_system.to_index()],
+				user.id,
+				Level::LEVEL_STRINGS[level.to_index()],
+			),
+			|row| Ok((row.get::<_, u32>("chart_id")?, Score(row.get("score")?))),
+		)?
+		.next()
+		.transpose()?;
+
+	let reached = match peak {
+		Some(peak) => format!("Your PM relay currently reaches level {peak}."),
+		None => "You have not started your PM relay yet.".to_string(),
+	};
+
+	let message = match easiest {
+		Some((chart_id, score)) => {
+			let (song, chart) = ctx.data().song_cache.lookup_chart(chart_id)?;
+			format!(
+				"{reached} Your easiest shot at a level {level} PM is {} [{}] (constant {:.1}), where your best is {}.",
+				song,
+				chart.difficulty,
+				chart.chart_constant.get() as f32 / 100.0,
+				score
+			)
+		}
+		None => format!(
+			"{reached} You have no scores on level {level} charts yet, so try one from `stats missing {level}`."
+		),
+	};
+
+	ctx.reply(&message).await?;
+
+	Ok((Some(level), easiest.map(|(chart_id, _)| chart_id)))
+}
+// }}}
+// {{{ Tests
+#[cfg(test)]
+mod unplayed_pm_relay_tests {
+	use std::{path::PathBuf, str::FromStr};
+
+	use crate::{
+		commands::{discord::mock::MockContext, score::magic_impl},
+		golden_test,
+	};
+
+	use super::*;
+
+	#[test]
+	fn relay_levels() {
+		assert_eq!(next_relay_level(None), Some(Level::One));
+		assert_eq!(next_relay_level(Some(Level::Nine)), Some(Level::NineP));
+		assert_eq!(next_relay_level(Some(Level::Twelve)), None);
+	}
+
+	golden_test!(no_pms, "commands/chart/unplayed_pm_relay/no_pms");
+	async fn no_pms(ctx: &mut MockContext) -> Result<(), TaggedError> {
+		magic_impl(ctx, &[PathBuf::from_str("test/screenshots/alter_ego.jpg")?]).await?;
+
+		// ALTER EGO is a level 10 chart, which is nowhere near the relay start
+		let (level, suggestion) = unplayed_pm_relay_impl(ctx, None).await?;
+		assert_eq!(level, Some(Level::One));
+		assert_eq!(suggestion, None);
+
+		Ok(())
+	}
+}
+// }}}
+// {{{ Discord wrapper
+/// Suggest a chart to PM next, in order to extend your PM relay.
+#[poise::command(
+	prefix_command,
+	slash_command,
+	user_cooldown = 1,
+	rename = "unplayed-pm-relay"
+)]
+async fn unplayed_pm_relay(
+	mut ctx: Context<'_>,
+	scoring_system: Option<ScoringSystem>,
+) -> Result<(), Error> {
+	let res = unplayed_pm_relay_impl(&mut ctx, scoring_system).await;
 	ctx.handle_error(res).await?;
 
 	Ok(())
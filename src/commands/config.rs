@@ -0,0 +1,104 @@
+// {{{ Imports
+use anyhow::anyhow;
+
+use crate::context::{Context, Error, ErrorKind, TagError, TaggedError};
+
+use super::discord::MessageContext;
+// }}}
+
+// {{{ Top command
+/// Per-server settings. Only available to server managers.
+#[poise::command(
+	prefix_command,
+	slash_command,
+	subcommands("prefix"),
+	subcommand_required,
+	guild_only,
+	required_permissions = "MANAGE_GUILD"
+)]
+pub async fn config(_ctx: Context<'_>) -> Result<(), Error> {
+	Ok(())
+}
+// }}}
+// {{{ Prefix
+// {{{ Implementation
+/// How long custom prefixes are allowed to be.
+const MAX_PREFIX_LENGTH: usize = 5;
+
+/// Changes the prefix commands must start with inside the given guild.
+async fn prefix_impl<C: MessageContext>(
+	ctx: &mut C,
+	guild_id: Option<u64>,
+	prefix: &str,
+) -> Result<(), TaggedError> {
+	let guild_id = guild_id
+		.ok_or_else(|| anyhow!("Prefixes can only be configured inside servers"))
+		.map_err(|e| e.tag(ErrorKind::User))?;
+
+	let prefix = prefix.trim();
+	if prefix.is_empty()
+		|| prefix.chars().count() > MAX_PREFIX_LENGTH
+		|| prefix.chars().any(char::is_whitespace)
+	{
+		return Err(anyhow!(
+			"Prefixes must be between 1 and {MAX_PREFIX_LENGTH} characters long, without any spaces"
+		)
+		.tag(ErrorKind::User));
+	}
+
+	ctx.data()
+		.guild_configs
+		.set_prefix(&ctx.data().db, guild_id, prefix)?;
+
+	ctx.reply(&format!(
+		"Commands in this server now start with `{prefix}`"
+	))
+	.await?;
+
+	Ok(())
+}
+// }}}
+// {{{ Tests
+#[cfg(test)]
+mod prefix_tests {
+	use crate::{commands::discord::mock::MockContext, golden_test};
+
+	use super::*;
+
+	golden_test!(invalid_prefix, "commands/config/prefix/invalid_prefix");
+	async fn invalid_prefix(ctx: &mut MockContext) -> Result<(), TaggedError> {
+		prefix_impl(ctx, Some(1), "a b").await?;
+		Ok(())
+	}
+
+	golden_test!(sets_prefix, "commands/config/prefix/sets_prefix");
+	async fn sets_prefix(ctx: &mut MockContext) -> Result<(), TaggedError> {
+		assert_eq!(ctx.data().guild_configs.prefix(1), "!");
+
+		prefix_impl(ctx, Some(1), "?").await?;
+		assert_eq!(ctx.data().guild_configs.prefix(1), "?");
+		assert_eq!(ctx.data().guild_configs.prefix(2), "!");
+
+		// Changing the prefix again must overwrite the previous one
+		prefix_impl(ctx, Some(1), "s!").await?;
+		assert_eq!(ctx.data().guild_configs.prefix(1), "s!");
+
+		Ok(())
+	}
+}
+// }}}
+// {{{ Discord wrapper
+/// Change the prefix commands must start with in this server
+#[poise::command(prefix_command, slash_command)]
+async fn prefix(
+	mut ctx: Context<'_>,
+	#[description = "The new prefix (e.g. `?`)"] prefix: String,
+) -> Result<(), Error> {
+	let guild_id = ctx.guild_id().map(|id| id.get());
+	let res = prefix_impl(&mut ctx, guild_id, &prefix).await;
+	ctx.handle_error(res).await?;
+
+	Ok(())
+}
+// }}}
+// }}}
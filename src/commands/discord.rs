@@ -1,8 +1,9 @@
 // {{{ Imports
+use std::env::var;
 use std::num::NonZeroU64;
 use std::str::FromStr;
 
-use poise::serenity_prelude::futures::future::join_all;
+use poise::serenity_prelude::futures::{stream, StreamExt, TryStreamExt};
 use poise::serenity_prelude::{CreateAttachment, CreateEmbed};
 use poise::CreateReply;
 
@@ -11,6 +12,21 @@ use crate::context::{Error, ErrorKind, TaggedError, UserContext};
 use crate::timed;
 // }}}
 
+// {{{ Download concurrency
+/// How many attachments get downloaded at once, unless overriden using
+/// `SHIMMERING_DOWNLOAD_CONCURRENCY`.
+const DEFAULT_DOWNLOAD_CONCURRENCY: usize = 6;
+
+/// The maximum number of attachments to download at the same time.
+#[inline]
+pub fn download_concurrency() -> usize {
+	var("SHIMMERING_DOWNLOAD_CONCURRENCY")
+		.ok()
+		.and_then(|limit| limit.parse().ok())
+		.filter(|limit| *limit > 0)
+		.unwrap_or(DEFAULT_DOWNLOAD_CONCURRENCY)
+}
+// }}}
 // {{{ Trait
 pub trait MessageContext {
 	/// Get the user context held by the message
@@ -26,6 +42,17 @@ pub trait MessageContext {
 	/// Deliver a message
 	async fn send(&mut self, message: CreateReply) -> Result<(), Error>;
 
+	// {{{ Editable replies
+	type ReplyHandle;
+
+	/// Reply to the current message, keeping a handle around such that the
+	/// reply can later be edited (see [Self::edit_reply]).
+	async fn reply_editable(&mut self, text: &str) -> Result<Self::ReplyHandle, Error>;
+
+	/// Replace the contents of a reply sent using [Self::reply_editable].
+	async fn edit_reply(&mut self, handle: &Self::ReplyHandle, text: &str) -> Result<(), Error>;
+	// }}}
+
 	// {{{ Input attachments
 	type Attachment;
 
@@ -36,7 +63,8 @@ pub trait MessageContext {
 	/// Downloads a single file.
 	async fn download(&self, attachment: &Self::Attachment) -> Result<Vec<u8>, Error>;
 
-	/// Downloads every image
+	/// Downloads every image, at most [download_concurrency] at a time. The
+	/// results are returned in the same order as the given attachments.
 	async fn download_images<'a>(
 		&self,
 		attachments: &'a [Self::Attachment],
@@ -44,13 +72,14 @@ pub trait MessageContext {
 		let download_tasks = attachments
 			.iter()
 			.filter(|file| Self::is_image(file))
-			.map(|file| async move { (file, self.download(file).await) });
+			.map(|file| async move { self.download(file).await.map(|bytes| (file, bytes)) });
 
-		let downloaded = timed!("dowload_files", { join_all(download_tasks).await });
-		downloaded
-			.into_iter()
-			.map(|(file, bytes)| Ok((file, bytes?)))
-			.collect::<Result<_, Error>>()
+		timed!("dowload_files", {
+			stream::iter(download_tasks)
+				.buffered(download_concurrency())
+				.try_collect()
+				.await
+		})
 	}
 	// }}}
 	// {{{ Erorr handling
@@ -98,6 +127,21 @@ impl<'a> MessageContext for poise::Context<'a, UserContext, Error> {
 		Ok(())
 	}
 
+	// {{{ Editable replies
+	type ReplyHandle = poise::ReplyHandle<'a>;
+
+	async fn reply_editable(&mut self, text: &str) -> Result<Self::ReplyHandle, Error> {
+		Ok(Self::reply(*self, text).await?)
+	}
+
+	async fn edit_reply(&mut self, handle: &Self::ReplyHandle, text: &str) -> Result<(), Error> {
+		handle
+			.edit(*self, CreateReply::default().content(text))
+			.await?;
+		Ok(())
+	}
+	// }}}
+
 	// {{{ Input attachments
 	fn attachment_id(attachment: &Self::Attachment) -> NonZeroU64 {
 		NonZeroU64::new(attachment.id.get()).unwrap()
@@ -140,11 +184,16 @@ pub mod mock {
 		description: Option<String>,
 		/// SHA-256 hash of the file
 		hash: String,
+		/// The raw file, which only gets written to disk for images
+		/// (see [MockContext::golden]).
+		#[serde(skip)]
+		data: Vec<u8>,
 	}
 
 	impl AttachmentEssence {
 		pub fn new(filename: String, description: Option<String>, data: &[u8]) -> Self {
 			Self {
+				data: data.to_vec(),
 				filename,
 				description,
 				hash: {
@@ -157,6 +206,15 @@ pub mod mock {
 				},
 			}
 		}
+
+		#[inline]
+		fn is_image(&self) -> bool {
+			let extension = Path::new(&self.filename)
+				.extension()
+				.and_then(|ext| ext.to_str());
+
+			matches!(extension, Some("png" | "jpg" | "jpeg" | "webp"))
+		}
 	}
 
 	/// Holds test-relevant data about a reply.
@@ -224,35 +282,117 @@ pub mod mock {
 		/// 2. If the data already exists on disk, the two copies will be
 		///    compared. A panic will occur on disagreements.
 		/// 3. `SHIMMERING_TEST_REGEN=1` can be passed to overwrite disagreements.
+		///    Alternatively, a comma separated list of test paths (relative to
+		///    the `test` directory) only overwrites the outputs of those tests
+		///    (see [regen_requested]).
+		///
+		/// Attachments are only compared by their hashes, so image attachments
+		/// get saved next to the golden copy as well (and must be committed
+		/// alongside it). Golden copies missing their images get them written
+		/// the next time they pass. When `SHIMMERING_TEST_DIFF_DIR` is set,
+		/// disagreeing messages have both the expected and the actual images
+		/// written to said directory, such that they can be inspected by hand.
 		pub fn golden(&self, path: &PathBuf) -> Result<(), Error> {
-			if env::var("SHIMMERING_TEST_REGEN").unwrap_or_default() == "1" {
+			let regen = env::var("SHIMMERING_TEST_REGEN").unwrap_or_default();
+			if regen_requested(&regen, path) && path.exists() {
 				fs::remove_dir_all(path)?;
 			}
 
 			fs::create_dir_all(path)?;
 
 			for (i, message) in self.messages.iter().enumerate() {
-				let file = path.join(format!("{i}.toml"));
-				Self::golden_impl(&file, message)?;
+				Self::golden_impl(path, i, message)?;
 			}
 
 			Ok(())
 		}
 
-		/// Runs the golden testing logic for a single file.
+		/// Runs the golden testing logic for a single message.
 		/// See [Self::golden] for more details.
-		fn golden_impl(path: &Path, message: &impl Serialize) -> Result<(), Error> {
+		fn golden_impl(dir: &Path, index: usize, message: &ReplyEssence) -> Result<(), Error> {
+			let path = dir.join(format!("{index}.toml"));
+			let actual = toml::to_string_pretty(message)?;
+
 			if path.exists() {
-				assert_eq!(toml::to_string_pretty(message)?, fs::read_to_string(path)?);
+				let expected = fs::read_to_string(&path)?;
+				if actual != expected {
+					if let Ok(diff_dir) = env::var("SHIMMERING_TEST_DIFF_DIR") {
+						Self::write_image_diff(&PathBuf::from(diff_dir), dir, index, message)?;
+					}
+				}
+
+				assert_eq!(actual, expected);
 			} else {
-				fs::write(path, toml::to_string_pretty(message)?)?;
+				fs::write(&path, actual)?;
+			}
+
+			// The hashes agree at this point, so any missing images can be
+			// recreated from the actual message.
+			Self::save_missing_images(dir, index, message)?;
+
+			Ok(())
+		}
+
+		/// Saves the image attachments of a message next to its golden copy
+		/// (as `<index>-<filename>`), skipping the ones already on disk.
+		fn save_missing_images(
+			dir: &Path,
+			index: usize,
+			message: &ReplyEssence,
+		) -> Result<(), Error> {
+			for attachment in message.attachments.iter().filter(|a| a.is_image()) {
+				let file = dir.join(format!("{index}-{}", attachment.filename));
+				if !file.exists() {
+					fs::write(file, &attachment.data)?;
+				}
+			}
+
+			Ok(())
+		}
+
+		/// Writes the expected and actual images of a message which disagrees
+		/// with its golden copy to `<diff_dir>/<test path>/{expected,actual}`.
+		/// Expected images which were never committed cannot be recovered, in
+		/// which case only the actual ones get written.
+		fn write_image_diff(
+			diff_dir: &Path,
+			dir: &Path,
+			index: usize,
+			message: &ReplyEssence,
+		) -> Result<(), Error> {
+			let out = diff_dir.join(dir.strip_prefix("test").unwrap_or(dir));
+			fs::create_dir_all(out.join("expected"))?;
+			fs::create_dir_all(out.join("actual"))?;
+
+			for attachment in message.attachments.iter().filter(|a| a.is_image()) {
+				let name = format!("{index}-{}", attachment.filename);
+				fs::write(out.join("actual").join(&name), &attachment.data)?;
+
+				let expected = dir.join(&name);
+				if expected.exists() {
+					fs::copy(expected, out.join("expected").join(&name))?;
+				}
 			}
 
+			println!("Wrote the images of message {index} to {out:?}");
 			Ok(())
 		}
 		// }}}
 	}
 
+	/// Decides whether the golden output at the given path should be
+	/// regenerated, given the value of `SHIMMERING_TEST_REGEN`. The value `1`
+	/// regenerates everything, while something like
+	/// `commands/score/show/no_ids,commands/stats/recent/no_plays` only
+	/// regenerates the listed tests.
+	pub fn regen_requested(regen: &str, path: &Path) -> bool {
+		regen == "1"
+			|| regen
+				.split(',')
+				.map(str::trim)
+				.any(|test| !test.is_empty() && path.ends_with(test))
+	}
+
 	impl MessageContext for MockContext {
 		fn author_id(&self) -> u64 {
 			self.user_id
@@ -285,6 +425,31 @@ pub mod mock {
 			Ok(())
 		}
 
+		// {{{ Editable replies
+		/// The index of the reply inside the saved messages.
+		type ReplyHandle = usize;
+
+		async fn reply_editable(&mut self, text: &str) -> Result<Self::ReplyHandle, Error> {
+			let index = self.messages.len();
+			self.reply(text).await?;
+			Ok(index)
+		}
+
+		/// Edits happen in place, so golden copies only contain the final
+		/// contents of each reply.
+		async fn edit_reply(
+			&mut self,
+			handle: &Self::ReplyHandle,
+			text: &str,
+		) -> Result<(), Error> {
+			if let Some(message) = self.messages.get_mut(*handle) {
+				message.content = Some(text.to_string());
+			}
+
+			Ok(())
+		}
+		// }}}
+
 		// {{{ Input attachments
 		type Attachment = PathBuf;
 
@@ -314,6 +479,88 @@ pub mod mock {
 	// }}}
 }
 // }}}
+// {{{ Tests
+#[cfg(test)]
+mod download_tests {
+	use std::fs;
+	use std::path::PathBuf;
+
+	use crate::context::testing::get_mock_context;
+
+	use super::mock::MockContext;
+	use super::*;
+
+	#[tokio::test]
+	async fn results_match_input_order() -> Result<(), Error> {
+		let (ctx, _guard) = get_mock_context().await?;
+		let dir = tempfile::tempdir()?;
+
+		// Enough files for the downloads to span multiple batches.
+		let mut files = Vec::new();
+		for i in 0..4 * DEFAULT_DOWNLOAD_CONCURRENCY {
+			let file = dir.path().join(format!("{i}.png"));
+			fs::write(&file, i.to_string())?;
+			files.push(file);
+		}
+
+		// Non-images get skipped, without messing up the pairing.
+		let note = dir.path().join("note.txt");
+		fs::write(&note, "not an image")?;
+		files.insert(3, note);
+
+		let downloaded = ctx.download_images(&files).await?;
+		assert_eq!(downloaded.len(), files.len() - 1);
+
+		let expected = files.iter().filter(|file| MockContext::is_image(file));
+		for ((file, bytes), expected) in downloaded.into_iter().zip(expected) {
+			assert_eq!(file, expected);
+			assert_eq!(
+				String::from_utf8(bytes)?,
+				MockContext::filename(file).trim_end_matches(".png")
+			);
+		}
+
+		Ok(())
+	}
+
+	#[tokio::test]
+	async fn errors_are_propagated() -> Result<(), Error> {
+		let (ctx, _guard) = get_mock_context().await?;
+		let files = [PathBuf::from("test/screenshots/does_not_exist.png")];
+		assert!(ctx.download_images(&files).await.is_err());
+
+		Ok(())
+	}
+}
+
+#[cfg(test)]
+mod golden_tests {
+	use std::path::Path;
+
+	use super::mock::regen_requested;
+
+	#[test]
+	fn regen_filters() {
+		let path = Path::new("test/commands/score/show/no_ids");
+
+		assert!(!regen_requested("", path));
+		assert!(!regen_requested("0", path));
+		assert!(regen_requested("1", path));
+
+		assert!(regen_requested("commands/score/show/no_ids", path));
+		assert!(regen_requested("show/no_ids", path));
+		assert!(regen_requested(
+			"commands/stats/recent/no_plays, commands/score/show/no_ids",
+			path
+		));
+
+		// Entire path components must match
+		assert!(!regen_requested("ids", path));
+		assert!(!regen_requested("commands/score/show", path));
+		assert!(!regen_requested("commands/score/show/no_ids_either", path));
+	}
+}
+// }}}
 // {{{ Helpers
 #[inline]
 #[allow(dead_code)] // Currently only used for testing
@@ -321,6 +568,9 @@ pub fn play_song_title<'a>(ctx: &'a impl MessageContext, play: &'a Play) -> Resu
 	Ok(&ctx.data().song_cache.lookup_chart(play.chart_id)?.0.title)
 }
 
+/// Discord does not allow more embeds to be attached to a single message.
+pub const MAX_EMBEDS_PER_MESSAGE: usize = 10;
+
 pub trait CreateReplyExtra {
 	fn attachments(self, attachments: impl IntoIterator<Item = CreateAttachment>) -> Self;
 	fn embeds(self, embeds: impl IntoIterator<Item = CreateEmbed>) -> Self;
@@ -1,11 +1,15 @@
+use crate::commands::utils::two_columns::two_columns;
 use crate::context::{Context, Error};
 
+pub mod admin;
+pub mod calc;
 pub mod chart;
+pub mod config;
 pub mod discord;
 pub mod score;
 pub mod stats;
+pub mod user;
 pub mod utils;
-pub mod calc;
 
 // {{{ Help
 /// Show this help menu
@@ -30,6 +34,149 @@ pub async fn help(
 	Ok(())
 }
 // }}}
+// {{{ Commands overview
+/// Lists every command in a compact overview
+#[poise::command(
+	prefix_command,
+	slash_command,
+	subcommands("overview"),
+	subcommand_required
+)]
+pub async fn commands(_ctx: Context<'_>) -> Result<(), Error> {
+	Ok(())
+}
+
+/// Lists every command alongside a short description
+#[poise::command(prefix_command, slash_command)]
+async fn overview(ctx: Context<'_>) -> Result<(), Error> {
+	fn description(command: &poise::Command<crate::context::UserContext, Error>) -> String {
+		command.description.clone().unwrap_or_default()
+	}
+
+	// Commands without subcommands get grouped together
+	let mut general = Vec::new();
+	let mut sections = Vec::new();
+
+	for command in &ctx.framework().options().commands {
+		if command.hide_in_help {
+			continue;
+		}
+
+		if command.subcommands.is_empty() {
+			general.push((command.name.clone(), description(command)));
+			continue;
+		}
+
+		let rows: Vec<_> = command
+			.subcommands
+			.iter()
+			.filter(|sub| !sub.hide_in_help)
+			.map(|sub| (format!("{} {}", command.name, sub.name), description(sub)))
+			.collect();
+
+		sections.push((command.name.clone(), rows));
+	}
+
+	if !general.is_empty() {
+		sections.insert(0, ("general".to_string(), general));
+	}
+
+	for message in overview_messages(&sections) {
+		ctx.say(message).await?;
+	}
+
+	Ok(())
+}
+
+/// Discord messages are limited to 2000 characters.
+const MESSAGE_LIMIT: usize = 2000;
+
+/// Renders the given rows as a titled code block.
+fn overview_block(name: &str, rows: &[(String, String)]) -> String {
+	format!("**{name}**\n```\n{}```\n", two_columns(rows))
+}
+
+/// Lays out the given sections as code blocks, packing as many of them as
+/// possible into each message. Sections too long to fit inside a single
+/// message get split into multiple blocks.
+fn overview_messages(sections: &[(String, Vec<(String, String)>)]) -> Vec<String> {
+	let mut blocks = Vec::new();
+	for (name, rows) in sections {
+		let mut start = 0;
+		while start < rows.len() {
+			// Every block holds at least one row, even if said row doesn't fit
+			let mut end = start + 1;
+			while end < rows.len()
+				&& overview_block(name, &rows[start..=end]).len() <= MESSAGE_LIMIT
+			{
+				end += 1;
+			}
+
+			blocks.push(overview_block(name, &rows[start..end]));
+			start = end;
+		}
+	}
+
+	let mut messages = Vec::new();
+	let mut content = String::new();
+	for block in blocks {
+		if content.len() + block.len() > MESSAGE_LIMIT && !content.is_empty() {
+			messages.push(std::mem::take(&mut content));
+		}
+
+		content.push_str(&block);
+	}
+
+	if !content.is_empty() {
+		messages.push(content);
+	}
+
+	messages
+}
+
+#[cfg(test)]
+mod overview_tests {
+	use super::*;
+
+	fn rows(prefix: &str, count: usize) -> Vec<(String, String)> {
+		(0..count)
+			.map(|i| (format!("{prefix} {i}"), "Some description".to_string()))
+			.collect()
+	}
+
+	#[test]
+	fn small_sections_share_a_message() {
+		let sections = [
+			("general".to_string(), rows("help", 3)),
+			("stats".to_string(), rows("stats", 3)),
+		];
+
+		let messages = overview_messages(&sections);
+		assert_eq!(messages.len(), 1);
+		assert!(messages[0].contains("**general**") && messages[0].contains("**stats**"));
+	}
+
+	#[test]
+	fn long_sections_get_split() {
+		let sections = [("chart".to_string(), rows("chart", 200))];
+
+		let messages = overview_messages(&sections);
+		assert!(messages.len() > 1);
+		assert!(messages.iter().all(|m| m.len() <= MESSAGE_LIMIT));
+
+		// Every block is a complete code block, and no rows get lost
+		for message in &messages {
+			assert_eq!(message.matches("```").count() % 2, 0);
+		}
+
+		let listed: usize = messages
+			.iter()
+			.map(|m| m.matches("Some description").count())
+			.sum();
+		assert_eq!(listed, 200);
+	}
+}
+// }}}
 // {{{ Scoring help
 /// Explains the different scoring systems
 #[poise::command(prefix_command, slash_command)]
@@ -1,15 +1,27 @@
 // {{{ Imports
-use crate::arcaea::play::{CreatePlay, Play};
-use crate::arcaea::score::Score;
+use crate::arcaea::chart::{Chart, Difficulty, Level, Song};
+use crate::arcaea::play::{generate_missing_scores, ClearKind, CreatePlay, Play};
+use crate::arcaea::score::{DistributionConsensus, Score, ScoringSystem};
 use crate::context::{Context, Error, ErrorKind, TagError, TaggedError};
-use crate::recognition::recognize::{ImageAnalyzer, ScoreKind};
-use crate::user::User;
+use crate::logs;
+use crate::metrics::{self, OcrStage};
+use crate::recognition::fuzzy_song_name::guess_song_and_chart;
+use crate::recognition::recognize::{
+	limit_image_size, max_ocr_dimension, ImageAnalyzer, ScoreKind,
+};
+use crate::recognition::ui::UIMeasurementRect;
+use crate::user::{auto_pookie_threshold, User};
 use crate::{get_user_error, timed, try_block};
 use anyhow::anyhow;
-use image::DynamicImage;
+use chrono::NaiveDate;
+use image::{DynamicImage, GenericImageView};
 use poise::{serenity_prelude as serenity, CreateReply};
+use std::io::Cursor;
+use std::str::FromStr;
+use std::time::Instant;
 
 use super::discord::{CreateReplyExtra, MessageContext};
+use super::stats::{encode_image, render_play_grid, OutputFormat};
 // }}}
 
 // {{{ Score
@@ -17,7 +29,7 @@ use super::discord::{CreateReplyExtra, MessageContext};
 #[poise::command(
 	prefix_command,
 	slash_command,
-	subcommands("magic", "delete", "show"),
+	subcommands("magic", "delete", "show", "explain", "random", "debug", "verify"),
 	subcommand_required
 )]
 pub async fn score(_ctx: Context<'_>) -> Result<(), Error> {
@@ -26,12 +38,337 @@ pub async fn score(_ctx: Context<'_>) -> Result<(), Error> {
 // }}}
 // {{{ Score magic
 // {{{ Implementation
+/// Tries to recognise common phone screenshot formats we cannot decode,
+/// such that users get a more helpful error than whatever `image` says.
+fn guess_unsupported_format(bytes: &[u8]) -> Option<&'static str> {
+	// HEIF-based formats start with an `ftyp` box followed by a brand.
+	if bytes.get(4..8) != Some(b"ftyp".as_slice()) {
+		return None;
+	}
+
+	match bytes.get(8..12)? {
+		b"heic" | b"heix" | b"heim" | b"heis" | b"hevc" | b"mif1" | b"msf1" => Some("HEIC"),
+		b"avif" | b"avis" => Some("AVIF"),
+		_ => None,
+	}
+}
+
+/// Explains why an image could not be decoded. Formats we can usually
+/// decode (like GIF or BMP) must have been truncated or corrupted.
+fn describe_decoding_failure(filename: &str, bytes: &[u8]) -> String {
+	if let Some(format) = guess_unsupported_format(bytes) {
+		return format!(
+			"Image `{filename}` looks like a {format} file, which I cannot read. Please re-export the screenshot as a PNG, JPG or WebP file."
+		);
+	}
+
+	match image::guess_format(bytes) {
+		Ok(format) => format!(
+			"Image `{filename}` looks like a {} file, but could not be decoded. The upload might have been cut off, so please try sending it again.",
+			format.extensions_str()[0].to_uppercase()
+		),
+		Err(_) => format!(
+			"Image `{filename}` is in a format which I cannot read. Please re-export the screenshot as a PNG, JPG or WebP file."
+		),
+	}
+}
+
+/// Decodes an attached screenshot, and scales it down to a size the OCR
+/// pipeline can handle.
+pub fn decode_screenshot<C: MessageContext>(
+	attachment: &C::Attachment,
+	bytes: &[u8],
+) -> Result<DynamicImage, TaggedError> {
+	let image = image::load_from_memory(bytes).map_err(|err| {
+		anyhow::Error::new(err)
+			.context(describe_decoding_failure(C::filename(attachment), bytes))
+			.tag(ErrorKind::User)
+	})?;
+
+	Ok(limit_image_size(image, max_ocr_dimension()))
+}
+
+/// Screenshots whose shorter side is below this many pixels are thumbnails
+/// (or truncated uploads), which we cannot read anything off of.
+const MIN_SCREENSHOT_DIMENSION: u32 = 200;
+
+/// Screenshots whose brightness varies less than this (measured as the
+/// variance of the grayscale pixel values) are blank for all intents and
+/// purposes.
+const MIN_SCREENSHOT_VARIANCE: f64 = 16.0;
+
+/// Rejects screenshots which decoded fine, but which are too small or too
+/// uniform for recognition to produce anything but garbage.
+fn check_screenshot_usable(
+	grayscale_image: &DynamicImage,
+	filename: &str,
+) -> Result<(), TaggedError> {
+	let (width, height) = grayscale_image.dimensions();
+	if width.min(height) < MIN_SCREENSHOT_DIMENSION {
+		return Err(anyhow!(
+			"Image `{filename}` is only {width}x{height} pixels, which is too small to read anything off of. Please send the full-size screenshot instead of a thumbnail."
+		)
+		.tag(ErrorKind::User));
+	}
+
+	let mut sum = 0.0;
+	let mut sum_of_squares = 0.0;
+	for (_, _, pixel) in grayscale_image.pixels() {
+		let value = pixel.0[0] as f64;
+		sum += value;
+		sum_of_squares += value * value;
+	}
+
+	let count = (width as f64) * (height as f64);
+	let mean = sum / count;
+	let variance = sum_of_squares / count - mean * mean;
+	if variance < MIN_SCREENSHOT_VARIANCE {
+		return Err(anyhow!(
+			"Image `{filename}` looks (almost) completely blank. The upload might have been cut off, so please try sending the screenshot again."
+		)
+		.tag(ErrorKind::User));
+	}
+
+	Ok(())
+}
+
+/// Reasons to distrust the max recall read off of a screenshot.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum MaxRecallIssue {
+	/// The max recall exceeds the note count of the chart.
+	AboveNoteCount(u32),
+	/// PMs are always full combos, so anything well below the note count
+	/// hints at a misread screenshot.
+	LowForPm(u32),
+}
+
+/// Sanity-checks a max recall read against the chart it was read for. Bad
+/// reads should never prevent a score from being saved, so impossible values
+/// get discarded instead of failing, while suspicious ones are kept around.
+fn check_max_recall(
+	max_recall: Option<u32>,
+	score: Score,
+	note_count: u32,
+) -> (Option<u32>, Option<MaxRecallIssue>) {
+	match max_recall {
+		Some(max_recall) if max_recall > note_count => {
+			(None, Some(MaxRecallIssue::AboveNoteCount(max_recall)))
+		}
+		Some(max_recall) if score.is_pm() && max_recall * 10 < note_count * 9 => {
+			(Some(max_recall), Some(MaxRecallIssue::LowForPm(max_recall)))
+		}
+		max_recall => (max_recall, None),
+	}
+}
+
+/// Runs every recognition step `score magic` performs on a single screenshot,
+/// without saving anything. Any non-fatal issues get pushed onto `warnings`.
+///
+/// The CLI reuses this for analysing screenshots in bulk.
+pub fn magic_detect_one<'a, C: MessageContext>(
+	ctx: &'a C,
+	analyzer: &mut ImageAnalyzer,
+	image: &mut DynamicImage,
+	grayscale_image: &mut DynamicImage,
+	attachment: &C::Attachment,
+	warnings: &mut Vec<String>,
+) -> Result<(&'a Song, &'a Chart, CreatePlay), TaggedError> {
+	let filename = C::filename(attachment);
+	check_screenshot_usable(grayscale_image, filename)?;
+
+	let kind = timed!("read_score_kind", {
+		analyzer
+			.read_score_kind(ctx.data(), grayscale_image)
+			.inspect_err(|_| metrics::record_ocr_failure(OcrStage::ScoreKind))?
+	});
+
+	// Do not use `ocr_image` because this reads the colors
+	let difficulty = timed!("read_difficulty", {
+		analyzer
+			.read_difficulty(ctx.data(), image, grayscale_image, kind)
+			.inspect_err(|_| metrics::record_ocr_failure(OcrStage::Difficulty))?
+	});
+
+	let (song, chart) = timed!("read_jacket", {
+		analyzer
+			.read_jacket(ctx.data(), image, kind, difficulty)
+			.inspect_err(|_| metrics::record_ocr_failure(OcrStage::Jacket))?
+	});
+
+	let max_recall = match kind {
+		ScoreKind::ScoreScreen => {
+			// NOTE: are we ok with discarding errors like that?
+			analyzer.read_max_recall(ctx.data(), grayscale_image).ok()
+		}
+		ScoreKind::SongSelect => None,
+	};
+
+	// Not every layout has its clear banner measured yet
+	let clear_kind = match kind {
+		ScoreKind::ScoreScreen => analyzer.read_clear_kind(ctx.data(), grayscale_image).ok(),
+		ScoreKind::SongSelect => None,
+	};
+
+	grayscale_image.invert();
+	let note_distribution = match kind {
+		ScoreKind::ScoreScreen => Some(
+			analyzer
+				.read_distribution(ctx.data(), grayscale_image)
+				.inspect_err(|_| metrics::record_ocr_failure(OcrStage::Distribution))?,
+		),
+		ScoreKind::SongSelect => None,
+	};
+
+	// Failed runs can end with partial distributions, which would only
+	// mislead the far note inference below.
+	let note_distribution = note_distribution.filter(|(pures, fars, losts)| {
+		clear_kind != Some(ClearKind::TrackLost) || pures + fars + losts == chart.note_count
+	});
+
+	let reading = timed!("read_score", {
+		analyzer
+			.read_score(ctx.data(), Some(chart.note_count), grayscale_image, kind)
+			.map_err(|err| {
+				metrics::record_ocr_failure(OcrStage::Score);
+				anyhow!(
+					"Could not read score for chart {} [{:?}]: {err}",
+					song.title,
+					chart.difficulty
+				)
+			})?
+	});
+
+	let score = reading.score;
+	if reading.used_fallback {
+		warnings.push(format!(
+			"I had trouble reading your score in `{}`, so please double check {} on {} [{:?}]",
+			filename, score, song.title, chart.difficulty,
+		));
+	} else if !reading.is_complete() {
+		warnings.push(format!(
+			"I may have misread your score in `{}`: only {}/{} characters could be recognised, so please double check {} on {} [{:?}]",
+			filename,
+			reading.recognised,
+			reading.seen,
+			score,
+			song.title,
+			chart.difficulty,
+		));
+	}
+
+	// {{{ Max recall sanity checks
+	// These are issues with the recognition rather than with the play itself,
+	// so they go to the operator log instead of bothering the user.
+	let (max_recall, max_recall_issue) = check_max_recall(max_recall, score, chart.note_count);
+	match max_recall_issue {
+		Some(MaxRecallIssue::AboveNoteCount(max_recall)) => println!(
+			"Warning: discarding max recall {} for {} [{:?}] ({} notes) in `{}`",
+			max_recall, song.title, chart.difficulty, chart.note_count, filename
+		),
+		Some(MaxRecallIssue::LowForPm(max_recall)) => println!(
+			"Warning: suspicious screenshot `{}`: got a PM on {} [{:?}], but the max recall is only {}/{}",
+			filename, song.title, chart.difficulty, max_recall, chart.note_count
+		),
+		None => {}
+	}
+	// }}}
+	// {{{ Build play
+	let maybe_fars =
+		Score::resolve_distibution_ambiguities(score, note_distribution, chart.note_count);
+
+	// Only keep the full distribution around if it's self-consistent
+	let reliable_distribution = note_distribution.filter(|(pures, fars, losts)| {
+		pures + fars + losts == chart.note_count && Some(*fars) == maybe_fars
+	});
+
+	let play = CreatePlay::new(score)
+		.with_attachment(C::attachment_id(attachment))
+		.with_fars(maybe_fars)
+		.with_distribution(reliable_distribution)
+		.with_max_recall(max_recall)
+		.with_clear_kind(clear_kind);
+	// }}}
+
+	Ok((song, chart, play))
+}
+
+/// Debug images produced while processing a single `magic` invocation all end
+/// up in the same folder (see [logs::with_invocation_dir]).
 pub async fn magic_impl<C: MessageContext>(
 	ctx: &mut C,
 	files: &[C::Attachment],
+) -> Result<Vec<Play>, TaggedError> {
+	magic_for_impl(ctx, files, None, false).await
+}
+
+/// Like [magic_impl], except admins can save the plays under the account of
+/// the user with the given discord id. When `recap` is set, the recorded
+/// plays also get summarized in a single image (see [send_recap]).
+pub async fn magic_for_impl<C: MessageContext>(
+	ctx: &mut C,
+	files: &[C::Attachment],
+	target_discord_id: Option<&str>,
+	recap: bool,
+) -> Result<Vec<Play>, TaggedError> {
+	logs::with_invocation_dir(
+		"magic",
+		magic_impl_inner(ctx, files, target_discord_id, recap),
+	)
+	.await
+}
+
+/// How many tiles a session recap fits on a single row.
+const RECAP_COLUMNS: u32 = 5;
+
+/// Renders the given plays as a grid of b30-style tiles, in order.
+async fn send_recap<C: MessageContext>(ctx: &mut C, plays: &[Play]) -> Result<(), TaggedError> {
+	let song_cache = &ctx.data().song_cache;
+	let tiles = plays
+		.iter()
+		.map(|play| {
+			let (song, chart) = song_cache.lookup_chart(play.chart_id)?;
+			Ok((play.clone(), song, chart))
+		})
+		.collect::<Result<Vec<_>, Error>>()?;
+
+	let count = tiles.len() as u32;
+	let grid_size = (count.min(RECAP_COLUMNS), count.div_ceil(RECAP_COLUMNS));
+	let image = render_play_grid(&tiles, ScoringSystem::default(), grid_size, false)?;
+
+	let format = OutputFormat::default();
+	let bytes = encode_image(&image, format)?;
+
+	ctx.send(
+		CreateReply::default()
+			.reply(true)
+			.content(format!("Session recap ({count} play(s))"))
+			.attachment(serenity::CreateAttachment::bytes(
+				bytes,
+				format!("recap.{}", format.extension()),
+			)),
+	)
+	.await?;
+
+	Ok(())
+}
+
+async fn magic_impl_inner<C: MessageContext>(
+	ctx: &mut C,
+	files: &[C::Attachment],
+	target_discord_id: Option<&str>,
+	recap: bool,
 ) -> Result<Vec<Play>, TaggedError> {
 	let user = User::from_context(ctx)?;
+	let mut user = match target_discord_id {
+		Some(discord_id) => {
+			user.assert_is_admin()?;
+			User::by_discord_id(ctx.data(), discord_id)?
+		}
+		None => user,
+	};
+
 	let files = ctx.download_images(files).await?;
+	metrics::record_magic_invocation();
 
 	if files.is_empty() {
 		return Err(anyhow!("No images found attached to message").tag(ErrorKind::User));
@@ -40,71 +377,30 @@ pub async fn magic_impl<C: MessageContext>(
 	let mut embeds = Vec::with_capacity(files.len());
 	let mut attachments = Vec::with_capacity(files.len());
 	let mut plays = Vec::with_capacity(files.len());
+	let mut warnings = Vec::new();
 	let mut analyzer = ImageAnalyzer::default();
 
 	for (i, (attachment, bytes)) in files.into_iter().enumerate() {
 		// {{{ Preapare image
-		let mut image = image::load_from_memory(&bytes)?;
+		let mut image = decode_screenshot::<C>(attachment, &bytes)?;
 		let mut grayscale_image = DynamicImage::ImageLuma8(image.to_luma8());
+		let start = Instant::now();
 		// }}}
 
 		let result: Result<(), TaggedError> = try_block!({
 			// {{{ Detection
+			let (song, chart, play) = magic_detect_one(
+				ctx,
+				&mut analyzer,
+				&mut image,
+				&mut grayscale_image,
+				attachment,
+				&mut warnings,
+			)?;
 
-			let kind = timed!("read_score_kind", {
-				analyzer.read_score_kind(ctx.data(), &grayscale_image)?
-			});
-
-			// Do not use `ocr_image` because this reads the colors
-			let difficulty = timed!("read_difficulty", {
-				analyzer.read_difficulty(ctx.data(), &image, &grayscale_image, kind)?
-			});
-
-			let (song, chart) = timed!("read_jacket", {
-				analyzer.read_jacket(ctx.data(), &mut image, kind, difficulty)?
-			});
-
-			let max_recall = match kind {
-				ScoreKind::ScoreScreen => {
-					// NOTE: are we ok with discarding errors like that?
-					analyzer.read_max_recall(ctx.data(), &grayscale_image).ok()
-				}
-				ScoreKind::SongSelect => None,
-			};
-
-			grayscale_image.invert();
-			let note_distribution = match kind {
-				ScoreKind::ScoreScreen => {
-					Some(analyzer.read_distribution(ctx.data(), &grayscale_image)?)
-				}
-				ScoreKind::SongSelect => None,
-			};
-
-			let score = timed!("read_score", {
-				analyzer
-					.read_score(ctx.data(), Some(chart.note_count), &grayscale_image, kind)
-					.map_err(|err| {
-						anyhow!(
-							"Could not read score for chart {} [{:?}]: {err}",
-							song.title,
-							chart.difficulty
-						)
-					})?
-			});
-
-			// {{{ Build play
-			let maybe_fars =
-				Score::resolve_distibution_ambiguities(score, note_distribution, chart.note_count);
-
-			let play = CreatePlay::new(score)
-				.with_attachment(C::attachment_id(attachment))
-				.with_fars(maybe_fars)
-				.with_max_recall(max_recall)
-				.save(ctx.data(), &user, chart)?;
-			// }}}
+			let play = play.save(ctx.data(), &user, chart)?;
 			// }}}
 			// {{{ Deliver embed
-
 			let (embed, attachment) = timed!("to embed", {
 				play.to_embed(ctx.data(), &user, song, chart, i, None)?
 			});
@@ -115,6 +411,8 @@ pub async fn magic_impl<C: MessageContext>(
 			// }}}
 		});
 
+		metrics::record_processing_time(start.elapsed());
+
 		if let Err(err) = result {
 			let user_err = get_user_error!(err);
 			analyzer
@@ -123,6 +421,14 @@ pub async fn magic_impl<C: MessageContext>(
 		}
 	}
 
+	// Play counts only ever change here, so there's no point in checking the
+	// threshold on every other command.
+	if !plays.is_empty() {
+		if let Some(threshold) = auto_pookie_threshold() {
+			user.auto_pookify(ctx.data(), threshold)?;
+		}
+	}
+
 	if !embeds.is_empty() {
 		ctx.send(
 			CreateReply::default()
@@ -133,6 +439,14 @@ pub async fn magic_impl<C: MessageContext>(
 		.await?;
 	}
 
+	if !warnings.is_empty() {
+		ctx.reply(&warnings.join("\n")).await?;
+	}
+
+	if recap && !plays.is_empty() {
+		send_recap(ctx, &plays).await?;
+	}
+
 	Ok(plays)
 }
 // }}}
@@ -145,11 +459,116 @@ mod magic_tests {
 	use crate::{
 		arcaea::score::ScoringSystem,
 		commands::discord::{mock::MockContext, play_song_title},
+		context::testing::get_mock_context,
 		golden_test, with_test_ctx,
 	};
 
 	use super::*;
 
+	#[test]
+	fn decoding_failures_name_the_format() {
+		let heic = b"\0\0\0\x18ftypheic\0\0\0\0";
+		assert!(describe_decoding_failure("a", heic).contains("HEIC file, which I cannot read"));
+
+		// GIFs can be decoded, so this one must have been cut off
+		let gif = b"GIF89a\x01\0";
+		assert!(describe_decoding_failure("a", gif).contains("GIF file, but could not be decoded"));
+
+		assert!(describe_decoding_failure("a", b"garbage").contains("format which I cannot read"));
+	}
+
+	#[test]
+	fn unusable_screenshots_get_rejected() -> Result<(), Error> {
+		let rejected = |image: DynamicImage| {
+			let grayscale = DynamicImage::ImageLuma8(image.to_luma8());
+			matches!(
+				check_screenshot_usable(&grayscale, "test.png"),
+				Err(TaggedError {
+					kind: ErrorKind::User,
+					..
+				})
+			)
+		};
+
+		let blank = |size| DynamicImage::new_rgb8(size, size);
+		assert!(rejected(blank(10)));
+		assert!(rejected(blank(1000)));
+
+		let screenshot = image::open("test/screenshots/alter_ego.jpg")?;
+		assert!(!rejected(screenshot));
+
+		Ok(())
+	}
+
+	#[test]
+	fn max_recall_above_note_count_gets_discarded() {
+		assert_eq!(
+			check_max_recall(Some(1280), Score(9_000_000), 1279),
+			(None, Some(MaxRecallIssue::AboveNoteCount(1280)))
+		);
+
+		// The note count itself is fine
+		assert_eq!(
+			check_max_recall(Some(1279), Score(9_000_000), 1279),
+			(Some(1279), None)
+		);
+		assert_eq!(check_max_recall(None, Score(9_000_000), 1279), (None, None));
+	}
+
+	#[test]
+	fn pms_with_low_max_recall_are_suspicious() {
+		assert_eq!(
+			check_max_recall(Some(300), Score(10_001_279), 1279),
+			(Some(300), Some(MaxRecallIssue::LowForPm(300)))
+		);
+
+		// Slightly misread max recalls don't count
+		assert_eq!(
+			check_max_recall(Some(1200), Score(10_001_279), 1279),
+			(Some(1200), None)
+		);
+
+		// Neither do low max recalls on non-PMs
+		assert_eq!(
+			check_max_recall(Some(300), Score(9_900_000), 1279),
+			(Some(300), None)
+		);
+	}
+
+	#[tokio::test]
+	async fn dropped_score_characters_are_never_silent() -> Result<(), Error> {
+		let (ctx, _guard) = get_mock_context().await?;
+		let path = PathBuf::from_str("test/screenshots/fracture_ray_ex.jpg")?;
+		let bytes = std::fs::read(&path)?;
+		let mut image = decode_screenshot::<MockContext>(&path, &bytes).map_err(|e| e.error)?;
+		let mut grayscale_image = DynamicImage::ImageLuma8(image.to_luma8());
+
+		// Rejecting every glyph makes hyperglass drop the entire score
+		let mut analyzer = ImageAnalyzer::default().with_max_char_distance(0.0);
+		let mut warnings = Vec::new();
+		let result = magic_detect_one(
+			&ctx,
+			&mut analyzer,
+			&mut image,
+			&mut grayscale_image,
+			&path,
+			&mut warnings,
+		);
+
+		match result {
+			Ok((_, _, play)) => assert!(
+				!warnings.is_empty(),
+				"Read {} without warning about the dropped characters",
+				play.score()
+			),
+			Err(err) => {
+				assert!(format!("{}", err.error).contains("characters could be recognised"))
+			}
+		}
+
+		Ok(())
+	}
+
 	#[tokio::test]
 	async fn no_pics() -> Result<(), Error> {
 		with_test_ctx!("commands/score/magic/no_pics", |ctx| async move {
@@ -187,6 +606,68 @@ mod magic_tests {
 
 		Ok(())
 	}
+
+	golden_test!(
+		for_other_not_admin,
+		"commands/score/magic/for_other_not_admin"
+	);
+	async fn for_other_not_admin(ctx: &mut MockContext) -> Result<(), TaggedError> {
+		ctx.data
+			.db
+			.get()?
+			.execute("INSERT INTO users(discord_id) VALUES ('42')", ())?;
+
+		magic_for_impl(
+			ctx,
+			&[PathBuf::from_str("test/screenshots/alter_ego.jpg")?],
+			Some("42"),
+			false,
+		)
+		.await?;
+
+		Ok(())
+	}
+
+	golden_test!(for_other, "commands/score/magic/for_other");
+	async fn for_other(ctx: &mut MockContext) -> Result<(), TaggedError> {
+		let conn = ctx.data.db.get()?;
+		conn.execute("UPDATE users SET is_admin=1", ())?;
+		let target_id: u32 = conn.query_row(
+			"INSERT INTO users(discord_id) VALUES ('42') RETURNING id",
+			(),
+			|row| row.get(0),
+		)?;
+		drop(conn);
+
+		let plays = magic_for_impl(
+			ctx,
+			&[PathBuf::from_str("test/screenshots/alter_ego.jpg")?],
+			Some("42"),
+			false,
+		)
+		.await?;
+
+		assert_eq!(plays.len(), 1);
+		assert_eq!(plays[0].user_id, target_id);
+		Ok(())
+	}
+
+	golden_test!(recap, "commands/score/magic/recap");
+	async fn recap(ctx: &mut MockContext) -> Result<(), TaggedError> {
+		let plays = magic_for_impl(
+			ctx,
+			&[
+				PathBuf::from_str("test/screenshots/alter_ego.jpg")?,
+				PathBuf::from_str("test/screenshots/fracture_ray_ex.jpg")?,
+			],
+			None,
+			true,
+		)
+		.await?;
+
+		assert_eq!(plays.len(), 2);
+		Ok(())
+	}
 }
 // }}}
 // {{{ Discord wrapper
@@ -195,8 +676,19 @@ mod magic_tests {
 pub async fn magic(
 	mut ctx: Context<'_>,
 	#[description = "Images containing scores"] files: Vec<serenity::Attachment>,
+	#[rename = "for"]
+	#[description = "Save the plays for this user instead (admins only)"]
+	target: Option<serenity::User>,
+	#[description = "Also summarize the plays in a single image"] recap: Option<bool>,
 ) -> Result<(), Error> {
-	let res = magic_impl(&mut ctx, &files).await;
+	let target_discord_id = target.map(|u| u.id.to_string());
+	let res = magic_for_impl(
+		&mut ctx,
+		&files,
+		target_discord_id.as_deref(),
+		recap.unwrap_or(false),
+	)
+	.await;
 	ctx.handle_error(res).await?;
 
 	Ok(())
@@ -223,7 +715,7 @@ pub async fn show_impl<C: MessageContext>(
 				"
           SELECT
             p.id, p.chart_id, p.user_id, p.created_at,
-            p.max_recall, p.far_notes, s.score,
+            p.max_recall, p.far_notes, p.pure_notes, p.lost_notes, p.clear_kind, s.score,
             u.discord_id
           FROM plays p
           JOIN scores s ON s.play_id = p.id
@@ -336,125 +828,1076 @@ pub async fn show(
 }
 // }}}
 // }}}
-// {{{ Score delete
+// {{{ Score verify
 // {{{ Implementation
-pub async fn delete_impl<C: MessageContext>(ctx: &mut C, ids: &[u32]) -> Result<(), TaggedError> {
+/// Re-runs recognition on a fresh screenshot of an existing play, and
+/// reports whether the chart and score it reads agree with the stored ones.
+/// On disagreements, the stored play gets overwritten if `update` is set.
+/// Returns whether the two agreed.
+pub async fn verify_impl<C: MessageContext>(
+	ctx: &mut C,
+	id: u32,
+	file: &C::Attachment,
+	update: bool,
+) -> Result<bool, TaggedError> {
 	let user = User::from_context(ctx)?;
 
-	if ids.is_empty() {
-		return Err(anyhow!("Empty ID list provided").tag(ErrorKind::User));
-	}
+	let stored = ctx
+		.data()
+		.db
+		.get()?
+		.prepare_cached(
+			"
+        SELECT
+          p.id, p.chart_id, p.user_id, p.created_at,
+          p.max_recall, p.far_notes, p.pure_notes, p.lost_notes, p.clear_kind, s.score
+        FROM plays p
+        JOIN scores s ON s.play_id = p.id
+        WHERE s.scoring_system='standard'
+        AND p.id=?
+        AND p.user_id=?
+      ",
+		)?
+		.query_and_then((id, user.id), |row| -> Result<_, Error> {
+			let (_, chart) = ctx.data().song_cache.lookup_chart(row.get("chart_id")?)?;
+			Ok(Play::from_sql(chart, row)?)
+		})?
+		.next()
+		.ok_or_else(|| anyhow!("You have no play with id {id}").tag(ErrorKind::User))??;
 
-	let mut count = 0;
+	let files = ctx.download_images(std::slice::from_ref(file)).await?;
+	let Some((attachment, bytes)) = files.into_iter().next() else {
+		return Err(anyhow!("No image found attached to message").tag(ErrorKind::User));
+	};
 
-	for id in ids {
-		let res = ctx
-			.data()
-			.db
-			.get()?
-			.prepare_cached("DELETE FROM plays WHERE id=? AND user_id=?")?
-			.execute((id, user.id))?;
+	let mut image = decode_screenshot::<C>(attachment, &bytes)?;
+	let mut grayscale_image = DynamicImage::ImageLuma8(image.to_luma8());
+	let mut analyzer = ImageAnalyzer::default();
+	let mut warnings = Vec::new();
 
-		if res == 0 {
-			ctx.reply(&format!("No play with id {} found", id)).await?;
-		} else {
-			count += 1;
-		}
+	let (song, chart, fresh) = magic_detect_one(
+		ctx,
+		&mut analyzer,
+		&mut image,
+		&mut grayscale_image,
+		attachment,
+		&mut warnings,
+	)?;
+
+	let (stored_song, stored_chart) = ctx.data().song_cache.lookup_chart(stored.chart_id)?;
+	let stored_score = stored.score(ScoringSystem::Standard);
+	let matches = chart.id == stored.chart_id && fresh.score() == stored_score;
+
+	let description = if matches {
+		"The screenshot agrees with the stored play."
+	} else if update {
+		"The screenshot disagreed with the stored play, which now matches the screenshot."
+	} else {
+		"The screenshot disagrees with the stored play. Run this command again with `update` enabled to overwrite the stored play."
+	};
+
+	// The songs are borrowed from the context, so we must let go of them
+	// before sending anything.
+	let embed = serenity::CreateEmbed::default()
+		.title(format!("Verifying play {id}"))
+		.description(description)
+		.field(
+			"Stored",
+			format!(
+				"{} [{}] {}",
+				stored_song, stored_chart.difficulty, stored_score
+			),
+			false,
+		)
+		.field(
+			"Screenshot",
+			format!("{} [{}] {}", song, chart.difficulty, fresh.score()),
+			false,
+		);
+
+	if !matches && update {
+		fresh.update(ctx.data(), id, chart)?;
+
+		// The creation ptt of this (and every later) play might have changed
+		generate_missing_scores(ctx.data(), Some(user.id)).await?;
 	}
 
-	if count > 0 {
-		ctx.reply(&format!("Deleted {} play(s) successfully!", count))
-			.await?;
+	ctx.send(CreateReply::default().reply(true).embed(embed))
+		.await?;
+
+	if !warnings.is_empty() {
+		ctx.reply(&warnings.join("\n")).await?;
 	}
 
-	Ok(())
+	Ok(matches)
 }
-/// }}}
+// }}}
 // {{{ Tests
 #[cfg(test)]
-mod delete_tests {
+mod verify_tests {
 	use super::*;
-	use crate::{
-		commands::discord::{mock::MockContext, play_song_title},
-		golden_test, with_test_ctx,
-	};
+	use crate::{commands::discord::mock::MockContext, golden_test, with_test_ctx};
 	use std::{path::PathBuf, str::FromStr};
 
-	#[tokio::test]
-	async fn no_ids() -> Result<(), Error> {
-		with_test_ctx!("commands/score/delete/no_ids", |ctx| async move {
-			delete_impl(ctx, &[]).await?;
-			Ok(())
-		})
-	}
-
 	#[tokio::test]
 	async fn nonexistent_id() -> Result<(), Error> {
-		with_test_ctx!("commands/score/delete/nonexistent_id", |ctx| async move {
-			delete_impl(ctx, &[666]).await?;
+		with_test_ctx!("commands/score/verify/nonexistent_id", |ctx| async move {
+			verify_impl(
+				ctx,
+				666,
+				&PathBuf::from_str("test/screenshots/alter_ego.jpg")?,
+				false,
+			)
+			.await?;
 			Ok(())
 		})
 	}
 
-	golden_test!(delete_twice, "commands/score/delete/delete_twice");
-	async fn delete_twice(ctx: &mut MockContext) -> Result<(), TaggedError> {
-		let plays =
-			magic_impl(ctx, &[PathBuf::from_str("test/screenshots/alter_ego.jpg")?]).await?;
+	golden_test!(same_screenshot, "commands/score/verify/same_screenshot");
+	async fn same_screenshot(ctx: &mut MockContext) -> Result<(), TaggedError> {
+		let file = PathBuf::from_str("test/screenshots/alter_ego.jpg")?;
+		let plays = magic_impl(ctx, &[file.clone()]).await?;
 
-		let id = plays[0].id;
-		delete_impl(ctx, &[id, id]).await?;
+		assert!(verify_impl(ctx, plays[0].id, &file, false).await?);
 		Ok(())
 	}
 
 	golden_test!(
-		no_show_after_delete,
-		"commands/score/delete/no_show_after_delete"
+		update_on_mismatch,
+		"commands/score/verify/update_on_mismatch"
 	);
-	async fn no_show_after_delete(ctx: &mut MockContext) -> Result<(), TaggedError> {
+	async fn update_on_mismatch(ctx: &mut MockContext) -> Result<(), TaggedError> {
 		let plays =
 			magic_impl(ctx, &[PathBuf::from_str("test/screenshots/alter_ego.jpg")?]).await?;
+		let other = PathBuf::from_str("test/screenshots/antithese_74_kerning.jpg")?;
 
-		// Showcase proper usage
-		let ids = [plays[0].id];
-		delete_impl(ctx, &ids).await?;
-
-		// This will tell the user the play doesn't exist
-		let shown_plays = show_impl(ctx, &ids).await?;
-		assert_eq!(shown_plays.len(), 0);
-
-		Ok(())
-	}
-
-	golden_test!(delete_multiple, "commands/score/delete/delete_multiple");
-	async fn delete_multiple(ctx: &mut MockContext) -> Result<(), TaggedError> {
-		let plays = magic_impl(
-			ctx,
-			&[
-				PathBuf::from_str("test/screenshots/antithese_74_kerning.jpg")?,
-				PathBuf::from_str("test/screenshots/alter_ego.jpg")?,
-				PathBuf::from_str("test/screenshots/genocider_24_kerning.jpg")?,
-			],
-		)
-		.await?;
-
-		delete_impl(ctx, &[plays[0].id, plays[2].id]).await?;
+		// Nothing gets overwritten unless asked to
+		assert!(!verify_impl(ctx, plays[0].id, &other, false).await?);
+		assert_eq!(show_impl(ctx, &[plays[0].id]).await?, plays);
 
-		// Ensure the second play still exists
-		let shown_plays = show_impl(ctx, &[plays[1].id]).await?;
-		assert_eq!(play_song_title(ctx, &shown_plays[0])?, "ALTER EGO");
+		assert!(!verify_impl(ctx, plays[0].id, &other, true).await?);
+		let updated = show_impl(ctx, &[plays[0].id]).await?;
+		assert_eq!(updated[0].id, plays[0].id);
+		assert_ne!(updated[0].chart_id, plays[0].chart_id);
 
+		assert!(verify_impl(ctx, plays[0].id, &other, false).await?);
 		Ok(())
 	}
 }
 // }}}
 // {{{ Discord wrapper
-/// Delete scores, given their IDs.
+/// Compare a stored play against a fresh screenshot of it.
 #[poise::command(prefix_command, slash_command)]
-pub async fn delete(
+pub async fn verify(
 	mut ctx: Context<'_>,
-	#[description = "Id of score to delete"] ids: Vec<u32>,
+	#[description = "Id of the play to verify"] id: u32,
+	#[description = "Screenshot of the play"] file: serenity::Attachment,
+	#[description = "Overwrite the stored play if the screenshot disagrees with it"] update: Option<
+		bool,
+	>,
+) -> Result<(), Error> {
+	let res = verify_impl(&mut ctx, id, &file, update.unwrap_or(false)).await;
+	ctx.handle_error(res).await?;
+
+	Ok(())
+}
+// }}}
+// }}}
+// {{{ Score explain
+// {{{ Implementation
+/// Re-runs the far note inference on the stored note distribution of a play,
+/// explaining which of the recomputed scores the decision was based on.
+///
+/// Only the resolved far count gets stored, not the one read off the
+/// screenshot, so this can confirm a stored distribution, but not reproduce
+/// an ambiguity the OCR originally ran into.
+pub async fn explain_impl<C: MessageContext>(
+	ctx: &mut C,
+	id: u32,
+) -> Result<Option<u32>, TaggedError> {
+	let (play, song, chart) = ctx
+		.data()
+		.db
+		.get()?
+		.prepare_cached(
+			"
+          SELECT
+            p.id, p.chart_id, p.user_id, p.created_at,
+            p.max_recall, p.far_notes, p.pure_notes, p.lost_notes, p.clear_kind, s.score
+          FROM plays p
+          JOIN scores s ON s.play_id = p.id
+          WHERE s.scoring_system='standard'
+          AND p.id=?
+        ",
+		)?
+		.query_and_then([id], |row| -> Result<_, Error> {
+			let (song, chart) = ctx.data().song_cache.lookup_chart(row.get("chart_id")?)?;
+			let play = Play::from_sql(chart, row)?;
+			Ok((play, song.to_string(), chart.clone()))
+		})?
+		.next()
+		.ok_or_else(|| anyhow!("Could not find play with id {id}").tag(ErrorKind::User))??;
+
+	let (Some(pures), Some(fars), Some(losts)) = (play.pure_notes, play.far_notes, play.lost_notes)
+	else {
+		return Err(anyhow!(
+			"Play {id} has no stored note distribution, so there is nothing to explain"
+		)
+		.tag(ErrorKind::User));
+	};
+
+	let score = play.score(ScoringSystem::Standard);
+	let analysis = Score::analyse_distribution(score, (pures, fars, losts), chart.note_count);
+	let verdict = |subpair: Score| {
+		if subpair == analysis.no_shiny_score {
+			"matches"
+		} else {
+			"differs"
+		}
+	};
+
+	let consensus = match analysis.consensus {
+		DistributionConsensus::Subpairs => {
+			"the pure+far and far+lost scores agree with each other, so the far count can be trusted"
+		}
+		DistributionConsensus::PureFar => {
+			"only the pure+far score matches, so the far count can be trusted"
+		}
+		DistributionConsensus::FarLost => {
+			"only the far+lost score matches, so the far count can be trusted"
+		}
+		DistributionConsensus::LostPure => {
+			"only the lost+pure score matches, so the far count was recomputed from the pure and lost counts"
+		}
+		DistributionConsensus::None => "no single recomputed score matches, so nothing can be concluded",
+	};
+
+	let result = match analysis.fars {
+		Some(fars) => format!("{fars} far notes"),
+		None => "unknown".to_string(),
+	};
+
+	ctx.reply(&format!(
+		"Play {id} on {song} [{}] ({} notes) with score {score}:
+- Note distribution: {pures} pure, {fars} far, {losts} lost
+- Score without shinies: {}
+- Pure+far score: {} ({})
+- Far+lost score: {} ({})
+- Lost+pure score: {} ({})
+- Consensus: {consensus}
+- Result: {result}
+Note: the far count above is the one stored after the original inference, not the one read off the screenshot.",
+		chart.difficulty,
+		chart.note_count,
+		analysis.no_shiny_score,
+		analysis.pf_score,
+		verdict(analysis.pf_score),
+		analysis.fl_score,
+		verdict(analysis.fl_score),
+		analysis.lp_score,
+		verdict(analysis.lp_score),
+	))
+	.await?;
+
+	Ok(analysis.fars)
+}
+// }}}
+// {{{ Tests
+#[cfg(test)]
+mod explain_tests {
+	use super::*;
+	use crate::{commands::discord::mock::MockContext, golden_test};
+	use std::{path::PathBuf, str::FromStr};
+
+	golden_test!(nonexistent_id, "commands/score/explain/nonexistent_id");
+	async fn nonexistent_id(ctx: &mut MockContext) -> Result<(), TaggedError> {
+		explain_impl(ctx, 666).await?;
+		Ok(())
+	}
+
+	golden_test!(
+		agrees_with_magic,
+		"commands/score/explain/agrees_with_magic"
+	);
+	async fn agrees_with_magic(ctx: &mut MockContext) -> Result<(), TaggedError> {
+		let plays =
+			magic_impl(ctx, &[PathBuf::from_str("test/screenshots/alter_ego.jpg")?]).await?;
+		let fars = explain_impl(ctx, plays[0].id).await?;
+		assert_eq!(fars, plays[0].far_notes);
+		Ok(())
+	}
+}
+// }}}
+// {{{ Discord wrapper
+/// Explain how the far notes of a score have been inferred.
+#[poise::command(prefix_command, slash_command)]
+pub async fn explain(
+	mut ctx: Context<'_>,
+	#[description = "Id of the score to explain"] id: u32,
+) -> Result<(), Error> {
+	let res = explain_impl(&mut ctx, id).await;
+	ctx.handle_error(res).await?;
+
+	Ok(())
+}
+// }}}
+// }}}
+// {{{ Score delete
+// {{{ Implementation
+pub async fn delete_impl<C: MessageContext>(ctx: &mut C, ids: &[u32]) -> Result<(), TaggedError> {
+	let user = User::from_context(ctx)?;
+
+	if ids.is_empty() {
+		return Err(anyhow!("Empty ID list provided").tag(ErrorKind::User));
+	}
+
+	let mut count = 0;
+
+	for id in ids {
+		let res = ctx
+			.data()
+			.db
+			.get()?
+			.prepare_cached("DELETE FROM plays WHERE id=? AND user_id=?")?
+			.execute((id, user.id))?;
+
+		if res == 0 {
+			ctx.reply(&format!("No play with id {} found", id)).await?;
+		} else {
+			count += 1;
+		}
+	}
+
+	if count > 0 {
+		ctx.data().best_plays_cache.clear();
+		ctx.reply(&format!("Deleted {} play(s) successfully!", count))
+			.await?;
+	}
+
+	Ok(())
+}
+
+/// Deletes every play of the current user on the given chart and/or outside
+/// the given dates (`after` and `before` are both exclusive). Since this can
+/// wipe out lots of plays at once, the user has to confirm the operation by
+/// passing in the number of plays about to get deleted.
+pub async fn delete_matching_impl<C: MessageContext>(
+	ctx: &mut C,
+	chart: Option<&str>,
+	after: Option<NaiveDate>,
+	before: Option<NaiveDate>,
+	confirmation: Option<u32>,
+) -> Result<usize, TaggedError> {
+	let user = User::from_context(ctx)?;
+
+	if chart.is_none() && after.is_none() && before.is_none() {
+		return Err(
+			anyhow!("Please provide a chart, or a date to delete plays before/after")
+				.tag(ErrorKind::User),
+		);
+	}
+
+	let mut description = String::new();
+	let chart_id = match chart {
+		Some(name) => {
+			let (song, chart) = guess_song_and_chart(ctx.data(), name)?;
+			description.push_str(&format!(" on {} [{}]", song.title, chart.difficulty));
+			Some(chart.id)
+		}
+		None => None,
+	};
+
+	// NOTE: timestamps are stored as text, so these must be converted to the
+	// exact same format for the comparisons to make sense.
+	let after = after.map(|after| {
+		description.push_str(&format!(" after {after}"));
+		after.and_hms_milli_opt(23, 59, 59, 999).unwrap()
+	});
+	let before = before.map(|before| {
+		description.push_str(&format!(" before {before}"));
+		before.and_hms_opt(0, 0, 0).unwrap()
+	});
+
+	let filter = "
+      WHERE user_id = ?1
+      AND (?2 IS NULL OR chart_id = ?2)
+      AND (?3 IS NULL OR created_at > ?3)
+      AND (?4 IS NULL OR created_at < ?4)
+    ";
+
+	let play_count: u32 = ctx
+		.data()
+		.db
+		.get()?
+		.prepare_cached(&format!("SELECT COUNT(*) FROM plays {filter}"))?
+		.query_row((user.id, chart_id, after, before), |row| row.get(0))?;
+
+	if play_count == 0 {
+		return Err(anyhow!("You have no plays{description}").tag(ErrorKind::User));
+	}
+
+	if confirmation != Some(play_count) {
+		return Err(anyhow!(
+			"This will permanently delete {play_count} of your plays{description}. To confirm, pass in {play_count} as the confirmation"
+		)
+		.tag(ErrorKind::User));
+	}
+
+	let deleted = ctx
+		.data()
+		.db
+		.get()?
+		.prepare_cached(&format!("DELETE FROM plays {filter}"))?
+		.execute((user.id, chart_id, after, before))?;
+
+	ctx.data().best_plays_cache.clear();
+
+	// The creation ptt of later plays might have depended on the deleted ones
+	generate_missing_scores(ctx.data(), Some(user.id)).await?;
+
+	ctx.reply(&format!("Deleted {deleted} play(s){description}"))
+		.await?;
+
+	Ok(deleted)
+}
+/// }}}
+// {{{ Tests
+#[cfg(test)]
+mod delete_tests {
+	use super::*;
+	use crate::{
+		arcaea::play::get_best_plays,
+		commands::discord::{mock::MockContext, play_song_title},
+		context::testing::get_mock_context,
+		golden_test, with_test_ctx,
+	};
+	use std::{path::PathBuf, str::FromStr};
+
+	/// Moves every play to 2024-03-15, such that the dates showing up in the
+	/// golden outputs do not depend on when the tests are run.
+	fn backdate_plays(ctx: &MockContext) -> Result<(), Error> {
+		ctx.data
+			.db
+			.get()?
+			.execute("UPDATE plays SET created_at='2024-03-15 12:00:00'", ())?;
+		Ok(())
+	}
+
+	#[tokio::test]
+	async fn deleting_clears_best_plays_cache() -> Result<(), Error> {
+		let (mut ctx, _guard) = get_mock_context().await?;
+		let user = User::create_from_context(&ctx).map_err(|e| e.error)?;
+		// Deleting plays needs a mutable context, so we can't hold onto these
+		let (_, fracture) = guess_song_and_chart(&ctx.data, "Fracture Ray [FTR]")?;
+		let fracture = fracture.clone();
+		let (_, pentiment) = guess_song_and_chart(&ctx.data, "Pentiment [BYD]")?;
+		let pentiment = pentiment.clone();
+
+		let best_play_ids = |ctx: &MockContext| -> Result<Vec<u32>, Error> {
+			let plays = get_best_plays(&ctx.data, user.id, ScoringSystem::Standard, 0, 30, None)
+				.map_err(|e| e.error)?;
+			Ok(plays.iter().map(|(play, _, _)| play.id).collect())
+		};
+
+		let first = CreatePlay::new(Score(9_500_000))
+			.save(&ctx.data, &user, &fracture)
+			.map_err(|e| e.error)?;
+		let second = CreatePlay::new(Score(9_900_000))
+			.save(&ctx.data, &user, &pentiment)
+			.map_err(|e| e.error)?;
+		assert_eq!(best_play_ids(&ctx)?, [second.id, first.id]);
+
+		// The new play reuses the id of the deleted one, so the play count
+		// and latest id the cache is keyed on end up unchanged
+		delete_impl(&mut ctx, &[second.id])
+			.await
+			.map_err(|e| e.error)?;
+		let third = CreatePlay::new(Score(9_600_000))
+			.save(&ctx.data, &user, &fracture)
+			.map_err(|e| e.error)?;
+		assert_eq!(best_play_ids(&ctx)?, [third.id]);
+
+		Ok(())
+	}
+
+	#[tokio::test]
+	async fn no_ids() -> Result<(), Error> {
+		with_test_ctx!("commands/score/delete/no_ids", |ctx| async move {
+			delete_impl(ctx, &[]).await?;
+			Ok(())
+		})
+	}
+
+	#[tokio::test]
+	async fn nonexistent_id() -> Result<(), Error> {
+		with_test_ctx!("commands/score/delete/nonexistent_id", |ctx| async move {
+			delete_impl(ctx, &[666]).await?;
+			Ok(())
+		})
+	}
+
+	golden_test!(delete_twice, "commands/score/delete/delete_twice");
+	async fn delete_twice(ctx: &mut MockContext) -> Result<(), TaggedError> {
+		let plays =
+			magic_impl(ctx, &[PathBuf::from_str("test/screenshots/alter_ego.jpg")?]).await?;
+
+		let id = plays[0].id;
+		delete_impl(ctx, &[id, id]).await?;
+		Ok(())
+	}
+
+	golden_test!(
+		no_show_after_delete,
+		"commands/score/delete/no_show_after_delete"
+	);
+	async fn no_show_after_delete(ctx: &mut MockContext) -> Result<(), TaggedError> {
+		let plays =
+			magic_impl(ctx, &[PathBuf::from_str("test/screenshots/alter_ego.jpg")?]).await?;
+
+		// Showcase proper usage
+		let ids = [plays[0].id];
+		delete_impl(ctx, &ids).await?;
+
+		// This will tell the user the play doesn't exist
+		let shown_plays = show_impl(ctx, &ids).await?;
+		assert_eq!(shown_plays.len(), 0);
+
+		Ok(())
+	}
+
+	golden_test!(delete_multiple, "commands/score/delete/delete_multiple");
+	async fn delete_multiple(ctx: &mut MockContext) -> Result<(), TaggedError> {
+		let plays = magic_impl(
+			ctx,
+			&[
+				PathBuf::from_str("test/screenshots/antithese_74_kerning.jpg")?,
+				PathBuf::from_str("test/screenshots/alter_ego.jpg")?,
+				PathBuf::from_str("test/screenshots/genocider_24_kerning.jpg")?,
+			],
+		)
+		.await?;
+
+		delete_impl(ctx, &[plays[0].id, plays[2].id]).await?;
+
+		// Ensure the second play still exists
+		let shown_plays = show_impl(ctx, &[plays[1].id]).await?;
+		assert_eq!(play_song_title(ctx, &shown_plays[0])?, "ALTER EGO");
+
+		Ok(())
+	}
+
+	#[tokio::test]
+	async fn no_filters() -> Result<(), Error> {
+		with_test_ctx!("commands/score/delete/no_filters", |ctx| async move {
+			delete_matching_impl(ctx, None, None, None, None).await?;
+			Ok(())
+		})
+	}
+
+	golden_test!(
+		matching_wrong_confirmation,
+		"commands/score/delete/matching_wrong_confirmation"
+	);
+	async fn matching_wrong_confirmation(ctx: &mut MockContext) -> Result<(), TaggedError> {
+		magic_impl(ctx, &[PathBuf::from_str("test/screenshots/alter_ego.jpg")?]).await?;
+		backdate_plays(ctx)?;
+
+		let before = NaiveDate::from_ymd_opt(2024, 3, 16).unwrap();
+		delete_matching_impl(ctx, None, None, Some(before), Some(2)).await?;
+		Ok(())
+	}
+
+	golden_test!(delete_by_chart, "commands/score/delete/delete_by_chart");
+	async fn delete_by_chart(ctx: &mut MockContext) -> Result<(), TaggedError> {
+		let plays = magic_impl(
+			ctx,
+			&[
+				PathBuf::from_str("test/screenshots/antithese_74_kerning.jpg")?,
+				PathBuf::from_str("test/screenshots/alter_ego.jpg")?,
+			],
+		)
+		.await?;
+
+		let (song, chart) = ctx.data().song_cache.lookup_chart(plays[1].chart_id)?;
+		let name = format!("{} [{}]", song.title, chart.difficulty);
+		let deleted = delete_matching_impl(ctx, Some(&name), None, None, Some(1)).await?;
+		assert_eq!(deleted, 1);
+
+		// Plays on other charts must be left alone
+		let shown_plays = show_impl(ctx, &[plays[0].id, plays[1].id]).await?;
+		assert_eq!(shown_plays.len(), 1);
+		assert_eq!(shown_plays[0].id, plays[0].id);
+
+		Ok(())
+	}
+
+	golden_test!(delete_by_date, "commands/score/delete/delete_by_date");
+	async fn delete_by_date(ctx: &mut MockContext) -> Result<(), TaggedError> {
+		magic_impl(
+			ctx,
+			&[
+				PathBuf::from_str("test/screenshots/antithese_74_kerning.jpg")?,
+				PathBuf::from_str("test/screenshots/alter_ego.jpg")?,
+			],
+		)
+		.await?;
+		backdate_plays(ctx)?;
+
+		// Every play was created on the same day, so nothing happened after it
+		let day = NaiveDate::from_ymd_opt(2024, 3, 15).unwrap();
+		let res = delete_matching_impl(ctx, None, Some(day), None, Some(2)).await;
+		assert!(res.is_err());
+
+		let next_day = day.succ_opt().unwrap();
+		let deleted = delete_matching_impl(ctx, None, None, Some(next_day), Some(2)).await?;
+		assert_eq!(deleted, 2);
+
+		Ok(())
+	}
+}
+// }}}
+// {{{ Discord wrapper
+/// Delete scores, given their IDs, or every score on a chart/in a date range.
+#[poise::command(prefix_command, slash_command)]
+pub async fn delete(
+	mut ctx: Context<'_>,
+	#[description = "Id of score to delete"] ids: Vec<u32>,
+	#[description = "Delete every play on this chart"] chart: Option<String>,
+	#[description = "Delete every play after this date (YYYY-MM-DD)"] after: Option<NaiveDate>,
+	#[description = "Delete every play before this date (YYYY-MM-DD)"] before: Option<NaiveDate>,
+	#[description = "Number of plays to delete, as confirmation"] confirmation: Option<u32>,
+) -> Result<(), Error> {
+	if chart.is_none() && after.is_none() && before.is_none() {
+		let res = delete_impl(&mut ctx, &ids).await;
+		ctx.handle_error(res).await?;
+	} else if ids.is_empty() {
+		let res =
+			delete_matching_impl(&mut ctx, chart.as_deref(), after, before, confirmation).await;
+		ctx.handle_error(res).await?;
+	} else {
+		let res: Result<(), TaggedError> = Err(anyhow!(
+			"Please provide either a list of ids or some filters, not both"
+		)
+		.tag(ErrorKind::User));
+		ctx.handle_error(res).await?;
+	}
+
+	Ok(())
+}
+// }}}
+// }}}
+// {{{ Score random
+// {{{ Implementation
+/// Restricts which charts `score random` may pick plays from.
+#[derive(Debug, Clone, Copy)]
+pub enum ChartFilter {
+	Difficulty(Difficulty),
+	Level(Level),
+}
+
+impl ChartFilter {
+	#[inline]
+	fn matches(self, chart: &Chart) -> bool {
+		match self {
+			Self::Difficulty(difficulty) => chart.difficulty == difficulty,
+			Self::Level(level) => chart.level == level,
+		}
+	}
+}
+
+impl FromStr for ChartFilter {
+	type Err = Error;
+
+	fn from_str(s: &str) -> Result<Self, Self::Err> {
+		let s = s.trim();
+		if let Ok(difficulty) = Difficulty::from_str(s) {
+			Ok(Self::Difficulty(difficulty))
+		} else if let Ok(level) = Level::from_str(s) {
+			Ok(Self::Level(level))
+		} else {
+			Err(anyhow!(
+				"`{s}` is neither a difficulty (e.g. FTR) nor a level (e.g. 9+)"
+			))
+		}
+	}
+}
+
+/// Shows a uniformly random play of the current user, optionally restricted
+/// to charts matching the given filter.
+pub async fn random_impl<C: MessageContext>(
+	ctx: &mut C,
+	filter: Option<ChartFilter>,
+) -> Result<Play, TaggedError> {
+	let user = User::from_context(ctx)?;
+
+	// Charts are looked up in the song cache, so the filtering happens
+	// on our side, going through the plays in random order.
+	let result = ctx
+		.data()
+		.db
+		.get()?
+		.prepare_cached(
+			"
+        SELECT
+          p.id, p.chart_id, p.user_id, p.created_at,
+          p.max_recall, p.far_notes, p.pure_notes, p.lost_notes, p.clear_kind, s.score
+        FROM plays p
+        JOIN scores s ON s.play_id = p.id
+        WHERE s.scoring_system='standard'
+        AND p.user_id=?
+        ORDER BY RANDOM()
+      ",
+		)?
+		.query_and_then([user.id], |row| -> Result<_, Error> {
+			let (song, chart) = ctx.data().song_cache.lookup_chart(row.get("chart_id")?)?;
+			let play = Play::from_sql(chart, row)?;
+			Ok((song, chart, play))
+		})?
+		.find(|result| match result {
+			Ok((_, chart, _)) => filter.map_or(true, |filter| filter.matches(chart)),
+			Err(_) => true,
+		})
+		.transpose()?;
+
+	let Some((song, chart, play)) = result else {
+		let message = match filter {
+			Some(_) => "You have no plays matching that filter yet. Go play something!",
+			None => "You have no plays yet. Go play something!",
+		};
+
+		return Err(anyhow!(message).tag(ErrorKind::User));
+	};
+
+	let author = ctx.fetch_user(&user.discord_id).await?;
+	let (embed, attachment) = play.to_embed(ctx.data(), &user, song, chart, 0, Some(&author))?;
+
+	ctx.send(
+		CreateReply::default()
+			.reply(true)
+			.embed(embed)
+			.attachments(attachment),
+	)
+	.await?;
+
+	Ok(play)
+}
+// }}}
+// {{{ Tests
+#[cfg(test)]
+mod random_tests {
+	use super::*;
+	use crate::{commands::discord::mock::MockContext, golden_test, with_test_ctx};
+	use std::path::PathBuf;
+
+	#[tokio::test]
+	async fn no_plays() -> Result<(), Error> {
+		with_test_ctx!("commands/score/random/no_plays", |ctx| async move {
+			random_impl(ctx, None).await?;
+			Ok(())
+		})
+	}
+
+	golden_test!(single_play, "commands/score/random/single_play");
+	async fn single_play(ctx: &mut MockContext) -> Result<(), TaggedError> {
+		let created = magic_impl(
+			ctx,
+			&[PathBuf::from_str("test/screenshots/fracture_ray_ex.jpg")?],
+		)
+		.await?;
+
+		let play = random_impl(ctx, Some(ChartFilter::from_str("ftr")?)).await?;
+		assert_eq!(created[0], play);
+
+		Ok(())
+	}
+
+	golden_test!(no_matching_plays, "commands/score/random/no_matching_plays");
+	async fn no_matching_plays(ctx: &mut MockContext) -> Result<(), TaggedError> {
+		magic_impl(
+			ctx,
+			&[PathBuf::from_str("test/screenshots/fracture_ray_ex.jpg")?],
+		)
+		.await?;
+
+		random_impl(ctx, Some(ChartFilter::from_str("PST")?)).await?;
+		Ok(())
+	}
+}
+// }}}
+// {{{ Discord wrapper
+/// Show a random play of yours, optionally filtered by difficulty or level.
+#[poise::command(prefix_command, slash_command, user_cooldown = 1)]
+pub async fn random(
+	mut ctx: Context<'_>,
+	#[description = "Difficulty (e.g. FTR) or level (e.g. 9+) to pick from"] filter: Option<String>,
+) -> Result<(), Error> {
+	let res = async {
+		let filter = filter
+			.as_deref()
+			.map(ChartFilter::from_str)
+			.transpose()
+			.map_err(|e| e.tag(ErrorKind::User))?;
+
+		random_impl(&mut ctx, filter).await
+	}
+	.await;
+	ctx.handle_error(res).await?;
+
+	Ok(())
+}
+// }}}
+// }}}
+// {{{ Score debug
+// {{{ Implementation
+/// The outcome of a single OCR stage, as reported by the `debug` command.
+struct DebugStage {
+	name: &'static str,
+	outcome: String,
+	crop: Option<(UIMeasurementRect, DynamicImage)>,
+}
+
+impl DebugStage {
+	fn new<T: std::fmt::Debug>(
+		name: &'static str,
+		result: Result<&T, &Error>,
+		analyzer: &mut ImageAnalyzer,
+		image: &DynamicImage,
+	) -> Self {
+		let outcome = match result {
+			Ok(value) => format!("{value:?}"),
+			Err(err) => format!("Failed: {err}"),
+		};
+
+		Self {
+			name,
+			outcome,
+			crop: analyzer.last_crop(image),
+		}
+	}
+
+	fn skipped(name: &'static str, reason: &str) -> Self {
+		Self {
+			name,
+			outcome: format!("Skipped: {reason}"),
+			crop: None,
+		}
+	}
+}
+
+/// Runs every OCR stage on the given images, replying with the text each
+/// stage recognised and the (last) crop it looked at. Nothing gets saved to
+/// the database. Returns the outcome of each stage, for every image.
+async fn debug_impl<C: MessageContext>(
+	ctx: &mut C,
+	files: &[C::Attachment],
+) -> Result<Vec<Vec<String>>, TaggedError> {
+	let user = User::from_context(ctx)?;
+	user.assert_is_pookie()?;
+
+	let files = ctx.download_images(files).await?;
+	if files.is_empty() {
+		return Err(anyhow!("No images found attached to message").tag(ErrorKind::User));
+	}
+
+	let mut outcomes = Vec::with_capacity(files.len());
+	let mut analyzer = ImageAnalyzer::default();
+
+	for (i, (attachment, bytes)) in files.into_iter().enumerate() {
+		let image = image::load_from_memory(&bytes).map_err(|err| {
+			anyhow!("Could not decode `{}`: {err}", C::filename(attachment)).tag(ErrorKind::User)
+		})?;
+		let mut image = limit_image_size(image, max_ocr_dimension());
+		let mut grayscale_image = DynamicImage::ImageLuma8(image.to_luma8());
+		let mut stages = Vec::with_capacity(6);
+
+		// {{{ Run stages
+		analyzer.clear();
+		let kind = analyzer.read_score_kind(ctx.data(), &grayscale_image);
+		stages.push(DebugStage::new(
+			"Score kind",
+			kind.as_ref(),
+			&mut analyzer,
+			&grayscale_image,
+		));
+
+		let kind = kind.ok();
+		let chart = if let Some(kind) = kind {
+			analyzer.clear();
+			let difficulty = analyzer.read_difficulty(ctx.data(), &image, &grayscale_image, kind);
+			stages.push(DebugStage::new(
+				"Difficulty",
+				difficulty.as_ref(),
+				&mut analyzer,
+				&image,
+			));
+
+			if let Ok(difficulty) = difficulty {
+				analyzer.clear();
+				let jacket = analyzer
+					.read_jacket(ctx.data(), &mut image, kind, difficulty)
+					.map(|(song, chart)| (song.title.as_str(), chart));
+
+				let mut stage = DebugStage::new(
+					"Jacket",
+					jacket.as_ref().map(|(title, _)| title),
+					&mut analyzer,
+					&image,
+				);
+
+				if let Some(distance) = analyzer.last_jacket_distance() {
+					stage.outcome = format!(
+						"{} (distance {distance:.2}, rejection threshold {:.2})",
+						stage.outcome,
+						ctx.data().jacket_cache.rejection_threshold
+					);
+				}
+
+				stages.push(stage);
+				jacket.ok().map(|(_, chart)| chart)
+			} else {
+				stages.push(DebugStage::skipped("Jacket", "no difficulty"));
+				None
+			}
+		} else {
+			stages.push(DebugStage::skipped("Difficulty", "unknown score kind"));
+			stages.push(DebugStage::skipped("Jacket", "unknown score kind"));
+			None
+		};
+
+		if kind == Some(ScoreKind::ScoreScreen) {
+			analyzer.clear();
+			let max_recall = analyzer.read_max_recall(ctx.data(), &grayscale_image);
+			stages.push(DebugStage::new(
+				"Max recall",
+				max_recall.as_ref(),
+				&mut analyzer,
+				&grayscale_image,
+			));
+		}
+
+		grayscale_image.invert();
+		if kind == Some(ScoreKind::ScoreScreen) {
+			analyzer.clear();
+			let distribution = analyzer.read_distribution(ctx.data(), &grayscale_image);
+			stages.push(DebugStage::new(
+				"Distribution",
+				distribution.as_ref(),
+				&mut analyzer,
+				&grayscale_image,
+			));
+		}
+
+		if let Some(kind) = kind {
+			analyzer.clear();
+			let reading = analyzer.read_score(
+				ctx.data(),
+				chart.map(|c| c.note_count),
+				&grayscale_image,
+				kind,
+			);
+			stages.push(DebugStage::new(
+				"Score",
+				reading.as_ref(),
+				&mut analyzer,
+				&grayscale_image,
+			));
+		} else {
+			stages.push(DebugStage::skipped("Score", "unknown score kind"));
+		}
+		// }}}
+		// {{{ Deliver embeds
+		let mut embeds = Vec::with_capacity(stages.len());
+		let mut attachments = Vec::with_capacity(stages.len());
+
+		for (j, stage) in stages.iter().enumerate() {
+			let mut embed = serenity::CreateEmbed::default()
+				.title(format!("{} — {}", C::filename(attachment), stage.name))
+				.description(&stage.outcome);
+
+			if let Some((ui_rect, crop)) = &stage.crop {
+				let filename = format!("debug-{i}-{j}.png");
+				let mut buffer = Vec::new();
+				crop.write_to(&mut Cursor::new(&mut buffer), image::ImageFormat::Png)?;
+
+				embed = embed
+					.attachment(&filename)
+					.footer(serenity::CreateEmbedFooter::new(format!("{ui_rect:?}")));
+				attachments.push(serenity::CreateAttachment::bytes(buffer, filename));
+			}
+
+			embeds.push(embed);
+		}
+
+		ctx.send(
+			CreateReply::default()
+				.reply(true)
+				.embeds(embeds)
+				.attachments(attachments),
+		)
+		.await?;
+		// }}}
+
+		outcomes.push(stages.into_iter().map(|s| s.outcome).collect());
+	}
+
+	Ok(outcomes)
+}
+// }}}
+// {{{ Tests
+#[cfg(test)]
+mod debug_tests {
+	use std::path::PathBuf;
+
+	use crate::{commands::discord::mock::MockContext, golden_test, with_test_ctx};
+
+	use super::*;
+
+	#[tokio::test]
+	async fn not_pookie() -> Result<(), Error> {
+		with_test_ctx!("commands/score/debug/not_pookie", |ctx| async move {
+			debug_impl(
+				ctx,
+				&[PathBuf::from_str("test/screenshots/fracture_ray_ex.jpg")?],
+			)
+			.await?;
+			Ok(())
+		})
+	}
+
+	golden_test!(score_screen, "commands/score/debug/score_screen");
+	async fn score_screen(ctx: &mut MockContext) -> Result<(), TaggedError> {
+		ctx.data()
+			.db
+			.get()?
+			.execute("UPDATE users SET is_pookie=1", ())?;
+
+		let outcomes = debug_impl(
+			ctx,
+			&[PathBuf::from_str("test/screenshots/fracture_ray_ex.jpg")?],
+		)
+		.await?;
+
+		assert!(outcomes[0].iter().all(|o| !o.starts_with("Failed")));
+
+		let play_count: u32 =
+			ctx.data()
+				.db
+				.get()?
+				.query_row("SELECT COUNT(*) FROM plays", (), |row| row.get(0))?;
+		assert_eq!(play_count, 0);
+
+		Ok(())
+	}
+}
+// }}}
+// {{{ Discord wrapper
+/// Show what each screenshot recognition step sees, without saving anything
+#[poise::command(prefix_command, slash_command, hide_in_help)]
+pub async fn debug(
+	mut ctx: Context<'_>,
+	#[description = "Images to run screenshot recognition on"] files: Vec<serenity::Attachment>,
 ) -> Result<(), Error> {
-	let res = delete_impl(&mut ctx, &ids).await;
+	let res = debug_impl(&mut ctx, &files).await;
 	ctx.handle_error(res).await?;
 
 	Ok(())
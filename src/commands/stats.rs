@@ -1,28 +1,37 @@
 // {{{ Imports
 use std::io::Cursor;
+use std::str::FromStr;
 
 use anyhow::anyhow;
 use image::{DynamicImage, ImageBuffer};
 use poise::serenity_prelude::{CreateAttachment, CreateEmbed};
 use poise::CreateReply;
 
-use crate::arcaea::achievement::GoalStats;
-use crate::arcaea::chart::Level;
+use crate::arcaea::achievement::{
+	compute_per_level_lowest_grades, GoalStats, GRADE_LEVEL_MINIMUMS,
+};
+use crate::arcaea::chart::{Chart, Difficulty, Level, Side, Song};
 use crate::arcaea::jacket::BITMAP_IMAGE_SIZE;
-use crate::arcaea::play::{compute_b30_ptt, get_best_plays};
-use crate::arcaea::rating::rating_as_float;
-use crate::arcaea::score::ScoringSystem;
+use crate::arcaea::play::{
+	compute_b30_ptt, count_b30_exclusions, get_b30_plays, get_best_plays, get_best_plays_in,
+	ClearKind, Play, PlayFilter, PlayOrder,
+};
+use crate::arcaea::rating::{Rating, RatingDisplay};
+use crate::arcaea::score::{Grade, ScoringSystem};
 use crate::assets::{
 	get_difficulty_background, with_font, B30_BACKGROUND, COUNT_BACKGROUND, EXO_FONT,
 	GRADE_BACKGROUND, NAME_BACKGROUND, PTT_EMBLEM, SCORE_BACKGROUND, STATUS_BACKGROUND,
 	TOP_BACKGROUND,
 };
-use crate::bitmap::{Align, BitmapCanvas, Color, LayoutDrawer, LayoutManager, Rect};
-use crate::context::{Context, Error, TaggedError};
+use crate::bitmap::{
+	Align, BitmapCanvas, Color, LayoutBoxId, LayoutDrawer, LayoutManager, Position, Rect,
+};
+use crate::context::{Context, Error, ErrorKind, TagError, TaggedError};
 use crate::logs::debug_image_log;
+use crate::recognition::fuzzy_song_name::guess_song_and_chart;
 use crate::user::User;
 
-use super::discord::MessageContext;
+use super::discord::{CreateReplyExtra, MessageContext, MAX_EMBEDS_PER_MESSAGE};
 // }}}
 
 // {{{ Stats
@@ -30,71 +39,214 @@ use super::discord::MessageContext;
 #[poise::command(
 	prefix_command,
 	slash_command,
-	subcommands("meta", "b30", "bany"),
+	subcommands(
+		"meta",
+		"b30",
+		"worst30",
+		"bany",
+		"missing",
+		"grade_goals",
+		"recent",
+		"packs",
+		"compare_systems",
+		"exclude",
+		"include"
+	),
 	subcommand_required
 )]
 pub async fn stats(_ctx: Context<'_>) -> Result<(), Error> {
 	Ok(())
 }
 // }}}
+// {{{ Output format
+/// The image format rendered best plays get encoded as.
+#[derive(Debug, Clone, Copy, poise::ChoiceParameter)]
+pub enum OutputFormat {
+	WebP,
+	Png,
+	Jpeg,
+}
+
+impl OutputFormat {
+	#[inline]
+	pub fn to_image_format(self) -> image::ImageFormat {
+		match self {
+			Self::WebP => image::ImageFormat::WebP,
+			Self::Png => image::ImageFormat::Png,
+			Self::Jpeg => image::ImageFormat::Jpeg,
+		}
+	}
+
+	#[inline]
+	pub fn extension(self) -> &'static str {
+		match self {
+			Self::WebP => "webp",
+			Self::Png => "png",
+			Self::Jpeg => "jpg",
+		}
+	}
+}
+
+impl Default for OutputFormat {
+	fn default() -> Self {
+		Self::WebP
+	}
+}
+// }}}
 // {{{ Render best plays
-async fn best_plays<C: MessageContext>(
-	ctx: &mut C,
-	user: &User,
+// {{{ Layout
+/// The boxes making up the best plays grid.
+struct BestPlaysLayout {
+	layout: LayoutManager,
+	jacket_area: LayoutBoxId,
+	jacket_with_border: LayoutBoxId,
+	jacket_margin: i32,
+	top_left_area: LayoutBoxId,
+	top_area: LayoutBoxId,
+	bottom_area: LayoutBoxId,
+	bottom_in_area: LayoutBoxId,
+	item_area: LayoutBoxId,
+	item_with_margin: LayoutBoxId,
+	item_grid: LayoutBoxId,
+	/// Top-left corner of each grid cell, in row-major order.
+	item_origins: Vec<Position>,
+	root: LayoutBoxId,
+}
+
+impl BestPlaysLayout {
+	fn new(grid_size: (u32, u32)) -> Self {
+		let mut layout = LayoutManager::default();
+		let jacket_area = layout.make_box(BITMAP_IMAGE_SIZE, BITMAP_IMAGE_SIZE);
+		let jacket_with_border = layout.margin_uniform(jacket_area, 3);
+		let jacket_margin = 10;
+		let jacket_with_margin = layout.margin(
+			jacket_with_border,
+			jacket_margin,
+			jacket_margin,
+			2,
+			jacket_margin,
+		);
+		let top_left_area = layout.make_box(90, layout.height(jacket_with_margin));
+		let top_area = layout.glue_vertically(top_left_area, jacket_with_margin);
+		let bottom_area = layout.make_box(layout.width(top_area), 43);
+		let bottom_in_area = layout.margin_xy(bottom_area, -20, -7);
+		let item_area = layout.glue_horizontally(top_area, bottom_area);
+		let item_with_margin = layout.margin_xy(item_area, 22, 17);
+		let (item_grid, item_origins) = layout.repeated_evenly(item_with_margin, grid_size);
+		let item_origins = item_origins.collect();
+		let root = layout.margin_uniform(item_grid, 30);
+
+		Self {
+			layout,
+			jacket_area,
+			jacket_with_border,
+			jacket_margin,
+			top_left_area,
+			top_area,
+			bottom_area,
+			bottom_in_area,
+			item_area,
+			item_with_margin,
+			item_grid,
+			item_origins,
+			root,
+		}
+	}
+}
+
+/// Computes the smallest integer factor by which the background must be
+/// scaled up in order to cover the entire canvas, whatever its aspect ratio.
+#[inline]
+fn background_scale((width, height): (u32, u32), (bg_width, bg_height): (u32, u32)) -> u32 {
+	width
+		.div_ceil(bg_width)
+		.max(height.div_ceil(bg_height))
+		.max(1)
+}
+// }}}
+// {{{ Tests
+#[cfg(test)]
+mod layout_tests {
+	use super::*;
+
+	#[test]
+	fn tall_grid_dimensions() {
+		let BestPlaysLayout {
+			layout,
+			item_with_margin,
+			item_origins,
+			root,
+			..
+		} = BestPlaysLayout::new((3, 10));
+
+		let item_width = layout.width(item_with_margin);
+		let item_height = layout.height(item_with_margin);
+
+		assert_eq!(layout.width(root), 3 * item_width + 60);
+		assert_eq!(layout.height(root), 10 * item_height + 60);
+		assert_eq!(item_origins.len(), 30);
+		assert_eq!(
+			item_origins.last().copied(),
+			Some((2 * item_width as i32, 9 * item_height as i32))
+		);
+	}
+
+	#[test]
+	fn background_covers_any_aspect_ratio() {
+		let bg = B30_BACKGROUND.dimensions();
+		for grid_size in [(5, 6), (3, 10), (10, 1), (1, 1), (20, 20)] {
+			let BestPlaysLayout { layout, root, .. } = BestPlaysLayout::new(grid_size);
+			let (width, height) = (layout.width(root), layout.height(root));
+			let scale = background_scale((width, height), bg);
+
+			assert!(
+				bg.0 * scale >= width,
+				"{grid_size:?} is not covered horizontally"
+			);
+			assert!(
+				bg.1 * scale >= height,
+				"{grid_size:?} is not covered vertically"
+			);
+		}
+	}
+}
+// }}}
+// {{{ Render grid
+/// Renders the given plays as a grid of tiles (like the ones in `stats b30`),
+/// in the given order. Slots past the end of the list stay empty. When
+/// `dual` is set, every tile also shows the score under a second system.
+pub fn render_play_grid(
+	plays: &[(Play, &Song, &Chart)],
 	scoring_system: ScoringSystem,
 	grid_size: (u32, u32),
-	require_full: bool,
-) -> Result<(), TaggedError> {
-	let user_ctx = ctx.data();
-	let plays = get_best_plays(
-		user_ctx,
-		user.id,
-		scoring_system,
-		if require_full {
-			grid_size.0 * grid_size.1
-		} else {
-			grid_size.0 * (grid_size.1.max(1) - 1) + 1
-		} as usize,
-		(grid_size.0 * grid_size.1) as usize,
-		None,
-	)?;
-
-	// {{{ Layout
-	let mut layout = LayoutManager::default();
-	let jacket_area = layout.make_box(BITMAP_IMAGE_SIZE, BITMAP_IMAGE_SIZE);
-	let jacket_with_border = layout.margin_uniform(jacket_area, 3);
-	let jacket_margin = 10;
-	let jacket_with_margin = layout.margin(
+	dual: bool,
+) -> Result<DynamicImage, TaggedError> {
+	// {{{ Rendering prep
+	let BestPlaysLayout {
+		layout,
+		jacket_area,
 		jacket_with_border,
 		jacket_margin,
-		jacket_margin,
-		2,
-		jacket_margin,
-	);
-	let top_left_area = layout.make_box(90, layout.height(jacket_with_margin));
-	let top_area = layout.glue_vertically(top_left_area, jacket_with_margin);
-	let bottom_area = layout.make_box(layout.width(top_area), 43);
-	let bottom_in_area = layout.margin_xy(bottom_area, -20, -7);
-	let item_area = layout.glue_horizontally(top_area, bottom_area);
-	let item_with_margin = layout.margin_xy(item_area, 22, 17);
-	let (item_grid, item_origins) =
-		layout.repeated_evenly(item_with_margin, (grid_size.0, grid_size.1));
-	let root = layout.margin_uniform(item_grid, 30);
-	// }}}
-	// {{{ Rendering prep
+		top_left_area,
+		top_area,
+		bottom_area,
+		bottom_in_area,
+		item_area,
+		item_with_margin,
+		item_grid,
+		item_origins,
+		root,
+	} = BestPlaysLayout::new(grid_size);
+
 	let width = layout.width(root);
 	let height = layout.height(root);
 
-	let canvas = BitmapCanvas::new(width, height);
+	let canvas = BitmapCanvas::new(width, height).with_rounded_blending();
 	let mut drawer = LayoutDrawer::new(layout, canvas);
 	// }}}
 	// {{{ Render background
 	let bg = &*B30_BACKGROUND;
-
-	let scale = (drawer.layout.width(root) as f32 / bg.width() as f32)
-		.max(drawer.layout.height(root) as f32 / bg.height() as f32)
-		.max(1.0)
-		.ceil() as u32;
+	let scale = background_scale((width, height), bg.dimensions());
 
 	drawer.blit_rbg_scaled_up(
 		root,
@@ -109,7 +261,10 @@ async fn best_plays<C: MessageContext>(
 	);
 	// }}}
 
-	for (i, origin) in item_origins.enumerate() {
+	// Plays are sorted, so once we run out, every remaining slot
+	// (i.e. the rest of a partial final row) stays empty.
+	for (i, (play, song, chart)) in plays.iter().enumerate().take(item_origins.len()) {
+		let origin = item_origins[i];
 		drawer
 			.layout
 			.edit_to_relative(item_with_margin, item_grid, origin.0, origin.1);
@@ -117,12 +272,6 @@ async fn best_plays<C: MessageContext>(
 		let top_bg = &*TOP_BACKGROUND;
 		drawer.blit_rbga(top_area, (0, 0), top_bg);
 
-		let (play, song, chart) = if let Some(item) = plays.get(i) {
-			item
-		} else {
-			break;
-		};
-
 		// {{{ Display index
 		let bg = &*COUNT_BACKGROUND;
 		let bg_center = Rect::from_image(bg).center();
@@ -192,7 +341,9 @@ async fn best_plays<C: MessageContext>(
 			)
 		})?;
 
-		drawer.fill(jacket_with_border, Color::from_rgb_int(0x271E35));
+		// Jackets are decoded at their final size when the bot starts up (see
+		// `read_jackets`), so they can be blitted as-is.
+		drawer.fill_rounded(jacket_with_border, 3.0, Color::from_rgb_int(0x271E35));
 		drawer.blit_rbg(jacket_area, (0, 0), jacket.bitmap);
 		// }}}
 		// {{{ Display difficulty background
@@ -266,6 +417,36 @@ async fn best_plays<C: MessageContext>(
 			)
 		})?;
 		// }}}
+		// {{{ Display secondary score text
+		if dual {
+			// Compare against standard scoring, or against EX scoring when
+			// standard scoring is the one displayed already.
+			let (secondary_system, label) = match scoring_system {
+				ScoringSystem::Standard => (ScoringSystem::EX, "EX"),
+				_ => (ScoringSystem::Standard, "STD"),
+			};
+
+			with_font(&EXO_FONT, |faces| {
+				drawer.text(
+					jacket_area,
+					(score_bg_pos.0 + 5, score_bg_pos.1 - 10),
+					faces,
+					crate::bitmap::TextStyle {
+						size: 15,
+						weight: Some(700),
+						color: Color::WHITE,
+						align: (Align::Start, Align::Center),
+						stroke: Some((Color::BLACK, 1.5)),
+						drop_shadow: None,
+					},
+					&format!(
+						"{label} {:0>10}",
+						format!("{}", play.score(secondary_system))
+					),
+				)
+			})?;
+		}
+		// }}}
 		// {{{ Display status background
 		let status_bg = &*STATUS_BACKGROUND;
 		let status_bg_area = Rect::from_image(status_bg).align_whole(
@@ -370,10 +551,8 @@ async fn best_plays<C: MessageContext>(
 				(top_left_center, 94),
 				faces,
 				style,
-				&format!(
-					"{:.2}",
-					play.play_rating_f32(scoring_system, chart.chart_constant)
-				),
+				&RatingDisplay(play.play_rating(scoring_system, chart.chart_constant.get()))
+					.to_string(),
 			)?;
 
 			Ok(())
@@ -390,7 +569,6 @@ async fn best_plays<C: MessageContext>(
 		// }}}
 	}
 
-	let mut out_buffer = Vec::new();
 	let mut image = DynamicImage::ImageRgb8(
 		ImageBuffer::from_raw(width, height, drawer.canvas.buffer.into_vec()).unwrap(),
 	);
@@ -401,15 +579,96 @@ async fn best_plays<C: MessageContext>(
 		image = image.resize(4096, 4096, image::imageops::FilterType::Nearest);
 	}
 
+	Ok(image)
+}
+
+/// Encodes a rendered image using the given format.
+pub fn encode_image(image: &DynamicImage, format: OutputFormat) -> Result<Vec<u8>, Error> {
+	let mut out_buffer = Vec::new();
 	let mut cursor = Cursor::new(&mut out_buffer);
-	image.write_to(&mut cursor, image::ImageFormat::WebP)?;
+	image.write_to(&mut cursor, format.to_image_format())?;
+	Ok(out_buffer)
+}
+// }}}
+#[allow(clippy::too_many_arguments)]
+async fn best_plays<C: MessageContext>(
+	ctx: &mut C,
+	user: &User,
+	scoring_system: ScoringSystem,
+	grid_size: (u32, u32),
+	require_full: bool,
+	format: OutputFormat,
+	filter: &PlayFilter,
+	order: PlayOrder,
+	dual: bool,
+) -> Result<(), TaggedError> {
+	let user_ctx = ctx.data();
+	let excluded = if filter.skip_b30_exclusions {
+		count_b30_exclusions(&user_ctx.db.get()?, user.id)?
+	} else {
+		0
+	};
+	let plays = get_best_plays_in(
+		&user_ctx.db.get()?,
+		user_ctx,
+		user.id,
+		scoring_system,
+		if !filter.is_empty() {
+			// Filtered selections are rarely large enough to fill the whole grid
+			1
+		} else if require_full {
+			grid_size.0 * grid_size.1
+		} else {
+			grid_size.0 * (grid_size.1.max(1) - 1) + 1
+		} as usize,
+		(grid_size.0 * grid_size.1) as usize,
+		None,
+		filter,
+		order,
+	)?;
+
+	let image = render_play_grid(&plays, scoring_system, grid_size, dual)?;
+	let out_buffer = encode_image(&image, format)?;
+
+	let mut content = if order == PlayOrder::Worst {
+		format!(
+			"The average rating of your {} worst plays on cleared charts is {}",
+			plays.len(),
+			RatingDisplay(compute_b30_ptt(scoring_system, &plays))
+		)
+	} else if filter.is_empty() {
+		format!(
+			"Your ptt is {}",
+			RatingDisplay(compute_b30_ptt(scoring_system, &plays))
+		)
+	} else {
+		format!(
+			"Your ptt would be {} if only counting charts matching ({})",
+			RatingDisplay(compute_b30_ptt(scoring_system, &plays)),
+			filter.describe()
+		)
+	};
+
+	if excluded > 0 {
+		content += &format!(
+			" ({excluded} excluded chart{} not counted)",
+			if excluded == 1 { "" } else { "s" }
+		);
+	}
 
 	let reply = CreateReply::default()
-		.attachment(CreateAttachment::bytes(out_buffer, "b30.png"))
-		.content(format!(
-			"Your ptt is {:.2}",
-			rating_as_float(compute_b30_ptt(scoring_system, &plays))
-		));
+		.attachment(CreateAttachment::bytes(
+			out_buffer,
+			format!(
+				"{}.{}",
+				match order {
+					PlayOrder::Best => "b30",
+					PlayOrder::Worst => "worst30",
+				},
+				format.extension()
+			),
+		))
+		.content(content);
 	ctx.send(reply).await?;
 
 	Ok(())
@@ -420,17 +679,83 @@ async fn best_plays<C: MessageContext>(
 pub async fn b30_impl<C: MessageContext>(
 	ctx: &mut C,
 	scoring_system: Option<ScoringSystem>,
+	format: Option<OutputFormat>,
+	pack: Option<String>,
+	side: Option<Side>,
+	dual: Option<bool>,
 ) -> Result<(), TaggedError> {
 	let user = User::from_context(ctx)?;
-	best_plays(ctx, &user, scoring_system.unwrap_or_default(), (5, 6), true).await?;
+	best_plays(
+		ctx,
+		&user,
+		scoring_system.unwrap_or_default(),
+		(5, 6),
+		true,
+		format.unwrap_or_default(),
+		&PlayFilter {
+			pack,
+			side,
+			..PlayFilter::b30()
+		},
+		PlayOrder::Best,
+		dual.unwrap_or(false),
+	)
+	.await?;
 	Ok(())
 }
 // }}}
 // {{{ Discord wrapper
 /// Show the 30 best scores
 #[poise::command(prefix_command, slash_command, user_cooldown = 30)]
-pub async fn b30(mut ctx: Context<'_>, scoring_system: Option<ScoringSystem>) -> Result<(), Error> {
-	let res = b30_impl(&mut ctx, scoring_system).await;
+pub async fn b30(
+	mut ctx: Context<'_>,
+	scoring_system: Option<ScoringSystem>,
+	#[description = "Image format to render the scores as"] format: Option<OutputFormat>,
+	#[description = "Only count songs from this pack"] pack: Option<String>,
+	#[description = "Only count songs from this side"] side: Option<Side>,
+	#[description = "Also show scores under a second scoring system"] dual: Option<bool>,
+) -> Result<(), Error> {
+	let res = b30_impl(&mut ctx, scoring_system, format, pack, side, dual).await;
+	ctx.handle_error(res).await?;
+	Ok(())
+}
+// }}}
+// }}}
+// {{{ Worst 30
+// {{{ Implementation
+pub async fn worst30_impl<C: MessageContext>(
+	ctx: &mut C,
+	scoring_system: Option<ScoringSystem>,
+	format: Option<OutputFormat>,
+) -> Result<(), TaggedError> {
+	let user = User::from_context(ctx)?;
+	best_plays(
+		ctx,
+		&user,
+		scoring_system.unwrap_or_default(),
+		(5, 6),
+		true,
+		format.unwrap_or_default(),
+		&PlayFilter {
+			cleared_only: true,
+			..Default::default()
+		},
+		PlayOrder::Worst,
+		false,
+	)
+	.await?;
+	Ok(())
+}
+// }}}
+// {{{ Discord wrapper
+/// Show the 30 lowest rated scores (one per cleared chart)
+#[poise::command(prefix_command, slash_command, user_cooldown = 30)]
+pub async fn worst30(
+	mut ctx: Context<'_>,
+	scoring_system: Option<ScoringSystem>,
+	#[description = "Image format to render the scores as"] format: Option<OutputFormat>,
+) -> Result<(), Error> {
+	let res = worst30_impl(&mut ctx, scoring_system, format).await;
 	ctx.handle_error(res).await?;
 	Ok(())
 }
@@ -443,6 +768,7 @@ async fn bany_impl<C: MessageContext>(
 	scoring_system: Option<ScoringSystem>,
 	width: u32,
 	height: u32,
+	format: Option<OutputFormat>,
 ) -> Result<(), TaggedError> {
 	let user = User::from_context(ctx)?;
 	user.assert_is_pookie()?;
@@ -452,6 +778,10 @@ async fn bany_impl<C: MessageContext>(
 		scoring_system.unwrap_or_default(),
 		(width, height),
 		false,
+		format.unwrap_or_default(),
+		&PlayFilter::b30(),
+		PlayOrder::Best,
+		false,
 	)
 	.await?;
 
@@ -465,8 +795,9 @@ pub async fn bany(
 	scoring_system: Option<ScoringSystem>,
 	width: u32,
 	height: u32,
+	#[description = "Image format to render the scores as"] format: Option<OutputFormat>,
 ) -> Result<(), Error> {
-	let res = bany_impl(&mut ctx, scoring_system, width, height).await;
+	let res = bany_impl(&mut ctx, scoring_system, width, height, format).await;
 	ctx.handle_error(res).await?;
 	Ok(())
 }
@@ -545,3 +876,983 @@ async fn meta(mut ctx: Context<'_>) -> Result<(), Error> {
 }
 // }}}
 // }}}
+// {{{ Missing
+// {{{ Implementation
+/// How many charts to list in a single embed.
+const MISSING_PAGE_SIZE: usize = 20;
+
+async fn missing_impl<C: MessageContext>(
+	ctx: &mut C,
+	level: Level,
+	difficulty: Option<Difficulty>,
+) -> Result<Vec<u32>, TaggedError> {
+	let user = User::from_context(ctx)?;
+
+	let played: Vec<u32> = ctx
+		.data()
+		.db
+		.get()?
+		.prepare_cached("SELECT DISTINCT chart_id FROM plays WHERE user_id=?")?
+		.query_map([user.id], |row| row.get(0))?
+		.collect::<Result<_, _>>()?;
+
+	let song_cache = &ctx.data().song_cache;
+	let mut missing = Vec::new();
+	for chart in song_cache.charts() {
+		if chart.level != level
+			|| difficulty.map_or(false, |d| d != chart.difficulty)
+			|| played.contains(&chart.id)
+		{
+			continue;
+		}
+
+		let song = &song_cache.lookup_song(chart.song_id)?.song;
+		missing.push((song, chart));
+	}
+
+	missing.sort_by(|(song_a, chart_a), (song_b, chart_b)| {
+		(&song_a.lowercase_title, chart_a.difficulty)
+			.cmp(&(&song_b.lowercase_title, chart_b.difficulty))
+	});
+
+	if missing.is_empty() {
+		ctx.reply(&format!("You have played every chart of level {level}!"))
+			.await?;
+		return Ok(Vec::new());
+	}
+
+	let chart_ids: Vec<u32> = missing.iter().map(|(_, chart)| chart.id).collect();
+	let page_count = missing.len().div_ceil(MISSING_PAGE_SIZE);
+	let embeds = missing
+		.chunks(MISSING_PAGE_SIZE)
+		.enumerate()
+		.map(|(i, page)| {
+			let description = page
+				.iter()
+				.map(|(song, chart)| format!("- {} [{}]", song, chart.difficulty))
+				.collect::<Vec<_>>()
+				.join("\n");
+
+			CreateEmbed::default()
+				.title(format!(
+					"Missing level {level} charts ({}/{})",
+					i + 1,
+					page_count
+				))
+				.description(description)
+		})
+		.collect::<Vec<_>>();
+
+	for embeds in embeds.chunks(MAX_EMBEDS_PER_MESSAGE) {
+		ctx.send(
+			CreateReply::default()
+				.reply(true)
+				.content(format!("{} chart(s) left to play", chart_ids.len()))
+				.embeds(embeds.iter().cloned()),
+		)
+		.await?;
+	}
+
+	Ok(chart_ids)
+}
+// }}}
+// {{{ Tests
+#[cfg(test)]
+mod missing_tests {
+	use crate::{commands::discord::mock::MockContext, golden_test};
+
+	use super::*;
+
+	golden_test!(no_plays, "commands/stats/missing/no_plays");
+	async fn no_plays(ctx: &mut MockContext) -> Result<(), TaggedError> {
+		let all = missing_impl(ctx, Level::Eleven, None).await?;
+		let ftr = missing_impl(ctx, Level::Eleven, Some(Difficulty::FTR)).await?;
+
+		assert!(!all.is_empty());
+		assert!(ftr.len() <= all.len());
+		Ok(())
+	}
+}
+// }}}
+// {{{ Discord wrapper
+/// List the charts of a given level you have not played yet.
+#[poise::command(prefix_command, slash_command, user_cooldown = 1)]
+async fn missing(
+	mut ctx: Context<'_>,
+	#[description = "Level to list the charts of (e.g. 9+)"] level: String,
+	#[description = "Only list charts of this difficulty"] difficulty: Option<String>,
+) -> Result<(), Error> {
+	let res = async {
+		let level = Level::from_str(&level).map_err(|e| e.tag(ErrorKind::User))?;
+		let difficulty = difficulty
+			.as_deref()
+			.map(Difficulty::from_str)
+			.transpose()
+			.map_err(|e| e.tag(ErrorKind::User))?;
+
+		missing_impl(&mut ctx, level, difficulty).await
+	}
+	.await;
+	ctx.handle_error(res).await?;
+
+	Ok(())
+}
+// }}}
+// }}}
+// {{{ Grade goals
+// {{{ Implementation
+/// How many of the charts holding a level back to list at most.
+const GRADE_GOALS_LIST_SIZE: usize = 15;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct GradeGoalProgress {
+	/// Charts of the given level the user has played at least once.
+	owned: usize,
+	lowest: Grade,
+	ex: usize,
+	exp: usize,
+	/// Charts below EX+, worst first.
+	lagging: Vec<u32>,
+}
+
+/// Describes how close the user is to getting a grade on an entire level.
+fn display_grade_goal(count: usize, owned: usize, min_owned: Option<usize>) -> String {
+	let progress = format!("{count}/{owned} charts");
+	match min_owned {
+		None => format!("{progress} (no achievement for this level)"),
+		Some(min) if owned < min => format!("{progress} (own at least {min} to qualify)"),
+		Some(_) if count == owned => format!("{progress} (complete!)"),
+		Some(_) => format!("{progress} ({} to go)", owned - count),
+	}
+}
+
+/// Shows the progress of the current user towards the
+/// [crate::arcaea::achievement::Goal::GradeEntireLevel] achievements of a
+/// given level, listing the charts that keep them from completing it.
+async fn grade_goals_impl<C: MessageContext>(
+	ctx: &mut C,
+	level: Level,
+	scoring_system: Option<ScoringSystem>,
+) -> Result<GradeGoalProgress, TaggedError> {
+	let user = User::from_context(ctx)?;
+	let scoring_system = scoring_system.unwrap_or_default();
+	let plays = get_best_plays(ctx.data(), user.id, scoring_system, 0, usize::MAX, None)?;
+
+	let (lowest, owned) = compute_per_level_lowest_grades(&plays, scoring_system)[level.to_index()];
+	if owned == 0 {
+		return Err(
+			anyhow!("You have not played any level {level} charts yet").tag(ErrorKind::User)
+		);
+	}
+
+	let mut level_plays: Vec<_> = plays
+		.iter()
+		.filter(|(_, _, chart)| chart.level == level)
+		.collect();
+	level_plays.sort_by_key(|(play, _, chart)| (play.score(scoring_system), chart.id));
+
+	let count_at_least = |grade: Grade| {
+		level_plays
+			.iter()
+			.filter(|(play, _, _)| play.score(scoring_system).grade() >= grade)
+			.count()
+	};
+
+	let ex = count_at_least(Grade::EX);
+	let exp = count_at_least(Grade::EXP);
+	let lagging: Vec<_> = level_plays
+		.iter()
+		.take_while(|(play, _, _)| play.score(scoring_system).grade() < Grade::EXP)
+		.collect();
+
+	let min_owned = GRADE_LEVEL_MINIMUMS
+		.iter()
+		.find(|(l, _)| *l == level)
+		.map(|(_, min)| *min);
+
+	let mut embed = CreateEmbed::default()
+		.title(format!("Level {level} grade goals"))
+		.description(format!(
+			"Lowest grade: {lowest} across {owned} owned chart(s)"
+		))
+		.field("EX", display_grade_goal(ex, owned, min_owned), true)
+		.field("EX+", display_grade_goal(exp, owned, min_owned), true);
+
+	if !lagging.is_empty() {
+		let mut list = lagging
+			.iter()
+			.take(GRADE_GOALS_LIST_SIZE)
+			.map(|(play, song, chart)| {
+				let score = play.score(scoring_system);
+				format!(
+					"- {} [{}] {} ({})",
+					song,
+					chart.difficulty,
+					score,
+					score.grade()
+				)
+			})
+			.collect::<Vec<_>>()
+			.join("\n");
+
+		if lagging.len() > GRADE_GOALS_LIST_SIZE {
+			list += &format!("\n...and {} more", lagging.len() - GRADE_GOALS_LIST_SIZE);
+		}
+
+		embed = embed.field("Holding you back", list, false);
+	}
+
+	// The songs are borrowed from the context, so we must let go of them
+	// before sending anything.
+	let progress = GradeGoalProgress {
+		owned,
+		lowest,
+		ex,
+		exp,
+		lagging: lagging.iter().map(|(_, _, chart)| chart.id).collect(),
+	};
+
+	ctx.send(CreateReply::default().reply(true).embed(embed))
+		.await?;
+
+	Ok(progress)
+}
+// }}}
+// {{{ Tests
+#[cfg(test)]
+mod grade_goals_tests {
+	use std::path::PathBuf;
+
+	use crate::{
+		commands::{discord::mock::MockContext, score::magic_impl},
+		golden_test, with_test_ctx,
+	};
+
+	use super::*;
+
+	#[test]
+	fn goal_descriptions() {
+		assert_eq!(
+			display_grade_goal(1, 1, None),
+			"1/1 charts (no achievement for this level)"
+		);
+		assert_eq!(
+			display_grade_goal(1, 1, Some(5)),
+			"1/1 charts (own at least 5 to qualify)"
+		);
+		assert_eq!(display_grade_goal(5, 5, Some(5)), "5/5 charts (complete!)");
+		assert_eq!(display_grade_goal(3, 5, Some(5)), "3/5 charts (2 to go)");
+	}
+
+	#[tokio::test]
+	async fn no_plays() -> Result<(), Error> {
+		with_test_ctx!("commands/stats/grade_goals/no_plays", |ctx| async move {
+			grade_goals_impl(ctx, Level::Ten, None).await?;
+			Ok(())
+		})
+	}
+
+	golden_test!(lagging_charts, "commands/stats/grade_goals/lagging_charts");
+	async fn lagging_charts(ctx: &mut MockContext) -> Result<(), TaggedError> {
+		let plays =
+			magic_impl(ctx, &[PathBuf::from_str("test/screenshots/alter_ego.jpg")?]).await?;
+
+		// ALTER EGO [FTR] is a level 10 chart with an EX+ standard score...
+		let progress = grade_goals_impl(ctx, Level::Ten, None).await?;
+		assert_eq!(progress.owned, 1);
+		assert_eq!(progress.lowest, Grade::EXP);
+		assert_eq!((progress.ex, progress.exp), (1, 1));
+		assert!(progress.lagging.is_empty());
+
+		// ...but only an AA ξ-score.
+		let progress = grade_goals_impl(ctx, Level::Ten, Some(ScoringSystem::EX)).await?;
+		assert_eq!(progress.lowest, Grade::AA);
+		assert_eq!((progress.ex, progress.exp), (0, 0));
+		assert_eq!(progress.lagging, vec![plays[0].chart_id]);
+
+		Ok(())
+	}
+}
+// }}}
+// {{{ Discord wrapper
+/// Show your progress towards getting EX/EX+ on every chart of a level.
+#[poise::command(
+	prefix_command,
+	slash_command,
+	user_cooldown = 1,
+	rename = "grade-goals"
+)]
+async fn grade_goals(
+	mut ctx: Context<'_>,
+	#[description = "Level to check the grades of (e.g. 9+)"] level: String,
+	scoring_system: Option<ScoringSystem>,
+) -> Result<(), Error> {
+	let res = async {
+		let level = Level::from_str(&level).map_err(|e| e.tag(ErrorKind::User))?;
+		grade_goals_impl(&mut ctx, level, scoring_system).await
+	}
+	.await;
+	ctx.handle_error(res).await?;
+
+	Ok(())
+}
+// }}}
+// }}}
+// {{{ Recent
+// {{{ Implementation
+/// How many plays `stats recent` shows by default.
+const RECENT_DEFAULT_COUNT: usize = 10;
+
+/// How many plays `stats recent` shows at most.
+const RECENT_MAX_COUNT: usize = 25;
+
+/// Formats a difference between two (fixed point) ptt values, e.g. `+0.03`.
+fn display_ptt_delta(delta: i32) -> String {
+	let sign = if delta < 0 { '-' } else { '+' };
+	let delta = delta.unsigned_abs();
+	format!("{sign}{}.{:0>2}", delta / 100, delta % 100)
+}
+
+/// Lists the latest plays of the current user (most recent first), alongside
+/// the change each of them caused to the user's b30 ptt.
+async fn recent_impl<C: MessageContext>(
+	ctx: &mut C,
+	count: Option<usize>,
+) -> Result<Vec<Play>, TaggedError> {
+	let user = User::from_context(ctx)?;
+	let count = count
+		.unwrap_or(RECENT_DEFAULT_COUNT)
+		.clamp(1, RECENT_MAX_COUNT);
+
+	// We fetch one extra play, such that the oldest row we show still has a
+	// ptt snapshot to diff against.
+	let plays = ctx
+		.data()
+		.db
+		.get()?
+		.prepare_cached(
+			"
+      SELECT 
+        p.id, p.chart_id, p.user_id, p.created_at,
+        p.max_recall, p.far_notes, p.pure_notes, p.lost_notes, p.clear_kind,
+        s.score, s.creation_ptt
+      FROM plays p
+      JOIN scores s ON s.play_id = p.id
+      WHERE s.scoring_system='standard'
+      AND p.user_id=?
+      ORDER BY p.created_at DESC, p.id DESC
+      LIMIT ?
+    ",
+		)?
+		.query_and_then((user.id, count + 1), |row| -> Result<_, Error> {
+			let (song, chart) = ctx.data().song_cache.lookup_chart(row.get("chart_id")?)?;
+			let creation_ptt: Option<i32> = row.get("creation_ptt")?;
+			Ok((Play::from_sql(chart, row)?, song, chart, creation_ptt))
+		})?
+		.collect::<Result<Vec<_>, _>>()?;
+
+	if plays.is_empty() {
+		return Err(anyhow!("You have no plays yet").tag(ErrorKind::User));
+	}
+
+	let mut description = String::new();
+	for (i, (play, song, chart, creation_ptt)) in plays.iter().enumerate().take(count) {
+		// No snapshot gets stored until the user has at least 30 plays
+		let prev_ptt = plays.get(i + 1).and_then(|(_, _, _, ptt)| *ptt);
+		let delta = match (creation_ptt, prev_ptt) {
+			(Some(ptt), Some(prev_ptt)) => display_ptt_delta(ptt - prev_ptt),
+			_ => "-".to_string(),
+		};
+
+		description += &format!(
+			"`{}` {} [{}] {} ({delta})\n",
+			play.created_at.format("%Y-%m-%d %H:%M"),
+			song.title,
+			chart.difficulty,
+			play.score(ScoringSystem::Standard)
+		);
+	}
+
+	// The songs are borrowed from the context, so we must let go of them
+	// before sending anything.
+	let plays = plays
+		.into_iter()
+		.take(count)
+		.map(|(play, _, _, _)| play)
+		.collect();
+
+	let embed = CreateEmbed::default()
+		.title("Recent plays")
+		.description(description);
+
+	ctx.send(CreateReply::default().reply(true).embed(embed))
+		.await?;
+
+	Ok(plays)
+}
+// }}}
+// {{{ Tests
+#[cfg(test)]
+mod recent_tests {
+	use std::path::PathBuf;
+
+	use crate::{
+		commands::{discord::mock::MockContext, score::magic_impl},
+		golden_test, with_test_ctx,
+	};
+
+	use super::*;
+
+	#[test]
+	fn ptt_deltas_are_signed() {
+		assert_eq!(display_ptt_delta(3), "+0.03");
+		assert_eq!(display_ptt_delta(0), "+0.00");
+		assert_eq!(display_ptt_delta(-125), "-1.25");
+	}
+
+	#[tokio::test]
+	async fn no_plays() -> Result<(), Error> {
+		with_test_ctx!("commands/stats/recent/no_plays", |ctx| async move {
+			recent_impl(ctx, None).await?;
+			Ok(())
+		})
+	}
+
+	golden_test!(most_recent_first, "commands/stats/recent/most_recent_first");
+	async fn most_recent_first(ctx: &mut MockContext) -> Result<(), TaggedError> {
+		let plays = magic_impl(
+			ctx,
+			&[
+				PathBuf::from_str("test/screenshots/fracture_ray_ex.jpg")?,
+				PathBuf::from_str("test/screenshots/alter_ego.jpg")?,
+			],
+		)
+		.await?;
+
+		let recent = recent_impl(ctx, None).await?;
+		assert_eq!(
+			recent.iter().map(|play| play.id).collect::<Vec<_>>(),
+			vec![plays[1].id, plays[0].id]
+		);
+
+		let recent = recent_impl(ctx, Some(1)).await?;
+		assert_eq!(recent.len(), 1);
+		assert_eq!(recent[0].id, plays[1].id);
+
+		Ok(())
+	}
+}
+// }}}
+// {{{ Discord wrapper
+/// List your latest plays, and how they affected your ptt.
+#[poise::command(prefix_command, slash_command, user_cooldown = 1)]
+async fn recent(
+	mut ctx: Context<'_>,
+	#[description = "How many plays to show (defaults to 10, at most 25)"] count: Option<usize>,
+) -> Result<(), Error> {
+	let res = recent_impl(&mut ctx, count).await;
+	ctx.handle_error(res).await?;
+
+	Ok(())
+}
+// }}}
+// }}}
+// {{{ Packs
+// {{{ Implementation
+/// How many packs to list in a single embed.
+const PACKS_PAGE_SIZE: usize = 20;
+
+/// Songs without a pack are listed under this name.
+const BASE_GAME_PACK: &str = "Base game";
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct PackCompletion {
+	pack: String,
+	total: u32,
+	/// Charts the user has at least one non-track-lost play on.
+	cleared: u32,
+	pms: u32,
+}
+
+/// Formats `part` as a percentage of `total`, truncated to a whole number.
+fn display_fraction(part: u32, total: u32) -> String {
+	format!("{}/{} ({}%)", part, total, part * 100 / total.max(1))
+}
+
+/// Lists every pack alongside the fraction of its charts the current user has
+/// cleared and PM'd, closest to full PM first.
+async fn packs_impl<C: MessageContext>(ctx: &mut C) -> Result<Vec<PackCompletion>, TaggedError> {
+	let user = User::from_context(ctx)?;
+
+	let mut packs = ctx
+		.data()
+		.db
+		.get()?
+		.prepare_cached(
+			"
+      SELECT
+        COALESCE(s.pack, ?2) as pack,
+        COUNT(*) as total,
+        COUNT(CASE WHEN best.has_clear THEN 1 END) as cleared,
+        COUNT(CASE WHEN best.score >= 10000000 THEN 1 END) as pms
+      FROM charts c
+      JOIN songs s ON s.id = c.song_id
+      LEFT JOIN (
+        SELECT
+          p.chart_id,
+          MAX(sc.score) as score,
+          MAX(p.clear_kind IS NULL OR p.clear_kind != ?3) as has_clear
+        FROM plays p
+        JOIN scores sc ON sc.play_id = p.id
+        WHERE sc.scoring_system='standard'
+        AND p.user_id=?1
+        GROUP BY p.chart_id
+      ) best ON best.chart_id = c.id
+      GROUP BY COALESCE(s.pack, ?2)
+    ",
+		)?
+		.query_map(
+			(
+				user.id,
+				BASE_GAME_PACK,
+				ClearKind::CLEAR_KIND_DB_STRINGS[ClearKind::TrackLost.to_index()],
+			),
+			|row| {
+				Ok(PackCompletion {
+					pack: row.get("pack")?,
+					total: row.get("total")?,
+					cleared: row.get("cleared")?,
+					pms: row.get("pms")?,
+				})
+			},
+		)?
+		.collect::<Result<Vec<_>, _>>()?;
+
+	// Cross-multiplying compares the fractions without any rounding
+	packs.sort_by(|a, b| {
+		(b.pms * a.total)
+			.cmp(&(a.pms * b.total))
+			.then((b.cleared * a.total).cmp(&(a.cleared * b.total)))
+			.then_with(|| a.pack.cmp(&b.pack))
+	});
+
+	if packs.is_empty() {
+		return Err(anyhow!("There are no packs in my database").tag(ErrorKind::User));
+	}
+
+	let name_width = packs
+		.iter()
+		.map(|p| p.pack.chars().count())
+		.max()
+		.unwrap_or(0);
+
+	let page_count = packs.len().div_ceil(PACKS_PAGE_SIZE);
+	let embeds = packs
+		.chunks(PACKS_PAGE_SIZE)
+		.enumerate()
+		.map(|(i, page)| {
+			let rows = page
+				.iter()
+				.map(|p| {
+					format!(
+						"{:<name_width$}  {:<16}  {}",
+						p.pack,
+						display_fraction(p.cleared, p.total),
+						display_fraction(p.pms, p.total),
+					)
+				})
+				.collect::<Vec<_>>()
+				.join("\n");
+
+			CreateEmbed::default()
+				.title(format!("Pack completion ({}/{})", i + 1, page_count))
+				.description(format!(
+					"```\n{:<name_width$}  {:<16}  PM'd\n{rows}\n```",
+					"Pack", "Cleared"
+				))
+		})
+		.collect::<Vec<_>>();
+
+	for embeds in embeds.chunks(MAX_EMBEDS_PER_MESSAGE) {
+		ctx.send(
+			CreateReply::default()
+				.reply(true)
+				.embeds(embeds.iter().cloned()),
+		)
+		.await?;
+	}
+
+	Ok(packs)
+}
+// }}}
+// {{{ Tests
+#[cfg(test)]
+mod packs_tests {
+	use std::path::PathBuf;
+
+	use crate::{
+		commands::{discord::mock::MockContext, score::magic_impl},
+		context::testing::get_mock_context,
+		golden_test,
+	};
+
+	use super::*;
+
+	#[test]
+	fn fractions_are_truncated() {
+		assert_eq!(display_fraction(2, 3), "2/3 (66%)");
+		assert_eq!(display_fraction(0, 0), "0/0 (0%)");
+		assert_eq!(display_fraction(5, 5), "5/5 (100%)");
+	}
+
+	golden_test!(no_plays, "commands/stats/packs/no_plays");
+	async fn no_plays(ctx: &mut MockContext) -> Result<(), TaggedError> {
+		let packs = packs_impl(ctx).await?;
+		assert!(!packs.is_empty());
+		assert!(packs.iter().all(|p| p.cleared == 0 && p.pms == 0));
+		Ok(())
+	}
+
+	golden_test!(single_play, "commands/stats/packs/single_play");
+	async fn single_play(ctx: &mut MockContext) -> Result<(), TaggedError> {
+		magic_impl(ctx, &[PathBuf::from_str("test/screenshots/alter_ego.jpg")?]).await?;
+
+		let packs = packs_impl(ctx).await?;
+		assert_eq!(packs.iter().map(|p| p.cleared).sum::<u32>(), 1);
+
+		// Cleared packs get sorted before uncleared ones
+		assert_eq!(packs[0].cleared, 1);
+		Ok(())
+	}
+
+	#[tokio::test]
+	async fn track_losts_are_not_clears() -> Result<(), Error> {
+		let (mut ctx, _guard) = get_mock_context().await?;
+		User::create_from_context(&ctx).map_err(|e| e.error)?;
+		magic_impl(
+			&mut ctx,
+			&[PathBuf::from_str("test/screenshots/alter_ego.jpg")?],
+		)
+		.await
+		.map_err(|e| e.error)?;
+
+		ctx.data.db.get()?.execute(
+			"UPDATE plays SET clear_kind=?",
+			[ClearKind::CLEAR_KIND_DB_STRINGS[ClearKind::TrackLost.to_index()]],
+		)?;
+
+		let packs = packs_impl(&mut ctx).await.map_err(|e| e.error)?;
+		assert!(packs.iter().all(|p| p.cleared == 0));
+		Ok(())
+	}
+}
+// }}}
+// {{{ Discord wrapper
+/// Show how much of every pack you have cleared and PM'd.
+#[poise::command(prefix_command, slash_command, user_cooldown = 1)]
+async fn packs(mut ctx: Context<'_>) -> Result<(), Error> {
+	let res = packs_impl(&mut ctx).await;
+	ctx.handle_error(res).await?;
+
+	Ok(())
+}
+// }}}
+// }}}
+// {{{ Compare systems
+// {{{ Implementation
+/// The b30 of a user under a single scoring system.
+#[derive(Debug, Clone, Copy)]
+struct SystemComparison {
+	system: ScoringSystem,
+	ptt: Rating,
+	/// How many charts in this b30 are missing from the standard b30.
+	shifted: usize,
+}
+
+/// Computes the b30 ptt of the current user under every scoring system.
+async fn compare_systems_impl<C: MessageContext>(
+	ctx: &mut C,
+) -> Result<Vec<SystemComparison>, TaggedError> {
+	let user = User::from_context(ctx)?;
+	let mut b30s = Vec::with_capacity(ScoringSystem::SCORING_SYSTEMS.len());
+	for system in ScoringSystem::SCORING_SYSTEMS {
+		b30s.push(get_b30_plays(ctx.data(), user.id, system, 0, 30, None)?);
+	}
+
+	if b30s[0].is_empty() {
+		return Err(anyhow!("You have no plays yet").tag(ErrorKind::User));
+	}
+
+	let standard = &b30s[ScoringSystem::Standard.to_index()];
+	let comparisons: Vec<_> = ScoringSystem::SCORING_SYSTEMS
+		.iter()
+		.zip(&b30s)
+		.map(|(system, plays)| SystemComparison {
+			system: *system,
+			ptt: compute_b30_ptt(*system, plays),
+			shifted: plays
+				.iter()
+				.filter(|(_, _, chart)| !standard.iter().any(|(_, _, c)| c.id == chart.id))
+				.count(),
+		})
+		.collect();
+
+	let standard_ptt = comparisons[0].ptt;
+	let mut embed = CreateEmbed::default()
+		.title("Potential across scoring systems")
+		.description(
+			"SDF and EX reward shiny accuracy, so the further they fall behind your standard potential, the more shinies you are leaving on the table.",
+		);
+
+	for comparison in &comparisons {
+		let mut value = format!("**{}**", RatingDisplay(comparison.ptt));
+		if comparison.system.to_index() != ScoringSystem::Standard.to_index() {
+			value += &format!(
+				" ({} vs Standard, {} chart{} shifted)",
+				RatingDisplay(comparison.ptt - standard_ptt),
+				comparison.shifted,
+				if comparison.shifted == 1 { "" } else { "s" }
+			);
+		}
+
+		embed = embed.field(
+			ScoringSystem::SCORING_SYSTEM_STRINGS[comparison.system.to_index()],
+			value,
+			false,
+		);
+	}
+
+	ctx.send(CreateReply::default().reply(true).embed(embed))
+		.await?;
+
+	Ok(comparisons)
+}
+// }}}
+// {{{ Tests
+#[cfg(test)]
+mod compare_systems_tests {
+	use std::path::PathBuf;
+
+	use crate::{
+		commands::{discord::mock::MockContext, score::magic_impl},
+		golden_test, with_test_ctx,
+	};
+
+	use super::*;
+
+	#[tokio::test]
+	async fn no_plays() -> Result<(), Error> {
+		with_test_ctx!(
+			"commands/stats/compare_systems/no_plays",
+			|ctx| async move {
+				compare_systems_impl(ctx).await?;
+				Ok(())
+			}
+		)
+	}
+
+	golden_test!(few_plays, "commands/stats/compare_systems/few_plays");
+	async fn few_plays(ctx: &mut MockContext) -> Result<(), TaggedError> {
+		magic_impl(
+			ctx,
+			&[
+				PathBuf::from_str("test/screenshots/alter_ego.jpg")?,
+				PathBuf::from_str("test/screenshots/fracture_ray_ex.jpg")?,
+			],
+		)
+		.await?;
+
+		let comparisons = compare_systems_impl(ctx).await?;
+		assert_eq!(comparisons.len(), ScoringSystem::SCORING_SYSTEMS.len());
+
+		// With only two plays, every system counts the same charts
+		assert!(comparisons.iter().all(|c| c.shifted == 0));
+
+		// EX scores never exceed standard scores
+		let standard = comparisons[ScoringSystem::Standard.to_index()].ptt;
+		assert!(comparisons[ScoringSystem::EX.to_index()].ptt <= standard);
+
+		Ok(())
+	}
+}
+// }}}
+// {{{ Discord wrapper
+/// Compare your potential under every scoring system.
+#[poise::command(
+	prefix_command,
+	slash_command,
+	rename = "compare-systems",
+	user_cooldown = 5
+)]
+async fn compare_systems(mut ctx: Context<'_>) -> Result<(), Error> {
+	let res = compare_systems_impl(&mut ctx).await;
+	ctx.handle_error(res).await?;
+
+	Ok(())
+}
+// }}}
+// }}}
+// {{{ Exclude
+// {{{ Implementation
+/// Stops a chart from counting towards the b30 of the current user. Plays on
+/// the chart are kept, and still show up in the play history.
+async fn exclude_impl<C: MessageContext>(ctx: &mut C, name: &str) -> Result<(), TaggedError> {
+	let user = User::from_context(ctx)?;
+	let (song, chart) = guess_song_and_chart(ctx.data(), name)?;
+
+	let inserted = ctx
+		.data()
+		.db
+		.get()?
+		.prepare_cached("INSERT OR IGNORE INTO b30_exclusions(user_id, chart_id) VALUES (?,?)")?
+		.execute((user.id, chart.id))?;
+
+	if inserted == 0 {
+		return Err(anyhow!(
+			"{} [{}] is already excluded from your b30",
+			song,
+			chart.difficulty
+		)
+		.tag(ErrorKind::User));
+	}
+
+	ctx.data().best_plays_cache.clear();
+	ctx.reply(&format!(
+		"{} [{}] no longer counts towards your b30",
+		song, chart.difficulty
+	))
+	.await?;
+
+	Ok(())
+}
+// }}}
+// {{{ Discord wrapper
+/// Stop a chart from counting towards your b30 (e.g. a removed chart).
+#[poise::command(prefix_command, slash_command, user_cooldown = 1)]
+async fn exclude(
+	mut ctx: Context<'_>,
+	#[rest]
+	#[description = "Name of chart (difficulty at the end)"]
+	name: String,
+) -> Result<(), Error> {
+	let res = exclude_impl(&mut ctx, &name).await;
+	ctx.handle_error(res).await?;
+
+	Ok(())
+}
+// }}}
+// }}}
+// {{{ Include
+// {{{ Implementation
+/// Undoes [exclude_impl].
+async fn include_impl<C: MessageContext>(ctx: &mut C, name: &str) -> Result<(), TaggedError> {
+	let user = User::from_context(ctx)?;
+	let (song, chart) = guess_song_and_chart(ctx.data(), name)?;
+
+	let removed = ctx
+		.data()
+		.db
+		.get()?
+		.prepare_cached("DELETE FROM b30_exclusions WHERE user_id=? AND chart_id=?")?
+		.execute((user.id, chart.id))?;
+
+	if removed == 0 {
+		return Err(anyhow!(
+			"{} [{}] is not excluded from your b30",
+			song,
+			chart.difficulty
+		)
+		.tag(ErrorKind::User));
+	}
+
+	ctx.data().best_plays_cache.clear();
+	ctx.reply(&format!(
+		"{} [{}] counts towards your b30 again",
+		song, chart.difficulty
+	))
+	.await?;
+
+	Ok(())
+}
+// }}}
+// {{{ Tests
+#[cfg(test)]
+mod exclusion_tests {
+	use std::{path::PathBuf, str::FromStr};
+
+	use crate::{
+		commands::{discord::mock::MockContext, score::magic_impl},
+		golden_test,
+	};
+
+	use super::*;
+
+	golden_test!(
+		exclude_then_include,
+		"commands/stats/exclusions/exclude_then_include"
+	);
+	async fn exclude_then_include(ctx: &mut MockContext) -> Result<(), TaggedError> {
+		magic_impl(
+			ctx,
+			&[
+				PathBuf::from_str("test/screenshots/alter_ego.jpg")?,
+				PathBuf::from_str("test/screenshots/fracture_ray_ex.jpg")?,
+			],
+		)
+		.await?;
+
+		let user = User::from_context(ctx)?;
+		let best = |ctx: &MockContext| {
+			get_b30_plays(&ctx.data, user.id, ScoringSystem::Standard, 0, 30, None)
+				.map(|plays| plays.iter().map(|(_, _, c)| c.id).collect::<Vec<_>>())
+		};
+
+		let before = best(ctx)?;
+		assert_eq!(before.len(), 2);
+
+		let chart_id = before[0];
+		let (song, chart) = ctx.data.song_cache.lookup_chart(chart_id)?;
+		let name = format!("{} [{}]", song.title, chart.difficulty);
+
+		exclude_impl(ctx, &name).await?;
+		assert!(!best(ctx)?.contains(&chart_id));
+		assert_eq!(count_b30_exclusions(&ctx.data.db.get()?, user.id)?, 1);
+
+		// Excluding the same chart twice is a user error
+		assert!(exclude_impl(ctx, &name).await.is_err());
+
+		// The play history is left untouched
+		let all = get_best_plays(&ctx.data, user.id, ScoringSystem::Standard, 0, 30, None)?;
+		assert!(all.iter().any(|(_, _, chart)| chart.id == chart_id));
+
+		let play_count: usize =
+			ctx.data
+				.db
+				.get()?
+				.query_row("SELECT COUNT(*) FROM plays", (), |row| row.get(0))?;
+		assert_eq!(play_count, 2);
+
+		include_impl(ctx, &name).await?;
+		assert_eq!(best(ctx)?, before);
+		assert!(include_impl(ctx, &name).await.is_err());
+
+		Ok(())
+	}
+}
+// }}}
+// {{{ Discord wrapper
+/// Make a previously excluded chart count towards your b30 again.
+#[poise::command(prefix_command, slash_command, user_cooldown = 1)]
+async fn include(
+	mut ctx: Context<'_>,
+	#[rest]
+	#[description = "Name of chart (difficulty at the end)"]
+	name: String,
+) -> Result<(), Error> {
+	let res = include_impl(&mut ctx, &name).await;
+	ctx.handle_error(res).await?;
+
+	Ok(())
+}
+// }}}
+// }}}
@@ -0,0 +1,194 @@
+// {{{ Imports
+use anyhow::anyhow;
+use poise::serenity_prelude::CreateEmbed;
+use poise::CreateReply;
+
+use crate::context::{Context, Error, ErrorKind, TagError, TaggedError};
+use crate::user::User;
+
+use super::discord::MessageContext;
+// }}}
+
+// {{{ Top command
+/// User-related utilities.
+#[poise::command(
+	prefix_command,
+	slash_command,
+	subcommands("whoami", "delete_all"),
+	subcommand_required
+)]
+pub async fn user(_ctx: Context<'_>) -> Result<(), Error> {
+	Ok(())
+}
+// }}}
+// {{{ Whoami
+// {{{ Implementation
+/// Shows the account state of the current user, such that they know which id
+/// to mention when reporting problems.
+async fn whoami_impl<C: MessageContext>(ctx: &mut C) -> Result<User, TaggedError> {
+	let user = User::from_context(ctx)?;
+
+	let play_count: u32 = ctx
+		.data()
+		.db
+		.get()?
+		.prepare_cached("SELECT COUNT(*) FROM plays WHERE user_id=?")?
+		.query_row([user.id], |row| row.get(0))?;
+
+	let embed = CreateEmbed::default()
+		.title("Account")
+		.field("Id", format!("{}", user.id), true)
+		.field("Discord id", &user.discord_id, true)
+		.field("Plays", format!("{play_count}"), true)
+		.field("Pookie", if user.is_pookie { "yes" } else { "no" }, true);
+
+	ctx.send(CreateReply::default().reply(true).embed(embed))
+		.await?;
+
+	Ok(user)
+}
+// }}}
+// {{{ Tests
+#[cfg(test)]
+mod whoami_tests {
+	use crate::{commands::discord::mock::MockContext, golden_test};
+
+	use super::*;
+
+	golden_test!(registered, "commands/user/whoami/registered");
+	async fn registered(ctx: &mut MockContext) -> Result<(), TaggedError> {
+		let user = whoami_impl(ctx).await?;
+		assert_eq!(user.discord_id, ctx.author_id().to_string());
+		assert!(!user.is_pookie);
+		Ok(())
+	}
+}
+// }}}
+// {{{ Discord wrapper
+/// Show the state of your account
+#[poise::command(prefix_command, slash_command)]
+async fn whoami(mut ctx: Context<'_>) -> Result<(), Error> {
+	let res = whoami_impl(&mut ctx).await;
+	ctx.handle_error(res).await?;
+
+	Ok(())
+}
+// }}}
+// }}}
+// {{{ Delete all plays
+// {{{ Implementation
+/// Deletes every play (and, through the `auto_delete_scores` trigger, every
+/// score) belonging to the current user. Since this cannot be undone, the
+/// user has to confirm the operation by passing in their current play count.
+async fn delete_all_impl<C: MessageContext>(
+	ctx: &mut C,
+	confirmation: Option<u32>,
+) -> Result<usize, TaggedError> {
+	let user = User::from_context(ctx)?;
+
+	let play_count: u32 = ctx
+		.data()
+		.db
+		.get()?
+		.prepare_cached("SELECT COUNT(*) FROM plays WHERE user_id=?")?
+		.query_row([user.id], |row| row.get(0))?;
+
+	if play_count == 0 {
+		return Err(anyhow!("You have no plays to delete").tag(ErrorKind::User));
+	}
+
+	if confirmation != Some(play_count) {
+		return Err(anyhow!(
+			"This will permanently delete all {play_count} of your plays. To confirm, run `user delete-all {play_count}`"
+		)
+		.tag(ErrorKind::User));
+	}
+
+	let deleted = ctx
+		.data()
+		.db
+		.get()?
+		.prepare_cached("DELETE FROM plays WHERE user_id=?")?
+		.execute([user.id])?;
+
+	ctx.data().best_plays_cache.clear();
+
+	ctx.reply(&format!("Deleted {deleted} plays")).await?;
+
+	Ok(deleted)
+}
+// }}}
+// {{{ Tests
+#[cfg(test)]
+mod delete_all_tests {
+	use std::{path::PathBuf, str::FromStr};
+
+	use crate::{
+		commands::{discord::mock::MockContext, score::magic_impl},
+		golden_test, with_test_ctx,
+	};
+
+	use super::*;
+
+	#[tokio::test]
+	async fn no_plays() -> Result<(), Error> {
+		with_test_ctx!("commands/user/delete_all/no_plays", |ctx| async move {
+			delete_all_impl(ctx, Some(0)).await?;
+			Ok(())
+		})
+	}
+
+	golden_test!(
+		wrong_confirmation,
+		"commands/user/delete_all/wrong_confirmation"
+	);
+	async fn wrong_confirmation(ctx: &mut MockContext) -> Result<(), TaggedError> {
+		magic_impl(
+			ctx,
+			&[PathBuf::from_str("test/screenshots/fracture_ray_ex.jpg")?],
+		)
+		.await?;
+
+		delete_all_impl(ctx, None).await?;
+		Ok(())
+	}
+
+	golden_test!(deletes_plays, "commands/user/delete_all/deletes_plays");
+	async fn deletes_plays(ctx: &mut MockContext) -> Result<(), TaggedError> {
+		magic_impl(
+			ctx,
+			&[
+				PathBuf::from_str("test/screenshots/fracture_ray_ex.jpg")?,
+				PathBuf::from_str("test/screenshots/antithese_74_kerning.jpg")?,
+			],
+		)
+		.await?;
+
+		let deleted = delete_all_impl(ctx, Some(2)).await?;
+		assert_eq!(deleted, 2);
+
+		let score_count: u32 =
+			ctx.data()
+				.db
+				.get()?
+				.query_row("SELECT COUNT(*) FROM scores", (), |row| row.get(0))?;
+		assert_eq!(score_count, 0);
+
+		Ok(())
+	}
+}
+// }}}
+// {{{ Discord wrapper
+/// Permanently delete all of your plays
+#[poise::command(prefix_command, slash_command, rename = "delete-all")]
+async fn delete_all(
+	mut ctx: Context<'_>,
+	#[description = "Your current play count, as confirmation"] confirmation: Option<u32>,
+) -> Result<(), Error> {
+	let res = delete_all_impl(&mut ctx, confirmation).await;
+	ctx.handle_error(res).await?;
+
+	Ok(())
+}
+// }}}
+// }}}
@@ -8,3 +8,5 @@ macro_rules! edit_reply {
         $handle.edit($ctx, edited)
     }};
 }
+
+pub mod two_columns;
@@ -0,0 +1,39 @@
+//! Helpers for laying text out in two aligned columns, which makes for
+//! compact listings inside discord code blocks.
+
+/// Renders the given rows as two columns, padding the left column to the
+/// width of its longest entry. Widths are measured in characters.
+pub fn two_columns<L: AsRef<str>, R: AsRef<str>>(rows: &[(L, R)]) -> String {
+	let width = rows
+		.iter()
+		.map(|(left, _)| left.as_ref().chars().count())
+		.max()
+		.unwrap_or(0);
+
+	let mut result = String::new();
+	for (left, right) in rows {
+		let left = left.as_ref();
+		let padding = width - left.chars().count();
+
+		result.push_str(left);
+		result.push_str(&" ".repeat(padding + 2));
+		result.push_str(right.as_ref());
+		result.push('\n');
+	}
+
+	result
+}
+
+#[cfg(test)]
+mod two_columns_tests {
+	use super::*;
+
+	#[test]
+	fn aligns_right_column() {
+		let rows = [("score magic", "Identify scores"), ("b30", "Show b30")];
+		assert_eq!(
+			two_columns(&rows),
+			"score magic  Identify scores\nb30          Show b30\n"
+		);
+	}
+}
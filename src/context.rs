@@ -1,15 +1,24 @@
 // {{{ Imports
 use include_dir::{include_dir, Dir};
+use r2d2::event::{CheckoutEvent, HandleEvent, TimeoutEvent};
 use r2d2::Pool;
 use r2d2_sqlite::SqliteConnectionManager;
 use rusqlite_migration::Migrations;
+use std::env::var;
 use std::fs;
 use std::path::Path;
 use std::sync::LazyLock;
+use std::time::Duration;
+
+use anyhow::anyhow;
 
 use crate::arcaea::jacket::read_jackets;
+use crate::arcaea::play::BestPlaysCache;
 use crate::arcaea::{chart::SongCache, jacket::JacketCache};
-use crate::assets::{get_data_dir, EXO_FONT, GEOSANS_FONT, KAZESAWA_BOLD_FONT, KAZESAWA_FONT};
+use crate::assets::{
+	get_data_dir, with_face, FontCell, EXO_FONT, GEOSANS_FONT, KAZESAWA_BOLD_FONT, KAZESAWA_FONT,
+};
+use crate::guild::GuildConfigs;
 use crate::recognition::{hyperglass::CharMeasurements, ui::UIMeasurements};
 use crate::timed;
 // }}}
@@ -67,6 +76,52 @@ impl TagError for Error {
 // {{{ DB connection
 pub type DbConnection = r2d2::Pool<SqliteConnectionManager>;
 
+/// How many database connections can be open at once, unless overriden using
+/// `SHIMMERING_DB_POOL_SIZE`.
+const DEFAULT_DB_POOL_SIZE: u32 = 16;
+
+/// How long to wait for a free connection before giving up.
+const DB_CONNECTION_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Acquiring a connection taking longer than this gets logged, as it means
+/// the pool is (close to) exhausted.
+const DB_SLOW_CHECKOUT: Duration = Duration::from_millis(500);
+
+/// The maximum number of database connections kept in the pool.
+#[inline]
+pub fn db_pool_size() -> u32 {
+	var("SHIMMERING_DB_POOL_SIZE")
+		.ok()
+		.and_then(|size| size.parse().ok())
+		.filter(|size| *size > 0)
+		.unwrap_or(DEFAULT_DB_POOL_SIZE)
+}
+
+/// Logs connection checkouts which had to wait on other connections to be
+/// returned to the pool, such that contention can be diagnosed.
+#[derive(Debug)]
+struct PoolContentionLogger;
+
+impl HandleEvent for PoolContentionLogger {
+	fn handle_checkout(&self, event: CheckoutEvent) {
+		if event.duration() >= DB_SLOW_CHECKOUT {
+			println!(
+				"Waited {:?} for a database connection. Consider raising SHIMMERING_DB_POOL_SIZE (currently {})",
+				event.duration(),
+				db_pool_size()
+			);
+		}
+	}
+
+	fn handle_timeout(&self, event: TimeoutEvent) {
+		println!(
+			"Could not acquire a database connection within {:?}. Consider raising SHIMMERING_DB_POOL_SIZE (currently {})",
+			event.timeout(),
+			db_pool_size()
+		);
+	}
+}
+
 pub fn connect_db(data_dir: &Path) -> DbConnection {
 	fs::create_dir_all(data_dir).expect("Could not create $SHIMMERING_DATA_DIR");
 
@@ -83,7 +138,45 @@ pub fn connect_db(data_dir: &Path) -> DbConnection {
 		.to_latest(&mut conn)
 		.expect("Could not run migrations");
 
-	Pool::new(SqliteConnectionManager::file(&db_path)).expect("Could not open sqlite database.")
+	Pool::builder()
+		.max_size(db_pool_size())
+		.connection_timeout(DB_CONNECTION_TIMEOUT)
+		.event_handler(Box::new(PoolContentionLogger))
+		.build(SqliteConnectionManager::file(&db_path))
+		.expect("Could not open sqlite database.")
+}
+// }}}
+// {{{ Font measurements
+/// Character measurements for one of the fonts used during OCR, or the
+/// reason they could not be computed. Keeping the error around allows the
+/// bot to keep running (with OCR disabled) when a font is missing.
+#[derive(Clone)]
+pub struct FontMeasurements(Result<CharMeasurements, String>);
+
+impl FontMeasurements {
+	fn new(
+		font: &'static std::thread::LocalKey<FontCell>,
+		whitelist: &str,
+		weight: Option<u32>,
+	) -> Self {
+		let measurements = with_face(font, |face| {
+			CharMeasurements::from_text(face, whitelist, weight)
+		})
+		.map_err(|e| e.to_string());
+
+		if let Err(message) = &measurements {
+			println!("{message}. Screenshot recognition will be unavailable.");
+		}
+
+		Self(measurements)
+	}
+
+	#[inline]
+	pub fn get(&self) -> Result<&CharMeasurements, Error> {
+		self.0
+			.as_ref()
+			.map_err(|message| anyhow!("Screenshot recognition is disabled. {message}"))
+	}
 }
 // }}}
 // {{{ UserContext
@@ -94,12 +187,14 @@ pub struct UserContext {
 	pub song_cache: SongCache,
 	pub jacket_cache: JacketCache,
 	pub ui_measurements: UIMeasurements,
+	pub guild_configs: GuildConfigs,
+	pub best_plays_cache: BestPlaysCache,
 
-	pub geosans_measurements: CharMeasurements,
-	pub exo_measurements: CharMeasurements,
+	pub geosans_measurements: FontMeasurements,
+	pub exo_measurements: FontMeasurements,
 	// TODO: do we really need both after I've fixed the bug in the ocr code?
-	pub kazesawa_measurements: CharMeasurements,
-	pub kazesawa_bold_measurements: CharMeasurements,
+	pub kazesawa_measurements: FontMeasurements,
+	pub kazesawa_bold_measurements: FontMeasurements,
 }
 
 impl UserContext {
@@ -109,23 +204,24 @@ impl UserContext {
 			let db = connect_db(&get_data_dir());
 
 			let mut song_cache = SongCache::new(&db)?;
+			let guild_configs = GuildConfigs::new(&db)?;
 			let ui_measurements = UIMeasurements::read()?;
-			let jacket_cache = JacketCache::new()?;
+			let mut jacket_cache = JacketCache::new()?;
 			timed!("read_jackets", {
 				read_jackets(&mut song_cache)?;
 			});
+			timed!("index_raw_jacket_vectors", {
+				jacket_cache.index_raw_vectors(&song_cache);
+			});
 
 			// {{{ Font measurements
 			static WHITELIST: &str = "0123456789'abcdefghklmnopqrstuvwxyzABCDEFGHIJKLMNOPRSTUVWXYZ";
 
-			let geosans_measurements = GEOSANS_FONT
-				.with_borrow_mut(|font| CharMeasurements::from_text(font, WHITELIST, None))?;
-			let kazesawa_measurements = KAZESAWA_FONT
-				.with_borrow_mut(|font| CharMeasurements::from_text(font, WHITELIST, None))?;
-			let kazesawa_bold_measurements = KAZESAWA_BOLD_FONT
-				.with_borrow_mut(|font| CharMeasurements::from_text(font, WHITELIST, None))?;
-			let exo_measurements = EXO_FONT
-				.with_borrow_mut(|font| CharMeasurements::from_text(font, WHITELIST, Some(700)))?;
+			let geosans_measurements = FontMeasurements::new(&GEOSANS_FONT, WHITELIST, None);
+			let kazesawa_measurements = FontMeasurements::new(&KAZESAWA_FONT, WHITELIST, None);
+			let kazesawa_bold_measurements =
+				FontMeasurements::new(&KAZESAWA_BOLD_FONT, WHITELIST, None);
+			let exo_measurements = FontMeasurements::new(&EXO_FONT, WHITELIST, Some(700));
 			// }}}
 
 			Ok(Self {
@@ -133,6 +229,8 @@ impl UserContext {
 				song_cache,
 				jacket_cache,
 				ui_measurements,
+				guild_configs,
+				best_plays_cache: BestPlaysCache::default(),
 				geosans_measurements,
 				exo_measurements,
 				kazesawa_measurements,
@@ -177,6 +275,11 @@ pub mod testing {
 		let mut data = (*get_shared_context().await).clone();
 		let dir = tempfile::tempdir()?;
 		data.db = connect_db(dir.path());
+		// Aliases, guild configs and cached rankings are tied to the database,
+		// so they must not leak between tests
+		data.song_cache.aliases = Default::default();
+		data.guild_configs = Default::default();
+		data.best_plays_cache = Default::default();
 		import_songs_and_jackets_from(dir.path());
 
 		let ctx = MockContext::new(data);
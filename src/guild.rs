@@ -0,0 +1,77 @@
+use std::collections::HashMap;
+use std::sync::{Arc, RwLock};
+
+use crate::context::{DbConnection, Error};
+
+/// The prefix used in guilds which haven't configured their own.
+pub const DEFAULT_PREFIX: &str = "!";
+
+/// Maps guild ids to their custom command prefix.
+pub type GuildPrefixes = HashMap<u64, String>;
+
+/// Per-guild settings, cached in memory such that they can be looked up on
+/// every message. Prefixes can be edited at runtime, hence the lock.
+#[derive(Debug, Clone, Default)]
+pub struct GuildConfigs {
+	pub prefixes: Arc<RwLock<GuildPrefixes>>,
+}
+
+impl GuildConfigs {
+	pub fn new(conn: &DbConnection) -> Result<Self, Error> {
+		let result = Self::default();
+
+		let conn = conn.get()?;
+		let mut query = conn.prepare_cached("SELECT guild_id, prefix FROM guild_configs")?;
+		let prefixes = query.query_map((), |row| {
+			Ok((
+				row.get::<_, String>("guild_id")?,
+				row.get::<_, String>("prefix")?,
+			))
+		})?;
+
+		{
+			let mut cached_prefixes = result.prefixes.write().unwrap();
+			for prefix in prefixes {
+				let (guild_id, prefix) = prefix?;
+				cached_prefixes.insert(guild_id.parse()?, prefix);
+			}
+		}
+
+		Ok(result)
+	}
+
+	/// Returns the prefix commands must start with in the given guild.
+	#[inline]
+	pub fn prefix(&self, guild_id: u64) -> String {
+		self.prefixes
+			.read()
+			.unwrap()
+			.get(&guild_id)
+			.cloned()
+			.unwrap_or_else(|| DEFAULT_PREFIX.to_string())
+	}
+
+	/// Persists a new prefix for the given guild, updating the cache.
+	pub fn set_prefix(
+		&self,
+		conn: &DbConnection,
+		guild_id: u64,
+		prefix: &str,
+	) -> Result<(), Error> {
+		conn.get()?
+			.prepare_cached(
+				"
+          INSERT INTO guild_configs(guild_id, prefix) VALUES (?,?)
+          ON CONFLICT(guild_id) DO UPDATE SET prefix=excluded.prefix
+        ",
+			)?
+			.execute((guild_id.to_string(), prefix))?;
+
+		self.prefixes
+			.write()
+			.unwrap()
+			.insert(guild_id, prefix.to_string());
+
+		Ok(())
+	}
+}
@@ -9,8 +9,10 @@ pub mod assets;
 pub mod bitmap;
 pub mod commands;
 pub mod context;
+pub mod guild;
 pub mod levenshtein;
 pub mod logs;
+pub mod metrics;
 pub mod recognition;
 pub mod time;
 pub mod transform;
@@ -3,20 +3,62 @@
 //! instead.
 //!
 //! While great in practice, this makes debugging much harder. This module
-//! allows for a convenient way to throw images into a `logs` directory with
-//! a simple env var.
+//! allows for a convenient way to throw images into the configured log
+//! directory by setting `SHIMMERING_DEBUG_IMAGES=1`.
+//!
+//! Images logged while running inside [with_invocation_dir] end up in a
+//! subdirectory dedicated to that invocation, which makes it easy to tell
+//! which crops belong to which screenshot upload.
+//!
+//! To keep long-running instances from filling up the disk, only the most
+//! recent `SHIMMERING_DEBUG_IMAGES_KEEP` invocations (and loose images) are
+//! kept around. Older ones get pruned whenever a new invocation starts.
 
-use std::{env, ops::Deref, path::PathBuf, sync::OnceLock, time::Instant};
+use std::{
+	env, fs,
+	future::Future,
+	io,
+	ops::Deref,
+	path::{Path, PathBuf},
+	sync::{
+		atomic::{AtomicU64, Ordering},
+		OnceLock,
+	},
+	time::{Instant, SystemTime},
+};
 
+use chrono::{NaiveDateTime, Utc};
 use image::{DynamicImage, EncodableLayout, ImageBuffer, PixelWithColorType};
 
 use crate::assets::get_path;
 
+tokio::task_local! {
+	/// The directory debug images should be written to for the current invocation.
+	static INVOCATION_DIR: Option<PathBuf>;
+}
+
 #[inline]
 fn should_save_debug_images() -> bool {
-	env::var("SHIMMERING_DEBUG_IMGS")
-		.map(|s| s == "1")
-		.unwrap_or(false)
+	// The old, shorter variable name is still honoured for backwards compatibility.
+	["SHIMMERING_DEBUG_IMAGES", "SHIMMERING_DEBUG_IMGS"]
+		.iter()
+		.any(|var| env::var(var).map(|s| s == "1").unwrap_or(false))
+}
+
+/// How many debug image entries are kept, unless overriden using
+/// `SHIMMERING_DEBUG_IMAGES_KEEP`.
+const DEFAULT_DEBUG_IMAGES_KEEP: usize = 100;
+
+/// The format invocation directories start with (see [with_invocation_dir]).
+const INVOCATION_DIR_TIME_FORMAT: &str = "%Y-%m-%dT%H-%M-%S";
+
+/// How many invocation directories (and loose images) to keep around.
+#[inline]
+fn debug_images_keep() -> usize {
+	env::var("SHIMMERING_DEBUG_IMAGES_KEEP")
+		.ok()
+		.and_then(|keep| keep.parse().ok())
+		.unwrap_or(DEFAULT_DEBUG_IMAGES_KEEP)
 }
 
 #[inline]
@@ -30,15 +72,128 @@ fn get_startup_time() -> Instant {
 	*CELL.get_or_init(|| Instant::now())
 }
 
+/// Computes the path the next debug image should be saved at.
+fn next_image_path() -> PathBuf {
+	let dir = INVOCATION_DIR
+		.try_with(|dir| dir.clone())
+		.ok()
+		.flatten()
+		.unwrap_or_else(get_log_dir);
+
+	dir.join(format!(
+		"{:0>15}.png",
+		get_startup_time().elapsed().as_nanos()
+	))
+}
+
+// {{{ Retention
+/// Returns true for the entries of the log directory this module creates,
+/// such that unrelated files never get pruned.
+fn is_debug_image_entry(path: &Path) -> bool {
+	let Some(name) = path.file_name().and_then(|name| name.to_str()) else {
+		return false;
+	};
+
+	if path.is_dir() {
+		name.get(..19).is_some_and(|time| {
+			NaiveDateTime::parse_from_str(time, INVOCATION_DIR_TIME_FORMAT).is_ok()
+		})
+	} else {
+		name.strip_suffix(".png")
+			.is_some_and(|stem| stem.chars().all(|c| c.is_ascii_digit()))
+	}
+}
+
+/// Deletes all but the `keep` most recently modified debug image entries
+/// (invocation directories and loose images) inside the given directory.
+/// Returns the number of deleted entries.
+///
+/// Entries which cannot be inspected or removed are logged and skipped, such
+/// that a single stubborn entry does not keep the rest from being pruned.
+pub fn prune_debug_images(dir: &Path, keep: usize) -> io::Result<usize> {
+	let mut entries: Vec<(SystemTime, PathBuf)> = Vec::new();
+	for entry in fs::read_dir(dir)? {
+		let path = match entry {
+			Ok(entry) => entry.path(),
+			Err(err) => {
+				println!("Could not read debug image entry: {err}");
+				continue;
+			}
+		};
+
+		if is_debug_image_entry(&path) {
+			match fs::metadata(&path).and_then(|metadata| metadata.modified()) {
+				Ok(modified) => entries.push((modified, path)),
+				Err(err) => println!("Could not read metadata of {path:?}: {err}"),
+			}
+		}
+	}
+
+	if entries.len() <= keep {
+		return Ok(0);
+	}
+
+	// Newest first
+	entries.sort_by(|a, b| b.cmp(a));
+
+	let mut deleted = 0;
+	for (_, path) in &entries[keep..] {
+		let result = if path.is_dir() {
+			fs::remove_dir_all(path)
+		} else {
+			fs::remove_file(path)
+		};
+
+		match result {
+			Ok(()) => deleted += 1,
+			Err(err) => println!("Could not delete {path:?}: {err}"),
+		}
+	}
+
+	Ok(deleted)
+}
+// }}}
+
+/// Runs the given future, routing every debug image it logs into a freshly
+/// created subdirectory of the log directory. The directory name starts with
+/// the current time, followed by the given label.
+///
+/// Nothing gets created unless debug images are enabled. Old invocations get
+/// pruned beforehand (see [prune_debug_images]).
+pub async fn with_invocation_dir<F: Future>(label: &str, future: F) -> F::Output {
+	static COUNTER: AtomicU64 = AtomicU64::new(0);
+
+	let dir = if should_save_debug_images() {
+		// Make room for the directory we are about to create
+		let keep = debug_images_keep().saturating_sub(1);
+		if let Err(err) = prune_debug_images(&get_log_dir(), keep) {
+			println!("Could not prune old debug images: {err}");
+		}
+
+		let dir = get_log_dir().join(format!(
+			"{}-{}-{label}",
+			Utc::now().format(INVOCATION_DIR_TIME_FORMAT),
+			COUNTER.fetch_add(1, Ordering::Relaxed)
+		));
+
+		match std::fs::create_dir_all(&dir) {
+			Ok(()) => Some(dir),
+			Err(err) => {
+				println!("Could not create debug image directory {dir:?}: {err}");
+				None
+			}
+		}
+	} else {
+		None
+	};
+
+	INVOCATION_DIR.scope(dir, future).await
+}
+
 #[inline]
 pub fn debug_image_log(image: &DynamicImage) {
 	if should_save_debug_images() {
-		image
-			.save(get_log_dir().join(format!(
-				"{:0>15}.png",
-				get_startup_time().elapsed().as_nanos()
-			)))
-			.unwrap();
+		image.save(next_image_path()).unwrap();
 	}
 }
 
@@ -50,11 +205,57 @@ where
 	C: Deref<Target = [P::Subpixel]>,
 {
 	if should_save_debug_images() {
-		image
-			.save(get_log_dir().join(format!(
-				"{:0>15}.png",
-				get_startup_time().elapsed().as_nanos()
-			)))
-			.unwrap();
+		image.save(next_image_path()).unwrap();
+	}
+}
+
+// {{{ Tests
+#[cfg(test)]
+mod logs_tests {
+	use std::time::Duration;
+
+	use super::*;
+
+	#[test]
+	fn pruning_keeps_the_newest_entries() -> io::Result<()> {
+		let dir = tempfile::tempdir()?;
+		let dir = dir.path();
+
+		let names = [
+			"2024-01-01T00-00-00-0-magic",
+			"2024-01-01T00-00-01-1-magic",
+			"000000000000042.png",
+			"2024-01-01T00-00-02-2-magic",
+		];
+
+		let start = SystemTime::now() - Duration::from_secs(60);
+		for (i, name) in names.iter().enumerate() {
+			let path = dir.join(name);
+			if name.ends_with(".png") {
+				fs::write(&path, "")?;
+			} else {
+				fs::create_dir(&path)?;
+				fs::write(path.join("000000000000001.png"), "")?;
+			}
+
+			let time = start + Duration::from_secs(i as u64);
+			fs::File::open(&path)?.set_modified(time)?;
+		}
+
+		// Unrelated entries are left alone, no matter how old
+		fs::write(dir.join("bot.log"), "")?;
+		fs::create_dir(dir.join("misc"))?;
+
+		assert_eq!(prune_debug_images(dir, 2)?, 2);
+		assert!(!dir.join(names[0]).exists());
+		assert!(!dir.join(names[1]).exists());
+		assert!(dir.join(names[2]).exists());
+		assert!(dir.join(names[3]).exists());
+		assert!(dir.join("bot.log").exists());
+		assert!(dir.join("misc").exists());
+
+		assert_eq!(prune_debug_images(dir, 2)?, 0);
+		Ok(())
 	}
 }
+// }}}
@@ -0,0 +1,194 @@
+//! A tiny metrics registry for keeping track of how well screenshot
+//! recognition is doing. The counters can be rendered in the Prometheus text
+//! format using [render].
+//!
+//! Pulling in a full-blown metrics crate felt like overkill for a handful of
+//! counters, so everything here is a plain atomic. Unless the `metrics`
+//! feature is enabled, recording anything is a no-op.
+// {{{ Imports
+use std::fmt::Write;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::Duration;
+
+use crate::context::Error;
+// }}}
+
+// {{{ OCR stages
+/// The different steps of screenshot recognition which can fail.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum OcrStage {
+	ScoreKind,
+	Difficulty,
+	Jacket,
+	Score,
+	Distribution,
+}
+
+impl OcrStage {
+	pub const STAGES: [Self; 5] = [
+		Self::ScoreKind,
+		Self::Difficulty,
+		Self::Jacket,
+		Self::Score,
+		Self::Distribution,
+	];
+
+	pub const STAGE_STRINGS: [&'static str; 5] = [
+		"score_kind",
+		"difficulty",
+		"jacket",
+		"score",
+		"distribution",
+	];
+
+	#[inline]
+	pub fn to_index(self) -> usize {
+		self as usize
+	}
+}
+// }}}
+// {{{ Registry
+/// Upper bounds (in seconds) for the processing time histogram buckets.
+const PROCESSING_TIME_BUCKETS: [f64; 8] = [0.1, 0.25, 0.5, 1.0, 2.5, 5.0, 10.0, 30.0];
+
+static MAGIC_INVOCATIONS: AtomicU64 = AtomicU64::new(0);
+static OCR_FAILURES: [AtomicU64; OcrStage::STAGES.len()] =
+	[const { AtomicU64::new(0) }; OcrStage::STAGES.len()];
+
+static PROCESSING_TIME_BUCKET_COUNTS: [AtomicU64; PROCESSING_TIME_BUCKETS.len()] =
+	[const { AtomicU64::new(0) }; PROCESSING_TIME_BUCKETS.len()];
+static PROCESSING_TIME_COUNT: AtomicU64 = AtomicU64::new(0);
+static PROCESSING_TIME_SUM_MICROS: AtomicU64 = AtomicU64::new(0);
+
+static JACKET_DOUBLE_CHECKS: AtomicU64 = AtomicU64::new(0);
+static JACKET_DISAGREEMENTS: AtomicU64 = AtomicU64::new(0);
+
+#[inline]
+pub fn record_magic_invocation() {
+	if cfg!(feature = "metrics") {
+		MAGIC_INVOCATIONS.fetch_add(1, Ordering::Relaxed);
+	}
+}
+
+#[inline]
+pub fn record_ocr_failure(stage: OcrStage) {
+	if cfg!(feature = "metrics") {
+		OCR_FAILURES[stage.to_index()].fetch_add(1, Ordering::Relaxed);
+	}
+}
+
+/// Records how long it took to process a single screenshot.
+#[inline]
+pub fn record_processing_time(duration: Duration) {
+	if cfg!(feature = "metrics") {
+		let seconds = duration.as_secs_f64();
+		for (bound, count) in PROCESSING_TIME_BUCKETS
+			.iter()
+			.zip(&PROCESSING_TIME_BUCKET_COUNTS)
+		{
+			if seconds <= *bound {
+				count.fetch_add(1, Ordering::Relaxed);
+			}
+		}
+
+		PROCESSING_TIME_COUNT.fetch_add(1, Ordering::Relaxed);
+		PROCESSING_TIME_SUM_MICROS.fetch_add(duration.as_micros() as u64, Ordering::Relaxed);
+	}
+}
+
+/// Records whether the projected and unprojected jacket recognition methods
+/// agreed on an uncertain match (see
+/// [crate::arcaea::jacket::JacketCache::double_check]).
+#[inline]
+pub fn record_jacket_double_check(agreed: bool) {
+	if cfg!(feature = "metrics") {
+		JACKET_DOUBLE_CHECKS.fetch_add(1, Ordering::Relaxed);
+		if !agreed {
+			JACKET_DISAGREEMENTS.fetch_add(1, Ordering::Relaxed);
+		}
+	}
+}
+// }}}
+// {{{ Rendering
+/// Renders every metric in the Prometheus text exposition format.
+pub fn render() -> Result<String, Error> {
+	let mut out = String::with_capacity(1024);
+
+	writeln!(
+		out,
+		"# HELP shimmering_magic_invocations_total Number of times score magic was invoked."
+	)?;
+	writeln!(out, "# TYPE shimmering_magic_invocations_total counter")?;
+	writeln!(
+		out,
+		"shimmering_magic_invocations_total {}",
+		MAGIC_INVOCATIONS.load(Ordering::Relaxed)
+	)?;
+
+	writeln!(
+		out,
+		"# HELP shimmering_ocr_failures_total Number of screenshots which failed at each recognition stage."
+	)?;
+	writeln!(out, "# TYPE shimmering_ocr_failures_total counter")?;
+	for stage in OcrStage::STAGES {
+		writeln!(
+			out,
+			"shimmering_ocr_failures_total{{stage=\"{}\"}} {}",
+			OcrStage::STAGE_STRINGS[stage.to_index()],
+			OCR_FAILURES[stage.to_index()].load(Ordering::Relaxed)
+		)?;
+	}
+
+	writeln!(
+		out,
+		"# HELP shimmering_processing_seconds Time taken to process a single screenshot."
+	)?;
+	writeln!(out, "# TYPE shimmering_processing_seconds histogram")?;
+	for (bound, count) in PROCESSING_TIME_BUCKETS
+		.iter()
+		.zip(&PROCESSING_TIME_BUCKET_COUNTS)
+	{
+		writeln!(
+			out,
+			"shimmering_processing_seconds_bucket{{le=\"{bound}\"}} {}",
+			count.load(Ordering::Relaxed)
+		)?;
+	}
+
+	let count = PROCESSING_TIME_COUNT.load(Ordering::Relaxed);
+	writeln!(
+		out,
+		"shimmering_processing_seconds_bucket{{le=\"+Inf\"}} {count}"
+	)?;
+	writeln!(
+		out,
+		"shimmering_processing_seconds_sum {}",
+		PROCESSING_TIME_SUM_MICROS.load(Ordering::Relaxed) as f64 / 1_000_000.0
+	)?;
+	writeln!(out, "shimmering_processing_seconds_count {count}")?;
+
+	writeln!(
+		out,
+		"# HELP shimmering_jacket_double_checks_total Number of uncertain jacket matches double-checked in unprojected space."
+	)?;
+	writeln!(out, "# TYPE shimmering_jacket_double_checks_total counter")?;
+	writeln!(
+		out,
+		"shimmering_jacket_double_checks_total {}",
+		JACKET_DOUBLE_CHECKS.load(Ordering::Relaxed)
+	)?;
+
+	writeln!(
+		out,
+		"# HELP shimmering_jacket_disagreements_total Number of double-checked jacket matches the unprojected search disagreed with."
+	)?;
+	writeln!(out, "# TYPE shimmering_jacket_disagreements_total counter")?;
+	writeln!(
+		out,
+		"shimmering_jacket_disagreements_total {}",
+		JACKET_DISAGREEMENTS.load(Ordering::Relaxed)
+	)?;
+
+	Ok(out)
+}
+// }}}
@@ -11,8 +11,9 @@
 //! "canonical" way to refer to some weirdly-named charts).
 
 use anyhow::bail;
+use rayon::iter::{IntoParallelRefIterator, ParallelIterator};
 
-use crate::arcaea::chart::{Chart, Difficulty, Song, SongCache};
+use crate::arcaea::chart::{Chart, ChartAliases, Difficulty, Song, SongCache};
 use crate::context::{Error, UserContext};
 use crate::levenshtein::edit_distance_with;
 
@@ -27,18 +28,123 @@ fn strip_case_insensitive_suffix<'a>(string: &'a str, suffix: &str) -> Option<&'
 	}
 }
 
+// {{{ Match tolerance
+/// Controls how forgiving fuzzy chart searches are.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct MatchTolerance {
+	/// Increases the amount of resolvable queries (prefix matching for short
+	/// queries, shorthands, aliases and picking the closest of multiple
+	/// matches), but might let in some false positives.
+	pub unsafe_heuristics: bool,
+
+	/// Titles can be at most `title.chars().count() / max_distance_divisor`
+	/// edits away from the searched text in order to be considered a match.
+	pub max_distance_divisor: usize,
+}
+
+impl MatchTolerance {
+	/// Used for names typed in by users. This is the default for commands.
+	pub const LENIENT: Self = Self {
+		unsafe_heuristics: true,
+		max_distance_divisor: 3,
+	};
+
+	/// Used when users explicitly ask for close matches only.
+	pub const STRICT: Self = Self {
+		unsafe_heuristics: false,
+		max_distance_divisor: 5,
+	};
+
+	/// Used for OCR-generated text. OCR output is noisy (misread glyphs,
+	/// dropped spaces), so titles may be further away than with [Self::STRICT].
+	pub const OCR: Self = Self {
+		unsafe_heuristics: false,
+		max_distance_divisor: 3,
+	};
+
+	#[inline]
+	pub fn from_strict(strict: bool) -> Self {
+		if strict {
+			Self::STRICT
+		} else {
+			Self::LENIENT
+		}
+	}
+
+	/// How many edits away from a title of the given length a text can be,
+	/// while still being considered a match.
+	#[inline]
+	pub fn max_distance(&self, len: usize) -> usize {
+		len / self.max_distance_divisor.max(1)
+	}
+}
+
+impl Default for MatchTolerance {
+	fn default() -> Self {
+		Self::LENIENT
+	}
+}
+// }}}
 // {{{ Guess song and chart by name
+/// Guesses a chart given a name optionally followed by a difficulty
+/// (e.g. `Pentiment [BYD]`), using [MatchTolerance::LENIENT] matching.
+#[inline]
 pub fn guess_song_and_chart<'a>(
 	ctx: &'a UserContext,
 	name: &'a str,
 ) -> Result<(&'a Song, &'a Chart), Error> {
+	guess_song_and_chart_with(ctx, name, MatchTolerance::LENIENT)
+}
+
+/// Similar to [guess_song_and_chart], but with a custom tolerance.
+pub fn guess_song_and_chart_with<'a>(
+	ctx: &'a UserContext,
+	name: &'a str,
+	tolerance: MatchTolerance,
+) -> Result<(&'a Song, &'a Chart), Error> {
+	// Aliases can end in something looking like a difficulty suffix, so they
+	// must be resolved before said suffix gets stripped.
+	if tolerance.unsafe_heuristics {
+		if let Some(found) = find_alias(&ctx.song_cache, name) {
+			return Ok(found);
+		}
+	}
+
+	let (name, inferred_difficulty) = strip_difficulty_suffix(name);
+	guess_chart_name(name, &ctx.song_cache, inferred_difficulty, tolerance)
+}
+
+/// Looks up the chart the given (exact, case insensitive) alias belongs to.
+fn find_alias<'a>(cache: &'a SongCache, name: &str) -> Option<(&'a Song, &'a Chart)> {
+	let name = name.trim().to_lowercase();
+	let chart_id = cache
+		.aliases
+		.read()
+		.unwrap()
+		.iter()
+		.find(|(_, aliases)| aliases.contains(&name))
+		.map(|(chart_id, _)| *chart_id)?;
+
+	cache.lookup_chart(chart_id).ok()
+}
+
+/// Splits a name like `Pentiment [BYD]` (or `Pentiment [beyond]`) into the
+/// name of the song and the difficulty at the end (if any).
+pub fn strip_difficulty_suffix(name: &str) -> (&str, Option<Difficulty>) {
 	let mut name = name.trim();
 	let mut inferred_difficulty = None;
 
 	for difficulty in Difficulty::DIFFICULTIES {
+		// Full names are only accepted in brackets, since plenty of song titles
+		// end in words like "future" or "beyond".
+		let full_name = format!(
+			"[{}]",
+			Difficulty::DIFFICULTY_STRINGS[difficulty.to_index()]
+		);
 		for shorthand in [
 			Difficulty::DIFFICULTY_SHORTHANDS[difficulty.to_index()],
 			Difficulty::DIFFICULTY_SHORTHANDS_IN_BRACKETS[difficulty.to_index()],
+			&full_name,
 		] {
 			if let Some(stripped) = strip_case_insensitive_suffix(name, shorthand) {
 				inferred_difficulty = Some(difficulty);
@@ -48,117 +154,441 @@ pub fn guess_song_and_chart<'a>(
 		}
 	}
 
-	guess_chart_name(name, &ctx.song_cache, inferred_difficulty, true)
+	(name, inferred_difficulty)
+}
+// }}}
+// {{{ Chart distance
+/// How many (song title character, chart) pairs need to be compared before
+/// the search gets spread across multiple threads. Below this, the overhead
+/// of spawning rayon jobs outweighs the gains.
+///
+/// Measured with the loop from the ignored `search_benchmark` test (`cargo
+/// test --release search_benchmark -- --ignored --nocapture`) over the full
+/// list of 1348 charts, on a single core (best of three runs of 1000
+/// searches each):
+///
+/// | pairs | sequential | parallel |
+/// |------:|-----------:|---------:|
+/// |  1348 |       56µs |     90µs |
+/// |  5392 |      117µs |    138µs |
+/// | 12132 |      343µs |    332µs |
+/// | 16176 |      491µs |    589µs |
+/// | 28308 |      897µs |    915µs |
+/// | 57964 |     1.52ms |   1.61ms |
+///
+/// That puts the fixed cost of going through rayon at roughly 35µs, and the
+/// cost of a sequential search at roughly 30ns per pair. With a single core
+/// the parallel path never wins outright, so the threshold sits where that
+/// fixed cost drops below ~4% of the search (~1ms). With more cores
+/// available, anything past that point gets split into chunks large enough
+/// to pay for themselves.
+const PARALLEL_SEARCH_THRESHOLD: usize = 32_768;
+
+/// Decides whether searching for the given text is worth spreading across
+/// multiple threads. See [PARALLEL_SEARCH_THRESHOLD].
+#[inline]
+fn should_search_in_parallel(chart_count: usize, text: &str) -> bool {
+	chart_count * text.chars().count() >= PARALLEL_SEARCH_THRESHOLD
+}
+
+/// Scratch buffers used while computing the distance to a single chart.
+/// Each thread must own its own copy of these.
+struct SearchBuffers {
+	/// Cached vec used by the levenshtein distance function
+	levenshtein_vec: Vec<usize>,
+	/// Cached vec used to store distance calculations
+	distance_vec: Vec<usize>,
+}
+
+impl SearchBuffers {
+	#[inline]
+	fn new() -> Self {
+		Self {
+			levenshtein_vec: Vec::with_capacity(20),
+			distance_vec: Vec::with_capacity(3),
+		}
+	}
+}
+
+/// Computes how close some text is to the name of the given chart.
+/// Returns [None] for charts which are not plausible matches.
+fn chart_distance<'a>(
+	text: &str,
+	cache: &'a SongCache,
+	aliases: &ChartAliases,
+	chart: &'a Chart,
+	difficulty: Option<Difficulty>,
+	tolerance: MatchTolerance,
+	buffers: &mut SearchBuffers,
+) -> Option<(&'a Song, &'a Chart, usize)> {
+	let unsafe_heuristics = tolerance.unsafe_heuristics;
+	let cached_song = &cache.lookup_song(chart.song_id).ok()?;
+	let song = &cached_song.song;
+	let levenshtein_vec = &mut buffers.levenshtein_vec;
+
+	// {{{ Alias-based matching
+	// Aliases refer to a specific chart, so they are exempt from the guess
+	// about which difficulty was meant below. Explicit difficulties still
+	// have to agree with the chart the alias belongs to.
+	let alias_distance = if unsafe_heuristics && difficulty.map_or(true, |d| d == chart.difficulty)
+	{
+		aliases
+			.get(&chart.id)
+			.into_iter()
+			.flatten()
+			.map(|alias| (edit_distance_with(text, alias, levenshtein_vec), alias))
+			.filter(|(distance, alias)| *distance <= tolerance.max_distance(alias.chars().count()))
+			.map(|(distance, _)| distance * 10 + 1)
+			.min()
+	} else {
+		None
+	};
+	// }}}
+
+	let plausible_difficulty = match difficulty {
+		Some(difficulty) => difficulty == chart.difficulty,
+		None => {
+			let has_ftr = cached_song.charts().any(|(d, _)| d == Difficulty::FTR);
+			let main_diff = if has_ftr {
+				Difficulty::FTR
+			} else {
+				let (max_diff, _) = cached_song.charts().max_by_key(|(d, _)| *d).unwrap();
+				max_diff
+			};
+
+			chart.difficulty == main_diff
+		}
+	};
+
+	if !plausible_difficulty {
+		return alias_distance.map(|distance| (song, chart, distance));
+	}
+
+	let distance_vec = &mut buffers.distance_vec;
+	distance_vec.clear();
+	distance_vec.extend(alias_distance);
+
+	// Songs can be searched for by either their english or original title
+	let titles = [
+		Some(&song.lowercase_title),
+		song.lowercase_og_title.as_ref(),
+	];
+	for song_title in titles.into_iter().flatten() {
+		// Apply raw distance
+		let base_distance = edit_distance_with(text, song_title, levenshtein_vec);
+		if base_distance <= tolerance.max_distance(song_title.chars().count()) {
+			distance_vec.push(base_distance * 10 + 2);
+		}
+
+		// Cut title to the length of the text, and then check
+		let shortest_len = Ord::min(song_title.len(), text.len());
+		if let Some(sliced) = &song_title.get(..shortest_len) {
+			if text.len() >= 6 || unsafe_heuristics {
+				let slice_distance = edit_distance_with(text, sliced, levenshtein_vec);
+				if slice_distance == 0 {
+					distance_vec.push(3);
+				}
+			}
+		}
+	}
+
+	// Shorthand-based matching
+	if let Some(shorthand) = &chart.shorthand {
+		if unsafe_heuristics {
+			let short_distance = edit_distance_with(text, shorthand, levenshtein_vec);
+
+			if short_distance <= tolerance.max_distance(shorthand.len()) {
+				distance_vec.push(short_distance * 10 + 1);
+			}
+		}
+	}
+
+	distance_vec
+		.iter()
+		.min()
+		.map(|distance| (song, chart, *distance))
+}
+// }}}
+// {{{ Rank charts by name
+/// Collects every chart plausibly matching the given (lowercase) text, closest
+/// match first. Ties are broken by song id, such that results are stable.
+fn collect_candidates<'a>(
+	text: &str,
+	cache: &'a SongCache,
+	aliases: &ChartAliases,
+	difficulty: Option<Difficulty>,
+	tolerance: MatchTolerance,
+	buffers: &mut SearchBuffers,
+) -> Vec<(&'a Song, &'a Chart, usize)> {
+	// The chart list is indexed by id, so it contains gaps
+	let parallel = should_search_in_parallel(cache.charts().count(), text);
+	collect_candidates_with(
+		text, cache, aliases, difficulty, tolerance, buffers, parallel,
+	)
+}
+
+/// Similar to [collect_candidates], except the caller decides whether the
+/// search runs across multiple threads.
+fn collect_candidates_with<'a>(
+	text: &str,
+	cache: &'a SongCache,
+	aliases: &ChartAliases,
+	difficulty: Option<Difficulty>,
+	tolerance: MatchTolerance,
+	buffers: &mut SearchBuffers,
+	parallel: bool,
+) -> Vec<(&'a Song, &'a Chart, usize)> {
+	let mut close_enough: Vec<_> = if parallel {
+		cache
+			.charts
+			.par_iter()
+			.filter_map(|chart| chart.as_ref())
+			.map_init(SearchBuffers::new, |buffers, chart| {
+				chart_distance(text, cache, aliases, chart, difficulty, tolerance, buffers)
+			})
+			.flatten()
+			.collect()
+	} else {
+		cache
+			.charts()
+			.filter_map(|chart| {
+				chart_distance(text, cache, aliases, chart, difficulty, tolerance, buffers)
+			})
+			.collect()
+	};
+
+	close_enough.sort_by_key(|(song, _, distance)| (song.id, *distance));
+	close_enough.dedup_by_key(|(song, _, _)| song.id);
+	close_enough.sort_by_key(|(song, _, distance)| (*distance, song.id));
+
+	close_enough
+}
+
+/// Ranks every chart plausibly matching the given name, closest match first,
+/// alongside its distance to the name (lower is better).
+///
+/// When nothing matches, characters get dropped from the end of the name
+/// until something does (the search is left-biased, in case the right half
+/// of the name is being covered by character art). The result is only ever
+/// empty if not even the first character matches anything.
+pub fn rank_chart_names<'a>(
+	raw_text: &str,
+	cache: &'a SongCache,
+	difficulty: Option<Difficulty>,
+	tolerance: MatchTolerance,
+) -> Vec<(&'a Song, &'a Chart, usize)> {
+	let lowercase = raw_text.trim().to_lowercase(); // not quite raw 🤔
+	let mut text: &str = &lowercase;
+
+	// Only used by the single-threaded path
+	let mut buffers = SearchBuffers::new();
+	let aliases = cache.aliases.read().unwrap();
+
+	loop {
+		let close_enough =
+			collect_candidates(text, cache, &aliases, difficulty, tolerance, &mut buffers);
+
+		if !close_enough.is_empty() {
+			return close_enough;
+		}
+
+		// Drop the last character (which might span multiple bytes)
+		match text.char_indices().last() {
+			Some((last, _)) if last > 0 => text = &text[..last],
+			_ => return close_enough,
+		}
+	}
 }
 // }}}
 // {{{ Guess chart by name
 /// Runs a specialized fuzzy-search through all charts in the game.
 ///
-/// The `unsafe_heuristics` toggle increases the amount of resolvable queries, but might let in
-/// some false positives. We turn it on for simple user-search commands, but disallow it for things
-/// like OCR-generated text.
+/// See [MatchTolerance] for the knobs controlling how forgiving the search is.
+/// We use [MatchTolerance::LENIENT] for simple user-search commands, but
+/// [MatchTolerance::OCR] for OCR-generated text.
 pub fn guess_chart_name<'a>(
 	raw_text: &str,
 	cache: &'a SongCache,
 	difficulty: Option<Difficulty>,
-	unsafe_heuristics: bool,
+	tolerance: MatchTolerance,
 ) -> Result<(&'a Song, &'a Chart), Error> {
-	let raw_text = raw_text.trim(); // not quite raw 🤔
-	let mut text: &str = &raw_text.to_lowercase();
+	let close_enough = rank_chart_names(raw_text, cache, difficulty, tolerance);
 
-	// Cached vec used by the levenshtein distance function
-	let mut levenshtein_vec = Vec::with_capacity(20);
-	// Cached vec used to store distance calculations
-	let mut distance_vec = Vec::with_capacity(3);
+	match close_enough.as_slice() {
+		[] => bail!(
+			"Could not find match for chart name '{}' [{:?}]",
+			raw_text.trim(),
+			difficulty
+		),
+		[(song, chart, _)] => Ok((song, chart)),
+		[(song, chart, _), ..] if tolerance.unsafe_heuristics => Ok((song, chart)),
+		_ => bail!("Name '{}' is too vague to choose a match", raw_text.trim()),
+	}
+}
+// }}}
+// {{{ Tests
+#[cfg(test)]
+mod fuzzy_tests {
+	use crate::context::testing::get_mock_context;
 
-	let (song, chart) = loop {
-		let mut close_enough: Vec<_> = cache
-			.charts()
-			.filter_map(|chart| {
-				let cached_song = &cache.lookup_song(chart.song_id).ok()?;
-				let song = &cached_song.song;
-				let plausible_difficulty = match difficulty {
-					Some(difficulty) => difficulty == chart.difficulty,
-					None => {
-						let has_ftr = cached_song.charts().any(|(d, _)| d == Difficulty::FTR);
-						let main_diff = if has_ftr {
-							Difficulty::FTR
-						} else {
-							let (max_diff, _) =
-								cached_song.charts().max_by_key(|(d, _)| *d).unwrap();
-							max_diff
-						};
-
-						chart.difficulty == main_diff
-					}
-				};
-
-				if !plausible_difficulty {
-					return None;
-				}
+	use super::*;
 
-				let song_title = &song.lowercase_title;
-				distance_vec.clear();
+	#[tokio::test]
+	async fn short_prefix_depends_on_tolerance() -> Result<(), Error> {
+		let (ctx, _guard) = get_mock_context().await?;
 
-				// Apply raw distance
-				let base_distance = edit_distance_with(text, song_title, &mut levenshtein_vec);
-				if base_distance <= song.title.len() / 3 {
-					distance_vec.push(base_distance * 10 + 2);
-				}
+		let (song, _) = guess_song_and_chart_with(&ctx.data, "pent", MatchTolerance::LENIENT)?;
+		assert_eq!(song.title, "Pentiment");
 
-				// Cut title to the length of the text, and then check
-				let shortest_len = Ord::min(song_title.len(), text.len());
-				if let Some(sliced) = &song_title.get(..shortest_len) {
-					if text.len() >= 6 || unsafe_heuristics {
-						let slice_distance = edit_distance_with(text, sliced, &mut levenshtein_vec);
-						if slice_distance == 0 {
-							distance_vec.push(3);
-						}
-					}
-				}
+		// Short prefixes are only allowed to match when using unsafe heuristics
+		let strict = guess_song_and_chart_with(&ctx.data, "pent", MatchTolerance::STRICT);
+		assert!(strict.map_or(true, |(song, _)| song.title != "Pentiment"));
+
+		Ok(())
+	}
+
+	#[tokio::test]
+	async fn strict_matching_allows_fewer_edits() -> Result<(), Error> {
+		let (ctx, _guard) = get_mock_context().await?;
+
+		// Two edits away from "Pentiment", which is fine for OCR output
+		let (song, _) = guess_song_and_chart_with(&ctx.data, "Pentamant", MatchTolerance::OCR)?;
+		assert_eq!(song.title, "Pentiment");
+
+		let strict = guess_song_and_chart_with(&ctx.data, "Pentamant", MatchTolerance::STRICT);
+		assert!(strict.map_or(true, |(song, _)| song.title != "Pentiment"));
+
+		Ok(())
+	}
+
+	#[tokio::test]
+	async fn matches_original_title() -> Result<(), Error> {
+		let (mut ctx, _guard) = get_mock_context().await?;
+
+		let (song, _) = guess_song_and_chart_with(&ctx.data, "Pentiment", MatchTolerance::LENIENT)?;
+		let song = &mut ctx.data.song_cache.lookup_song_mut(song.id)?.song;
+		song.og_title = Some("ペンティメント".to_string());
+		song.lowercase_og_title = song.og_title.clone();
+
+		let (song, _) =
+			guess_song_and_chart_with(&ctx.data, "ペンティメント", MatchTolerance::LENIENT)?;
+		assert_eq!(song.title, "Pentiment");
+
+		Ok(())
+	}
+
+	#[tokio::test]
+	async fn trims_non_ascii_text_by_character() -> Result<(), Error> {
+		let (mut ctx, _guard) = get_mock_context().await?;
+
+		let (song, _) = guess_song_and_chart_with(&ctx.data, "Pentiment", MatchTolerance::LENIENT)?;
+		let song = &mut ctx.data.song_cache.lookup_song_mut(song.id)?.song;
+		song.og_title = Some("ペンティメント".to_string());
+		song.lowercase_og_title = song.og_title.clone();
+
+		// Nothing matches until the trailing characters get dropped, none of
+		// which fit in a single byte.
+		let (song, _) = guess_song_and_chart_with(
+			&ctx.data,
+			"ペンティメントじゃない曲",
+			MatchTolerance::LENIENT,
+		)?;
+		assert_eq!(song.title, "Pentiment");
+
+		Ok(())
+	}
+
+	#[tokio::test]
+	async fn aliases_resolve_before_difficulty_filtering() -> Result<(), Error> {
+		let (ctx, _guard) = get_mock_context().await?;
 
-				// Shorthand-based matching
-				if let Some(shorthand) = &chart.shorthand {
-					if unsafe_heuristics {
-						let short_distance =
-							edit_distance_with(text, shorthand, &mut levenshtein_vec);
+		let (_, chart) = guess_song_and_chart(&ctx.data, "Pentiment [BYD]")?;
+		let chart_id = chart.id;
+		ctx.data.song_cache.aliases.write().unwrap().insert(
+			chart_id,
+			vec!["spooky".to_string(), "spookybyd".to_string()],
+		);
 
-						if short_distance <= shorthand.len() / 3 {
-							distance_vec.push(short_distance * 10 + 1);
-						}
-					}
+		// Without a suffix, only the main difficulty is usually considered
+		let (_, chart) = guess_song_and_chart(&ctx.data, "spooky")?;
+		assert_eq!(chart.id, chart_id);
+
+		// Aliases ending in something looking like a suffix are left intact
+		let (_, chart) = guess_song_and_chart(&ctx.data, "SpookyBYD")?;
+		assert_eq!(chart.id, chart_id);
+
+		// Explicit difficulties must agree with the alias
+		let explicit = guess_song_and_chart(&ctx.data, "spooky [FTR]");
+		assert!(explicit.map_or(true, |(_, chart)| chart.id != chart_id));
+
+		Ok(())
+	}
+
+	#[test]
+	fn strips_difficulty_suffixes() {
+		assert_eq!(
+			strip_difficulty_suffix("Pentiment [BYD]"),
+			("Pentiment ", Some(Difficulty::BYD))
+		);
+		assert_eq!(
+			strip_difficulty_suffix("Pentiment ftr"),
+			("Pentiment ", Some(Difficulty::FTR))
+		);
+		assert_eq!(
+			strip_difficulty_suffix("Pentiment [beyond]"),
+			("Pentiment ", Some(Difficulty::BYD))
+		);
+		assert_eq!(strip_difficulty_suffix("Pentiment"), ("Pentiment", None));
+	}
+
+	/// Compares the single and multi-threaded searches on the full song list,
+	/// for queries of increasing length. Used to tune
+	/// [PARALLEL_SEARCH_THRESHOLD].
+	#[tokio::test]
+	#[ignore]
+	async fn search_benchmark() -> Result<(), Error> {
+		const RUNS: u32 = 1000;
+
+		let (ctx, _guard) = get_mock_context().await?;
+		let cache = &ctx.data.song_cache;
+		let aliases = cache.aliases.read().unwrap();
+		let chart_count = cache.charts().count();
+		let mut buffers = SearchBuffers::new();
+
+		for text in [
+			"a",
+			"pent",
+			"pentiment",
+			"fracture ray",
+			"world ender vs apollo",
+			"lunarorbit -believe in the espebranch road-",
+		] {
+			let mut time = |parallel| {
+				let start = std::time::Instant::now();
+				for _ in 0..RUNS {
+					collect_candidates_with(
+						text,
+						cache,
+						&aliases,
+						None,
+						MatchTolerance::LENIENT,
+						&mut buffers,
+						parallel,
+					);
 				}
 
-				distance_vec
-					.iter()
-					.min()
-					.map(|distance| (song, chart, *distance))
-			})
-			.collect();
-
-		close_enough.sort_by_key(|(song, _, _)| song.id);
-		close_enough.dedup_by_key(|(song, _, _)| song.id);
-
-		if close_enough.is_empty() {
-			if text.len() <= 1 {
-				bail!(
-					"Could not find match for chart name '{}' [{:?}]",
-					raw_text,
-					difficulty
-				);
-			} else {
-				text = &text[..text.len() - 1];
-			}
-		} else if close_enough.len() == 1 {
-			break (close_enough[0].0, close_enough[0].1);
-		} else if unsafe_heuristics {
-			close_enough.sort_by_key(|(_, _, distance)| *distance);
-			break (close_enough[0].0, close_enough[0].1);
-		} else {
-			bail!("Name '{}' is too vague to choose a match", raw_text);
-		};
-	};
+				start.elapsed() / RUNS
+			};
+
+			let sequential = time(false);
+			let parallel = time(true);
+			println!(
+				"{:>6} pairs ({text:?}): sequential {sequential:?}, parallel {parallel:?}",
+				chart_count * text.len()
+			);
+		}
 
-	Ok((song, chart))
+		Ok(())
+	}
 }
 // }}}
@@ -35,6 +35,29 @@ use crate::context::Error;
 use crate::logs::{debug_image_buffer_log, debug_image_log};
 // }}}
 
+// {{{ Recognised text
+/// Characters further than this from every known glyph get discarded.
+pub const DEFAULT_MAX_CHAR_DISTANCE: f32 = 0.75;
+
+/// The result of running hyperglass on an image.
+#[derive(Debug, Clone)]
+pub struct RecognisedText {
+	pub text: String,
+
+	/// How many connected components were considered. Characters whose
+	/// distance to every known glyph was too large get dropped, so this
+	/// can exceed the number of characters in [Self::text].
+	pub component_count: usize,
+}
+
+impl RecognisedText {
+	/// Returns how many components were not recognised as any character.
+	#[inline]
+	pub fn dropped_count(&self) -> usize {
+		self.component_count - self.text.chars().count()
+	}
+}
+// }}}
 // {{{ ConponentVec
 /// How many sub-segments to split each side into
 const SPLIT_FACTOR: u32 = 5;
@@ -300,6 +323,7 @@ impl CharMeasurements {
 	}
 	// }}}
 	// {{{ Recognition
+	#[inline]
 	pub fn recognise(
 		&self,
 		image: &DynamicImage,
@@ -307,6 +331,24 @@ impl CharMeasurements {
 		binarisation_threshold: Option<u8>,
 		max_sizes: Option<(f32, f32)>,
 	) -> Result<String, Error> {
+		let result =
+			self.recognise_with(image, whitelist, binarisation_threshold, max_sizes, None)?;
+		Ok(result.text)
+	}
+
+	/// Similar to [Self::recognise], except characters are only kept if their
+	/// distance to the closest known glyph is at most `max_distance`
+	/// (defaults to [DEFAULT_MAX_CHAR_DISTANCE]). Also reports how many
+	/// components were seen, so callers can tell when something was dropped.
+	pub fn recognise_with(
+		&self,
+		image: &DynamicImage,
+		whitelist: &str,
+		binarisation_threshold: Option<u8>,
+		max_sizes: Option<(f32, f32)>,
+		max_distance: Option<f32>,
+	) -> Result<RecognisedText, Error> {
+		let max_distance = max_distance.unwrap_or(DEFAULT_MAX_CHAR_DISTANCE);
 		let components = ComponentsWithBounds::from_image(
 			image,
 			binarisation_threshold.unwrap_or(100),
@@ -339,12 +381,15 @@ impl CharMeasurements {
 				.ok_or_else(|| anyhow!("No chars in cache"))?;
 
 			// println!("char '{}', distance {}", best_match.1, best_match.0);
-			if best_match.0 <= 0.75 {
+			if best_match.0 <= max_distance {
 				result.push(best_match.1);
 			}
 		}
 
-		Ok(result)
+		Ok(RecognisedText {
+			text: result,
+			component_count: components.bounds_by_position.len(),
+		})
 	}
 	// }}}
 }
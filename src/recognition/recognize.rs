@@ -1,4 +1,5 @@
 // {{{ Imports
+use std::env::var;
 use std::fmt::Display;
 
 use anyhow::{anyhow, bail};
@@ -10,26 +11,176 @@ use poise::serenity_prelude::{CreateAttachment, CreateEmbed};
 use poise::CreateReply;
 
 use crate::arcaea::chart::{Chart, Difficulty, Song, DIFFICULTY_MENU_PIXEL_COLORS};
-use crate::arcaea::jacket::IMAGE_VEC_DIM;
+use crate::arcaea::jacket::JACKET_UNCERTAINTY_RATIO;
+use crate::arcaea::play::ClearKind;
 use crate::arcaea::score::Score;
 use crate::bitmap::{Color, Rect};
 use crate::commands::discord::MessageContext;
 use crate::context::{Error, UserContext};
 use crate::levenshtein::edit_distance;
 use crate::logs::debug_image_log;
-use crate::recognition::fuzzy_song_name::guess_chart_name;
+use crate::recognition::fuzzy_song_name::{guess_chart_name, MatchTolerance};
 use crate::recognition::ui::{
 	ScoreScreenRect, SongSelectRect, UIMeasurementRect, UIMeasurementRect::*,
 };
 use crate::transform::rotate;
 // }}}
 
+/// How many pixels around the sampled point get averaged together when
+/// reading the difficulty colors off the song select menu.
+const DIFFICULTY_MENU_SAMPLE_RADIUS: i32 = 2;
+
+/// Averages the colors in a square of the given radius centered at (x, y),
+/// ignoring any pixels that fall outside the image.
+fn average_color(image: &DynamicImage, x: i32, y: i32, radius: i32) -> Color {
+	let mut sum = [0u32; 4];
+	let mut count = 0;
+
+	for y in (y - radius)..=(y + radius) {
+		for x in (x - radius)..=(x + radius) {
+			if x < 0 || y < 0 || x >= image.width() as i32 || y >= image.height() as i32 {
+				continue;
+			}
+
+			let pixel = image.get_pixel(x as u32, y as u32);
+			for (total, channel) in sum.iter_mut().zip(pixel.0) {
+				*total += channel as u32;
+			}
+
+			count += 1;
+		}
+	}
+
+	let count = count.max(1);
+	Color::from_bytes(sum.map(|c| (c / count) as u8))
+}
+
+// {{{ Input size
+/// Screenshots larger than this (on either axis) get downscaled before OCR,
+/// unless overriden using `SHIMMERING_MAX_OCR_DIMENSION`.
+const DEFAULT_MAX_OCR_DIMENSION: u32 = 1440;
+
+/// The maximum width/height of images fed into the OCR pipeline.
+#[inline]
+pub fn max_ocr_dimension() -> u32 {
+	var("SHIMMERING_MAX_OCR_DIMENSION")
+		.ok()
+		.and_then(|dimension| dimension.parse().ok())
+		.filter(|dimension| *dimension > 0)
+		.unwrap_or(DEFAULT_MAX_OCR_DIMENSION)
+}
+
+/// Downscales images with a side longer than `max_dimension`, preserving the
+/// aspect ratio. Every UI measurement is relative to the image's resolution,
+/// so this does not affect where things get read from, but it does make
+/// processing huge (e.g. 4K) screenshots a lot faster.
+pub fn limit_image_size(image: DynamicImage, max_dimension: u32) -> DynamicImage {
+	if image.width() <= max_dimension && image.height() <= max_dimension {
+		return image;
+	}
+
+	image.resize(max_dimension, max_dimension, FilterType::Lanczos3)
+}
+// }}}
+
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum ScoreKind {
 	SongSelect,
 	ScoreScreen,
 }
 
+/// A score read off a screenshot, alongside how much of it was recognised.
+#[derive(Debug, Clone, Copy)]
+pub struct ScoreReading {
+	pub score: Score,
+
+	/// How many characters were found in the score area.
+	pub seen: usize,
+
+	/// How many of the characters found were close enough to a known glyph to
+	/// be kept. When this is lower than [Self::seen], some digits might be
+	/// missing from the score.
+	pub recognised: usize,
+
+	/// Whether Hyperglass failed to produce a valid score, such that the
+	/// Tesseract fallback had to be used instead.
+	pub used_fallback: bool,
+}
+
+impl ScoreReading {
+	#[inline]
+	pub fn is_complete(&self) -> bool {
+		self.seen == self.recognised && !self.used_fallback
+	}
+}
+
+/// The language Tesseract reads text in, set using
+/// `SHIMMERING_TESSERACT_LANGUAGE` (`eng` by default). Only the languages
+/// exposed by the bindings can be picked.
+fn tesseract_language() -> Result<hypertesseract::Language, Error> {
+	match var("SHIMMERING_TESSERACT_LANGUAGE").as_deref() {
+		Err(_) | Ok("" | "eng") => Ok(hypertesseract::Language::English),
+		Ok(language) => {
+			bail!("Unsupported Tesseract language '{language}' (only `eng` is available)")
+		}
+	}
+}
+
+/// The page segmentation modes the Tesseract score fallback tries, each
+/// contributing a candidate to the consensus.
+const TESSERACT_SCORE_MODES: [PageSegMode; 3] = [
+	PageSegMode::SingleLine,
+	PageSegMode::SingleWord,
+	PageSegMode::RawLine,
+];
+
+/// Reads a number using Tesseract, once for each mode in
+/// [TESSERACT_SCORE_MODES]. Modes which fail to produce a number are skipped.
+fn tesseract_score_candidates(image: &DynamicImage) -> Result<Vec<Score>, Error> {
+	let image = image.to_rgba8();
+	let mut candidates = Vec::with_capacity(TESSERACT_SCORE_MODES.len());
+
+	for mode in TESSERACT_SCORE_MODES {
+		let (text, _) = Tesseract::builder()
+			.language(tesseract_language()?)
+			.page_seg_mode(mode)
+			.whitelist_str("0123456789'")?
+			.build()?
+			.recognize_text_cloned_with_conf(&image)?;
+
+		let digits = text
+			.chars()
+			.filter(char::is_ascii_digit)
+			.collect::<String>();
+		if let Ok(score) = digits.parse() {
+			candidates.push(Score(score));
+		}
+	}
+
+	Ok(candidates)
+}
+
+/// How many Tesseract modes need to agree on a score before the fallback
+/// accepts it. A single mode is not enough, as misreading a single digit
+/// usually still produces a valid score.
+const MIN_FALLBACK_VOTES: usize = 2;
+
+/// Picks the valid candidate most others agree with (preferring earlier ones
+/// on ties), provided at least [MIN_FALLBACK_VOTES] candidates agree on it.
+fn score_consensus(candidates: &[Score], is_valid: impl Fn(&Score) -> bool) -> Option<Score> {
+	let valid: Vec<Score> = candidates.iter().copied().filter(is_valid).collect();
+	valid
+		.iter()
+		.enumerate()
+		.map(|(i, candidate)| {
+			let votes = valid.iter().filter(|other| *other == candidate).count();
+			(votes, i, *candidate)
+		})
+		.filter(|(votes, _, _)| *votes >= MIN_FALLBACK_VOTES)
+		.max_by_key(|(votes, i, _)| (*votes, usize::MAX - i))
+		.map(|(_, _, candidate)| candidate)
+}
+
 /// Caches a byte vector in order to prevent reallocation
 #[derive(Debug, Clone, Default)]
 pub struct ImageAnalyzer {
@@ -38,14 +189,44 @@ pub struct ImageAnalyzer {
 
 	/// Last rect used to crop something
 	last_rect: Option<(UIMeasurementRect, Rect)>,
+
+	/// Distance between the last recognised jacket and its closest match
+	last_jacket_distance: Option<f32>,
+
+	/// Maximum distance between a score character and the closest known
+	/// glyph. Defaults to
+	/// [crate::recognition::hyperglass::DEFAULT_MAX_CHAR_DISTANCE].
+	max_char_distance: Option<f32>,
 }
 
 impl ImageAnalyzer {
+	/// Overrides the OCR confidence threshold used when reading scores.
+	#[inline]
+	pub fn with_max_char_distance(mut self, max_char_distance: f32) -> Self {
+		self.max_char_distance = Some(max_char_distance);
+		self
+	}
+
 	/// Similar to reinitializing this, but without deallocating memory
 	#[inline]
 	pub fn clear(&mut self) {
 		self.bytes.clear();
 		self.last_rect = None;
+		self.last_jacket_distance = None;
+	}
+
+	/// Returns the area of the image the last crop was taken from, alongside
+	/// the part of the UI it was meant to contain.
+	#[inline]
+	pub fn last_crop(&mut self, image: &DynamicImage) -> Option<(UIMeasurementRect, DynamicImage)> {
+		let (ui_rect, rect) = self.last_rect?;
+		Some((ui_rect, self.crop(image, rect)))
+	}
+
+	/// Distance between the last jacket read and the closest known jacket.
+	#[inline]
+	pub fn last_jacket_distance(&self) -> Option<f32> {
+		self.last_jacket_distance
 	}
 
 	// {{{ Crop
@@ -137,7 +318,7 @@ impl ImageAnalyzer {
 		note_count: Option<u32>,
 		image: &DynamicImage,
 		kind: ScoreKind,
-	) -> Result<Score, Error> {
+	) -> Result<ScoreReading, Error> {
 		let image = self.interp_crop(
 			ctx,
 			image,
@@ -150,29 +331,84 @@ impl ImageAnalyzer {
 		let measurements = match kind {
 			ScoreKind::SongSelect => &ctx.exo_measurements,
 			ScoreKind::ScoreScreen => &ctx.geosans_measurements,
+		}
+		.get()?;
+
+		let recognised = measurements.recognise_with(
+			&image,
+			"0123456789'",
+			None,
+			None,
+			self.max_char_distance,
+		)?;
+		let reading_info = if recognised.dropped_count() > 0 {
+			format!(
+				" (only {}/{} characters could be recognised)",
+				recognised.component_count - recognised.dropped_count(),
+				recognised.component_count
+			)
+		} else {
+			String::new()
 		};
 
-		let result = Score(
-			measurements
-				.recognise(&image, "0123456789'", None, None)?
-				.chars()
-				.filter(|c| *c != '\'')
-				.collect::<String>()
-				.parse()?,
-		);
+		let result = recognised
+			.text
+			.chars()
+			.filter(|c| *c != '\'')
+			.collect::<String>()
+			.parse()
+			.map(Score)
+			.map_err(|err| anyhow!("Could not parse score{reading_info}: {err}"));
+
+		// Discard scores if it's impossible. Very low scores are technically
+		// achievable, but are much more likely to be misreads.
+		let is_valid = |score: &Score| {
+			let valid_analysis = note_count.map_or(true, |note_count| {
+				let (zeta, _, _) = score.analyse(note_count);
+				8_000_000 <= zeta.0 && score.is_achievable(note_count)
+			});
+
+			score.0 <= 10_010_000 && valid_analysis
+		};
 
-		// Discard scores if it's impossible
-		let valid_analysis = note_count.map_or(true, |note_count| {
-			let (zeta, shinies, score_units) = result.analyse(note_count);
-			8_000_000 <= zeta.0
-				&& zeta.0 <= 10_000_000
-				&& shinies <= note_count
-				&& score_units <= 2 * note_count
-		});
-		if result.0 <= 10_010_000 && valid_analysis {
-			Ok(result)
-		} else {
-			Err(anyhow!("Score {result} is not vaild"))
+		let mut reading = ScoreReading {
+			score: Score(0),
+			seen: recognised.component_count,
+			recognised: recognised.component_count - recognised.dropped_count(),
+			used_fallback: false,
+		};
+
+		let error = match result {
+			Ok(score) if is_valid(&score) => {
+				reading.score = score;
+				return Ok(reading);
+			}
+			Ok(score) => anyhow!("Score {score} is not vaild{reading_info}"),
+			Err(err) => err,
+		};
+
+		// {{{ Tesseract fallback
+		// Hyperglass' connected component step occasionally fails, in which
+		// case we let a few Tesseract modes vote on the score instead. The
+		// (invalid) hyperglass read is left out, as it could never win.
+		let candidates = match tesseract_score_candidates(&image) {
+			Ok(candidates) => candidates,
+			Err(err) => {
+				println!("Tesseract score fallback failed: {err}");
+				Vec::new()
+			}
+		};
+
+		let consensus = score_consensus(&candidates, is_valid);
+		// }}}
+
+		match consensus {
+			Some(score) => {
+				reading.score = score;
+				reading.used_fallback = true;
+				Ok(reading)
+			}
+			None => Err(error),
 		}
 	}
 	// }}}
@@ -202,10 +438,10 @@ impl ImageAnalyzer {
 						)
 						.unwrap();
 
-					let image_color = image.get_pixel(rect.x as u32, rect.y as u32);
-					let image_color = Color::from_bytes(image_color.0);
+					let image_color =
+						average_color(image, rect.x, rect.y, DIFFICULTY_MENU_SAMPLE_RADIUS);
 
-					let distance = c.distance(image_color);
+					let distance = c.perceptual_distance(image_color);
 					(distance * 10000.0) as u32
 				})
 				.unwrap();
@@ -219,7 +455,7 @@ impl ImageAnalyzer {
 			ScoreScreen(ScoreScreenRect::Difficulty),
 		)?;
 
-		let text = ctx.kazesawa_bold_measurements.recognise(
+		let text = ctx.kazesawa_bold_measurements.get()?.recognise(
 			&image,
 			"PASTPRESENTFUTUREETERNALBEYOND",
 			Some(200), // We can afford to be generous with binarization here
@@ -243,9 +479,10 @@ impl ImageAnalyzer {
 		image: &DynamicImage,
 	) -> Result<ScoreKind, Error> {
 		let image = self.interp_crop(ctx, image, PlayKind)?;
-		let text = ctx
-			.kazesawa_measurements
-			.recognise(&image, "ResultSelectaSong ", None, None)?;
+		let text =
+			ctx.kazesawa_measurements
+				.get()?
+				.recognise(&image, "ResultSelectaSong ", None, None)?;
 
 		let result = if edit_distance(&text, "Result") < edit_distance(&text, "SelectaSong") {
 			ScoreKind::ScoreScreen
@@ -257,6 +494,13 @@ impl ImageAnalyzer {
 	}
 	// }}}
 	// {{{ Read song
+	/// Reads the song title using Tesseract. This predates jacket recognition
+	/// and is no longer used by `score magic`.
+	///
+	/// Tesseract looks for its trained data in the directory pointed at by
+	/// the standard `TESSDATA_PREFIX` environment variable, falling back to
+	/// the location it was compiled with. The language can be picked using
+	/// `SHIMMERING_TESSERACT_LANGUAGE` (see [tesseract_language]).
 	pub fn read_song<'a>(
 		&mut self,
 		ctx: &'a UserContext,
@@ -264,7 +508,7 @@ impl ImageAnalyzer {
 		difficulty: Difficulty,
 	) -> Result<(&'a Song, &'a Chart), Error> {
 		let (text, conf) = Tesseract::builder()
-			.language(hypertesseract::Language::English)
+			.language(tesseract_language()?)
 			.page_seg_mode(PageSegMode::SingleLine)
 			.whitelist_str("abcdefghijklmnopqrstuvwxyzABCDEFGHIJKLMNOPQRSTUVWXYZ0123456789,.()- ")?
 			.build()?
@@ -282,7 +526,13 @@ impl ImageAnalyzer {
 			);
 		}
 
-		guess_chart_name(&text, &ctx.song_cache, Some(difficulty), false)
+		// OCR output is noisy, so we'd rather fail than guess between candidates
+		guess_chart_name(
+			&text,
+			&ctx.song_cache,
+			Some(difficulty),
+			MatchTolerance::OCR,
+		)
 	}
 	// }}}
 	// {{{ Read jacket
@@ -293,14 +543,14 @@ impl ImageAnalyzer {
 		kind: ScoreKind,
 		difficulty: Difficulty,
 	) -> Result<(&'a Song, &'a Chart), Error> {
-		let rect = ctx.ui_measurements.interpolate(
-			if kind == ScoreKind::ScoreScreen {
-				ScoreScreen(ScoreScreenRect::Jacket)
-			} else {
-				SongSelect(SongSelectRect::Jacket)
-			},
-			image,
-		)?;
+		let ui_rect = if kind == ScoreKind::ScoreScreen {
+			ScoreScreen(ScoreScreenRect::Jacket)
+		} else {
+			SongSelect(SongSelectRect::Jacket)
+		};
+
+		let rect = ctx.ui_measurements.interpolate(ui_rect, image)?;
+		self.last_rect = Some((ui_rect, rect));
 
 		let cropped = if kind == ScoreKind::ScoreScreen {
 			image.view(rect.x as u32, rect.y as u32, rect.width, rect.height)
@@ -322,11 +572,19 @@ impl ImageAnalyzer {
 			.jacket_cache
 			.recognise(&*cropped)
 			.ok_or_else(|| anyhow!("Could not recognise jacket"))?;
+		self.last_jacket_distance = Some(distance);
 
-		if distance > (IMAGE_VEC_DIM * 3) as f32 {
+		let rejection_threshold = ctx.jacket_cache.rejection_threshold;
+		if distance > rejection_threshold {
 			bail!("No known jacket looks like this");
 		}
 
+		if distance > rejection_threshold * JACKET_UNCERTAINTY_RATIO
+			&& !ctx.jacket_cache.double_check(&*cropped, song_id)
+		{
+			bail!("Jacket is too similar to multiple known jackets");
+		}
+
 		let (song, chart) = ctx.song_cache.lookup_by_difficulty(song_id, difficulty)?;
 
 		Ok((song, chart))
@@ -347,6 +605,7 @@ impl ImageAnalyzer {
 			let image = self.interp_crop(ctx, image, ScoreScreen(KINDS[i]))?;
 			out[i] = ctx
 				.kazesawa_bold_measurements
+				.get()?
 				// We need to be very strict with binarization here
 				.recognise(&image, "0123456789", Some(30), Some((0.33, 0.85)))?
 				.parse()
@@ -367,6 +626,7 @@ impl ImageAnalyzer {
 		let image = self.interp_crop(ctx, image, ScoreScreen(ScoreScreenRect::MaxRecall))?;
 		let max_recall = ctx
 			.exo_measurements
+			.get()?
 			// We can afford to be generous with binarization here
 			.recognise(&image, "0123456789", Some(200), None)?
 			.parse()?;
@@ -374,4 +634,233 @@ impl ImageAnalyzer {
 		Ok(max_recall)
 	}
 	// }}}
+	// {{{ Read clear kind
+	/// Reads the banner telling how the run ended. Only score screens show
+	/// it, and layouts where it has not been measured yet error out, as do
+	/// banners which cannot be matched (see [match_clear_banner]).
+	pub fn read_clear_kind(
+		&mut self,
+		ctx: &UserContext,
+		image: &DynamicImage,
+	) -> Result<ClearKind, Error> {
+		let rect = ScoreScreen(ScoreScreenRect::ClearBanner);
+
+		let area = ctx.ui_measurements.interpolate(rect, image)?;
+		if area.width == 0 || area.height == 0 {
+			bail!("The clear banner has not been measured for this layout");
+		}
+
+		let image = self.interp_crop(ctx, image, rect)?;
+		let text = ctx.kazesawa_bold_measurements.get()?.recognise(
+			&image,
+			"TRACKLOSTCOMPLETEFULLRECALLPUREMEMORY",
+			None,
+			None,
+		)?;
+
+		let clear_kind = match_clear_banner(&text)
+			.ok_or_else(|| anyhow!("Unrecognised clear banner '{}'", text))?;
+
+		Ok(clear_kind)
+	}
+	// }}}
+}
+// {{{ Clear banner matching
+/// Finds the clear kind whose banner is closest to the given OCR output, as
+/// long as it is within [MatchTolerance::OCR] of it.
+fn match_clear_banner(text: &str) -> Option<ClearKind> {
+	ClearKind::CLEAR_KINDS
+		.iter()
+		.zip(ClearKind::CLEAR_KIND_BANNERS)
+		.map(|(clear_kind, banner)| {
+			let banner = banner.replace(' ', "");
+			(*clear_kind, edit_distance(&banner, text), banner.len())
+		})
+		.filter(|(_, distance, len)| *distance <= MatchTolerance::OCR.max_distance(*len))
+		.min_by_key(|(_, distance, _)| *distance)
+		.map(|(clear_kind, _, _)| clear_kind)
+}
+// }}}
+// {{{ Tests
+#[cfg(test)]
+mod recognize_tests {
+	use image::Luma;
+
+	use crate::context::testing::get_mock_context;
+
+	use super::*;
+
+	/// Loads a screenshot, preparing it the same way `score magic` does
+	/// before reading the score.
+	fn load_grayscale(path: &str) -> Result<DynamicImage, Error> {
+		let mut image = DynamicImage::ImageLuma8(image::open(path)?.to_luma8());
+		image.invert();
+		Ok(image)
+	}
+
+	#[tokio::test]
+	async fn clean_score_is_fully_recognised() -> Result<(), Error> {
+		let (ctx, _guard) = get_mock_context().await?;
+		let image = load_grayscale("test/screenshots/fracture_ray_ex.jpg")?;
+
+		let reading =
+			ImageAnalyzer::default().read_score(&ctx.data, None, &image, ScoreKind::ScoreScreen)?;
+
+		assert_eq!(reading.score, Score(9_805_651));
+		assert!(reading.is_complete());
+
+		Ok(())
+	}
+
+	#[tokio::test]
+	async fn unmeasured_clear_banner_is_skipped() -> Result<(), Error> {
+		let (ctx, _guard) = get_mock_context().await?;
+		let image = DynamicImage::new_luma8(2532, 1170);
+
+		let err = ImageAnalyzer::default()
+			.read_clear_kind(&ctx.data, &image)
+			.unwrap_err();
+
+		assert!(format!("{err}").contains("not been measured"));
+
+		Ok(())
+	}
+
+	#[test]
+	fn clear_banners_need_to_be_close() {
+		assert_eq!(
+			match_clear_banner("TRACKCOMPLETE"),
+			Some(ClearKind::NormalClear)
+		);
+		assert_eq!(match_clear_banner("TRACKL0ST"), Some(ClearKind::TrackLost));
+		assert_eq!(match_clear_banner("FULLRECAL"), Some(ClearKind::FullRecall));
+		assert_eq!(match_clear_banner(""), None);
+		assert_eq!(match_clear_banner("EMPTY"), None);
+	}
+
+	#[test]
+	fn oversized_images_get_downscaled() {
+		let image = DynamicImage::new_luma8(4000, 3000);
+		let limited = limit_image_size(image, 1440);
+		assert_eq!((limited.width(), limited.height()), (1440, 1080));
+
+		let image = DynamicImage::new_luma8(1000, 3000);
+		let limited = limit_image_size(image, 1440);
+		assert_eq!((limited.width(), limited.height()), (480, 1440));
+
+		let image = DynamicImage::new_luma8(800, 600);
+		let limited = limit_image_size(image, 1440);
+		assert_eq!((limited.width(), limited.height()), (800, 600));
+	}
+
+	#[tokio::test]
+	async fn large_score_is_recognised_after_downscaling() -> Result<(), Error> {
+		let (ctx, _guard) = get_mock_context().await?;
+		let image = image::open("test/screenshots/fracture_ray_ex.jpg")?;
+
+		// Blow the screenshot up past the limit, like a 4K phone would
+		let image = image.resize(image.width() * 3, image.height() * 3, FilterType::Triangle);
+		let image = limit_image_size(image, max_ocr_dimension());
+		assert!(image.width().max(image.height()) <= max_ocr_dimension());
+
+		let mut image = DynamicImage::ImageLuma8(image.to_luma8());
+		image.invert();
+
+		let reading =
+			ImageAnalyzer::default().read_score(&ctx.data, None, &image, ScoreKind::ScoreScreen)?;
+
+		assert_eq!(reading.score, Score(9_805_651));
+
+		Ok(())
+	}
+
+	#[tokio::test]
+	async fn strict_threshold_reports_dropped_characters() -> Result<(), Error> {
+		let (ctx, _guard) = get_mock_context().await?;
+		let image = load_grayscale("test/screenshots/fracture_ray_ex.jpg")?;
+
+		let reading = ImageAnalyzer::default()
+			.with_max_char_distance(0.0)
+			.read_score(&ctx.data, None, &image, ScoreKind::ScoreScreen);
+
+		// Hyperglass gives up on every character, so the score can only come
+		// from the Tesseract fallback, which must either get it right or fail.
+		match reading {
+			Ok(reading) => {
+				assert!(reading.used_fallback);
+				assert_eq!(reading.score, Score(9_805_651));
+			}
+			Err(err) => assert!(format!("{err}").contains("characters could be recognised")),
+		}
+
+		Ok(())
+	}
+
+	#[test]
+	fn fallback_needs_multiple_modes_to_agree() {
+		let is_valid = |score: &Score| score.0 <= 10_010_000;
+
+		// A single (valid) read is not enough
+		assert_eq!(score_consensus(&[Score(9_805_651)], is_valid), None);
+		assert_eq!(
+			score_consensus(&[Score(9_805_651), Score(9_805_657)], is_valid),
+			None
+		);
+
+		assert_eq!(
+			score_consensus(
+				&[Score(9_805_657), Score(9_805_651), Score(9_805_651)],
+				is_valid
+			),
+			Some(Score(9_805_651))
+		);
+
+		// Invalid reads never get a say, no matter how many modes agree
+		assert_eq!(
+			score_consensus(
+				&[Score(98_056_510), Score(98_056_510), Score(9_805_651)],
+				is_valid
+			),
+			None
+		);
+
+		// Ties go to the earlier mode
+		assert_eq!(
+			score_consensus(&[Score(1), Score(2), Score(2), Score(1)], is_valid),
+			Some(Score(1))
+		);
+	}
+
+	#[tokio::test]
+	async fn noisy_score_is_never_silently_wrong() -> Result<(), Error> {
+		let (ctx, _guard) = get_mock_context().await?;
+		let mut image = load_grayscale("test/screenshots/fracture_ray_ex.jpg")?;
+
+		// Deterministic salt & pepper noise
+		let mut state: u64 = 0x5eed;
+		for pixel in image.as_mut_luma8().unwrap().pixels_mut() {
+			state = state
+				.wrapping_mul(6364136223846793005)
+				.wrapping_add(1442695040888963407);
+			match (state >> 33) % 40 {
+				0 => *pixel = Luma([0]),
+				1 => *pixel = Luma([255]),
+				_ => {}
+			}
+		}
+
+		let reading =
+			ImageAnalyzer::default().read_score(&ctx.data, None, &image, ScoreKind::ScoreScreen);
+
+		if let Ok(reading) = reading {
+			assert!(
+				reading.score == Score(9_805_651) || !reading.is_complete(),
+				"Misread score {} without reporting any dropped characters",
+				reading.score
+			);
+		}
+
+		Ok(())
+	}
 }
+// }}}
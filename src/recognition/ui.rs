@@ -20,6 +20,7 @@ pub enum ScoreScreenRect {
 	Lost,
 	MaxRecall,
 	Title,
+	ClearBanner,
 }
 
 #[derive(Debug, Clone, Copy)]
@@ -58,11 +59,12 @@ impl UIMeasurementRect {
 			Self::SongSelect(SongSelectRect::Present) => 12,
 			Self::SongSelect(SongSelectRect::Future) => 13,
 			Self::SongSelect(SongSelectRect::Beyond) => 14,
+			Self::ScoreScreen(ScoreScreenRect::ClearBanner) => 15,
 		}
 	}
 }
 
-pub const UI_RECT_COUNT: usize = 15;
+pub const UI_RECT_COUNT: usize = 16;
 // }}}
 // {{{ Measurement
 #[derive(Debug, Clone)]
@@ -1,14 +1,27 @@
+use std::env::var;
+
 use anyhow::anyhow;
 use rusqlite::Row;
 
 use crate::commands::discord::MessageContext;
 use crate::context::{ErrorKind, TagError, TaggedError, UserContext};
 
+/// When `SHIMMERING_AUTO_POOKIE_PLAYS` is set, users with more plays than
+/// its value automatically become pookies once they save their next play.
+/// Returns [None] (i.e. the policy is disabled) otherwise.
+#[inline]
+pub fn auto_pookie_threshold() -> Option<u32> {
+	var("SHIMMERING_AUTO_POOKIE_PLAYS")
+		.ok()
+		.and_then(|threshold| threshold.parse().ok())
+}
+
 #[derive(Debug, Clone)]
 pub struct User {
 	pub id: u32,
 	pub discord_id: String,
 	pub is_pookie: bool,
+	pub is_admin: bool,
 }
 
 impl User {
@@ -18,6 +31,7 @@ impl User {
 			id: row.get("id")?,
 			discord_id: row.get("discord_id")?,
 			is_pookie: row.get("is_pookie")?,
+			is_admin: row.get("is_admin")?,
 		})
 	}
 
@@ -41,6 +55,7 @@ impl User {
 			discord_id,
 			id: user_id,
 			is_pookie: false,
+			is_admin: false,
 		})
 	}
 
@@ -60,6 +75,51 @@ impl User {
 		Ok(user)
 	}
 
+	/// Grants pookie status to the user if they have more than `threshold`
+	/// plays. Returns whether the status has been granted by this call.
+	///
+	/// This only ever grants pookie status, never admin status (see
+	/// [Self::assert_is_admin]).
+	pub fn auto_pookify(&mut self, ctx: &UserContext, threshold: u32) -> Result<bool, TaggedError> {
+		if self.is_pookie {
+			return Ok(false);
+		}
+
+		let conn = ctx.db.get()?;
+		let play_count: u32 = conn
+			.prepare_cached("SELECT COUNT(*) FROM plays WHERE user_id=?")?
+			.query_row([self.id], |row| row.get(0))?;
+
+		if play_count <= threshold {
+			return Ok(false);
+		}
+
+		conn.prepare_cached("UPDATE users SET is_pookie=1 WHERE id=?")?
+			.execute([self.id])?;
+		self.is_pookie = true;
+
+		println!(
+			"Automatically granted pookie status to user {} ({} plays)",
+			self.discord_id, play_count
+		);
+
+		Ok(true)
+	}
+
+	pub fn by_discord_id(ctx: &UserContext, discord_id: &str) -> Result<Self, TaggedError> {
+		let user = ctx
+			.db
+			.get()?
+			.prepare_cached("SELECT * FROM users WHERE discord_id = ?")?
+			.query_map([discord_id], Self::from_row)?
+			.next()
+			.ok_or_else(|| {
+				anyhow!("That user is not in my database, sowwy ^~^").tag(ErrorKind::User)
+			})??;
+
+		Ok(user)
+	}
+
 	pub fn by_id(ctx: &UserContext, id: u32) -> Result<Self, TaggedError> {
 		let user = ctx
 			.db
@@ -84,4 +144,55 @@ impl User {
 
 		Ok(())
 	}
+
+	/// Admin status is never granted automatically, so this (rather than
+	/// [Self::assert_is_pookie]) guards commands which modify global state or
+	/// other users' data.
+	#[inline]
+	pub fn assert_is_admin(&self) -> Result<(), TaggedError> {
+		if !self.is_admin {
+			return Err(
+				anyhow!("This feature is reserved for my admins. Sowwy :3").tag(ErrorKind::User)
+			);
+		}
+
+		Ok(())
+	}
+}
+
+#[cfg(test)]
+mod user_tests {
+	use crate::context::testing::get_mock_context;
+	use crate::context::Error;
+
+	use super::*;
+
+	#[tokio::test]
+	async fn auto_pookify_above_threshold() -> Result<(), Error> {
+		let (ctx, _guard) = get_mock_context().await?;
+		let mut user = User::create_from_context(&ctx).map_err(|e| e.error)?;
+
+		let conn = ctx.data.db.get()?;
+		for _ in 0..3 {
+			conn.execute(
+				"INSERT INTO plays(user_id, chart_id) SELECT ?, id FROM charts LIMIT 1",
+				[user.id],
+			)?;
+		}
+
+		assert!(!user.auto_pookify(&ctx.data, 3).map_err(|e| e.error)?);
+		assert!(!user.is_pookie);
+
+		assert!(user.auto_pookify(&ctx.data, 2).map_err(|e| e.error)?);
+		assert!(user.is_pookie);
+
+		let stored = User::by_id(&ctx.data, user.id).map_err(|e| e.error)?;
+		assert!(stored.is_pookie);
+		assert!(!stored.is_admin);
+		assert!(stored.assert_is_admin().is_err());
+
+		// Existing pookies are left alone
+		assert!(!user.auto_pookify(&ctx.data, 0).map_err(|e| e.error)?);
+		Ok(())
+	}
 }